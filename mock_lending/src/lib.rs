@@ -0,0 +1,145 @@
+// Minimal lending program used as the strategy target in tests.
+// Implements the adapter interface expected by tail_risk_insurance_pool:
+//   deposit(u64) / withdraw(u64) with accounts [authority, depositor_token, token_program, ...]
+// `accrue` lets a test simulate interest by funding the reserve.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+declare_id!("8zxEJYbCp2j8GsZ5M166FQ7b9BREEeQ6pwtkA1kgSZpF");
+
+#[program]
+pub mod mock_lending {
+    use super::*;
+
+    pub fn init_obligation(ctx: Context<InitObligation>, authority: Pubkey) -> Result<()> {
+        let o = &mut ctx.accounts.obligation;
+        o.authority = authority;
+        o.balance = 0;
+        o.bump = ctx.bumps.obligation;
+        Ok(())
+    }
+
+    pub fn deposit(ctx: Context<Move>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.depositor_token.to_account_info(),
+                to: ctx.accounts.reserve_ata.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        let o = &mut ctx.accounts.obligation;
+        o.balance = o.balance.checked_add(amount).ok_or(MockError::Overflow)?;
+        Ok(())
+    }
+
+    /// `u64::MAX` withdraws the full balance (principal + accrued)
+    pub fn withdraw(ctx: Context<Move>, amount: u64) -> Result<()> {
+        let o = &mut ctx.accounts.obligation;
+        let take = if amount == u64::MAX { o.balance } else { amount };
+        require!(take <= o.balance, MockError::Insufficient);
+        o.balance -= take;
+
+        let bump = ctx.bumps.reserve_authority;
+        let seeds: &[&[u8]] = &[b"reserve", &[bump]];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.reserve_ata.to_account_info(),
+                to: ctx.accounts.depositor_token.to_account_info(),
+                authority: ctx.accounts.reserve_authority.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, take)
+    }
+
+    /// Test helper: fund the reserve and credit it to an obligation as interest
+    pub fn accrue(ctx: Context<Accrue>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.funder_token.to_account_info(),
+                to: ctx.accounts.reserve_ata.to_account_info(),
+                authority: ctx.accounts.funder.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        let o = &mut ctx.accounts.obligation;
+        o.balance = o.balance.checked_add(amount).ok_or(MockError::Overflow)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(authority: Pubkey)]
+pub struct InitObligation<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"obligation", authority.as_ref()],
+        bump,
+        space = 8 + Obligation::SIZE
+    )]
+    pub obligation: Account<'info, Obligation>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Move<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub depositor_token: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        mut,
+        seeds = [b"obligation", authority.key().as_ref()],
+        bump = obligation.bump
+    )]
+    pub obligation: Account<'info, Obligation>,
+
+    #[account(mut, constraint = reserve_ata.owner == reserve_authority.key())]
+    pub reserve_ata: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA signer for the reserve token account
+    #[account(seeds = [b"reserve"], bump)]
+    pub reserve_authority: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Accrue<'info> {
+    pub funder: Signer<'info>,
+    #[account(mut)]
+    pub funder_token: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub reserve_ata: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub obligation: Account<'info, Obligation>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[account]
+pub struct Obligation {
+    pub authority: Pubkey,
+    pub balance: u64,
+    pub bump: u8,
+}
+impl Obligation {
+    pub const SIZE: usize = 32 + 8 + 1;
+}
+
+#[error_code]
+pub enum MockError {
+    #[msg("Math overflow")]
+    Overflow,
+    #[msg("Insufficient obligation balance")]
+    Insufficient,
+}
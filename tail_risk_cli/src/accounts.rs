@@ -0,0 +1,639 @@
+//! Borsh mirrors of the program accounts.
+//!
+//! Field order and types match the `#[account]` structs in the program exactly;
+//! data starts with Anchor's 8-byte discriminator `sha256("account:<Name>")[..8]`.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{hash::hash, pubkey::Pubkey};
+
+/// `MAX_LOTS` in the program.
+pub const MAX_LOTS: usize = 16;
+/// `MAX_ORACLES` in the program.
+pub const MAX_ORACLES: usize = 16;
+/// `MAX_COLLATERALS` in the program.
+pub const MAX_COLLATERALS: usize = 4;
+/// `MAX_REFERRAL_TIERS` in the program.
+pub const MAX_REFERRAL_TIERS: usize = 3;
+/// `EPOCH_HISTORY_LEN` in the program.
+pub const EPOCH_HISTORY_LEN: usize = 16;
+/// `ACCOUNT_VERSION` in the program (0 = written before layout versioning).
+pub const ACCOUNT_VERSION: u8 = 1;
+/// `PAUSE_*` bits of `State::pause_flags` in the program.
+pub const PAUSE_DEPOSITS: u8 = 1 << 0;
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 1;
+pub const PAUSE_TRIGGERS: u8 = 1 << 2;
+pub const PAUSE_CLAIMS: u8 = 1 << 3;
+pub const PAUSE_FEE_SWEEPS: u8 = 1 << 4;
+pub const PAUSE_ALL: u8 = PAUSE_DEPOSITS | PAUSE_WITHDRAWALS | PAUSE_TRIGGERS | PAUSE_CLAIMS | PAUSE_FEE_SWEEPS;
+pub const PAUSE_OUTFLOWS: u8 = PAUSE_WITHDRAWALS | PAUSE_CLAIMS | PAUSE_FEE_SWEEPS;
+/// Longest `reserved` tail (`State`), which an account written before its reserve grew may lack.
+const MAX_RESERVED: usize = 512;
+
+/// Anchor discriminator for account type `name`.
+pub fn discriminator(name: &str) -> [u8; 8] {
+    let mut d = [0u8; 8];
+    d.copy_from_slice(&hash(format!("account:{name}").as_bytes()).to_bytes()[..8]);
+    d
+}
+
+/// A program account that can be decoded from raw data and listed field by field.
+pub trait ProgramAccount: BorshSerialize + BorshDeserialize + Sized {
+    /// Rust type name in the program (discriminator seed).
+    const NAME: &'static str;
+
+    /// `(field, value)` pairs in declaration order, used for printing and diffs.
+    fn fields(&self) -> Vec<(&'static str, String)>;
+
+    /// Layout version.
+    fn version(&self) -> u8;
+
+    /// Set the layout version.
+    fn set_version(&mut self, version: u8);
+
+    /// Decode account data, checking the discriminator. Trailing bytes are ignored.
+    /// Accounts written before layout versioning are decoded from their old layout
+    /// (`decode_v0`); a missing `reserved` tail decodes as zeros.
+    fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        anyhow::ensure!(data.len() >= 8, "{}: account data too short", Self::NAME);
+        anyhow::ensure!(data[..8] == discriminator(Self::NAME), "not a {} account", Self::NAME);
+        if let Some(legacy) = Self::decode_v0(&data[8..]) {
+            return legacy;
+        }
+        let mut body = data[8..].to_vec();
+        body.resize(body.len() + MAX_RESERVED, 0);
+        Ok(Self::deserialize(&mut body.as_slice())?)
+    }
+
+    /// Decode `body` if it is exactly the layout written before versioning: version 0,
+    /// with the fields it lacks at the defaults `migrate_state` / `migrate_position` start from.
+    fn decode_v0(_body: &[u8]) -> Option<anyhow::Result<Self>> {
+        None
+    }
+
+    /// Encode with discriminator (as the program would store it).
+    fn encode(&self) -> Vec<u8> {
+        let mut out = discriminator(Self::NAME).to_vec();
+        self.serialize(&mut out).expect("writing to a Vec cannot fail");
+        out
+    }
+}
+
+macro_rules! program_account {
+    ($(#[$meta:meta])* $name:ident $(: v0 $v0:ty)? { $($(#[$fmeta:meta])* $field:ident : $ty:ty,)* }) => {
+        $(#[$meta])*
+        #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+        pub struct $name {
+            $($(#[$fmeta])* pub $field: $ty,)*
+        }
+
+        impl ProgramAccount for $name {
+            const NAME: &'static str = stringify!($name);
+
+            fn fields(&self) -> Vec<(&'static str, String)> {
+                vec![$((stringify!($field), format!("{:?}", self.$field)),)*]
+            }
+
+            fn version(&self) -> u8 {
+                self.version
+            }
+
+            fn set_version(&mut self, version: u8) {
+                self.version = version;
+            }
+
+            $(
+                fn decode_v0(body: &[u8]) -> Option<anyhow::Result<Self>> {
+                    (body.len() == <$v0>::LEN).then(|| Ok(<$v0>::try_from_slice(body)?.into()))
+                }
+            )?
+        }
+    };
+}
+
+/// A deposit lot.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Lot {
+    /// Amount (fixed-point).
+    pub amount_fp: u128,
+    /// Deposit time.
+    pub ts: i64,
+}
+
+/// FIFO ring of lots.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct Lots {
+    /// Oldest lot.
+    pub head: u8,
+    /// Live lots.
+    pub len: u8,
+    /// Storage.
+    pub buf: [Lot; MAX_LOTS],
+}
+
+/// Volume-based referral tier.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReferralTier {
+    /// Referred volume needed (0 = unused).
+    pub min_volume_fp: u128,
+    /// Fee for this tier.
+    pub fee_bps: u16,
+}
+
+/// A finalized epoch in the `EpochHistory` ring.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct EpochRecord {
+    /// Epoch id.
+    pub epoch_id: u64,
+    /// Effective severity.
+    pub severity_bps: u16,
+    /// Paid out (fixed-point).
+    pub total_payout_fp: u128,
+    /// Unpaid entitlements (fixed-point).
+    pub shortfall_fp: u128,
+}
+
+/// Registered collateral.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct CollateralAsset {
+    /// Mint.
+    pub mint: Pubkey,
+    /// Program-owned vault ATA.
+    pub vault: Pubkey,
+    /// Mint decimals.
+    pub decimals: u8,
+    /// Valuation discount.
+    pub haircut_bps: u16,
+    /// Max share of pool value.
+    pub concentration_cap_bps: u16,
+    /// Accepts new deposits.
+    pub enabled: bool,
+    /// Par held in vault (common units).
+    pub balance_fp: u128,
+}
+
+program_account! {
+    /// Global pool state (`State`).
+    State: v0 StateV0 {
+        admin: Pubkey,
+        usdc_mint: Pubkey,
+        protocol_treasury: Pubkey,
+        claim_window_open: bool,
+        processing: bool,
+        payout_policy: u8,
+        user_deposit_cap_fp: u128,
+        min_deposit_fp: u128,
+        protocol_fee_bps: u16,
+        referral_fee_bps: u16,
+        lockup_secs: i64,
+        min_seconds_between_deposits: i64,
+        fee_model: u8,
+        performance_fee_bps: u16,
+        protocol_fees_accrued_fp: u128,
+        referral_tiers: [ReferralTier; MAX_REFERRAL_TIERS],
+        referral_rewards_owed_fp: u128,
+        epoch_cap_fp: u128,
+        rolling_mode: bool,
+        max_stale_secs: i64,
+        sev_quad_a_fp: u128,
+        sev_quad_b_fp: u128,
+        sev_quad_c_fp: u128,
+        severity_floor_bps: u16,
+        tranche_weight_senior_bps: u16,
+        tranche_weight_junior_bps: u16,
+        last_event_ts: i64,
+        total_deposited_fp: u128,
+        carryover_shortfall_fp: u128,
+        strategy_program: Pubkey,
+        strategy_max_bps: u16,
+        strategy_junior_yield_bps: u16,
+        strategy_deployed_fp: u128,
+        senior_total_fp: u128,
+        junior_total_fp: u128,
+        senior_yield_index_fp: u128,
+        junior_yield_index_fp: u128,
+        yield_reserve_fp: u128,
+        bump: u8,
+        version: u8,
+        shutdown: bool,
+        shutdown_ts: i64,
+        guardian: Pubkey,
+        pause_flags: u8,
+        outflow_window_secs: i64,
+        outflow_cap_fp: u128,
+        outflow_cap_bps: u16,
+        outflow_window_updated_ts: i64,
+        outflow_window_base_fp: u128,
+        outflow_window_used_fp: u128,
+        crank_reward_u64: u64,
+        current_epoch_id: u64,
+        epoch_duration_secs: u32,
+        cover_owed_fp: u128,
+        reserved: [u8; 496],
+    }
+}
+
+program_account! {
+    /// A user's position (`UserPosition`).
+    UserPosition: v0 UserPositionV0 {
+        owner: Pubkey,
+        senior_deposited_fp: u128,
+        junior_deposited_fp: u128,
+        senior_withdrawable_fp: u128,
+        junior_withdrawable_fp: u128,
+        senior_lots: Lots,
+        junior_lots: Lots,
+        last_deposit_ts: i64,
+        referrer: Pubkey,
+        senior_yield_debt_fp: u128,
+        junior_yield_debt_fp: u128,
+        yield_accrued_fp: u128,
+        bump: u8,
+        version: u8,
+        pending_epoch_id: u64,
+        pending_senior_fp: u128,
+        pending_junior_fp: u128,
+        reserved: [u8; 88],
+    }
+}
+
+program_account! {
+    /// A coverage epoch (`Epoch`).
+    Epoch: v0 EpochV0 {
+        epoch_id: u64,
+        start_ts: i64,
+        end_ts: i64,
+        total_stake_snapshot_fp: u128,
+        total_payout_fp: u128,
+        shortfall_fp: u128,
+        severity_bps: u16,
+        user_cap_bps: u16,
+        epoch_cap_fp: u128,
+        triggered: bool,
+        closed: bool,
+        evidence_hash: [u8; 32],
+        evidence_ts: i64,
+        finalized_ts: i64,
+        open_receipts: u32,
+        bump: u8,
+        version: u8,
+        merkle_root: [u8; 32],
+        merkle_total_fp: u128,
+        pending_deposits_fp: u128,
+        cover_sold_fp: u128,
+        cover_claimed_fp: u128,
+        cover_paid_fp: u128,
+        triggered_ts: i64,
+        reserved: [u8; 72],
+    }
+}
+
+program_account! {
+    /// Per-epoch, per-user claim receipt (`ClaimReceipt`).
+    ClaimReceipt: v0 ClaimReceiptV0 {
+        epoch_id: u64,
+        owner: Pubkey,
+        claimed_fp: u128,
+        bump: u8,
+        version: u8,
+        payer: Pubkey,
+        reserved: [u8; 16],
+    }
+}
+
+program_account! {
+    /// Claimed leaves of a Merkle-settled epoch (`ClaimBitmap`).
+    ClaimBitmap {
+        epoch_id: u64,
+        claimants: u32,
+        claimed: u32,
+        bump: u8,
+        version: u8,
+        reserved: [u8; 16],
+        bits: Vec<u8>,
+    }
+}
+
+program_account! {
+    /// Epoch registry and ring of finalized epochs (`EpochHistory`).
+    EpochHistory {
+        last_epoch_id: u64,
+        last_end_ts: i64,
+        count: u32,
+        records: [EpochRecord; EPOCH_HISTORY_LEN],
+        bump: u8,
+        version: u8,
+        reserved: [u8; 64],
+    }
+}
+
+program_account! {
+    /// Cover sold on the current epoch, with its limit and price (`CoverBook`).
+    CoverBook {
+        max_utilisation_bps: u16,
+        base_rate_bps: u16,
+        epoch_id: u64,
+        sold_fp: u128,
+        bump: u8,
+        version: u8,
+        kink_utilisation_bps: u16,
+        slope1_bps: u16,
+        slope2_bps: u16,
+        event_probability_bps: u16,
+        reference_input_bps: u16,
+        covers_minted: u64,
+        reserved: [u8; 46],
+    }
+}
+
+program_account! {
+    /// One cover purchase, owned by whoever holds its NFT (`Cover`).
+    Cover {
+        mint: Pubkey,
+        buyer: Pubkey,
+        epoch_id: u64,
+        notional_fp: u128,
+        premium_fp: u128,
+        claimed_fp: u128,
+        bump: u8,
+        version: u8,
+        reserved: [u8; 32],
+    }
+}
+
+program_account! {
+    /// Oracle allowlist (`OracleList`).
+    OracleList: v0 OracleListV0 {
+        enabled: bool,
+        count: u8,
+        keys: [Pubkey; MAX_ORACLES],
+        bump: u8,
+        version: u8,
+        reserved: [u8; 32],
+    }
+}
+
+program_account! {
+    /// Accepted collateral (`CollateralRegistry`).
+    CollateralRegistry {
+        count: u8,
+        assets: [CollateralAsset; MAX_COLLATERALS],
+        bump: u8,
+        version: u8,
+        reserved: [u8; 64],
+    }
+}
+
+program_account! {
+    /// A referrer's rewards (`Referrer`).
+    Referrer {
+        owner: Pubkey,
+        total_referred_fp: u128,
+        lifetime_rewards_fp: u128,
+        pending_rewards_fp: u128,
+        tier: u8,
+        bump: u8,
+        version: u8,
+        reserved: [u8; 32],
+    }
+}
+
+/// `State` as written before layout versioning (`StateV0` in the program).
+#[derive(BorshDeserialize)]
+struct StateV0 {
+    admin: Pubkey,
+    usdc_mint: Pubkey,
+    protocol_treasury: Pubkey,
+    _paused: bool,
+    processing: bool,
+    payout_policy: u8,
+    user_deposit_cap_fp: u128,
+    min_deposit_fp: u128,
+    protocol_fee_bps: u16,
+    referral_fee_bps: u16,
+    lockup_secs: i64,
+    min_seconds_between_deposits: i64,
+    epoch_cap_fp: u128,
+    rolling_mode: bool,
+    max_stale_secs: i64,
+    sev_quad_a_fp: u128,
+    sev_quad_b_fp: u128,
+    sev_quad_c_fp: u128,
+    severity_floor_bps: u16,
+    tranche_weight_senior_bps: u16,
+    tranche_weight_junior_bps: u16,
+    last_event_ts: i64,
+    total_deposited_fp: u128,
+    carryover_shortfall_fp: u128,
+    bump: u8,
+}
+
+impl StateV0 {
+    const LEN: usize = 271;
+}
+
+impl From<StateV0> for State {
+    fn from(v0: StateV0) -> Self {
+        State {
+            admin: v0.admin,
+            usdc_mint: v0.usdc_mint,
+            protocol_treasury: v0.protocol_treasury,
+            claim_window_open: false,
+            processing: v0.processing,
+            payout_policy: v0.payout_policy,
+            user_deposit_cap_fp: v0.user_deposit_cap_fp,
+            min_deposit_fp: v0.min_deposit_fp,
+            protocol_fee_bps: v0.protocol_fee_bps,
+            referral_fee_bps: v0.referral_fee_bps,
+            lockup_secs: v0.lockup_secs,
+            min_seconds_between_deposits: v0.min_seconds_between_deposits,
+            fee_model: 0,
+            performance_fee_bps: 0,
+            protocol_fees_accrued_fp: 0,
+            referral_tiers: [ReferralTier::default(); MAX_REFERRAL_TIERS],
+            referral_rewards_owed_fp: 0,
+            epoch_cap_fp: v0.epoch_cap_fp,
+            rolling_mode: v0.rolling_mode,
+            max_stale_secs: v0.max_stale_secs,
+            sev_quad_a_fp: v0.sev_quad_a_fp,
+            sev_quad_b_fp: v0.sev_quad_b_fp,
+            sev_quad_c_fp: v0.sev_quad_c_fp,
+            severity_floor_bps: v0.severity_floor_bps,
+            tranche_weight_senior_bps: v0.tranche_weight_senior_bps,
+            tranche_weight_junior_bps: v0.tranche_weight_junior_bps,
+            last_event_ts: v0.last_event_ts,
+            total_deposited_fp: v0.total_deposited_fp,
+            carryover_shortfall_fp: v0.carryover_shortfall_fp,
+            strategy_program: Pubkey::default(),
+            strategy_max_bps: 0,
+            strategy_junior_yield_bps: 0,
+            strategy_deployed_fp: 0,
+            senior_total_fp: 0,
+            junior_total_fp: 0,
+            senior_yield_index_fp: 0,
+            junior_yield_index_fp: 0,
+            yield_reserve_fp: 0,
+            bump: v0.bump,
+            version: 0,
+            shutdown: false,
+            shutdown_ts: 0,
+            guardian: Pubkey::default(),
+            pause_flags: 0,
+            outflow_window_secs: 0,
+            outflow_cap_fp: 0,
+            outflow_cap_bps: 0,
+            outflow_window_updated_ts: 0,
+            outflow_window_base_fp: 0,
+            outflow_window_used_fp: 0,
+            crank_reward_u64: 0,
+            current_epoch_id: 0,
+            epoch_duration_secs: 0,
+            cover_owed_fp: 0,
+            reserved: [0; 496],
+        }
+    }
+}
+
+/// `UserPosition` as written before layout versioning.
+#[derive(BorshDeserialize)]
+struct UserPositionV0 {
+    owner: Pubkey,
+    senior_deposited_fp: u128,
+    junior_deposited_fp: u128,
+    senior_withdrawable_fp: u128,
+    junior_withdrawable_fp: u128,
+    senior_lots: Lots,
+    junior_lots: Lots,
+    last_deposit_ts: i64,
+    referrer: Pubkey,
+    bump: u8,
+}
+
+impl UserPositionV0 {
+    const LEN: usize = 909;
+}
+
+impl From<UserPositionV0> for UserPosition {
+    fn from(v0: UserPositionV0) -> Self {
+        UserPosition {
+            owner: v0.owner,
+            senior_deposited_fp: v0.senior_deposited_fp,
+            junior_deposited_fp: v0.junior_deposited_fp,
+            senior_withdrawable_fp: v0.senior_withdrawable_fp,
+            junior_withdrawable_fp: v0.junior_withdrawable_fp,
+            senior_lots: v0.senior_lots,
+            junior_lots: v0.junior_lots,
+            last_deposit_ts: v0.last_deposit_ts,
+            referrer: v0.referrer,
+            senior_yield_debt_fp: 0,
+            junior_yield_debt_fp: 0,
+            yield_accrued_fp: 0,
+            bump: v0.bump,
+            version: 0,
+            pending_epoch_id: 0,
+            pending_senior_fp: 0,
+            pending_junior_fp: 0,
+            reserved: [0; 88],
+        }
+    }
+}
+
+/// `Epoch` as written before layout versioning.
+#[derive(BorshDeserialize)]
+struct EpochV0 {
+    epoch_id: u64,
+    start_ts: i64,
+    end_ts: i64,
+    total_stake_snapshot_fp: u128,
+    total_payout_fp: u128,
+    shortfall_fp: u128,
+    severity_bps: u16,
+    user_cap_bps: u16,
+    epoch_cap_fp: u128,
+    triggered: bool,
+    closed: bool,
+    evidence_hash: [u8; 32],
+    evidence_ts: i64,
+    bump: u8,
+}
+
+impl EpochV0 {
+    const LEN: usize = 135;
+}
+
+impl From<EpochV0> for Epoch {
+    fn from(v0: EpochV0) -> Self {
+        Epoch {
+            epoch_id: v0.epoch_id,
+            start_ts: v0.start_ts,
+            end_ts: v0.end_ts,
+            total_stake_snapshot_fp: v0.total_stake_snapshot_fp,
+            total_payout_fp: v0.total_payout_fp,
+            shortfall_fp: v0.shortfall_fp,
+            severity_bps: v0.severity_bps,
+            user_cap_bps: v0.user_cap_bps,
+            epoch_cap_fp: v0.epoch_cap_fp,
+            triggered: v0.triggered,
+            closed: v0.closed,
+            evidence_hash: v0.evidence_hash,
+            evidence_ts: v0.evidence_ts,
+            finalized_ts: 0,
+            open_receipts: 0,
+            bump: v0.bump,
+            version: 0,
+            merkle_root: [0; 32],
+            merkle_total_fp: 0,
+            pending_deposits_fp: 0,
+            cover_sold_fp: 0,
+            cover_claimed_fp: 0,
+            cover_paid_fp: 0,
+            triggered_ts: 0,
+            reserved: [0; 72],
+        }
+    }
+}
+
+/// `ClaimReceipt` as written before layout versioning.
+#[derive(BorshDeserialize)]
+struct ClaimReceiptV0 {
+    epoch_id: u64,
+    owner: Pubkey,
+    claimed_fp: u128,
+    bump: u8,
+}
+
+impl ClaimReceiptV0 {
+    const LEN: usize = 57;
+}
+
+impl From<ClaimReceiptV0> for ClaimReceipt {
+    fn from(v0: ClaimReceiptV0) -> Self {
+        ClaimReceipt {
+            epoch_id: v0.epoch_id,
+            owner: v0.owner,
+            claimed_fp: v0.claimed_fp,
+            bump: v0.bump,
+            version: 0,
+            payer: Pubkey::default(),
+            reserved: [0; 16],
+        }
+    }
+}
+
+/// `OracleList` as written before layout versioning.
+#[derive(BorshDeserialize)]
+struct OracleListV0 {
+    enabled: bool,
+    count: u8,
+    keys: [Pubkey; MAX_ORACLES],
+    bump: u8,
+}
+
+impl OracleListV0 {
+    const LEN: usize = 515;
+}
+
+impl From<OracleListV0> for OracleList {
+    fn from(v0: OracleListV0) -> Self {
+        OracleList { enabled: v0.enabled, count: v0.count, keys: v0.keys, bump: v0.bump, version: 0, reserved: [0; 32] }
+    }
+}
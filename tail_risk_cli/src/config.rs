@@ -0,0 +1,171 @@
+//! TOML configuration.
+//!
+//! ```toml
+//! [cluster]
+//! rpc_url = "https://api.devnet.solana.com"
+//! keypair = "~/.config/solana/id.json"
+//!
+//! [initialize]      # InitializeParams, plus `usdc_mint`
+//! [policy]          # payout_policy, epoch_cap_fp
+//! [curve]           # set_curve_and_weights arguments
+//! [cover]           # init_cover_book / set_cover_params arguments
+//! [strategy]        # accounts forwarded to the lending program on trigger
+//! ```
+//!
+//! `u128` amounts may be written as integers or, above `i64::MAX`, as strings
+//! (underscores allowed).
+
+use std::{fs, path::Path, str::FromStr};
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+
+use crate::{
+    ix::{CoverParams, CurveParams, InitializeParams},
+    pda::PROGRAM_ID,
+};
+
+/// Whole config file.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// RPC endpoint, signer and program id.
+    #[serde(default)]
+    pub cluster: Cluster,
+    /// `initialize` parameters.
+    pub initialize: Option<InitializeConfig>,
+    /// `set_policy` arguments.
+    pub policy: Option<PolicyConfig>,
+    /// `set_curve_and_weights` arguments.
+    pub curve: Option<CurveParams>,
+    /// `init_cover_book` / `set_cover_params` arguments.
+    pub cover: Option<CoverParams>,
+    /// Lending-program accounts for `trigger_event` while capital is deployed.
+    pub strategy: Option<StrategyConfig>,
+}
+
+/// `[cluster]`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Cluster {
+    /// JSON-RPC endpoint.
+    pub rpc_url: String,
+    /// Signer keypair file (admin or oracle); `~` is expanded.
+    pub keypair: String,
+    /// Program id.
+    #[serde(deserialize_with = "de_pubkey")]
+    pub program_id: Pubkey,
+    /// `processed`, `confirmed` or `finalized`.
+    pub commitment: String,
+}
+
+impl Default for Cluster {
+    fn default() -> Self {
+        Self {
+            rpc_url: "http://127.0.0.1:8899".into(),
+            keypair: "~/.config/solana/id.json".into(),
+            program_id: PROGRAM_ID,
+            commitment: "confirmed".into(),
+        }
+    }
+}
+
+/// `[initialize]`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct InitializeConfig {
+    /// Base collateral mint (registered as collateral entry 0).
+    #[serde(deserialize_with = "de_pubkey")]
+    pub usdc_mint: Pubkey,
+    /// Instruction parameters.
+    #[serde(flatten)]
+    pub params: InitializeParams,
+}
+
+/// `[policy]`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// 0 = Proportional, 1 = Capped, 2 = EpochBounded.
+    pub payout_policy: u8,
+    /// New epoch cap; omitted = unchanged.
+    #[serde(default, deserialize_with = "de_opt_u128")]
+    pub epoch_cap_fp: Option<u128>,
+}
+
+/// `[strategy]`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// Lending program (must equal `State.strategy_program`).
+    #[serde(deserialize_with = "de_pubkey")]
+    pub program: Pubkey,
+    /// Accounts its `withdraw` expects, in order.
+    #[serde(default)]
+    pub accounts: Vec<StrategyAccount>,
+}
+
+/// One forwarded account.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyAccount {
+    /// Address.
+    #[serde(deserialize_with = "de_pubkey")]
+    pub pubkey: Pubkey,
+    /// Passed writable.
+    #[serde(default)]
+    pub writable: bool,
+}
+
+impl StrategyConfig {
+    /// Remaining-account metas (never signers).
+    pub fn metas(&self) -> Vec<AccountMeta> {
+        self.accounts
+            .iter()
+            .map(|a| if a.writable { AccountMeta::new(a.pubkey, false) } else { AccountMeta::new_readonly(a.pubkey, false) })
+            .collect()
+    }
+}
+
+impl Config {
+    /// Parse a config file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| anyhow::anyhow!("cannot read {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| anyhow::anyhow!("invalid config {}: {e}", path.display()))
+    }
+
+    /// Parse config text.
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+}
+
+/// Base58 pubkey from a string.
+pub fn de_pubkey<'de, D: Deserializer<'de>>(d: D) -> Result<Pubkey, D::Error> {
+    let s = String::deserialize(d)?;
+    Pubkey::from_str(&s).map_err(|e| D::Error::custom(format!("invalid pubkey {s}: {e}")))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IntOrString {
+    Int(u64),
+    Str(String),
+}
+
+/// Parse a `u128` from a decimal string (underscores allowed).
+pub fn parse_u128(s: &str) -> Result<u128, String> {
+    s.replace('_', "").parse().map_err(|e| format!("invalid amount {s}: {e}"))
+}
+
+/// `u128` from an integer or a decimal string.
+pub fn de_u128<'de, D: Deserializer<'de>>(d: D) -> Result<u128, D::Error> {
+    match IntOrString::deserialize(d)? {
+        IntOrString::Int(v) => Ok(v as u128),
+        IntOrString::Str(s) => parse_u128(&s).map_err(D::Error::custom),
+    }
+}
+
+/// Optional [`de_u128`].
+pub fn de_opt_u128<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u128>, D::Error> {
+    de_u128(d).map(Some)
+}
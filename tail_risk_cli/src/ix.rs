@@ -0,0 +1,494 @@
+//! Instruction builders.
+//!
+//! Data is Anchor's `sha256("global:<name>")[..8]` followed by the Borsh-encoded
+//! arguments in declaration order; account metas follow the `#[derive(Accounts)]`
+//! field order. Optional accounts that are absent are passed as the program id.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::Deserialize;
+use solana_sdk::{
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+use crate::{
+    config::{de_pubkey, de_u128},
+    pda::{associated_token_address, Pdas, ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID},
+};
+
+/// Anchor discriminator for instruction `name`.
+pub fn sighash(name: &str) -> [u8; 8] {
+    let mut d = [0u8; 8];
+    d.copy_from_slice(&hash(format!("global:{name}").as_bytes()).to_bytes()[..8]);
+    d
+}
+
+fn data(name: &str, args: &impl BorshSerialize) -> Vec<u8> {
+    let mut out = sighash(name).to_vec();
+    args.serialize(&mut out).expect("writing to a Vec cannot fail");
+    out
+}
+
+/// `InitializeParams` (also the `[initialize]` table of the config file).
+#[derive(BorshSerialize, BorshDeserialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct InitializeParams {
+    /// Treasury receiving protocol fees and swept dust.
+    #[serde(deserialize_with = "de_pubkey")]
+    pub protocol_treasury: Pubkey,
+    /// 0 = Proportional, 1 = Capped, 2 = EpochBounded.
+    pub payout_policy: u8,
+    /// Per-user principal cap (fixed-point).
+    #[serde(deserialize_with = "de_u128")]
+    pub user_deposit_cap_fp: u128,
+    /// Minimum deposit (fixed-point).
+    #[serde(deserialize_with = "de_u128")]
+    pub min_deposit_fp: u128,
+    /// Protocol fee.
+    pub protocol_fee_bps: u16,
+    /// Base referral fee.
+    pub referral_fee_bps: u16,
+    /// Lot lockup (seconds).
+    pub lockup_secs: i64,
+    /// Deposit cooldown (seconds).
+    pub min_seconds_between_deposits: i64,
+    /// 0 = OnDeposit, 1 = OnPremium, 2 = OnYield.
+    pub fee_model: u8,
+    /// Protocol cut of strategy yield.
+    pub performance_fee_bps: u16,
+    /// Epoch liability cap under `EpochBounded` (fixed-point).
+    #[serde(deserialize_with = "de_u128")]
+    pub epoch_cap_fp: u128,
+    /// Allow open-ended epochs.
+    pub rolling_mode: bool,
+    /// Oracle evidence staleness bound (0 = off).
+    pub max_stale_secs: i64,
+    /// Severity curve, quadratic term (fixed-point).
+    #[serde(deserialize_with = "de_u128")]
+    pub sev_quad_a_fp: u128,
+    /// Linear term.
+    #[serde(deserialize_with = "de_u128")]
+    pub sev_quad_b_fp: u128,
+    /// Constant term.
+    #[serde(deserialize_with = "de_u128")]
+    pub sev_quad_c_fp: u128,
+    /// Severity floor.
+    pub severity_floor_bps: u16,
+    /// Senior payout weight.
+    pub tranche_weight_senior_bps: u16,
+    /// Junior payout weight.
+    pub tranche_weight_junior_bps: u16,
+}
+
+/// `set_curve_and_weights` arguments (also the `[curve]` table of the config file).
+#[derive(BorshSerialize, BorshDeserialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CurveParams {
+    /// Quadratic term (fixed-point).
+    #[serde(deserialize_with = "de_u128")]
+    pub sev_quad_a_fp: u128,
+    /// Linear term.
+    #[serde(deserialize_with = "de_u128")]
+    pub sev_quad_b_fp: u128,
+    /// Constant term.
+    #[serde(deserialize_with = "de_u128")]
+    pub sev_quad_c_fp: u128,
+    /// Severity floor.
+    pub severity_floor_bps: u16,
+    /// Senior payout weight.
+    pub tranche_weight_senior_bps: u16,
+    /// Junior payout weight.
+    pub tranche_weight_junior_bps: u16,
+}
+
+/// `init_cover_book` / `set_cover_params` arguments (also the `[cover]` table of the config
+/// file). Rates are annual.
+#[derive(BorshSerialize, BorshDeserialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CoverParams {
+    /// Most cover outstanding, in bps of tranche capital.
+    pub max_utilisation_bps: u16,
+    /// Premium rate at zero utilisation.
+    pub base_rate_bps: u16,
+    /// Utilisation where the second slope starts.
+    pub kink_utilisation_bps: u16,
+    /// Added up to the kink.
+    pub slope1_bps: u16,
+    /// Added from the kink to full utilisation.
+    pub slope2_bps: u16,
+    /// Chance of a trigger in a year.
+    pub event_probability_bps: u16,
+    /// Severity-curve input that trigger is assumed to have.
+    pub reference_input_bps: u16,
+}
+
+/// `trigger_event` arguments.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq)]
+pub struct TriggerArgs {
+    /// Input to the severity curve.
+    pub severity_input_bps: u16,
+    /// Per-user cap under `Capped`.
+    pub user_cap_bps: Option<u16>,
+    /// Overrides `State.epoch_cap_fp` under `EpochBounded`.
+    pub epoch_cap_fp_override: Option<u128>,
+    /// Oracle evidence hash.
+    pub evidence_hash: Option<[u8; 32]>,
+    /// Oracle evidence timestamp (checked against `max_stale_secs`).
+    pub evidence_ts: Option<i64>,
+}
+
+#[derive(BorshSerialize)]
+struct SetPolicyArgs {
+    payout_policy: u8,
+    epoch_cap_fp: Option<u128>,
+}
+
+#[derive(BorshSerialize)]
+struct StartEpochArgs {
+    epoch_id: u64,
+    start_ts: i64,
+    end_ts: i64,
+}
+
+/// Builds instructions for one deployment.
+#[derive(Clone, Copy, Debug)]
+pub struct Builder {
+    /// PDAs of the deployment.
+    pub pdas: Pdas,
+}
+
+impl Builder {
+    /// Builder for `program_id`.
+    pub fn new(program_id: Pubkey) -> Self {
+        Self { pdas: Pdas::new(program_id) }
+    }
+
+    fn ix(&self, accounts: Vec<AccountMeta>, data: Vec<u8>) -> Instruction {
+        Instruction { program_id: self.pdas.program_id, accounts, data }
+    }
+
+    /// `initialize(params)`; creates state, base vault, oracle list and collateral registry.
+    pub fn initialize(&self, admin: &Pubkey, usdc_mint: &Pubkey, params: &InitializeParams) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new_readonly(*usdc_mint, false),
+                AccountMeta::new(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.vault(usdc_mint), false),
+                AccountMeta::new(self.pdas.oracle(), false),
+                AccountMeta::new(self.pdas.collateral(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+                AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+            ],
+            data("initialize", params),
+        )
+    }
+
+    fn admin_only(&self, admin: &Pubkey, data: Vec<u8>) -> Instruction {
+        self.ix(vec![AccountMeta::new(*admin, true), AccountMeta::new(self.pdas.state(), false)], data)
+    }
+
+    /// `set_paused(flags)`.
+    pub fn set_paused(&self, admin: &Pubkey, flags: u8) -> Instruction {
+        self.admin_only(admin, data("set_paused", &flags))
+    }
+
+    /// `set_guardian(guardian)`.
+    pub fn set_guardian(&self, admin: &Pubkey, guardian: &Pubkey) -> Instruction {
+        self.admin_only(admin, data("set_guardian", guardian))
+    }
+
+    /// `guardian_pause(flags)`, signed by the guardian or the admin.
+    pub fn guardian_pause(&self, authority: &Pubkey, flags: u8) -> Instruction {
+        self.ix(
+            vec![AccountMeta::new_readonly(*authority, true), AccountMeta::new(self.pdas.state(), false)],
+            data("guardian_pause", &flags),
+        )
+    }
+
+    /// `set_outflow_limits(window_secs, cap_fp, cap_bps)`.
+    pub fn set_outflow_limits(&self, admin: &Pubkey, window_secs: i64, cap_fp: u128, cap_bps: u16) -> Instruction {
+        self.admin_only(admin, data("set_outflow_limits", &(window_secs, cap_fp, cap_bps)))
+    }
+
+    /// `set_crank_reward(reward_u64)`.
+    pub fn set_crank_reward(&self, admin: &Pubkey, reward_u64: u64) -> Instruction {
+        self.admin_only(admin, data("set_crank_reward", &reward_u64))
+    }
+
+    /// `set_rolling_mode(enabled, epoch_duration_secs)`.
+    pub fn set_rolling_mode(&self, admin: &Pubkey, enabled: bool, epoch_duration_secs: u32) -> Instruction {
+        self.admin_only(admin, data("set_rolling_mode", &(enabled, epoch_duration_secs)))
+    }
+
+    /// `init_epoch_history()`; `current_epoch` is the current epoch id, if that epoch exists.
+    pub fn init_epoch_history(&self, admin: &Pubkey, current_epoch: Option<u64>) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new_readonly(self.pdas.state(), false),
+                AccountMeta::new_readonly(current_epoch.map_or(self.pdas.program_id, |id| self.pdas.epoch(id)), false),
+                AccountMeta::new(self.pdas.epoch_history(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data("init_epoch_history", &()),
+        )
+    }
+
+    /// `init_cover_book(params)`.
+    pub fn init_cover_book(&self, admin: &Pubkey, params: &CoverParams) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new_readonly(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.cover_book(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data("init_cover_book", params),
+        )
+    }
+
+    /// `set_cover_params(params)`.
+    pub fn set_cover_params(&self, admin: &Pubkey, params: &CoverParams) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new_readonly(*admin, true),
+                AccountMeta::new_readonly(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.cover_book(), false),
+            ],
+            data("set_cover_params", params),
+        )
+    }
+
+    /// `shutdown()`, settling `open_epoch`'s claim window if one is open.
+    pub fn shutdown(&self, admin: &Pubkey, open_epoch: Option<u64>) -> Instruction {
+        let (epoch, history) = match open_epoch {
+            Some(id) => (AccountMeta::new(self.pdas.epoch(id), false), AccountMeta::new(self.pdas.epoch_history(), false)),
+            None => (
+                AccountMeta::new_readonly(self.pdas.program_id, false),
+                AccountMeta::new_readonly(self.pdas.program_id, false),
+            ),
+        };
+        self.ix(vec![AccountMeta::new(*admin, true), AccountMeta::new(self.pdas.state(), false), epoch, history], data("shutdown", &()))
+    }
+
+    /// `set_policy(payout_policy, epoch_cap_fp)`.
+    pub fn set_policy(&self, admin: &Pubkey, payout_policy: u8, epoch_cap_fp: Option<u128>) -> Instruction {
+        self.admin_only(admin, data("set_policy", &SetPolicyArgs { payout_policy, epoch_cap_fp }))
+    }
+
+    /// `set_curve_and_weights(a, b, c, floor, senior, junior)`.
+    pub fn set_curve_and_weights(&self, admin: &Pubkey, curve: &CurveParams) -> Instruction {
+        self.admin_only(admin, data("set_curve_and_weights", curve))
+    }
+
+    /// `start_epoch(epoch_id, start_ts, end_ts)`; `previous` is the current epoch id,
+    /// if that epoch exists.
+    pub fn start_epoch(&self, payer: &Pubkey, epoch_id: u64, start_ts: i64, end_ts: i64, previous: Option<u64>) -> Instruction {
+        let previous = match previous {
+            Some(id) => AccountMeta::new_readonly(self.pdas.epoch(id), false),
+            None => AccountMeta::new_readonly(self.pdas.program_id, false),
+        };
+        self.ix(
+            vec![
+                AccountMeta::new(*payer, true),
+                AccountMeta::new(self.pdas.state(), false),
+                previous,
+                AccountMeta::new(self.pdas.epoch_history(), false),
+                AccountMeta::new(self.pdas.epoch(epoch_id), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data("start_epoch", &StartEpochArgs { epoch_id, start_ts, end_ts }),
+        )
+    }
+
+    /// `end_epoch()` for an epoch that was never triggered.
+    pub fn end_epoch(&self, admin: &Pubkey, epoch_id: u64) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new_readonly(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.epoch(epoch_id), false),
+                AccountMeta::new(self.pdas.epoch_history(), false),
+            ],
+            data("end_epoch", &()),
+        )
+    }
+
+    /// `trigger_event(...)`. `strategy` is the lending program plus the accounts its
+    /// `withdraw` needs; required only while capital is deployed.
+    pub fn trigger_event(
+        &self,
+        signer: &Pubkey,
+        epoch_id: u64,
+        base_vault: &Pubkey,
+        strategy: Option<(&Pubkey, &[AccountMeta])>,
+        args: &TriggerArgs,
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*signer, true),
+            AccountMeta::new(self.pdas.state(), false),
+            AccountMeta::new(self.pdas.epoch(epoch_id), false),
+            AccountMeta::new_readonly(self.pdas.oracle(), false),
+            AccountMeta::new(self.pdas.collateral(), false),
+            AccountMeta::new(*base_vault, false),
+            AccountMeta::new_readonly(strategy.map_or(self.pdas.program_id, |(p, _)| *p), false),
+            AccountMeta::new_readonly(strategy.map_or(self.pdas.program_id, |_| self.pdas.strategy_authority()), false),
+            AccountMeta::new(strategy.map_or(self.pdas.program_id, |_| self.pdas.strategy_escrow()), false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ];
+        if let Some((_, remaining)) = strategy {
+            accounts.extend_from_slice(remaining);
+        }
+        self.ix(accounts, data("trigger_event", args))
+    }
+
+    /// `finalize_epoch(sweep_dust_u64)`; dust (if any) moves from the `asset_mint` vault to `treasury_ata`.
+    pub fn finalize_epoch(
+        &self,
+        admin: &Pubkey,
+        epoch_id: u64,
+        asset_mint: &Pubkey,
+        treasury_ata: &Pubkey,
+        sweep_dust: Option<u64>,
+    ) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.epoch(epoch_id), false),
+                AccountMeta::new(self.pdas.epoch_history(), false),
+                AccountMeta::new(self.pdas.vault(asset_mint), false),
+                AccountMeta::new(*treasury_ata, false),
+                AccountMeta::new_readonly(*asset_mint, false),
+                AccountMeta::new(self.pdas.collateral(), false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            ],
+            data("finalize_epoch", &sweep_dust),
+        )
+    }
+
+    /// `crank_payouts()` for the holders in `owners` (remaining accounts: position,
+    /// claim receipt and base USDC ATA per owner); the reward goes to `cranker_ata`.
+    pub fn crank_payouts(
+        &self,
+        cranker: &Pubkey,
+        epoch_id: u64,
+        usdc_mint: &Pubkey,
+        cranker_ata: &Pubkey,
+        owners: &[Pubkey],
+    ) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*cranker, true),
+            AccountMeta::new(self.pdas.state(), false),
+            AccountMeta::new(self.pdas.epoch(epoch_id), false),
+            AccountMeta::new(self.pdas.collateral(), false),
+            AccountMeta::new(self.pdas.vault(usdc_mint), false),
+            AccountMeta::new(*cranker_ata, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ];
+        for owner in owners {
+            accounts.push(AccountMeta::new_readonly(self.pdas.position(owner), false));
+            accounts.push(AccountMeta::new(self.pdas.claim(epoch_id, owner), false));
+            accounts.push(AccountMeta::new(associated_token_address(owner, usdc_mint), false));
+        }
+        self.ix(accounts, data("crank_payouts", &()))
+    }
+
+    /// `post_merkle_root(root, total_fp, claimants)`; `admin` pays for the claim bitmap.
+    pub fn post_merkle_root(&self, admin: &Pubkey, epoch_id: u64, root: [u8; 32], total_fp: u128, claimants: u32) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new_readonly(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.epoch(epoch_id), false),
+                AccountMeta::new(self.pdas.bitmap(epoch_id), false),
+                AccountMeta::new_readonly(self.pdas.collateral(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data("post_merkle_root", &(root, total_fp, claimants)),
+        )
+    }
+
+    /// `claim_with_proof(index, amount_fp, proof)`, paid in `asset_mint` to `user`'s ATA.
+    pub fn claim_with_proof(
+        &self,
+        user: &Pubkey,
+        epoch_id: u64,
+        asset_mint: &Pubkey,
+        index: u32,
+        amount_fp: u128,
+        proof: &[[u8; 32]],
+    ) -> Instruction {
+        self.ix(
+            vec![
+                AccountMeta::new_readonly(*user, true),
+                AccountMeta::new_readonly(*asset_mint, false),
+                AccountMeta::new(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.epoch(epoch_id), false),
+                AccountMeta::new(self.pdas.bitmap(epoch_id), false),
+                AccountMeta::new(self.pdas.vault(asset_mint), false),
+                AccountMeta::new(associated_token_address(user, asset_mint), false),
+                AccountMeta::new(self.pdas.collateral(), false),
+                AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            ],
+            data("claim_with_proof", &(index, amount_fp, proof.to_vec())),
+        )
+    }
+
+    /// `close_epoch()`; rent goes back to `admin`. A Merkle-settled epoch closes with its
+    /// claim bitmap (`with_bitmap`).
+    pub fn close_epoch(&self, admin: &Pubkey, epoch_id: u64, with_bitmap: bool) -> Instruction {
+        let bitmap = if with_bitmap {
+            AccountMeta::new(self.pdas.bitmap(epoch_id), false)
+        } else {
+            AccountMeta::new_readonly(self.pdas.program_id, false)
+        };
+        self.ix(
+            vec![
+                AccountMeta::new(*admin, true),
+                AccountMeta::new_readonly(self.pdas.state(), false),
+                AccountMeta::new(self.pdas.epoch(epoch_id), false),
+                bitmap,
+            ],
+            data("close_epoch", &()),
+        )
+    }
+
+    /// `migrate_state()`; `epoch_ids` are migrated too (remaining accounts).
+    pub fn migrate_state(&self, admin: &Pubkey, usdc_mint: &Pubkey, epoch_ids: &[u64]) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(self.pdas.state(), false),
+            AccountMeta::new(self.pdas.oracle(), false),
+            AccountMeta::new(self.pdas.collateral(), false),
+            AccountMeta::new_readonly(*usdc_mint, false),
+            AccountMeta::new_readonly(self.pdas.vault(usdc_mint), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ];
+        accounts.extend(epoch_ids.iter().map(|&id| AccountMeta::new(self.pdas.epoch(id), false)));
+        self.ix(accounts, data("migrate_state", &()))
+    }
+
+    /// `migrate_position()` for `owner`, plus the claim receipts of `claim_epoch_ids` and their
+    /// epochs (remaining accounts, in pairs).
+    pub fn migrate_position(&self, payer: &Pubkey, owner: &Pubkey, claim_epoch_ids: &[u64]) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new(self.pdas.position(owner), false),
+            AccountMeta::new(self.pdas.state(), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ];
+        for &id in claim_epoch_ids {
+            accounts.push(AccountMeta::new(self.pdas.claim(id, owner), false));
+            accounts.push(AccountMeta::new(self.pdas.epoch(id), false));
+        }
+        self.ix(accounts, data("migrate_position", &()))
+    }
+}
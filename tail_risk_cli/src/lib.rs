@@ -0,0 +1,17 @@
+//! Admin and operator tooling for the tail-risk insurance pool.
+//!
+//! - [`accounts`]: Borsh mirrors of the program accounts
+//! - [`pda`]: seeds for `state`, `oracle`, `collateral`, `epoch`, `position`, `claim`, `bitmap`
+//! - [`ix`]: instruction builders for the admin / oracle instructions
+//! - [`config`]: TOML config file
+//! - [`preview`]: expected account diffs for `--dry-run`
+//! - [`merkle`]: Merkle distributions for `post_merkle_root` / `claim_with_proof`
+//!
+//! The `tail-risk-cli` binary wires these to an RPC client.
+
+pub mod accounts;
+pub mod config;
+pub mod ix;
+pub mod merkle;
+pub mod pda;
+pub mod preview;
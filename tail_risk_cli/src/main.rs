@@ -0,0 +1,903 @@
+//! `tail-risk-cli`: build, sign and send (or serialize, or dry-run) admin and
+//! oracle instructions. Run with `--help` for usage.
+
+use std::{fs, path::PathBuf, process, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use clap::{Parser, Subcommand, ValueEnum};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Clock,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+use tail_risk_cli::{
+    accounts::{
+        ClaimBitmap, ClaimReceipt, CollateralRegistry, Cover, CoverBook, Epoch, EpochHistory, OracleList, ProgramAccount,
+        State, UserPosition, PAUSE_ALL, PAUSE_CLAIMS, PAUSE_DEPOSITS, PAUSE_FEE_SWEEPS, PAUSE_TRIGGERS, PAUSE_WITHDRAWALS,
+    },
+    config::{parse_u128, Config},
+    ix::{Builder, TriggerArgs},
+    merkle::{self, Distribution},
+    pda::{associated_token_address, Pdas},
+    preview::{self, Preview},
+};
+
+#[derive(Parser)]
+#[command(name = "tail-risk-cli", about = "Admin / oracle tool for the tail-risk insurance pool")]
+struct Cli {
+    /// TOML config file
+    #[arg(short, long, default_value = "pool.toml")]
+    config: PathBuf,
+    /// Override `cluster.rpc_url`
+    #[arg(long)]
+    url: Option<String>,
+    /// Override `cluster.keypair`
+    #[arg(long)]
+    keypair: Option<String>,
+    /// Signer / fee payer pubkey when no keypair is available (with --serialize or --dry-run)
+    #[arg(long)]
+    signer: Option<Pubkey>,
+    /// Print decoded accounts and the expected state diff; send nothing
+    #[arg(long, conflicts_with = "serialize")]
+    dry_run: bool,
+    /// Print the unsigned transaction (base64) for offline signing; send nothing
+    #[arg(long)]
+    serialize: bool,
+    /// Recent blockhash for --serialize (default: fetched from the RPC)
+    #[arg(long, requires = "serialize")]
+    blockhash: Option<Hash>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print PDAs
+    Pda {
+        #[arg(long)]
+        epoch: Option<u64>,
+        #[arg(long)]
+        user: Option<Pubkey>,
+        #[arg(long)]
+        cover: Option<u64>,
+    },
+    /// Fetch and decode an account
+    Show {
+        #[arg(value_enum)]
+        account: AccountKind,
+        /// Epoch id (epoch, claim, bitmap)
+        #[arg(long)]
+        epoch: Option<u64>,
+        /// Owner (position, claim)
+        #[arg(long)]
+        user: Option<Pubkey>,
+        /// Cover number, from 0 (cover)
+        #[arg(long)]
+        cover: Option<u64>,
+    },
+    /// `initialize` with `[initialize]` from the config
+    Initialize,
+    /// `init_epoch_history`: create the epoch registry `start-epoch` and `finalize-epoch` need
+    InitEpochHistory,
+    /// `set_paused`: replace the pause mask (`none` resumes everything)
+    SetPaused {
+        #[arg(value_enum, value_delimiter = ',', required = true)]
+        ops: Vec<PauseOp>,
+    },
+    /// `set_guardian` (the default pubkey `1111...1111` removes it)
+    SetGuardian { guardian: Pubkey },
+    /// `guardian_pause`: pause more operations (guardian or admin); only `set-paused` resumes
+    GuardianPause {
+        #[arg(value_enum, value_delimiter = ',', required = true)]
+        ops: Vec<PauseOp>,
+    },
+    /// `set_outflow_limits`: cap what may leave the vaults per window (0 = cap off)
+    SetOutflowLimits {
+        #[arg(long)]
+        window_secs: i64,
+        #[arg(long, default_value_t = 0, value_parser = parse_u128)]
+        cap_fp: u128,
+        /// Of the vault value at the window's start
+        #[arg(long, default_value_t = 0)]
+        cap_bps: u16,
+    },
+    /// `set_crank_reward`: base USDC paid per claimant by `crank_payouts`, from uncollected fees
+    SetCrankReward { reward_u64: u64 },
+    /// `set_rolling_mode`: chain fixed-length epochs; omit `--duration-secs` to turn it off
+    SetRollingMode {
+        #[arg(long)]
+        duration_secs: Option<u32>,
+    },
+    /// `init_cover_book` with `[cover]` from the config: the cover limit and premium curve
+    InitCoverBook,
+    /// `set_cover_params` with `[cover]` from the config
+    SetCoverParams,
+    /// `shutdown`: permanently stop deposits, premiums, epochs and triggers; LPs exit via
+    /// `emergency_withdraw`
+    Shutdown {
+        /// Epoch whose claim window is open (finalized by the shutdown, unpaid claims as shortfall)
+        #[arg(long)]
+        epoch_id: Option<u64>,
+    },
+    /// `set_policy` (defaults from `[policy]`)
+    SetPolicy {
+        #[arg(long)]
+        payout_policy: Option<u8>,
+        #[arg(long, value_parser = parse_u128)]
+        epoch_cap_fp: Option<u128>,
+    },
+    /// `set_curve_and_weights` with `[curve]` from the config
+    SetCurveAndWeights,
+    /// `start_epoch`; in rolling mode the window is the pool's and anyone may roll once
+    /// the current epoch has ended
+    StartEpoch {
+        /// Default: the epoch after the current one
+        #[arg(long)]
+        epoch_id: Option<u64>,
+        /// Not in the past. Default: when the transaction lands
+        #[arg(long)]
+        start_ts: Option<i64>,
+        /// 0 = open-ended
+        #[arg(long, conflicts_with = "duration_secs")]
+        end_ts: Option<i64>,
+        /// End `duration_secs` after start
+        #[arg(long)]
+        duration_secs: Option<i64>,
+    },
+    /// `trigger_event` (admin or allow-listed oracle)
+    TriggerEvent {
+        #[arg(long)]
+        epoch_id: u64,
+        #[arg(long)]
+        severity_bps: u16,
+        #[arg(long)]
+        user_cap_bps: Option<u16>,
+        #[arg(long, value_parser = parse_u128)]
+        epoch_cap_fp: Option<u128>,
+        /// 32-byte hex
+        #[arg(long, value_parser = parse_hash32)]
+        evidence_hash: Option<[u8; 32]>,
+        #[arg(long)]
+        evidence_ts: Option<i64>,
+    },
+    /// `end_epoch`: end an epoch that was never triggered (open-ended, or past its window)
+    EndEpoch {
+        #[arg(long)]
+        epoch_id: u64,
+    },
+    /// `finalize_epoch`
+    FinalizeEpoch {
+        #[arg(long)]
+        epoch_id: u64,
+        /// Max dust to sweep to the treasury (native units)
+        #[arg(long)]
+        sweep_dust: Option<u64>,
+        /// Vault to sweep from (default: `State.usdc_mint`)
+        #[arg(long)]
+        asset_mint: Option<Pubkey>,
+        /// Default: treasury's ATA for `asset_mint`
+        #[arg(long)]
+        treasury_ata: Option<Pubkey>,
+    },
+    /// `crank_payouts`: pay holders of a triggered epoch who have not claimed (anyone)
+    CrankPayouts {
+        #[arg(long)]
+        epoch_id: u64,
+        #[arg(long, value_delimiter = ',', required = true)]
+        owners: Vec<Pubkey>,
+        /// Reward account (default: the signer's base USDC ATA)
+        #[arg(long)]
+        cranker_ata: Option<Pubkey>,
+    },
+    /// Price every position of a triggered epoch and write the Merkle distribution (JSON)
+    MerkleBuild {
+        #[arg(long)]
+        epoch_id: u64,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// `post_merkle_root` from a distribution written by `merkle-build`
+    PostMerkleRoot {
+        #[arg(long)]
+        distribution: PathBuf,
+    },
+    /// `claim_with_proof`: claim the signer's entry of a posted distribution
+    ClaimWithProof {
+        #[arg(long)]
+        distribution: PathBuf,
+        /// Asset to be paid in (default: `State.usdc_mint`)
+        #[arg(long)]
+        asset_mint: Option<Pubkey>,
+    },
+    /// `close_epoch` (after finalize, once every claim receipt is closed)
+    CloseEpoch {
+        #[arg(long)]
+        epoch_id: u64,
+    },
+    /// `migrate_state`: upgrade legacy global accounts (and epochs) to the current layout
+    MigrateState {
+        /// Epochs to migrate too
+        #[arg(long, value_delimiter = ',')]
+        epochs: Vec<u64>,
+    },
+    /// `migrate_position`: upgrade a legacy position (and its claim receipts), after `migrate-state`
+    MigratePosition {
+        #[arg(long)]
+        owner: Pubkey,
+        /// Epochs whose claim receipts to migrate too (the epochs must be migrated already)
+        #[arg(long, value_delimiter = ',')]
+        claims: Vec<u64>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AccountKind {
+    State,
+    Oracle,
+    Collateral,
+    Epoch,
+    Position,
+    Claim,
+    Bitmap,
+    EpochHistory,
+    CoverBook,
+    Cover,
+}
+
+/// Operations of the pause mask (comma-separated on the command line).
+#[derive(Clone, Copy, ValueEnum)]
+enum PauseOp {
+    None,
+    Deposits,
+    Withdrawals,
+    Triggers,
+    Claims,
+    FeeSweeps,
+    All,
+}
+
+fn pause_flags(ops: &[PauseOp]) -> u8 {
+    ops.iter().fold(0, |flags, op| {
+        flags
+            | match op {
+                PauseOp::None => 0,
+                PauseOp::Deposits => PAUSE_DEPOSITS,
+                PauseOp::Withdrawals => PAUSE_WITHDRAWALS,
+                PauseOp::Triggers => PAUSE_TRIGGERS,
+                PauseOp::Claims => PAUSE_CLAIMS,
+                PauseOp::FeeSweeps => PAUSE_FEE_SWEEPS,
+                PauseOp::All => PAUSE_ALL,
+            }
+    })
+}
+
+fn parse_hash32(s: &str) -> Result<[u8; 32], String> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if s.len() != 64 {
+        return Err("expected 64 hex characters".into());
+    }
+    let mut out = [0u8; 32];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
+
+fn load_distribution(path: &PathBuf) -> Result<Distribution> {
+    let text = fs::read_to_string(path).with_context(|| format!("{}", path.display()))?;
+    let dist: Distribution = serde_json::from_str(&text).with_context(|| format!("{}", path.display()))?;
+    if let Some(bad) = dist.claims.iter().find(|c| !dist.verify(c)) {
+        bail!("{}: claim {} does not verify against the root", path.display(), bad.index);
+    }
+    Ok(dist)
+}
+
+struct Ctx {
+    cli_signer: Option<Pubkey>,
+    keypair_path: String,
+    rpc: RpcClient,
+    pdas: Pdas,
+    builder: Builder,
+}
+
+impl Ctx {
+    fn keypair(&self) -> Result<Keypair> {
+        let path = match self.keypair_path.strip_prefix("~/") {
+            Some(rest) => format!("{}/{rest}", std::env::var("HOME").unwrap_or_default()),
+            None => self.keypair_path.clone(),
+        };
+        read_keypair_file(&path).map_err(|e| anyhow!("cannot read keypair {path}: {e}"))
+    }
+
+    fn signer(&self) -> Result<Pubkey> {
+        match self.cli_signer {
+            Some(pk) => Ok(pk),
+            None => Ok(self.keypair()?.pubkey()),
+        }
+    }
+
+    fn fetch<T: ProgramAccount>(&self, address: &Pubkey) -> Result<Option<T>> {
+        let acc = self.rpc.get_account_with_commitment(address, self.rpc.commitment())?.value;
+        acc.map(|a| T::decode(&a.data).with_context(|| format!("{address}"))).transpose()
+    }
+
+    fn require<T: ProgramAccount>(&self, address: &Pubkey) -> Result<T> {
+        self.fetch(address)?.ok_or_else(|| anyhow!("{} account {address} not found", T::NAME))
+    }
+
+    /// Raw data of a non-program account (SPL mints and token accounts).
+    fn data(&self, address: &Pubkey) -> Result<Vec<u8>> {
+        Ok(self.rpc.get_account(address).with_context(|| format!("{address}"))?.data)
+    }
+
+    fn now(&self) -> Result<i64> {
+        let acc = self.rpc.get_account(&sysvar::clock::ID)?;
+        Ok(bincode::deserialize::<Clock>(&acc.data)?.unix_timestamp)
+    }
+}
+
+fn print_account<T: ProgramAccount>(address: &Pubkey, acc: &T) {
+    println!("{} {address}", T::NAME);
+    for (k, v) in acc.fields().into_iter().filter(|(k, _)| *k != "reserved") {
+        println!("  {k} = {v}");
+    }
+}
+
+fn print_instruction(name: &str, ix: &Instruction) {
+    println!("instruction {name} (program {})", ix.program_id);
+    for (i, m) in ix.accounts.iter().enumerate() {
+        let flags = match (m.is_signer, m.is_writable) {
+            (true, true) => " signer writable",
+            (true, false) => " signer",
+            (false, true) => " writable",
+            (false, false) => "",
+        };
+        println!("  #{i} {}{flags}", m.pubkey);
+    }
+    let hex: String = ix.data.iter().map(|b| format!("{b:02x}")).collect();
+    println!("  data {hex}");
+}
+
+fn print_preview(preview: &Preview) {
+    match preview {
+        Err(e) => println!("expected: fails with {e}"),
+        Ok(diffs) => {
+            println!("expected changes:");
+            for d in diffs {
+                println!("  {} {}", d.name, d.address);
+                if d.closed {
+                    println!("    (closed, rent returned)");
+                } else if d.changes.is_empty() {
+                    println!("    (no change)");
+                }
+                for c in &d.changes {
+                    match &c.before {
+                        Some(b) => println!("    {}: {b} -> {}", c.field, c.after),
+                        None => println!("    {}: {}", c.field, c.after),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Dry-run: print the instruction, the decoded accounts it reads and the preview.
+/// Otherwise build the transaction and either print it or sign and send it.
+fn submit(cli: &Cli, ctx: &Ctx, name: &str, ix: Instruction, preview: impl FnOnce() -> Result<Preview>) -> Result<()> {
+    if cli.dry_run {
+        print_instruction(name, &ix);
+        print_preview(&preview()?);
+        return Ok(());
+    }
+    let payer = ctx.signer()?;
+    if cli.serialize {
+        let blockhash = match cli.blockhash {
+            Some(h) => h,
+            None => ctx.rpc.get_latest_blockhash()?,
+        };
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&payer));
+        tx.message.recent_blockhash = blockhash;
+        println!("{}", BASE64.encode(bincode::serialize(&tx)?));
+        return Ok(());
+    }
+    let kp = ctx.keypair()?;
+    let blockhash = ctx.rpc.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&kp.pubkey()), &[&kp], blockhash);
+    let sig = ctx.rpc.send_and_confirm_transaction(&tx)?;
+    println!("{name}: {sig}");
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let config = if cli.config.exists() { Config::load(&cli.config)? } else { Config::default() };
+    let cluster = &config.cluster;
+    let commitment = CommitmentConfig::from_str(&cluster.commitment).map_err(|_| anyhow!("invalid commitment {}", cluster.commitment))?;
+    let ctx = Ctx {
+        cli_signer: cli.signer,
+        keypair_path: cli.keypair.clone().unwrap_or_else(|| cluster.keypair.clone()),
+        rpc: RpcClient::new_with_commitment(cli.url.clone().unwrap_or_else(|| cluster.rpc_url.clone()), commitment),
+        pdas: Pdas::new(cluster.program_id),
+        builder: Builder::new(cluster.program_id),
+    };
+    let pdas = ctx.pdas;
+    let state_addr = pdas.state();
+
+    match &cli.command {
+        Command::Pda { epoch, user, cover } => {
+            println!("program    {}", pdas.program_id);
+            println!("state      {state_addr}");
+            println!("oracle     {}", pdas.oracle());
+            println!("collateral {}", pdas.collateral());
+            println!("history    {}", pdas.epoch_history());
+            println!("cover book {}", pdas.cover_book());
+            if let Some(id) = epoch {
+                println!("epoch      {}", pdas.epoch(*id));
+                println!("bitmap     {}", pdas.bitmap(*id));
+            }
+            if let Some(u) = user {
+                println!("position   {}", pdas.position(u));
+                println!("referrer   {}", pdas.referrer(u));
+            }
+            if let (Some(id), Some(u)) = (epoch, user) {
+                println!("claim      {}", pdas.claim(*id, u));
+            }
+            if let Some(n) = cover {
+                println!("cover mint {}", pdas.cover_mint(*n));
+                println!("cover      {}", pdas.cover(&pdas.cover_mint(*n)));
+            }
+        }
+        Command::Show { account, epoch, user, cover } => {
+            let need_epoch = || epoch.ok_or_else(|| anyhow!("--epoch is required"));
+            let need_user = || user.ok_or_else(|| anyhow!("--user is required"));
+            let need_cover = || cover.ok_or_else(|| anyhow!("--cover is required"));
+            match account {
+                AccountKind::State => print_account(&state_addr, &ctx.require::<State>(&state_addr)?),
+                AccountKind::Oracle => print_account(&pdas.oracle(), &ctx.require::<OracleList>(&pdas.oracle())?),
+                AccountKind::Collateral => {
+                    print_account(&pdas.collateral(), &ctx.require::<CollateralRegistry>(&pdas.collateral())?)
+                }
+                AccountKind::Epoch => {
+                    let a = pdas.epoch(need_epoch()?);
+                    print_account(&a, &ctx.require::<Epoch>(&a)?)
+                }
+                AccountKind::Position => {
+                    let a = pdas.position(&need_user()?);
+                    print_account(&a, &ctx.require::<UserPosition>(&a)?)
+                }
+                AccountKind::Claim => {
+                    let a = pdas.claim(need_epoch()?, &need_user()?);
+                    print_account(&a, &ctx.require::<ClaimReceipt>(&a)?)
+                }
+                AccountKind::Bitmap => {
+                    let a = pdas.bitmap(need_epoch()?);
+                    print_account(&a, &ctx.require::<ClaimBitmap>(&a)?)
+                }
+                AccountKind::EpochHistory => {
+                    print_account(&pdas.epoch_history(), &ctx.require::<EpochHistory>(&pdas.epoch_history())?)
+                }
+                AccountKind::CoverBook => print_account(&pdas.cover_book(), &ctx.require::<CoverBook>(&pdas.cover_book())?),
+                AccountKind::Cover => {
+                    let a = pdas.cover(&pdas.cover_mint(need_cover()?));
+                    print_account(&a, &ctx.require::<Cover>(&a)?)
+                }
+            }
+        }
+        Command::Initialize => {
+            let init = config.initialize.as_ref().ok_or_else(|| anyhow!("config has no [initialize] table"))?;
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.initialize(&admin, &init.usdc_mint, &init.params);
+            submit(&cli, &ctx, "initialize", ix, || {
+                let existing = ctx.fetch::<State>(&state_addr)?;
+                Ok(preview::initialize(state_addr, existing.as_ref(), &admin, &init.usdc_mint, &init.params))
+            })?;
+        }
+        Command::InitEpochHistory => {
+            let admin = ctx.signer()?;
+            let state = ctx.require::<State>(&state_addr)?;
+            let current = match state.current_epoch_id {
+                0 => None,
+                id => ctx.fetch::<Epoch>(&pdas.epoch(id))?,
+            };
+            let ix = ctx.builder.init_epoch_history(&admin, current.as_ref().map(|e| e.epoch_id));
+            submit(&cli, &ctx, "init_epoch_history", ix, || {
+                let history_addr = pdas.epoch_history();
+                let existing = ctx.fetch::<EpochHistory>(&history_addr)?;
+                Ok(preview::init_epoch_history(&state, &admin, history_addr, existing.as_ref(), current.as_ref()))
+            })?;
+        }
+        Command::SetPaused { ops } => {
+            let flags = pause_flags(ops);
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.set_paused(&admin, flags);
+            submit(&cli, &ctx, "set_paused", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::set_paused(state_addr, &state, &admin, flags))
+            })?;
+        }
+        Command::SetGuardian { guardian } => {
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.set_guardian(&admin, guardian);
+            submit(&cli, &ctx, "set_guardian", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::set_guardian(state_addr, &state, &admin, guardian))
+            })?;
+        }
+        Command::GuardianPause { ops } => {
+            let flags = pause_flags(ops);
+            let authority = ctx.signer()?;
+            let ix = ctx.builder.guardian_pause(&authority, flags);
+            submit(&cli, &ctx, "guardian_pause", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::guardian_pause(state_addr, &state, &authority, flags))
+            })?;
+        }
+        Command::SetOutflowLimits { window_secs, cap_fp, cap_bps } => {
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.set_outflow_limits(&admin, *window_secs, *cap_fp, *cap_bps);
+            submit(&cli, &ctx, "set_outflow_limits", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::set_outflow_limits(state_addr, &state, &admin, *window_secs, *cap_fp, *cap_bps))
+            })?;
+        }
+        Command::SetCrankReward { reward_u64 } => {
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.set_crank_reward(&admin, *reward_u64);
+            submit(&cli, &ctx, "set_crank_reward", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::set_crank_reward(state_addr, &state, &admin, *reward_u64))
+            })?;
+        }
+        Command::SetRollingMode { duration_secs } => {
+            let admin = ctx.signer()?;
+            let (enabled, duration_secs) = (duration_secs.is_some(), duration_secs.unwrap_or(0));
+            let ix = ctx.builder.set_rolling_mode(&admin, enabled, duration_secs);
+            submit(&cli, &ctx, "set_rolling_mode", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::set_rolling_mode(state_addr, &state, &admin, enabled, duration_secs))
+            })?;
+        }
+        Command::InitCoverBook => {
+            let cover = config.cover.as_ref().ok_or_else(|| anyhow!("config has no [cover] table"))?;
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.init_cover_book(&admin, cover);
+            submit(&cli, &ctx, "init_cover_book", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                let book_addr = pdas.cover_book();
+                let existing = ctx.fetch::<CoverBook>(&book_addr)?;
+                Ok(preview::init_cover_book(&state, &admin, book_addr, existing.as_ref(), cover))
+            })?;
+        }
+        Command::SetCoverParams => {
+            let cover = config.cover.as_ref().ok_or_else(|| anyhow!("config has no [cover] table"))?;
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.set_cover_params(&admin, cover);
+            submit(&cli, &ctx, "set_cover_params", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                let book_addr = pdas.cover_book();
+                let book = ctx.require::<CoverBook>(&book_addr)?;
+                print_account(&book_addr, &book);
+                Ok(preview::set_cover_params(&state, &admin, book_addr, &book, cover))
+            })?;
+        }
+        Command::Shutdown { epoch_id } => {
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.shutdown(&admin, *epoch_id);
+            submit(&cli, &ctx, "shutdown", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                let history_addr = pdas.epoch_history();
+                let open = match epoch_id {
+                    Some(id) => Some((ctx.require::<Epoch>(&pdas.epoch(*id))?, ctx.require::<EpochHistory>(&history_addr)?)),
+                    None => None,
+                };
+                let open = open.as_ref().zip(*epoch_id).map(|((e, h), id)| (pdas.epoch(id), e, history_addr, h));
+                Ok(preview::shutdown(state_addr, &state, &admin, open, ctx.now()?))
+            })?;
+        }
+        Command::SetPolicy { payout_policy, epoch_cap_fp } => {
+            let policy = config.policy.as_ref();
+            let payout_policy = payout_policy
+                .or(policy.map(|p| p.payout_policy))
+                .ok_or_else(|| anyhow!("--payout-policy or [policy] is required"))?;
+            let epoch_cap_fp = epoch_cap_fp.or(policy.and_then(|p| p.epoch_cap_fp));
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.set_policy(&admin, payout_policy, epoch_cap_fp);
+            submit(&cli, &ctx, "set_policy", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::set_policy(state_addr, &state, &admin, payout_policy, epoch_cap_fp))
+            })?;
+        }
+        Command::SetCurveAndWeights => {
+            let curve = config.curve.as_ref().ok_or_else(|| anyhow!("config has no [curve] table"))?;
+            let admin = ctx.signer()?;
+            let ix = ctx.builder.set_curve_and_weights(&admin, curve);
+            submit(&cli, &ctx, "set_curve_and_weights", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                print_account(&state_addr, &state);
+                Ok(preview::set_curve_and_weights(state_addr, &state, &admin, curve))
+            })?;
+        }
+        Command::StartEpoch { epoch_id, start_ts, end_ts, duration_secs } => {
+            let state = ctx.require::<State>(&state_addr)?;
+            let epoch_id = epoch_id.unwrap_or(state.current_epoch_id.saturating_add(1));
+            let (start_ts, end_ts) = if state.rolling_mode {
+                if start_ts.is_some() || end_ts.is_some() || duration_secs.is_some() {
+                    bail!("rolling mode sets the epoch window; drop --start-ts / --end-ts / --duration-secs");
+                }
+                (0, 0)
+            } else {
+                let end_ts = match (end_ts, duration_secs) {
+                    (Some(t), _) => *t,
+                    (None, Some(d)) => {
+                        let from = match start_ts {
+                            Some(t) => *t,
+                            None => ctx.now()?,
+                        };
+                        from.checked_add(*d).ok_or_else(|| anyhow!("end_ts overflows"))?
+                    }
+                    (None, None) => 0,
+                };
+                (start_ts.unwrap_or(0), end_ts)
+            };
+            let payer = ctx.signer()?;
+            let epoch_addr = pdas.epoch(epoch_id);
+            let previous_addr = pdas.epoch(state.current_epoch_id);
+            let previous = match state.current_epoch_id {
+                0 => None,
+                _ => ctx.fetch::<Epoch>(&previous_addr)?,
+            };
+            let previous_id = previous.as_ref().map(|_| state.current_epoch_id);
+            let ix = ctx.builder.start_epoch(&payer, epoch_id, start_ts, end_ts, previous_id);
+            submit(&cli, &ctx, "start_epoch", ix, || {
+                let existing = ctx.fetch::<Epoch>(&epoch_addr)?;
+                let history_addr = pdas.epoch_history();
+                let history = ctx.require::<EpochHistory>(&history_addr)?;
+                print_account(&history_addr, &history);
+                Ok(preview::start_epoch(
+                    state_addr,
+                    &state,
+                    history_addr,
+                    &history,
+                    epoch_addr,
+                    existing.as_ref(),
+                    previous.as_ref(),
+                    &payer,
+                    epoch_id,
+                    start_ts,
+                    end_ts,
+                    ctx.now()?,
+                ))
+            })?;
+        }
+        Command::TriggerEvent { epoch_id, severity_bps, user_cap_bps, epoch_cap_fp, evidence_hash, evidence_ts } => {
+            let signer = ctx.signer()?;
+            let state = ctx.require::<State>(&state_addr)?;
+            let epoch_addr = pdas.epoch(*epoch_id);
+            let args = TriggerArgs {
+                severity_input_bps: *severity_bps,
+                user_cap_bps: *user_cap_bps,
+                epoch_cap_fp_override: *epoch_cap_fp,
+                evidence_hash: *evidence_hash,
+                evidence_ts: *evidence_ts,
+            };
+            let remaining = config.strategy.as_ref().map(|s| (s.program, s.metas()));
+            if state.strategy_deployed_fp > 0 && remaining.is_none() {
+                bail!("strategy capital is deployed: add a [strategy] table so it can be recalled");
+            }
+            let strategy = remaining.as_ref().map(|(p, m)| (p, m.as_slice()));
+            let ix = ctx.builder.trigger_event(&signer, *epoch_id, &pdas.vault(&state.usdc_mint), strategy, &args);
+            submit(&cli, &ctx, "trigger_event", ix, || {
+                let epoch = ctx.require::<Epoch>(&epoch_addr)?;
+                let oracles = ctx.require::<OracleList>(&pdas.oracle())?;
+                print_account(&state_addr, &state);
+                print_account(&epoch_addr, &epoch);
+                let allowed = signer == state.admin || oracles.keys[..oracles.count as usize].contains(&signer);
+                if state.strategy_deployed_fp > 0 {
+                    println!("note: deployed strategy capital is recalled first; its effect is not predicted");
+                }
+                Ok(preview::trigger_event(state_addr, &state, epoch_addr, &epoch, oracles.enabled, allowed, &args, ctx.now()?))
+            })?;
+        }
+        Command::EndEpoch { epoch_id } => {
+            let admin = ctx.signer()?;
+            let state = ctx.require::<State>(&state_addr)?;
+            let epoch_addr = pdas.epoch(*epoch_id);
+            let ix = ctx.builder.end_epoch(&admin, *epoch_id);
+            submit(&cli, &ctx, "end_epoch", ix, || {
+                let epoch = ctx.require::<Epoch>(&epoch_addr)?;
+                let history_addr = pdas.epoch_history();
+                let history = ctx.require::<EpochHistory>(&history_addr)?;
+                print_account(&epoch_addr, &epoch);
+                Ok(preview::end_epoch(&state, &admin, epoch_addr, &epoch, history_addr, &history, ctx.now()?))
+            })?;
+        }
+        Command::FinalizeEpoch { epoch_id, sweep_dust, asset_mint, treasury_ata } => {
+            let admin = ctx.signer()?;
+            let state = ctx.require::<State>(&state_addr)?;
+            let epoch_addr = pdas.epoch(*epoch_id);
+            let mint = asset_mint.unwrap_or(state.usdc_mint);
+            let treasury = treasury_ata.unwrap_or_else(|| associated_token_address(&state.protocol_treasury, &mint));
+            let ix = ctx.builder.finalize_epoch(&admin, *epoch_id, &mint, &treasury, *sweep_dust);
+            submit(&cli, &ctx, "finalize_epoch", ix, || {
+                let epoch = ctx.require::<Epoch>(&epoch_addr)?;
+                let history_addr = pdas.epoch_history();
+                let history = ctx.require::<EpochHistory>(&history_addr)?;
+                print_account(&state_addr, &state);
+                print_account(&epoch_addr, &epoch);
+                if sweep_dust.is_some_and(|d| d > 0) {
+                    println!("note: dust sweep moves tokens; its effect is not predicted");
+                }
+                let now = ctx.now()?;
+                Ok(preview::finalize_epoch(state_addr, &state, &admin, epoch_addr, &epoch, history_addr, &history, *sweep_dust, now))
+            })?;
+        }
+        Command::CrankPayouts { epoch_id, owners, cranker_ata } => {
+            let cranker = ctx.signer()?;
+            let state = ctx.require::<State>(&state_addr)?;
+            let epoch_addr = pdas.epoch(*epoch_id);
+            let reward_ata = cranker_ata.unwrap_or_else(|| associated_token_address(&cranker, &state.usdc_mint));
+            let ix = ctx.builder.crank_payouts(&cranker, *epoch_id, &state.usdc_mint, &reward_ata, owners);
+            submit(&cli, &ctx, "crank_payouts", ix, || {
+                let epoch = ctx.require::<Epoch>(&epoch_addr)?;
+                print_account(&state_addr, &state);
+                print_account(&epoch_addr, &epoch);
+                println!("note: payouts, receipts and the crank reward depend on balances; they are not predicted");
+                Ok(preview::crank_payouts(&state, &epoch, owners.len()))
+            })?;
+        }
+        Command::MerkleBuild { epoch_id, out } => {
+            let state = ctx.require::<State>(&state_addr)?;
+            let epoch = ctx.require::<Epoch>(&pdas.epoch(*epoch_id))?;
+            let collateral = ctx.require::<CollateralRegistry>(&pdas.collateral())?;
+            if !epoch.triggered || epoch.closed {
+                bail!("epoch {epoch_id} is not triggered and open");
+            }
+            let positions: Vec<UserPosition> = ctx
+                .rpc
+                .get_program_accounts(&pdas.program_id)?
+                .into_iter()
+                .filter_map(|(_, a)| UserPosition::decode(&a.data).ok())
+                .collect();
+            let pool_value_fp = merkle::pool_value_fp(&collateral).ok_or_else(|| anyhow!("pool value overflows"))?;
+            let owed = merkle::entitlements(&state, &epoch, pool_value_fp, &positions)
+                .ok_or_else(|| anyhow!("cannot price epoch {epoch_id}"))?;
+            let dist = Distribution::new(*epoch_id, &owed)
+                .ok_or_else(|| anyhow!("{} claimants: expected 1..={}", owed.len(), merkle::MAX_MERKLE_CLAIMANTS))?;
+            fs::write(out, serde_json::to_string_pretty(&dist)?).with_context(|| format!("{}", out.display()))?;
+            let root: String = dist.root.iter().map(|b| format!("{b:02x}")).collect();
+            println!("root      {root}");
+            println!("total_fp  {}", dist.total_fp);
+            println!("claimants {} (of {} positions)", dist.claims.len(), positions.len());
+        }
+        Command::PostMerkleRoot { distribution } => {
+            let dist = load_distribution(distribution)?;
+            let admin = ctx.signer()?;
+            let epoch_addr = pdas.epoch(dist.epoch_id);
+            let bitmap_addr = pdas.bitmap(dist.epoch_id);
+            let claimants = dist.claims.len() as u32;
+            let ix = ctx.builder.post_merkle_root(&admin, dist.epoch_id, dist.root, dist.total_fp, claimants);
+            submit(&cli, &ctx, "post_merkle_root", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                let epoch = ctx.require::<Epoch>(&epoch_addr)?;
+                let collateral = ctx.require::<CollateralRegistry>(&pdas.collateral())?;
+                let bitmap = ctx.fetch::<ClaimBitmap>(&bitmap_addr)?;
+                print_account(&epoch_addr, &epoch);
+                let pool_value_fp = merkle::pool_value_fp(&collateral).ok_or_else(|| anyhow!("pool value overflows"))?;
+                Ok(preview::post_merkle_root(
+                    &state,
+                    &admin,
+                    epoch_addr,
+                    &epoch,
+                    bitmap_addr,
+                    bitmap.as_ref(),
+                    pool_value_fp,
+                    dist.root,
+                    dist.total_fp,
+                    claimants,
+                ))
+            })?;
+        }
+        Command::ClaimWithProof { distribution, asset_mint } => {
+            let dist = load_distribution(distribution)?;
+            let user = ctx.signer()?;
+            let claim = dist.claim(&user).ok_or_else(|| anyhow!("{user} has no claim in the distribution"))?;
+            let state = ctx.require::<State>(&state_addr)?;
+            let mint = asset_mint.unwrap_or(state.usdc_mint);
+            let epoch_addr = pdas.epoch(dist.epoch_id);
+            let bitmap_addr = pdas.bitmap(dist.epoch_id);
+            let ix = ctx.builder.claim_with_proof(&user, dist.epoch_id, &mint, claim.index, claim.amount_fp, &claim.proof);
+            submit(&cli, &ctx, "claim_with_proof", ix, || {
+                let epoch = ctx.require::<Epoch>(&epoch_addr)?;
+                let bitmap = ctx.require::<ClaimBitmap>(&bitmap_addr)?;
+                print_account(&epoch_addr, &epoch);
+                println!("note: the payout transfer and the outflow breaker are not predicted");
+                Ok(preview::claim_with_proof(&state, epoch_addr, &epoch, bitmap_addr, &bitmap, claim))
+            })?;
+        }
+        Command::CloseEpoch { epoch_id } => {
+            let admin = ctx.signer()?;
+            let epoch_addr = pdas.epoch(*epoch_id);
+            let bitmap_addr = pdas.bitmap(*epoch_id);
+            let bitmap = ctx.fetch::<ClaimBitmap>(&bitmap_addr)?.map(|_| bitmap_addr);
+            let ix = ctx.builder.close_epoch(&admin, *epoch_id, bitmap.is_some());
+            submit(&cli, &ctx, "close_epoch", ix, || {
+                let state = ctx.require::<State>(&state_addr)?;
+                let epoch = ctx.require::<Epoch>(&epoch_addr)?;
+                print_account(&epoch_addr, &epoch);
+                Ok(preview::close_epoch(&state, &admin, epoch_addr, &epoch, bitmap))
+            })?;
+        }
+        Command::MigrateState { epochs } => {
+            let admin = ctx.signer()?;
+            let state = ctx.require::<State>(&state_addr)?;
+            let ix = ctx.builder.migrate_state(&admin, &state.usdc_mint, epochs);
+            submit(&cli, &ctx, "migrate_state", ix, || {
+                let oracle = ctx.require::<OracleList>(&pdas.oracle())?;
+                let collateral = ctx.fetch::<CollateralRegistry>(&pdas.collateral())?;
+                let vault_addr = pdas.vault(&state.usdc_mint);
+                // SPL layouts: mint decimals at byte 44, token account amount at 64..72
+                let decimals = *ctx.data(&state.usdc_mint)?.get(44).ok_or_else(|| anyhow!("bad mint account"))?;
+                let vault = ctx.data(&vault_addr)?;
+                let amount = u64::from_le_bytes(vault.get(64..72).ok_or_else(|| anyhow!("bad vault account"))?.try_into()?);
+                let epochs = epochs
+                    .iter()
+                    .map(|&id| Ok((pdas.epoch(id), ctx.require::<Epoch>(&pdas.epoch(id))?)))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(preview::migrate_state(
+                    (state_addr, &state),
+                    (pdas.oracle(), &oracle, pdas.oracle_bump()),
+                    (pdas.collateral(), collateral.as_ref()),
+                    (vault_addr, decimals, amount),
+                    &epochs,
+                    &admin,
+                    ctx.now()?,
+                ))
+            })?;
+        }
+        Command::MigratePosition { owner, claims } => {
+            let payer = ctx.signer()?;
+            let ix = ctx.builder.migrate_position(&payer, owner, claims);
+            submit(&cli, &ctx, "migrate_position", ix, || {
+                let position_addr = pdas.position(owner);
+                let position = ctx.require::<UserPosition>(&position_addr)?;
+                let state = ctx.require::<State>(&state_addr)?;
+                let claims = claims
+                    .iter()
+                    .map(|&id| {
+                        let (claim_addr, epoch_addr) = (pdas.claim(id, owner), pdas.epoch(id));
+                        Ok((claim_addr, ctx.require::<ClaimReceipt>(&claim_addr)?, epoch_addr, ctx.require::<Epoch>(&epoch_addr)?))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(preview::migrate_position(
+                    (position_addr, &position),
+                    owner,
+                    pdas.position_bump(owner),
+                    (state_addr, &state),
+                    &claims,
+                ))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("error: {e:#}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,206 @@
+//! Merkle distributions for epochs settled with `post_merkle_root`.
+//!
+//! [`entitlements`] prices every position the way `payout_user` would, all at
+//! the same pool value; [`Distribution`] hashes them into a tree with
+//! `tail_risk_core::merkle` (sha256) and carries each holder's proof for
+//! `claim_with_proof`. It is saved as JSON for the admin and the holders.
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use solana_sdk::{hash::hashv, pubkey::Pubkey};
+use tail_risk_core::{
+    math::apply_haircut,
+    merkle::{self, Hash},
+    payout::{epoch_liability, user_payout_fp, weighted_stake_fp},
+    PayoutPolicy,
+};
+
+use crate::{
+    accounts::{CollateralRegistry, Epoch, State, UserPosition},
+    config::{de_pubkey, de_u128},
+};
+
+/// `MAX_MERKLE_CLAIMANTS` in the program.
+pub const MAX_MERKLE_CLAIMANTS: usize = 65_536;
+
+fn sha256v(parts: &[&[u8]]) -> Hash {
+    hashv(parts).to_bytes()
+}
+
+/// Haircut value across the registry's vaults (`pool_value_fp` in the program).
+pub fn pool_value_fp(reg: &CollateralRegistry) -> Option<u128> {
+    reg.assets[..reg.count as usize]
+        .iter()
+        .try_fold(0u128, |acc, a| acc.checked_add(apply_haircut(a.balance_fp, a.haircut_bps)?))
+}
+
+/// What each position is owed from a triggered `epoch` at `pool_value_fp`: its pro-rata
+/// share of the liability under the pool's policy, as `payout_user` computes it for the
+/// first claimant, on the stake the epoch covers. The shares add up to at most the liability. Positions owed nothing are
+/// left out; the rest are sorted by owner.
+pub fn entitlements(state: &State, epoch: &Epoch, pool_value_fp: u128, positions: &[UserPosition]) -> Option<Vec<(Pubkey, u128)>> {
+    let policy = PayoutPolicy::from_u8(state.payout_policy)?;
+    let liability = epoch_liability(epoch.total_stake_snapshot_fp, epoch.severity_bps, policy, epoch.epoch_cap_fp, pool_value_fp)?;
+    let mut out = Vec::new();
+    for p in positions {
+        // Principal deposited during a rolling epoch is not covered by it
+        let (senior_pending_fp, junior_pending_fp) =
+            if p.pending_epoch_id == epoch.epoch_id { (p.pending_senior_fp, p.pending_junior_fp) } else { (0, 0) };
+        let stake_fp = weighted_stake_fp(
+            p.senior_deposited_fp.saturating_sub(senior_pending_fp),
+            p.junior_deposited_fp.saturating_sub(junior_pending_fp),
+            state.tranche_weight_senior_bps as u128,
+            state.tranche_weight_junior_bps as u128,
+        )?;
+        let owed_fp = user_payout_fp(liability.max_fp, stake_fp, epoch.total_stake_snapshot_fp, policy, epoch.user_cap_bps)?;
+        if owed_fp > 0 {
+            out.push((p.owner, owed_fp));
+        }
+    }
+    out.sort();
+    Some(out)
+}
+
+/// A tree over leaf hashes; an odd node out is carried up a level unchanged.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    /// Tree over `leaves` (at least one).
+    pub fn new(leaves: Vec<Hash>) -> Self {
+        assert!(!leaves.is_empty(), "a Merkle tree needs at least one leaf");
+        let mut levels = vec![leaves];
+        while let Some(level) = levels.last().filter(|l| l.len() > 1) {
+            let up = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => merkle::node(a, b, sha256v),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(up);
+        }
+        Self { levels }
+    }
+
+    /// Root hash.
+    pub fn root(&self) -> Hash {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Sibling hashes from leaf `index` up to the root.
+    pub fn proof(&self, mut index: usize) -> Vec<Hash> {
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+/// One holder's leaf and proof.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Claim {
+    /// Leaf index (bit in the claim bitmap).
+    pub index: u32,
+    /// Holder.
+    #[serde(serialize_with = "ser_display", deserialize_with = "de_pubkey")]
+    pub owner: Pubkey,
+    /// Amount owed (fixed-point).
+    #[serde(serialize_with = "ser_display", deserialize_with = "de_u128")]
+    pub amount_fp: u128,
+    /// Sibling hashes, hex.
+    #[serde(serialize_with = "ser_hashes", deserialize_with = "de_hashes")]
+    pub proof: Vec<Hash>,
+}
+
+/// A posted (or to-be-posted) distribution for one epoch.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Distribution {
+    /// Epoch it settles.
+    pub epoch_id: u64,
+    /// Tree root, hex.
+    #[serde(serialize_with = "ser_hash", deserialize_with = "de_hash")]
+    pub root: Hash,
+    /// Sum of the claims (fixed-point).
+    #[serde(serialize_with = "ser_display", deserialize_with = "de_u128")]
+    pub total_fp: u128,
+    /// Claims in leaf order.
+    pub claims: Vec<Claim>,
+}
+
+impl Distribution {
+    /// Distribution of `owed` (`(owner, amount_fp)`, indexed in order); `None` when empty
+    /// or larger than the program accepts.
+    pub fn new(epoch_id: u64, owed: &[(Pubkey, u128)]) -> Option<Self> {
+        if owed.is_empty() || owed.len() > MAX_MERKLE_CLAIMANTS {
+            return None;
+        }
+        let leaves = owed
+            .iter()
+            .enumerate()
+            .map(|(i, (owner, amount_fp))| merkle::leaf(i as u32, &owner.to_bytes(), *amount_fp, sha256v))
+            .collect();
+        let tree = MerkleTree::new(leaves);
+        let claims = owed
+            .iter()
+            .enumerate()
+            .map(|(i, (owner, amount_fp))| Claim { index: i as u32, owner: *owner, amount_fp: *amount_fp, proof: tree.proof(i) })
+            .collect();
+        Some(Self { epoch_id, root: tree.root(), total_fp: owed.iter().map(|(_, a)| a).sum(), claims })
+    }
+
+    /// `owner`'s claim, if any.
+    pub fn claim(&self, owner: &Pubkey) -> Option<&Claim> {
+        self.claims.iter().find(|c| c.owner == *owner)
+    }
+
+    /// Whether `claim`'s proof leads to the root.
+    pub fn verify(&self, claim: &Claim) -> bool {
+        verify_claim(&self.root, claim)
+    }
+}
+
+/// Whether `claim`'s proof leads to `root` (the check `claim_with_proof` makes).
+pub fn verify_claim(root: &Hash, claim: &Claim) -> bool {
+    let leaf = merkle::leaf(claim.index, &claim.owner.to_bytes(), claim.amount_fp, sha256v);
+    merkle::verify(leaf, &claim.proof, root, sha256v)
+}
+
+fn ser_display<T: std::fmt::Display, S: Serializer>(v: &T, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(v)
+}
+
+fn to_hex(h: &Hash) -> String {
+    h.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(s: &str) -> Result<Hash, String> {
+    let bytes = (0..s.len())
+        .step_by(2)
+        .map(|i| s.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format!("invalid hex {s}"))?;
+    Hash::try_from(bytes).map_err(|_| format!("expected 32 bytes of hex, got {s}"))
+}
+
+fn ser_hash<S: Serializer>(h: &Hash, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&to_hex(h))
+}
+
+fn de_hash<'de, D: Deserializer<'de>>(d: D) -> Result<Hash, D::Error> {
+    from_hex(&String::deserialize(d)?).map_err(D::Error::custom)
+}
+
+fn ser_hashes<S: Serializer>(hs: &[Hash], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(hs.iter().map(to_hex))
+}
+
+fn de_hashes<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Hash>, D::Error> {
+    Vec::<String>::deserialize(d)?.iter().map(|h| from_hex(h).map_err(D::Error::custom)).collect()
+}
@@ -0,0 +1,122 @@
+//! PDA derivation (seeds as declared in the program's `#[derive(Accounts)]` structs).
+
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+/// `declare_id!` of the program.
+pub const PROGRAM_ID: Pubkey = pubkey!("9XjXYmL9TLB3FuszEuXCTkjC6a4vHZ5TPWczyNMLKHRg");
+/// SPL Token program.
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// SPL Associated Token Account program.
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// PDAs of one deployment of the program.
+#[derive(Clone, Copy, Debug)]
+pub struct Pdas {
+    /// Program id the seeds are derived under.
+    pub program_id: Pubkey,
+}
+
+impl Pdas {
+    /// PDAs under `program_id`.
+    pub fn new(program_id: Pubkey) -> Self {
+        Self { program_id }
+    }
+
+    fn find(&self, seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &self.program_id).0
+    }
+
+    /// `[b"state", program_id]`.
+    pub fn state(&self) -> Pubkey {
+        self.find(&[b"state", self.program_id.as_ref()])
+    }
+
+    /// `[b"oracle", program_id]`.
+    pub fn oracle(&self) -> Pubkey {
+        self.find(&[b"oracle", self.program_id.as_ref()])
+    }
+
+    /// Bump of [`oracle`](Self::oracle).
+    pub fn oracle_bump(&self) -> u8 {
+        Pubkey::find_program_address(&[b"oracle", self.program_id.as_ref()], &self.program_id).1
+    }
+
+    /// `[b"collateral", program_id]`.
+    pub fn collateral(&self) -> Pubkey {
+        self.find(&[b"collateral", self.program_id.as_ref()])
+    }
+
+    /// `[b"epoch_history", program_id]`.
+    pub fn epoch_history(&self) -> Pubkey {
+        self.find(&[b"epoch_history", self.program_id.as_ref()])
+    }
+
+    /// `[b"cover_book", program_id]`.
+    pub fn cover_book(&self) -> Pubkey {
+        self.find(&[b"cover_book", self.program_id.as_ref()])
+    }
+
+    /// `[b"epoch", epoch_id.to_le_bytes()]`.
+    pub fn epoch(&self, epoch_id: u64) -> Pubkey {
+        self.find(&[b"epoch", &epoch_id.to_le_bytes()])
+    }
+
+    /// `[b"bitmap", epoch_id.to_le_bytes()]`.
+    pub fn bitmap(&self, epoch_id: u64) -> Pubkey {
+        self.find(&[b"bitmap", &epoch_id.to_le_bytes()])
+    }
+
+    /// `[b"position", user]`.
+    pub fn position(&self, user: &Pubkey) -> Pubkey {
+        self.find(&[b"position", user.as_ref()])
+    }
+
+    /// Bump of [`position`](Self::position).
+    pub fn position_bump(&self, user: &Pubkey) -> u8 {
+        Pubkey::find_program_address(&[b"position", user.as_ref()], &self.program_id).1
+    }
+
+    /// `[b"claim", epoch_id.to_le_bytes(), user]`.
+    pub fn claim(&self, epoch_id: u64, user: &Pubkey) -> Pubkey {
+        self.find(&[b"claim", &epoch_id.to_le_bytes(), user.as_ref()])
+    }
+
+    /// `[b"cover_mint", id.to_le_bytes()]`: NFT of the cover book's `id`-th cover.
+    pub fn cover_mint(&self, id: u64) -> Pubkey {
+        self.find(&[b"cover_mint", &id.to_le_bytes()])
+    }
+
+    /// `[b"cover", mint]`.
+    pub fn cover(&self, mint: &Pubkey) -> Pubkey {
+        self.find(&[b"cover", mint.as_ref()])
+    }
+
+    /// `[b"referrer", owner]`.
+    pub fn referrer(&self, owner: &Pubkey) -> Pubkey {
+        self.find(&[b"referrer", owner.as_ref()])
+    }
+
+    /// `[b"strategy", program_id]`: signer of strategy CPIs.
+    pub fn strategy_authority(&self) -> Pubkey {
+        self.find(&[b"strategy", self.program_id.as_ref()])
+    }
+
+    /// `[b"strategy_escrow", program_id]`: token account between the vault and the strategy.
+    pub fn strategy_escrow(&self) -> Pubkey {
+        self.find(&[b"strategy_escrow", self.program_id.as_ref()])
+    }
+
+    /// Pool vault for `mint` (ATA owned by the state PDA).
+    pub fn vault(&self, mint: &Pubkey) -> Pubkey {
+        associated_token_address(&self.state(), mint)
+    }
+}
+
+/// Associated token account of `owner` for `mint`.
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
@@ -0,0 +1,813 @@
+//! Dry-run previews: the account changes an instruction is expected to make.
+//!
+//! Each function mirrors the corresponding instruction handler and its
+//! `require!` checks, starting from the decoded on-chain accounts. Token
+//! movements (dust sweep, strategy recall) are not predicted.
+
+use solana_sdk::pubkey::Pubkey;
+use tail_risk_core::{cover, math, merkle, payout, severity, yields, PayoutPolicy};
+
+use crate::{
+    accounts::{
+        ClaimBitmap, ClaimReceipt, CollateralAsset, CollateralRegistry, CoverBook, Epoch, EpochHistory, EpochRecord, Lots,
+        OracleList, ProgramAccount, ReferralTier, State, UserPosition, ACCOUNT_VERSION, EPOCH_HISTORY_LEN, MAX_COLLATERALS,
+        MAX_LOTS, MAX_REFERRAL_TIERS, PAUSE_ALL, PAUSE_CLAIMS, PAUSE_FEE_SWEEPS, PAUSE_TRIGGERS,
+    },
+    ix::{CoverParams, CurveParams, InitializeParams, TriggerArgs},
+    merkle::{verify_claim, Claim, MAX_MERKLE_CLAIMANTS},
+};
+
+/// `MAX_CRANK_REWARD_U64` in the program.
+const MAX_CRANK_REWARD_U64: u64 = 10_000_000;
+
+/// `COVER_CLAIM_WINDOW_SECS` in the program.
+const COVER_CLAIM_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// One changed field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// Field name.
+    pub field: &'static str,
+    /// Value before (`None` = account does not exist yet).
+    pub before: Option<String>,
+    /// Value after.
+    pub after: String,
+}
+
+/// Expected effect on one account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountDiff {
+    /// Account type.
+    pub name: &'static str,
+    /// Address.
+    pub address: Pubkey,
+    /// Changed fields, in declaration order.
+    pub changes: Vec<Change>,
+    /// The instruction closes the account.
+    pub closed: bool,
+}
+
+/// Preview result: the diffs, or the program error the instruction would fail with.
+pub type Preview = Result<Vec<AccountDiff>, &'static str>;
+
+/// Field-level diff between two versions of an account (`reserved` bytes are skipped).
+pub fn diff<T: ProgramAccount>(address: Pubkey, before: Option<&T>, after: &T) -> AccountDiff {
+    let old = before.map(ProgramAccount::fields);
+    let changes = after
+        .fields()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (field, _))| *field != "reserved")
+        .filter_map(|(i, (field, value))| {
+            let prev = old.as_ref().map(|o| o[i].1.clone());
+            (prev.as_deref() != Some(value.as_str())).then_some(Change { field, before: prev, after: value })
+        })
+        .collect();
+    AccountDiff { name: T::NAME, address, changes, closed: false }
+}
+
+/// An account the instruction closes.
+pub fn closed<T: ProgramAccount>(address: Pubkey) -> AccountDiff {
+    AccountDiff { name: T::NAME, address, changes: Vec::new(), closed: true }
+}
+
+/// `assert_param_bounds` in the program.
+pub fn check_param_bounds(s: &State) -> Result<(), &'static str> {
+    let ok = s.protocol_fee_bps <= 1_000
+        && s.referral_fee_bps <= 1_000
+        && s.fee_model <= 2
+        && s.performance_fee_bps <= 5_000
+        && s.tranche_weight_senior_bps <= 10_000
+        && s.tranche_weight_junior_bps <= 10_000
+        && s.strategy_max_bps <= 10_000
+        && s.strategy_junior_yield_bps <= 10_000
+        && s.outflow_window_secs >= 0
+        && s.outflow_cap_bps <= 10_000
+        && s.crank_reward_u64 <= MAX_CRANK_REWARD_U64;
+    let mut last_volume = 0u128;
+    for t in s.referral_tiers.iter().filter(|t| t.min_volume_fp > 0) {
+        if t.min_volume_fp <= last_volume || t.fee_bps > 1_000 {
+            return Err("ParamOutOfBounds");
+        }
+        last_volume = t.min_volume_fp;
+    }
+    if ok {
+        Ok(())
+    } else {
+        Err("ParamOutOfBounds")
+    }
+}
+
+fn require(cond: bool, err: &'static str) -> Result<(), &'static str> {
+    if cond {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+/// Close `e`'s claim window at `now` (as `finalize_epoch` / `shutdown`).
+fn settle_epoch(s: &mut State, e: &mut Epoch, h: &mut EpochHistory, now: i64) {
+    s.carryover_shortfall_fp = s.carryover_shortfall_fp.saturating_add(e.shortfall_fp);
+    s.claim_window_open = false;
+    s.cover_owed_fp = 0;
+    e.closed = true;
+    e.finalized_ts = now;
+    if e.epoch_id == s.current_epoch_id {
+        h.last_end_ts = coverage_end_ts(e);
+    }
+    h.records[h.count as usize % EPOCH_HISTORY_LEN] = EpochRecord {
+        epoch_id: e.epoch_id,
+        severity_bps: e.severity_bps,
+        total_payout_fp: e.total_payout_fp,
+        shortfall_fp: e.shortfall_fp,
+    };
+    h.count = h.count.saturating_add(1);
+    h.last_epoch_id = e.epoch_id;
+}
+
+/// `claim_base_fp` in the program: the stake snapshot and cover sold on `e`.
+fn claim_base_fp(e: &Epoch) -> u128 {
+    e.total_stake_snapshot_fp.saturating_add(e.cover_sold_fp)
+}
+
+/// `coverage_end_ts` in the program: when `e` stops covering.
+fn coverage_end_ts(e: &Epoch) -> i64 {
+    let end_ts = if e.end_ts == 0 { i64::MAX } else { e.end_ts };
+    if e.closed {
+        end_ts.min(e.finalized_ts)
+    } else {
+        end_ts
+    }
+}
+
+fn admin_update(state_addr: Pubkey, state: &State, admin: &Pubkey, f: impl FnOnce(&mut State)) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    let mut after = state.clone();
+    f(&mut after);
+    check_param_bounds(&after)?;
+    Ok(vec![diff(state_addr, Some(state), &after)])
+}
+
+/// `initialize`: the new `State` (oracle list, registry and vault are created alongside).
+pub fn initialize(state_addr: Pubkey, existing: Option<&State>, admin: &Pubkey, usdc_mint: &Pubkey, p: &InitializeParams) -> Preview {
+    require(existing.is_none(), "AccountAlreadyInitialized")?;
+    let state = State {
+        admin: *admin,
+        usdc_mint: *usdc_mint,
+        protocol_treasury: p.protocol_treasury,
+        claim_window_open: false,
+        processing: false,
+        payout_policy: p.payout_policy,
+        user_deposit_cap_fp: p.user_deposit_cap_fp,
+        min_deposit_fp: p.min_deposit_fp,
+        protocol_fee_bps: p.protocol_fee_bps,
+        referral_fee_bps: p.referral_fee_bps,
+        lockup_secs: p.lockup_secs,
+        min_seconds_between_deposits: p.min_seconds_between_deposits,
+        fee_model: p.fee_model,
+        performance_fee_bps: p.performance_fee_bps,
+        protocol_fees_accrued_fp: 0,
+        referral_tiers: [ReferralTier::default(); MAX_REFERRAL_TIERS],
+        referral_rewards_owed_fp: 0,
+        epoch_cap_fp: p.epoch_cap_fp,
+        rolling_mode: p.rolling_mode,
+        max_stale_secs: p.max_stale_secs,
+        sev_quad_a_fp: p.sev_quad_a_fp,
+        sev_quad_b_fp: p.sev_quad_b_fp,
+        sev_quad_c_fp: p.sev_quad_c_fp,
+        severity_floor_bps: p.severity_floor_bps,
+        tranche_weight_senior_bps: p.tranche_weight_senior_bps,
+        tranche_weight_junior_bps: p.tranche_weight_junior_bps,
+        last_event_ts: 0,
+        total_deposited_fp: 0,
+        carryover_shortfall_fp: 0,
+        strategy_program: Pubkey::default(),
+        strategy_max_bps: 0,
+        strategy_junior_yield_bps: 0,
+        strategy_deployed_fp: 0,
+        senior_total_fp: 0,
+        junior_total_fp: 0,
+        senior_yield_index_fp: 0,
+        junior_yield_index_fp: 0,
+        yield_reserve_fp: 0,
+        bump: 0,
+        version: ACCOUNT_VERSION,
+        shutdown: false,
+        shutdown_ts: 0,
+        guardian: Pubkey::default(),
+        pause_flags: 0,
+        outflow_window_secs: 0,
+        outflow_cap_fp: 0,
+        outflow_cap_bps: 0,
+        outflow_window_updated_ts: 0,
+        outflow_window_base_fp: 0,
+        outflow_window_used_fp: 0,
+        crank_reward_u64: 0,
+        current_epoch_id: 0,
+        epoch_duration_secs: 0,
+        cover_owed_fp: 0,
+        reserved: [0; 496],
+    };
+    check_param_bounds(&state)?;
+    let mut d = diff(state_addr, None, &state);
+    d.changes.retain(|c| c.field != "bump");
+    Ok(vec![d])
+}
+
+/// `set_paused`: replaces the pause mask.
+pub fn set_paused(state_addr: Pubkey, state: &State, admin: &Pubkey, flags: u8) -> Preview {
+    require(flags & !PAUSE_ALL == 0, "ParamOutOfBounds")?;
+    admin_update(state_addr, state, admin, |s| s.pause_flags = flags)
+}
+
+/// `set_guardian`.
+pub fn set_guardian(state_addr: Pubkey, state: &State, admin: &Pubkey, guardian: &Pubkey) -> Preview {
+    admin_update(state_addr, state, admin, |s| s.guardian = *guardian)
+}
+
+/// `guardian_pause` signed by `authority` (guardian or admin): adds `flags` to the mask.
+pub fn guardian_pause(state_addr: Pubkey, state: &State, authority: &Pubkey, flags: u8) -> Preview {
+    require(*authority == state.guardian || *authority == state.admin, "Unauthorized")?;
+    require(flags != 0 && flags & !PAUSE_ALL == 0, "ParamOutOfBounds")?;
+    let mut after = state.clone();
+    after.pause_flags |= flags;
+    Ok(vec![diff(state_addr, Some(state), &after)])
+}
+
+/// `set_outflow_limits`: new limits, current window restarted.
+pub fn set_outflow_limits(state_addr: Pubkey, state: &State, admin: &Pubkey, window_secs: i64, cap_fp: u128, cap_bps: u16) -> Preview {
+    admin_update(state_addr, state, admin, |s| {
+        (s.outflow_window_secs, s.outflow_cap_fp, s.outflow_cap_bps) = (window_secs, cap_fp, cap_bps);
+        (s.outflow_window_updated_ts, s.outflow_window_base_fp, s.outflow_window_used_fp) = (0, 0, 0);
+    })
+}
+
+/// `set_crank_reward`.
+pub fn set_crank_reward(state_addr: Pubkey, state: &State, admin: &Pubkey, reward_u64: u64) -> Preview {
+    admin_update(state_addr, state, admin, |s| s.crank_reward_u64 = reward_u64)
+}
+
+/// `set_rolling_mode`.
+pub fn set_rolling_mode(state_addr: Pubkey, state: &State, admin: &Pubkey, enabled: bool, epoch_duration_secs: u32) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    require(!enabled || epoch_duration_secs > 0, "ParamOutOfBounds")?;
+    let mut after = state.clone();
+    after.rolling_mode = enabled;
+    after.epoch_duration_secs = epoch_duration_secs;
+    Ok(vec![diff(state_addr, Some(state), &after)])
+}
+
+/// `init_epoch_history`; `current_epoch` is the current epoch, if it exists.
+pub fn init_epoch_history(
+    state: &State,
+    admin: &Pubkey,
+    history_addr: Pubkey,
+    existing: Option<&EpochHistory>,
+    current_epoch: Option<&Epoch>,
+) -> Preview {
+    require(existing.is_none(), "AccountAlreadyInitialized")?;
+    require(state.admin == *admin, "Unauthorized")?;
+    let history = EpochHistory {
+        last_epoch_id: 0,
+        last_end_ts: current_epoch.map_or(0, coverage_end_ts),
+        count: 0,
+        records: [EpochRecord::default(); EPOCH_HISTORY_LEN],
+        bump: 0,
+        version: ACCOUNT_VERSION,
+        reserved: [0; 64],
+    };
+    let mut d = diff(history_addr, None, &history);
+    d.changes.retain(|c| c.field != "bump");
+    Ok(vec![d])
+}
+
+/// `assert_cover_bounds` + `apply_cover_params` in the program.
+fn apply_cover_params(book: &mut CoverBook, p: &CoverParams) -> Result<(), &'static str> {
+    let all = [
+        p.max_utilisation_bps,
+        p.base_rate_bps,
+        p.kink_utilisation_bps,
+        p.slope1_bps,
+        p.slope2_bps,
+        p.event_probability_bps,
+        p.reference_input_bps,
+    ];
+    require(all.iter().all(|&bps| bps <= 10_000), "ParamOutOfBounds")?;
+    book.max_utilisation_bps = p.max_utilisation_bps;
+    book.base_rate_bps = p.base_rate_bps;
+    book.kink_utilisation_bps = p.kink_utilisation_bps;
+    book.slope1_bps = p.slope1_bps;
+    book.slope2_bps = p.slope2_bps;
+    book.event_probability_bps = p.event_probability_bps;
+    book.reference_input_bps = p.reference_input_bps;
+    Ok(())
+}
+
+/// `init_cover_book`.
+pub fn init_cover_book(state: &State, admin: &Pubkey, book_addr: Pubkey, existing: Option<&CoverBook>, p: &CoverParams) -> Preview {
+    require(existing.is_none(), "AccountAlreadyInitialized")?;
+    require(state.admin == *admin, "Unauthorized")?;
+    let mut book = CoverBook {
+        max_utilisation_bps: 0,
+        base_rate_bps: 0,
+        epoch_id: 0,
+        sold_fp: 0,
+        bump: 0,
+        version: ACCOUNT_VERSION,
+        kink_utilisation_bps: 0,
+        slope1_bps: 0,
+        slope2_bps: 0,
+        event_probability_bps: 0,
+        reference_input_bps: 0,
+        covers_minted: 0,
+        reserved: [0; 46],
+    };
+    apply_cover_params(&mut book, p)?;
+    let mut d = diff(book_addr, None, &book);
+    d.changes.retain(|c| c.field != "bump");
+    Ok(vec![d])
+}
+
+/// `set_cover_params`.
+pub fn set_cover_params(state: &State, admin: &Pubkey, book_addr: Pubkey, book: &CoverBook, p: &CoverParams) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    let mut after = book.clone();
+    apply_cover_params(&mut after, p)?;
+    Ok(vec![diff(book_addr, Some(book), &after)])
+}
+
+/// `shutdown` at time `now` (strategy capital must already be recalled). `open` is the epoch
+/// whose claim window is open and the history registry, required while a window is open.
+pub fn shutdown(
+    state_addr: Pubkey,
+    state: &State,
+    admin: &Pubkey,
+    open: Option<(Pubkey, &Epoch, Pubkey, &EpochHistory)>,
+    now: i64,
+) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    require(!state.shutdown, "PoolShutdown")?;
+    require(state.strategy_deployed_fp == 0, "StrategyActive")?;
+    let mut after = state.clone();
+    let mut out = Vec::new();
+    if state.claim_window_open {
+        let (epoch_addr, epoch, history_addr, history) = open.ok_or("EpochNotActive")?;
+        require(epoch.triggered && !epoch.closed, "EpochNotActive")?;
+        let (mut e, mut h) = (epoch.clone(), history.clone());
+        // Claims stop for good: what the epoch still owes beyond its payouts is shortfall
+        let policy = PayoutPolicy::from_u8(state.payout_policy).ok_or("ParamOutOfBounds")?;
+        let capped_fp = payout::epoch_liability(claim_base_fp(&e), e.severity_bps, policy, e.epoch_cap_fp, 0)
+            .ok_or("MathOverflow")?
+            .capped_fp;
+        let owed_fp = match e.merkle_root != [0; 32] {
+            true => {
+                let cover_fp = payout::user_payout_fp(capped_fp, e.cover_sold_fp, claim_base_fp(&e), policy, 0).ok_or("MathOverflow")?;
+                e.merkle_total_fp.saturating_add(cover_fp)
+            }
+            false => capped_fp,
+        };
+        e.shortfall_fp = e.shortfall_fp.max(owed_fp.saturating_sub(e.total_payout_fp));
+        settle_epoch(&mut after, &mut e, &mut h, now);
+        out.push(diff(epoch_addr, Some(epoch), &e));
+        out.push(diff(history_addr, Some(history), &h));
+    }
+    after.shutdown = true;
+    after.shutdown_ts = now;
+    out.insert(0, diff(state_addr, Some(state), &after));
+    Ok(out)
+}
+
+/// `set_policy`.
+pub fn set_policy(state_addr: Pubkey, state: &State, admin: &Pubkey, payout_policy: u8, epoch_cap_fp: Option<u128>) -> Preview {
+    admin_update(state_addr, state, admin, |s| {
+        s.payout_policy = payout_policy;
+        if let Some(cap) = epoch_cap_fp {
+            s.epoch_cap_fp = cap;
+        }
+    })
+}
+
+/// `set_curve_and_weights`.
+pub fn set_curve_and_weights(state_addr: Pubkey, state: &State, admin: &Pubkey, c: &CurveParams) -> Preview {
+    admin_update(state_addr, state, admin, |s| {
+        s.sev_quad_a_fp = c.sev_quad_a_fp;
+        s.sev_quad_b_fp = c.sev_quad_b_fp;
+        s.sev_quad_c_fp = c.sev_quad_c_fp;
+        s.severity_floor_bps = c.severity_floor_bps;
+        s.tranche_weight_senior_bps = c.tranche_weight_senior_bps;
+        s.tranche_weight_junior_bps = c.tranche_weight_junior_bps;
+    })
+}
+
+/// `start_epoch` at time `now`, paid by `payer`. In rolling mode the window comes from the
+/// pool's epoch duration (`start_ts` / `end_ts` must be 0) and `previous` is the current
+/// epoch, if it exists; rolls after an ended epoch need no admin. A manual `start_ts` of 0
+/// means `now`.
+#[allow(clippy::too_many_arguments)]
+pub fn start_epoch(
+    state_addr: Pubkey,
+    state: &State,
+    history_addr: Pubkey,
+    history: &EpochHistory,
+    epoch_addr: Pubkey,
+    existing: Option<&Epoch>,
+    previous: Option<&Epoch>,
+    payer: &Pubkey,
+    epoch_id: u64,
+    start_ts: i64,
+    end_ts: i64,
+    now: i64,
+) -> Preview {
+    require(existing.is_none(), "AccountAlreadyInitialized")?;
+    require(!state.shutdown, "PoolShutdown")?;
+    let is_admin = *payer == state.admin;
+    let (start_ts, end_ts) = if state.rolling_mode {
+        require(start_ts == 0 && end_ts == 0, "ParamOutOfBounds")?;
+        require(epoch_id == state.current_epoch_id.saturating_add(1), "ParamOutOfBounds")?;
+        let duration = state.epoch_duration_secs as i64;
+        require(duration > 0, "ParamOutOfBounds")?;
+        let start = match previous.filter(|e| e.end_ts != 0) {
+            Some(prev) => {
+                require(prev.end_ts <= now, "EpochNotActive")?;
+                let missed = now.saturating_sub(prev.end_ts) / duration;
+                prev.end_ts.saturating_add(missed.saturating_mul(duration))
+            }
+            None => {
+                require(is_admin, "Unauthorized")?;
+                now
+            }
+        };
+        (start, start.saturating_add(duration))
+    } else {
+        require(is_admin, "Unauthorized")?;
+        require(epoch_id > state.current_epoch_id, "ParamOutOfBounds")?;
+        let start_ts = if start_ts == 0 { now } else { start_ts };
+        require(start_ts >= now, "EpochNotActive")?;
+        require(end_ts == 0 || end_ts > start_ts, "EpochNotActive")?;
+        (start_ts, end_ts)
+    };
+    require(start_ts >= history.last_end_ts, "EpochOverlap")?;
+    let mut h = history.clone();
+    h.last_end_ts = if end_ts == 0 { i64::MAX } else { end_ts };
+    let mut s = state.clone();
+    s.current_epoch_id = epoch_id;
+    let epoch = Epoch {
+        epoch_id,
+        start_ts,
+        end_ts,
+        total_stake_snapshot_fp: 0,
+        total_payout_fp: 0,
+        shortfall_fp: 0,
+        severity_bps: 0,
+        user_cap_bps: 0,
+        epoch_cap_fp: 0,
+        triggered: false,
+        closed: false,
+        evidence_hash: [0; 32],
+        evidence_ts: 0,
+        finalized_ts: 0,
+        open_receipts: 0,
+        bump: 0,
+        version: ACCOUNT_VERSION,
+        merkle_root: [0; 32],
+        merkle_total_fp: 0,
+        pending_deposits_fp: 0,
+        cover_sold_fp: 0,
+        cover_claimed_fp: 0,
+        cover_paid_fp: 0,
+        triggered_ts: 0,
+        reserved: [0; 72],
+    };
+    let mut d = diff(epoch_addr, None, &epoch);
+    d.changes.retain(|c| c.field != "bump");
+    Ok(vec![diff(state_addr, Some(state), &s), diff(history_addr, Some(history), &h), d])
+}
+
+/// `end_epoch` at time `now`: an untriggered epoch, open-ended and started or past its window.
+pub fn end_epoch(
+    state: &State,
+    admin: &Pubkey,
+    epoch_addr: Pubkey,
+    epoch: &Epoch,
+    history_addr: Pubkey,
+    history: &EpochHistory,
+    now: i64,
+) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    require(!epoch.triggered && !epoch.closed, "EpochNotActive")?;
+    require(now >= if epoch.end_ts == 0 { epoch.start_ts } else { epoch.end_ts }, "EpochNotActive")?;
+    let (mut e, mut h) = (epoch.clone(), history.clone());
+    e.closed = true;
+    e.finalized_ts = now;
+    if e.epoch_id == state.current_epoch_id {
+        h.last_end_ts = coverage_end_ts(&e);
+    }
+    Ok(vec![diff(epoch_addr, Some(epoch), &e), diff(history_addr, Some(history), &h)])
+}
+
+/// `trigger_event` at time `now`. `allowed` = the signer is the admin or on the oracle list.
+#[allow(clippy::too_many_arguments)]
+pub fn trigger_event(
+    state_addr: Pubkey,
+    state: &State,
+    epoch_addr: Pubkey,
+    epoch: &Epoch,
+    oracle_enabled: bool,
+    allowed: bool,
+    args: &TriggerArgs,
+    now: i64,
+) -> Preview {
+    require(!state.processing, "Busy")?;
+    require(oracle_enabled && allowed, "Unauthorized")?;
+    require(!epoch.triggered, "EpochAlreadyTriggered")?;
+    require(!epoch.closed, "EpochNotActive")?;
+    require(state.pause_flags & PAUSE_TRIGGERS == 0 && !state.claim_window_open, "Paused")?;
+    require(!state.shutdown, "PoolShutdown")?;
+    require(now >= epoch.start_ts && (epoch.end_ts == 0 || now <= epoch.end_ts), "EpochNotActive")?;
+    let mut e = epoch.clone();
+    if let Some(ts) = args.evidence_ts {
+        if state.max_stale_secs > 0 {
+            require(now.saturating_sub(ts) <= state.max_stale_secs, "EpochNotActive")?;
+        }
+        e.evidence_ts = ts;
+    }
+    let mut s = state.clone();
+    // Deposits made during a rolling epoch are covered from the next one
+    e.total_stake_snapshot_fp = s.total_deposited_fp.saturating_sub(e.pending_deposits_fp);
+    e.severity_bps = severity::effective_severity_bps(
+        args.severity_input_bps as u128,
+        s.sev_quad_a_fp,
+        s.sev_quad_b_fp,
+        s.sev_quad_c_fp,
+        s.severity_floor_bps,
+    )
+    .ok_or("MathOverflow")? as u16;
+    e.user_cap_bps = args.user_cap_bps.unwrap_or(0);
+    e.epoch_cap_fp = if s.payout_policy == 2 { args.epoch_cap_fp_override.unwrap_or(s.epoch_cap_fp) } else { 0 };
+    e.triggered = true;
+    e.triggered_ts = now;
+    let unclaimed_fp = e.cover_sold_fp.saturating_sub(e.cover_claimed_fp);
+    s.cover_owed_fp = cover::cover_payout_fp(unclaimed_fp, e.severity_bps).ok_or("MathOverflow")?;
+    if let Some(h) = args.evidence_hash {
+        e.evidence_hash = h;
+    }
+    s.last_event_ts = now;
+    s.claim_window_open = true;
+    Ok(vec![diff(state_addr, Some(state), &s), diff(epoch_addr, Some(epoch), &e)])
+}
+
+/// `finalize_epoch` at time `now` (the dust sweep's effect is not predicted).
+#[allow(clippy::too_many_arguments)]
+pub fn finalize_epoch(
+    state_addr: Pubkey,
+    state: &State,
+    admin: &Pubkey,
+    epoch_addr: Pubkey,
+    epoch: &Epoch,
+    history_addr: Pubkey,
+    history: &EpochHistory,
+    sweep_dust: Option<u64>,
+    now: i64,
+) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    require(!state.processing, "Busy")?;
+    require(epoch.triggered && !epoch.closed, "EpochNotActive")?;
+    let cover_window_over = now >= epoch.triggered_ts.saturating_add(COVER_CLAIM_WINDOW_SECS);
+    require(epoch.cover_claimed_fp >= epoch.cover_sold_fp || cover_window_over, "CoverClaimWindowActive")?;
+    require(sweep_dust.is_none() || state.pause_flags & PAUSE_FEE_SWEEPS == 0, "Paused")?;
+    let (mut s, mut e, mut h) = (state.clone(), epoch.clone(), history.clone());
+    settle_epoch(&mut s, &mut e, &mut h, now);
+    Ok(vec![
+        diff(state_addr, Some(state), &s),
+        diff(epoch_addr, Some(epoch), &e),
+        diff(history_addr, Some(history), &h),
+    ])
+}
+
+/// `crank_payouts` for `holders` triples. Only the checks are mirrored: who is paid, the
+/// receipts created and the reward depend on vault balances and are not predicted.
+pub fn crank_payouts(state: &State, epoch: &Epoch, holders: usize) -> Preview {
+    require(!state.processing, "Busy")?;
+    require(state.pause_flags & PAUSE_CLAIMS == 0, "Paused")?;
+    require(epoch.triggered && !epoch.closed, "EpochNotActive")?;
+    require(epoch.merkle_root == [0; 32], "SettlementModeConflict")?;
+    require(holders > 0, "InvalidRemainingAccounts")?;
+    Ok(Vec::new())
+}
+
+/// `post_merkle_root` with the registry at `pool_value_fp`; `bitmap` is the claim bitmap
+/// account if it already exists.
+#[allow(clippy::too_many_arguments)]
+pub fn post_merkle_root(
+    state: &State,
+    admin: &Pubkey,
+    epoch_addr: Pubkey,
+    epoch: &Epoch,
+    bitmap_addr: Pubkey,
+    bitmap: Option<&ClaimBitmap>,
+    pool_value_fp: u128,
+    root: [u8; 32],
+    total_fp: u128,
+    claimants: u32,
+) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    require(bitmap.is_none(), "AccountAlreadyInitialized")?;
+    require(epoch.triggered && !epoch.closed, "EpochNotActive")?;
+    require(epoch.merkle_root == [0; 32] && epoch.total_payout_fp == epoch.cover_paid_fp, "SettlementModeConflict")?;
+    require(root != [0; 32] && (1..=MAX_MERKLE_CLAIMANTS).contains(&(claimants as usize)), "ParamOutOfBounds")?;
+    let policy = PayoutPolicy::from_u8(state.payout_policy).ok_or("ParamOutOfBounds")?;
+    let liability = payout::epoch_liability(claim_base_fp(epoch), epoch.severity_bps, policy, epoch.epoch_cap_fp, pool_value_fp)
+        .ok_or("MathOverflow")?;
+    let depositors_fp =
+        payout::user_payout_fp(liability.max_fp, epoch.total_stake_snapshot_fp, claim_base_fp(epoch), policy, 0).ok_or("MathOverflow")?;
+    require(total_fp > 0 && total_fp <= depositors_fp, "ParamOutOfBounds")?;
+    let mut e = epoch.clone();
+    if liability.shortfall_fp > 0 {
+        e.shortfall_fp = liability.shortfall_fp;
+    }
+    e.merkle_root = root;
+    e.merkle_total_fp = total_fp;
+    let new_bitmap = ClaimBitmap {
+        epoch_id: epoch.epoch_id,
+        claimants,
+        claimed: 0,
+        bump: 0,
+        version: ACCOUNT_VERSION,
+        reserved: [0; 16],
+        bits: vec![0; merkle::bitmap_len(claimants)],
+    };
+    let mut b = diff(bitmap_addr, None, &new_bitmap);
+    b.changes.retain(|c| c.field != "bump" && c.field != "bits");
+    Ok(vec![diff(epoch_addr, Some(epoch), &e), b])
+}
+
+/// `claim_with_proof` of `claim` (the token transfer and the outflow breaker are not predicted).
+pub fn claim_with_proof(state: &State, epoch_addr: Pubkey, epoch: &Epoch, bitmap_addr: Pubkey, bitmap: &ClaimBitmap, claim: &Claim) -> Preview {
+    require(!state.processing, "Busy")?;
+    require(state.pause_flags & PAUSE_CLAIMS == 0, "Paused")?;
+    require(epoch.triggered && !epoch.closed, "EpochNotActive")?;
+    require(epoch.merkle_root != [0; 32], "SettlementModeConflict")?;
+    require(verify_claim(&epoch.merkle_root, claim), "InvalidMerkleProof")?;
+    require(claim.amount_fp > 0 && !merkle::is_claimed(&bitmap.bits, claim.index), "NothingToPayout")?;
+    let paid_fp = epoch.total_payout_fp.checked_add(claim.amount_fp).ok_or("MathOverflow")?;
+    require(paid_fp.saturating_sub(epoch.cover_paid_fp) <= epoch.merkle_total_fp, "InsufficientPoolBalance")?;
+    let mut e = epoch.clone();
+    e.total_payout_fp = paid_fp;
+    let mut b = bitmap.clone();
+    merkle::set_claimed(&mut b.bits, claim.index);
+    b.claimed = b.claimed.saturating_add(1);
+    let mut bd = diff(bitmap_addr, Some(bitmap), &b);
+    bd.changes.retain(|c| c.field != "bits");
+    Ok(vec![diff(epoch_addr, Some(epoch), &e), bd])
+}
+
+/// `close_epoch`: a finalized epoch whose claim receipts are all closed, with its claim
+/// bitmap (`bitmap`) if it was settled by Merkle root.
+pub fn close_epoch(state: &State, admin: &Pubkey, epoch_addr: Pubkey, epoch: &Epoch, bitmap: Option<Pubkey>) -> Preview {
+    require(state.admin == *admin, "Unauthorized")?;
+    require(epoch.closed, "EpochNotActive")?;
+    require(epoch.open_receipts == 0, "AccountNotEmpty")?;
+    require(epoch.merkle_root == [0; 32] || bitmap.is_some(), "SettlementModeConflict")?;
+    Ok([closed::<Epoch>(epoch_addr)].into_iter().chain(bitmap.map(closed::<ClaimBitmap>)).collect())
+}
+
+/// One account under `migrate_state` / `migrate_position`: a legacy account (decoded from
+/// its old layout as version 0) gets `upgrade` and the current version; a current one is left
+/// as is. Returns the diff and the account as migrated.
+fn migrate<T: ProgramAccount + Clone>(
+    address: Pubkey,
+    acc: &T,
+    upgrade: impl FnOnce(&mut T) -> Result<(), &'static str>,
+) -> Result<(AccountDiff, T), &'static str> {
+    let mut after = acc.clone();
+    if acc.version() == 0 {
+        upgrade(&mut after)?;
+        after.set_version(ACCOUNT_VERSION);
+    }
+    require(after.version() == ACCOUNT_VERSION, "UnsupportedAccountVersion")?;
+    Ok((diff(address, Some(acc), &after), after))
+}
+
+/// `migrate_state` over the global accounts and `epochs` at `now` (rent top-up not predicted).
+/// `oracle_list` carries its PDA bump, `vault` is the base vault's address, mint decimals and
+/// balance; a missing collateral registry is created from them.
+pub fn migrate_state(
+    state: (Pubkey, &State),
+    oracle_list: (Pubkey, &OracleList, u8),
+    collateral_registry: (Pubkey, Option<&CollateralRegistry>),
+    vault: (Pubkey, u8, u64),
+    epochs: &[(Pubkey, Epoch)],
+    admin: &Pubkey,
+    now: i64,
+) -> Preview {
+    let mut out = Vec::new();
+    let (mut claim_window_open, mut last_epoch_id) = (false, 0);
+    for (address, epoch) in epochs {
+        let (d, e) = migrate(*address, epoch, |e| {
+            if e.closed {
+                e.finalized_ts = now;
+            }
+            Ok(())
+        })?;
+        claim_window_open |= e.triggered && !e.closed;
+        last_epoch_id = last_epoch_id.max(e.epoch_id);
+        out.push(d);
+    }
+    let (d, s) = migrate(state.0, state.1, |s| {
+        require(s.admin == *admin, "Unauthorized")?;
+        s.claim_window_open = claim_window_open;
+        s.current_epoch_id = last_epoch_id;
+        s.pause_flags = PAUSE_ALL;
+        Ok(())
+    })?;
+    out.insert(0, d);
+    out.insert(1, migrate(oracle_list.0, oracle_list.1, |o| {
+        o.bump = oracle_list.2;
+        Ok(())
+    })?.0);
+
+    let (registry_addr, registry) = collateral_registry;
+    if registry.is_none() {
+        let (vault_addr, decimals, balance) = vault;
+        let mut assets = [CollateralAsset::default(); MAX_COLLATERALS];
+        assets[0] = CollateralAsset {
+            mint: s.usdc_mint,
+            vault: vault_addr,
+            decimals,
+            haircut_bps: 0,
+            concentration_cap_bps: 10_000,
+            enabled: true,
+            balance_fp: math::asset_to_fp(balance, decimals).ok_or("MathOverflow")?,
+        };
+        let created = CollateralRegistry { count: 1, assets, bump: 0, version: ACCOUNT_VERSION, reserved: [0; 64] };
+        let mut d = diff(registry_addr, None, &created);
+        d.changes.retain(|c| c.field != "bump");
+        out.insert(2, d);
+    }
+    Ok(out)
+}
+
+/// `migrate_position` for `owner`, whose position PDA has bump `bump` (rent top-up not
+/// predicted). `claims` are `(receipt address, receipt, epoch address, epoch)`; the state and
+/// the epochs must already be migrated.
+pub fn migrate_position(
+    position: (Pubkey, &UserPosition),
+    owner: &Pubkey,
+    bump: u8,
+    state: (Pubkey, &State),
+    claims: &[(Pubkey, ClaimReceipt, Pubkey, Epoch)],
+) -> Preview {
+    require(state.1.version() == ACCOUNT_VERSION, "AccountDidNotDeserialize")?;
+    let idx = (state.1.senior_yield_index_fp, state.1.junior_yield_index_fp);
+    let (d, p) = migrate(position.0, position.1, |p| {
+        require(p.owner == *owner, "Unauthorized")?;
+        p.bump = bump;
+        trim_lots(&mut p.senior_lots, p.senior_deposited_fp);
+        trim_lots(&mut p.junior_lots, p.junior_deposited_fp);
+        p.senior_withdrawable_fp = p.senior_deposited_fp - locked_fp(&p.senior_lots);
+        p.junior_withdrawable_fp = p.junior_deposited_fp - locked_fp(&p.junior_lots);
+        p.senior_yield_debt_fp = yields::accrued_fp(p.senior_deposited_fp, idx.0).ok_or("MathOverflow")?;
+        p.junior_yield_debt_fp = yields::accrued_fp(p.junior_deposited_fp, idx.1).ok_or("MathOverflow")?;
+        Ok(())
+    })?;
+    let mut out = vec![d];
+    if position.1.version() == 0 {
+        let mut s = state.1.clone();
+        s.senior_total_fp = s.senior_total_fp.checked_add(p.senior_deposited_fp).ok_or("MathOverflow")?;
+        s.junior_total_fp = s.junior_total_fp.checked_add(p.junior_deposited_fp).ok_or("MathOverflow")?;
+        out.push(diff(state.0, Some(state.1), &s));
+    }
+    for (address, c, epoch_addr, epoch) in claims {
+        require(epoch.version() == ACCOUNT_VERSION, "AccountDidNotDeserialize")?;
+        let (mut d, mut c_after) = migrate(*address, c, |c| require(c.owner == *owner, "Unauthorized"))?;
+        require(c_after.epoch_id == epoch.epoch_id, "InvalidRemainingAccounts")?;
+        // Receipts written before the payer was recorded were paid by their claimant
+        if c_after.payer == Pubkey::default() {
+            c_after.payer = c_after.owner;
+            d = diff(*address, Some(c), &c_after);
+        }
+        out.push(d);
+        if c.version() == 0 {
+            let e = Epoch { open_receipts: epoch.open_receipts.checked_add(1).ok_or("MathOverflow")?, ..epoch.clone() };
+            out.push(diff(*epoch_addr, Some(epoch), &e));
+        }
+    }
+    Ok(out)
+}
+
+/// Live lots in a ring.
+fn locked_fp(lots: &Lots) -> u128 {
+    (0..lots.len as usize).map(|i| lots.buf[(lots.head as usize + i) % MAX_LOTS].amount_fp).sum()
+}
+
+/// Shrink the oldest lots until they add up to at most `cap_fp` (as `migrate_position`).
+fn trim_lots(lots: &mut Lots, cap_fp: u128) {
+    let mut excess = locked_fp(lots).saturating_sub(cap_fp);
+    for i in 0..lots.len as usize {
+        let lot = &mut lots.buf[(lots.head as usize + i) % MAX_LOTS];
+        let cut = excess.min(lot.amount_fp);
+        lot.amount_fp -= cut;
+        excess -= cut;
+    }
+}
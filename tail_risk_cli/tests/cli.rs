@@ -0,0 +1,534 @@
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+use tail_risk_cli::{
+    accounts::{
+        discriminator, ClaimBitmap, ClaimReceipt, Cover, CoverBook, Epoch, EpochHistory, OracleList,
+        ProgramAccount, State, UserPosition, ACCOUNT_VERSION, PAUSE_ALL, PAUSE_CLAIMS, PAUSE_DEPOSITS, PAUSE_FEE_SWEEPS, PAUSE_TRIGGERS,
+    },
+    config::Config,
+    ix::{sighash, Builder, CoverParams, InitializeParams, TriggerArgs},
+    merkle::{self, Distribution},
+    pda::{associated_token_address, Pdas, PROGRAM_ID},
+    preview,
+};
+
+const EXAMPLE: &str = include_str!("../pool.example.toml");
+const FP: u128 = 1_000_000_000_000;
+
+macro_rules! fixture {
+    ($name:literal) => {
+        include_bytes!(concat!("../../tail_risk_insurance_pool/tests/fixtures/v0/", $name)).as_slice()
+    };
+}
+
+// `State::SIZE` / `Epoch::SIZE` / `EpochHistory::SIZE` / `CoverBook::SIZE` / `Cover::SIZE` in the program
+const STATE_SIZE: usize = 1_133;
+const EPOCH_SIZE: usize = 340;
+const HISTORY_SIZE: usize = 758;
+const COVER_BOOK_SIZE: usize = 94;
+const COVER_SIZE: usize = 154;
+
+fn zeroed<T: ProgramAccount>(size: usize) -> T {
+    let mut data = discriminator(T::NAME).to_vec();
+    data.resize(8 + size, 0);
+    T::decode(&data).unwrap()
+}
+
+fn state(admin: Pubkey) -> State {
+    let mut s: State = zeroed(STATE_SIZE);
+    s.admin = admin;
+    s.total_deposited_fp = 2_000 * 1_000_000_000_000;
+    s.sev_quad_b_fp = 1_000_000;
+    s.severity_floor_bps = 100;
+    s.payout_policy = 2;
+    s.epoch_cap_fp = 500 * 1_000_000_000_000;
+    s
+}
+
+#[test]
+fn example_config_parses() {
+    let cfg = Config::parse(EXAMPLE).unwrap();
+    assert_eq!(cfg.cluster.program_id, PROGRAM_ID);
+    let init = cfg.initialize.unwrap();
+    assert_eq!(init.params.user_deposit_cap_fp, 1_000_000_000_000_000_000);
+    assert_eq!(init.params.tranche_weight_junior_bps, 10_000);
+    assert_eq!(cfg.policy.unwrap().epoch_cap_fp, Some(250_000_000_000_000_000));
+    assert_eq!(cfg.curve.unwrap().tranche_weight_senior_bps, 8_000);
+    assert_eq!(cfg.cover.unwrap().kink_utilisation_bps, 8_000);
+    assert!(Config::parse("[cluster]\nrpc = \"x\"").is_err());
+}
+
+#[test]
+fn account_layouts_round_trip() {
+    // Mirrors serialise to exactly the program's account sizes
+    let s = state(Pubkey::new_unique());
+    assert_eq!(s.encode().len(), 8 + STATE_SIZE);
+    assert_eq!(State::decode(&s.encode()).unwrap(), s);
+    assert!(State::decode(&s.encode()[..100]).is_err());
+    assert!(Epoch::decode(&s.encode()).is_err(), "discriminator is checked");
+
+    let e: Epoch = zeroed(EPOCH_SIZE);
+    assert_eq!(e.encode().len(), 8 + EPOCH_SIZE);
+    let h: EpochHistory = zeroed(HISTORY_SIZE);
+    assert_eq!(h.encode().len(), 8 + HISTORY_SIZE);
+    assert_eq!(zeroed::<CoverBook>(COVER_BOOK_SIZE).encode().len(), 8 + COVER_BOOK_SIZE);
+    assert_eq!(zeroed::<Cover>(COVER_SIZE).encode().len(), 8 + COVER_SIZE);
+}
+
+#[test]
+fn instruction_encoding_matches_anchor() {
+    let admin = Pubkey::new_unique();
+    let b = Builder::new(PROGRAM_ID);
+    let pdas = Pdas::new(PROGRAM_ID);
+
+    let ix = b.set_policy(&admin, 2, Some(7));
+    assert_eq!(&ix.data[..8], &sighash("set_policy"));
+    assert_eq!(&ix.data[8..], &[2, 1, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(ix.accounts[1].pubkey, pdas.state());
+    assert!(ix.accounts[0].is_signer && ix.accounts[1].is_writable);
+
+    let ix = b.start_epoch(&admin, 3, 100, 0, None);
+    assert_eq!((ix.accounts[2].pubkey, ix.accounts[2].is_writable), (PROGRAM_ID, false), "no previous epoch");
+    assert_eq!((ix.accounts[3].pubkey, ix.accounts[3].is_writable), (pdas.epoch_history(), true));
+    assert_eq!(ix.accounts[4].pubkey, pdas.epoch(3));
+    assert!(ix.accounts[1].is_writable, "start_epoch records the current epoch id");
+    assert_eq!(ix.data.len(), 8 + 24);
+    assert_eq!(b.start_epoch(&admin, 3, 0, 0, Some(2)).accounts[2].pubkey, pdas.epoch(2));
+    let ix = b.init_epoch_history(&admin, Some(2));
+    assert_eq!(&ix.data, &sighash("init_epoch_history"));
+    assert_eq!(ix.accounts[2].pubkey, pdas.epoch(2));
+    assert!(!ix.accounts[1].is_writable && ix.accounts[3].is_writable);
+    assert_eq!(b.finalize_epoch(&admin, 3, &Pubkey::new_unique(), &admin, None).accounts[3].pubkey, pdas.epoch_history());
+    let ix = b.end_epoch(&admin, 3);
+    assert_eq!((&ix.data[..], ix.accounts[2].pubkey), (&sighash("end_epoch")[..], pdas.epoch(3)));
+    assert!(!ix.accounts[1].is_writable && ix.accounts[3].is_writable);
+    let cover = Config::parse(EXAMPLE).unwrap().cover.unwrap();
+    let ix = b.set_cover_params(&admin, &cover);
+    assert_eq!(&ix.data[8..12], &[0x88, 0x13, 0x64, 0x00]);
+    assert_eq!(ix.data.len(), 8 + 7 * 2);
+    assert_eq!((ix.accounts[2].pubkey, ix.accounts[2].is_writable), (pdas.cover_book(), true));
+    assert_eq!(b.init_cover_book(&admin, &cover).accounts.len(), 4);
+    assert_ne!(pdas.cover(&pdas.cover_mint(0)), pdas.cover(&pdas.cover_mint(1)));
+    let ix = b.set_rolling_mode(&admin, true, 86_400);
+    assert_eq!(&ix.data[8..], &[1, 0x80, 0x51, 0x01, 0x00]);
+
+    let ix = b.trigger_event(&admin, 3, &pdas.vault(&Pubkey::new_unique()), None, &TriggerArgs { severity_input_bps: 500, ..Default::default() });
+    assert_eq!(ix.accounts[6].pubkey, PROGRAM_ID, "absent optional account is the program id");
+    assert_eq!((ix.accounts[7].pubkey, ix.accounts[8].pubkey), (PROGRAM_ID, PROGRAM_ID));
+    assert_eq!(&ix.data[8..], &[0xf4, 0x01, 0, 0, 0, 0]);
+    let ix = b.trigger_event(&admin, 3, &pdas.vault(&Pubkey::new_unique()), Some((&Pubkey::new_unique(), &[][..])), &TriggerArgs::default());
+    assert_eq!((ix.accounts[7].pubkey, ix.accounts[8].pubkey), (pdas.strategy_authority(), pdas.strategy_escrow()));
+
+    let (mint, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let ix = b.crank_payouts(&admin, 3, &mint, &Pubkey::new_unique(), &[admin, bob]);
+    assert_eq!(ix.accounts.len(), 8 + 2 * 3);
+    assert_eq!(ix.accounts[4].pubkey, pdas.vault(&mint));
+    let bob_metas: Vec<_> = ix.accounts[11..].iter().map(|m| (m.pubkey, m.is_writable)).collect();
+    assert_eq!(bob_metas, [(pdas.position(&bob), false), (pdas.claim(3, &bob), true), (associated_token_address(&bob, &mint), true)]);
+
+    let init = Config::parse(EXAMPLE).unwrap().initialize.unwrap();
+    let ix = b.initialize(&admin, &init.usdc_mint, &init.params);
+    assert_eq!(InitializeParams::try_from_slice(&ix.data[8..]).unwrap(), init.params);
+    assert_eq!(ix.accounts.len(), 10);
+    assert_ne!(pdas.claim(3, &admin), pdas.claim(4, &admin));
+}
+
+#[test]
+fn previews_mirror_handlers() {
+    let admin = Pubkey::new_unique();
+    let state = state(admin);
+    let (sa, ea, ha) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let history: EpochHistory = zeroed(HISTORY_SIZE);
+
+    let init = Config::parse(EXAMPLE).unwrap().initialize.unwrap();
+    let d = preview::initialize(sa, None, &admin, &init.usdc_mint, &init.params).unwrap();
+    assert!(d[0].changes.iter().all(|c| c.before.is_none()));
+    assert_eq!(preview::initialize(sa, Some(&state), &admin, &init.usdc_mint, &init.params), Err("AccountAlreadyInitialized"));
+
+    assert_eq!(preview::set_paused(sa, &state, &Pubkey::new_unique(), PAUSE_DEPOSITS), Err("Unauthorized"));
+    assert_eq!(preview::set_paused(sa, &state, &admin, 1 << 5), Err("ParamOutOfBounds"));
+    let d = preview::set_paused(sa, &state, &admin, PAUSE_DEPOSITS | PAUSE_CLAIMS).unwrap();
+    assert_eq!(d[0].changes.len(), 1);
+    assert_eq!((d[0].changes[0].before.as_deref(), d[0].changes[0].after.as_str()), (Some("0"), "9"));
+
+    // The guardian only adds to the mask
+    let guardian = Pubkey::new_unique();
+    assert_eq!(preview::guardian_pause(sa, &state, &guardian, PAUSE_TRIGGERS), Err("Unauthorized"));
+    let guarded = State { guardian, pause_flags: PAUSE_DEPOSITS, ..state.clone() };
+    assert!(preview::set_guardian(sa, &state, &admin, &guardian).unwrap()[0].changes.iter().any(|c| c.field == "guardian"));
+    assert_eq!(preview::set_paused(sa, &guarded, &guardian, 0), Err("Unauthorized"));
+    assert_eq!(preview::guardian_pause(sa, &guarded, &guardian, 0), Err("ParamOutOfBounds"));
+    let d = preview::guardian_pause(sa, &guarded, &guardian, PAUSE_TRIGGERS).unwrap();
+    assert_eq!(d[0].changes[0].after, (PAUSE_DEPOSITS | PAUSE_TRIGGERS).to_string());
+
+    let mut curve = Config::parse(EXAMPLE).unwrap().curve.unwrap();
+    curve.tranche_weight_junior_bps = 15_000;
+    assert_eq!(preview::set_curve_and_weights(sa, &state, &admin, &curve), Err("ParamOutOfBounds"));
+
+    let start = |state: &State, history: &EpochHistory, payer: &Pubkey, id: u64, start_ts: i64, end_ts: i64| {
+        preview::start_epoch(sa, state, ha, history, ea, None, None, payer, id, start_ts, end_ts, 150)
+    };
+    let epoch_diff = start(&state, &history, &admin, 1, 200, 300).unwrap();
+    assert!(epoch_diff[0].changes.iter().any(|c| c.field == "current_epoch_id" && c.after == "1"));
+    assert!(epoch_diff[1].changes.iter().any(|c| c.field == "last_end_ts" && c.after == "300"));
+    assert!(epoch_diff[2].changes.iter().any(|c| c.field == "end_ts" && c.after == "300"));
+    assert_eq!(start(&state, &history, &admin, 1, 300, 200), Err("EpochNotActive"));
+    assert_eq!(start(&state, &history, &admin, 1, 100, 200), Err("EpochNotActive"), "no start in the past");
+    assert_eq!(start(&state, &history, &Pubkey::new_unique(), 1, 200, 300), Err("Unauthorized"));
+
+    // Ids only go up; an epoch starts once the previous one's cover has ended (0 = now)
+    let d = start(&state, &history, &admin, 1, 0, 0).unwrap();
+    assert!(d[1].changes.iter().any(|c| c.field == "last_end_ts" && c.after == i64::MAX.to_string()));
+    assert!(d[2].changes.iter().any(|c| c.field == "start_ts" && c.after == "150"));
+    let later = State { current_epoch_id: 3, ..state.clone() };
+    assert_eq!(start(&later, &history, &admin, 3, 200, 300), Err("ParamOutOfBounds"));
+    assert!(start(&later, &history, &admin, 5, 200, 300).is_ok(), "ids may skip");
+    let covered = EpochHistory { last_end_ts: 250, ..history.clone() };
+    assert_eq!(start(&state, &covered, &admin, 1, 200, 300), Err("EpochOverlap"));
+    assert!(start(&state, &covered, &admin, 1, 250, 300).is_ok());
+
+    let mut epoch: Epoch = zeroed(EPOCH_SIZE);
+    (epoch.start_ts, epoch.end_ts) = (100, 200);
+    let args = TriggerArgs { severity_input_bps: 50, ..Default::default() };
+    assert_eq!(preview::trigger_event(sa, &state, ea, &epoch, true, false, &args, 150), Err("Unauthorized"));
+    assert_eq!(preview::trigger_event(sa, &state, ea, &epoch, true, true, &args, 250), Err("EpochNotActive"));
+    for frozen in [State { claim_window_open: true, ..state.clone() }, State { pause_flags: PAUSE_TRIGGERS, ..state.clone() }] {
+        assert_eq!(preview::trigger_event(sa, &frozen, ea, &epoch, true, true, &args, 150), Err("Paused"));
+    }
+    let busy = State { processing: true, ..state.clone() };
+    assert_eq!(preview::trigger_event(sa, &busy, ea, &epoch, true, false, &args, 150), Err("Busy"));
+    let unrelated = State { pause_flags: PAUSE_ALL & !PAUSE_TRIGGERS, ..state.clone() };
+    assert!(preview::trigger_event(sa, &unrelated, ea, &epoch, true, true, &args, 150).is_ok());
+    let d = preview::trigger_event(sa, &state, ea, &epoch, true, true, &args, 150).unwrap();
+    let after = |i: usize, f: &str| d[i].changes.iter().find(|c| c.field == f).map(|c| c.after.clone());
+    assert_eq!(after(0, "claim_window_open").as_deref(), Some("true"));
+    assert_eq!(after(1, "severity_bps").as_deref(), Some("100"), "floored to severity_floor_bps");
+    assert_eq!(after(1, "epoch_cap_fp"), Some(state.epoch_cap_fp.to_string()));
+    assert_eq!(after(1, "total_stake_snapshot_fp"), Some(state.total_deposited_fp.to_string()));
+    let sold = Epoch { cover_sold_fp: 500 * FP, ..epoch.clone() };
+    let d = preview::trigger_event(sa, &state, ea, &sold, true, true, &args, 150).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "cover_owed_fp" && c.after == (5 * FP).to_string()));
+    assert!(d[1].changes.iter().any(|c| c.field == "triggered_ts" && c.after == "150"));
+
+    // An untriggered epoch ends past its window, an open-ended one once it has started
+    let end = |epoch: &Epoch, now: i64| preview::end_epoch(&state, &admin, ea, epoch, ha, &history, now);
+    assert_eq!(end(&epoch, 150), Err("EpochNotActive"));
+    assert_eq!(preview::end_epoch(&state, &Pubkey::new_unique(), ea, &epoch, ha, &history, 250), Err("Unauthorized"));
+    let open_ended = Epoch { end_ts: 0, ..epoch.clone() };
+    assert_eq!(end(&open_ended, 50), Err("EpochNotActive"), "not started");
+    let d = end(&open_ended, 150).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "closed" && c.after == "true"));
+    assert!(d[1].changes.iter().any(|c| c.field == "last_end_ts" && c.after == "150"));
+    let ended = Epoch { closed: true, finalized_ts: 250, ..epoch.clone() };
+    assert_eq!(end(&ended, 300), Err("EpochNotActive"));
+    assert_eq!(preview::trigger_event(sa, &state, ea, &ended, true, true, &args, 150), Err("EpochNotActive"));
+
+    epoch.triggered = true;
+    epoch.shortfall_fp = 5;
+    epoch.epoch_id = 1;
+    let finalize = |state: &State, epoch: &Epoch, sweep_dust: Option<u64>| {
+        preview::finalize_epoch(sa, state, &admin, ea, epoch, ha, &history, sweep_dust, 300)
+    };
+    let d = finalize(&state, &epoch, Some(1)).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "carryover_shortfall_fp" && c.after == "5"));
+    assert!(d[1].changes.iter().any(|c| c.field == "closed"));
+    assert!(d[1].changes.iter().any(|c| c.field == "finalized_ts" && c.after == "300"));
+    let changed: Vec<_> = d[2].changes.iter().map(|c| c.field).collect();
+    assert_eq!(changed, ["last_epoch_id", "count", "records"], "an earlier epoch leaves the registry's end alone");
+    let open_ended = Epoch { end_ts: 0, ..epoch.clone() };
+    let d = finalize(&State { current_epoch_id: 1, ..state.clone() }, &open_ended, None).unwrap();
+    assert!(d[2].changes.iter().any(|c| c.field == "last_end_ts" && c.after == "300"), "finalizing ends its cover");
+    // Unclaimed cover holds finalization off for a week after the trigger
+    let covered = Epoch { cover_sold_fp: 500 * FP, cover_claimed_fp: 300 * FP, triggered_ts: 200, ..epoch.clone() };
+    assert_eq!(finalize(&state, &covered, None), Err("CoverClaimWindowActive"));
+    let claimed = Epoch { cover_claimed_fp: 500 * FP, ..covered.clone() };
+    assert!(finalize(&state, &claimed, None).is_ok());
+    let lapsed = Epoch { triggered_ts: 300 - 7 * 86_400, ..covered.clone() };
+    let owing = State { cover_owed_fp: 20 * FP, ..state.clone() };
+    let d = finalize(&owing, &lapsed, None).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "cover_owed_fp" && c.after == "0"));
+    let no_sweeps = State { pause_flags: PAUSE_FEE_SWEEPS, ..state.clone() };
+    assert_eq!(finalize(&no_sweeps, &epoch, Some(1)), Err("Paused"));
+    assert!(finalize(&no_sweeps, &epoch, None).is_ok());
+    assert_eq!(finalize(&busy, &epoch, None), Err("Busy"));
+
+    let shut = preview::shutdown(sa, &state, &admin, None, 300).unwrap();
+    assert!(shut[0].changes.iter().any(|c| c.field == "shutdown_ts" && c.after == "300"));
+    // An open claim window is settled by the shutdown, its unpaid liability booked as shortfall
+    let claiming = State { claim_window_open: true, ..state.clone() };
+    assert_eq!(preview::shutdown(sa, &claiming, &admin, None, 300), Err("EpochNotActive"));
+    let paying = Epoch { total_stake_snapshot_fp: 2_000 * FP, severity_bps: 1_000, epoch_cap_fp: 500 * FP, ..epoch.clone() };
+    let paying = Epoch { total_payout_fp: 150 * FP, ..paying };
+    let shut = preview::shutdown(sa, &claiming, &admin, Some((ea, &paying, ha, &history)), 300).unwrap();
+    assert!(shut[0].changes.iter().any(|c| c.field == "claim_window_open" && c.after == "false"));
+    assert!(shut[1].changes.iter().any(|c| c.field == "shortfall_fp" && c.after == (50 * FP).to_string()));
+    let shut = State { shutdown: true, ..state.clone() };
+    assert_eq!(preview::shutdown(sa, &shut, &admin, None, 300), Err("PoolShutdown"));
+    let untriggered = Epoch { triggered: false, ..epoch.clone() };
+    assert_eq!(preview::trigger_event(sa, &shut, ea, &untriggered, true, true, &args, 150), Err("PoolShutdown"));
+    assert_eq!(start(&shut, &history, &admin, 2, 200, 300), Err("PoolShutdown"));
+    let running = State { outflow_window_updated_ts: 90, outflow_window_used_fp: 7, ..state.clone() };
+    let d = preview::set_outflow_limits(sa, &running, &admin, 3_600, 1_000 * FP, 2_000).unwrap();
+    let changed: Vec<_> = d[0].changes.iter().map(|c| c.field).collect();
+    assert_eq!(changed, ["outflow_window_secs", "outflow_cap_fp", "outflow_cap_bps", "outflow_window_updated_ts", "outflow_window_used_fp"]);
+    assert_eq!(preview::set_outflow_limits(sa, &state, &admin, 3_600, 0, 10_001), Err("ParamOutOfBounds"));
+    assert_eq!(preview::set_outflow_limits(sa, &state, &admin, -1, 0, 0), Err("ParamOutOfBounds"));
+    let d = preview::set_crank_reward(sa, &state, &admin, 1_000_000).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "crank_reward_u64" && c.after == "1000000"));
+    assert_eq!(preview::set_crank_reward(sa, &state, &admin, 10_000_001), Err("ParamOutOfBounds"));
+    assert_eq!(preview::crank_payouts(&state, &epoch, 2), Ok(Vec::new()));
+    assert_eq!(preview::crank_payouts(&state, &epoch, 0), Err("InvalidRemainingAccounts"));
+    assert_eq!(preview::crank_payouts(&busy, &epoch, 2), Err("Busy"));
+    let no_claims = State { pause_flags: PAUSE_CLAIMS, ..state.clone() };
+    assert_eq!(preview::crank_payouts(&no_claims, &epoch, 2), Err("Paused"));
+    assert_eq!(preview::crank_payouts(&state, &untriggered, 2), Err("EpochNotActive"));
+    let deployed = State { strategy_deployed_fp: 1, ..state.clone() };
+    assert_eq!(preview::shutdown(sa, &deployed, &admin, None, 300), Err("StrategyActive"));
+
+    assert_eq!(preview::close_epoch(&state, &admin, ea, &epoch, None), Err("EpochNotActive"));
+    (epoch.closed, epoch.open_receipts) = (true, 2);
+    assert_eq!(preview::close_epoch(&state, &admin, ea, &epoch, None), Err("AccountNotEmpty"));
+    epoch.open_receipts = 0;
+    assert_eq!(preview::close_epoch(&state, &Pubkey::new_unique(), ea, &epoch, None), Err("Unauthorized"));
+    let d = preview::close_epoch(&state, &admin, ea, &epoch, None).unwrap();
+    assert!(d[0].closed && d[0].changes.is_empty());
+
+    // A pool with an epoch running registers its cover
+    assert_eq!(preview::init_epoch_history(&state, &admin, ha, Some(&history), None), Err("AccountAlreadyInitialized"));
+    assert_eq!(preview::init_epoch_history(&state, &Pubkey::new_unique(), ha, None, None), Err("Unauthorized"));
+    let d = preview::init_epoch_history(&state, &admin, ha, None, Some(&open_ended)).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "last_end_ts" && c.after == i64::MAX.to_string()));
+
+    // Cover limits and the premium curve are bounded like every other bps parameter
+    let ba = Pubkey::new_unique();
+    let cover = Config::parse(EXAMPLE).unwrap().cover.unwrap();
+    let steep = CoverParams { slope2_bps: 10_001, ..cover.clone() };
+    assert_eq!(preview::init_cover_book(&state, &admin, ba, None, &steep), Err("ParamOutOfBounds"));
+    assert_eq!(preview::init_cover_book(&state, &Pubkey::new_unique(), ba, None, &cover), Err("Unauthorized"));
+    let d = preview::init_cover_book(&state, &admin, ba, None, &cover).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "max_utilisation_bps" && c.after == "5000"));
+    let mut book: CoverBook = zeroed(COVER_BOOK_SIZE);
+    assert_eq!(preview::set_cover_params(&state, &admin, ba, &book, &steep), Err("ParamOutOfBounds"));
+    book.max_utilisation_bps = cover.max_utilisation_bps;
+    let d = preview::set_cover_params(&state, &admin, ba, &book, &CoverParams { base_rate_bps: 100, ..flat(5_000) }).unwrap();
+    assert_eq!(d[0].changes.len(), 1);
+    assert_eq!((d[0].changes[0].field, d[0].changes[0].after.as_str()), ("base_rate_bps", "100"));
+}
+
+/// Cover book params with no premium.
+fn flat(max_utilisation_bps: u16) -> CoverParams {
+    CoverParams {
+        max_utilisation_bps,
+        base_rate_bps: 0,
+        kink_utilisation_bps: 0,
+        slope1_bps: 0,
+        slope2_bps: 0,
+        event_probability_bps: 0,
+        reference_input_bps: 0,
+    }
+}
+
+#[test]
+fn rolling_epochs_chain_and_cover_deposits_from_the_next_epoch() {
+    let (admin, anyone) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (sa, ea, ha) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let history: EpochHistory = zeroed(HISTORY_SIZE);
+    let state = state(admin);
+    assert_eq!(preview::set_rolling_mode(sa, &state, &admin, true, 0), Err("ParamOutOfBounds"));
+    assert_eq!(preview::set_rolling_mode(sa, &state, &anyone, true, 100), Err("Unauthorized"));
+    let d = preview::set_rolling_mode(sa, &state, &admin, true, 100).unwrap();
+    let changed: Vec<_> = d[0].changes.iter().map(|c| c.field).collect();
+    assert_eq!(changed, ["rolling_mode", "epoch_duration_secs"]);
+    let rolling = State { rolling_mode: true, epoch_duration_secs: 100, current_epoch_id: 1, ..state.clone() };
+
+    // The window comes from the pool; ids are sequential
+    let start = |payer: &Pubkey, id: u64, ts: (i64, i64), prev: Option<&Epoch>, now: i64| {
+        preview::start_epoch(sa, &rolling, ha, &history, ea, None, prev, payer, id, ts.0, ts.1, now)
+    };
+    let mut prev: Epoch = zeroed(EPOCH_SIZE);
+    (prev.epoch_id, prev.start_ts, prev.end_ts) = (1, 1_000, 1_100);
+    assert_eq!(start(&anyone, 2, (1_000, 0), Some(&prev), 1_150), Err("ParamOutOfBounds"));
+    assert_eq!(start(&anyone, 3, (0, 0), Some(&prev), 1_150), Err("ParamOutOfBounds"));
+    assert_eq!(start(&anyone, 2, (0, 0), Some(&prev), 1_050), Err("EpochNotActive"));
+    assert_eq!(start(&anyone, 2, (0, 0), None, 1_150), Err("Unauthorized"), "only the admin starts the first chain");
+    let window = |d: &[preview::AccountDiff]| {
+        let get = |f: &str| d[2].changes.iter().find(|c| c.field == f).unwrap().after.clone();
+        (get("start_ts"), get("end_ts"))
+    };
+    // Anyone rolls once the previous epoch has ended, skipping missed durations
+    assert_eq!(window(&start(&anyone, 2, (0, 0), Some(&prev), 1_100).unwrap()), ("1100".into(), "1200".into()));
+    assert_eq!(window(&start(&anyone, 2, (0, 0), Some(&prev), 1_350).unwrap()), ("1300".into(), "1400".into()));
+    assert_eq!(window(&start(&admin, 2, (0, 0), None, 1_150).unwrap()), ("1150".into(), "1250".into()));
+
+    // Principal deposited during the epoch is left out of its snapshot and of the owner's share
+    let mut epoch = Epoch { epoch_id: 2, start_ts: 1_100, end_ts: 1_200, pending_deposits_fp: 500 * FP, ..prev };
+    let args = TriggerArgs { severity_input_bps: 1_000, ..Default::default() };
+    let d = preview::trigger_event(sa, &rolling, ea, &epoch, true, true, &args, 1_150).unwrap();
+    assert!(d[1].changes.iter().any(|c| c.field == "total_stake_snapshot_fp" && c.after == (1_500 * FP).to_string()));
+    (epoch.triggered, epoch.severity_bps, epoch.total_stake_snapshot_fp) = (true, 1_000, 1_500 * FP);
+    epoch.epoch_cap_fp = rolling.epoch_cap_fp;
+    let weights = State { tranche_weight_senior_bps: 10_000, tranche_weight_junior_bps: 10_000, ..rolling };
+    let position = |senior: u128, pending_epoch_id: u64| UserPosition {
+        owner: Pubkey::new_unique(),
+        senior_deposited_fp: senior * FP,
+        junior_deposited_fp: 0,
+        pending_epoch_id,
+        pending_senior_fp: 500 * FP,
+        ..UserPosition::decode(fixture!("user_position.bin")).unwrap()
+    };
+    let positions = [position(1_000, 1), position(1_000, 2)];
+    let owed = merkle::entitlements(&weights, &epoch, 1_000_000 * FP, &positions).unwrap();
+    assert_eq!(owed.iter().find(|(o, _)| *o == positions[0].owner).unwrap().1, 100 * FP, "pending from epoch 1 is covered");
+    assert_eq!(owed.iter().find(|(o, _)| *o == positions[1].owner).unwrap().1, 50 * FP);
+}
+
+#[test]
+fn merkle_distribution_prices_positions_and_verifies() {
+    let admin = Pubkey::new_unique();
+    let state = State { tranche_weight_senior_bps: 10_000, tranche_weight_junior_bps: 10_000, ..state(admin) };
+    let mut epoch: Epoch = zeroed(EPOCH_SIZE);
+    (epoch.epoch_id, epoch.triggered, epoch.severity_bps) = (3, true, 1_000);
+    (epoch.total_stake_snapshot_fp, epoch.epoch_cap_fp) = (state.total_deposited_fp, state.epoch_cap_fp);
+    let position = |senior: u128, junior: u128| UserPosition {
+        owner: Pubkey::new_unique(),
+        senior_deposited_fp: senior * FP,
+        junior_deposited_fp: junior * FP,
+        ..UserPosition::decode(fixture!("user_position.bin")).unwrap()
+    };
+    let positions = [position(1_000, 0), position(0, 500), position(0, 0)];
+
+    // 10% of a 2,000 snapshot, shared pro rata; the empty position is left out
+    let owed = merkle::entitlements(&state, &epoch, 1_000_000 * FP, &positions).unwrap();
+    assert_eq!(owed.len(), 2);
+    assert!(owed.windows(2).all(|w| w[0].0 < w[1].0), "sorted by owner");
+    assert_eq!(owed.iter().find(|(o, _)| *o == positions[0].owner).unwrap().1, 100 * FP);
+    assert_eq!(owed.iter().find(|(o, _)| *o == positions[1].owner).unwrap().1, 50 * FP);
+    // Below the liability, shares shrink with the pool
+    let thin = merkle::entitlements(&state, &epoch, 30 * FP, &positions).unwrap();
+    assert_eq!(thin.iter().map(|(_, a)| a).sum::<u128>(), 45 * FP / 2);
+
+    let dist = Distribution::new(3, &owed).unwrap();
+    assert_eq!(dist.total_fp, 150 * FP);
+    assert!(dist.claims.iter().all(|c| dist.verify(c)));
+    let mut forged = dist.claim(&positions[1].owner).unwrap().clone();
+    forged.amount_fp += 1;
+    assert!(!dist.verify(&forged));
+    assert!(Distribution::new(3, &[]).is_none());
+    let json = serde_json::to_string(&dist).unwrap();
+    assert_eq!(serde_json::from_str::<Distribution>(&json).unwrap(), dist);
+    let five: Vec<_> = (0..5u128).map(|i| (Pubkey::new_unique(), i + 1)).collect();
+    let odd = Distribution::new(3, &five).unwrap();
+    assert!(odd.claims.iter().all(|c| odd.verify(c)), "odd leaves carry up");
+
+    let b = Builder::new(PROGRAM_ID);
+    let pdas = Pdas::new(PROGRAM_ID);
+    let ix = b.post_merkle_root(&admin, 3, dist.root, dist.total_fp, 2);
+    assert_eq!(&ix.data[..8], &sighash("post_merkle_root"));
+    assert_eq!(ix.data.len(), 8 + 32 + 16 + 4);
+    assert_eq!(ix.accounts[3].pubkey, pdas.bitmap(3));
+    let claim = &dist.claims[0];
+    let mint = Pubkey::new_unique();
+    let ix = b.claim_with_proof(&claim.owner, 3, &mint, claim.index, claim.amount_fp, &claim.proof);
+    assert_eq!(ix.data.len(), 8 + 4 + 16 + 4 + 32 * claim.proof.len());
+    assert!(ix.accounts[0].is_signer && !ix.accounts[0].is_writable);
+    assert_eq!(ix.accounts[6].pubkey, associated_token_address(&claim.owner, &mint));
+    let ix = b.close_epoch(&admin, 3, false);
+    assert_eq!((ix.accounts[3].pubkey, ix.accounts[3].is_writable), (PROGRAM_ID, false));
+    assert!(b.close_epoch(&admin, 3, true).accounts[3].is_writable);
+
+    let (ea, ba) = (pdas.epoch(3), pdas.bitmap(3));
+    let post = |e: &Epoch, bm: Option<&ClaimBitmap>, total_fp: u128, n: u32| {
+        preview::post_merkle_root(&state, &admin, ea, e, ba, bm, 30 * FP, dist.root, total_fp, n)
+    };
+    assert_eq!(post(&epoch, None, 31 * FP, 2), Err("ParamOutOfBounds"), "above the liability at the pool value");
+    assert_eq!(post(&epoch, None, 30 * FP, 0), Err("ParamOutOfBounds"));
+    let paid = Epoch { total_payout_fp: 1, ..epoch.clone() };
+    assert_eq!(post(&paid, None, 30 * FP, 2), Err("SettlementModeConflict"));
+    let d = post(&epoch, None, 30 * FP, 2).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "shortfall_fp" && c.after == (170 * FP).to_string()));
+    assert!(d[1].changes.iter().all(|c| c.before.is_none()) && d[1].changes.iter().any(|c| c.field == "claimants"));
+
+    let posted = Epoch { merkle_root: dist.root, merkle_total_fp: dist.total_fp, ..epoch.clone() };
+    let bitmap = ClaimBitmap { epoch_id: 3, claimants: 2, claimed: 0, bump: 255, version: ACCOUNT_VERSION, reserved: [0; 16], bits: vec![0] };
+    assert_eq!(post(&posted, Some(&bitmap), 30 * FP, 2), Err("AccountAlreadyInitialized"));
+    assert_eq!(preview::crank_payouts(&state, &posted, 2), Err("SettlementModeConflict"));
+    let d = preview::claim_with_proof(&state, ea, &posted, ba, &bitmap, claim).unwrap();
+    assert!(d[0].changes.iter().any(|c| c.field == "total_payout_fp" && c.after == claim.amount_fp.to_string()));
+    assert!(d[1].changes.iter().any(|c| c.field == "claimed" && c.after == "1"));
+    assert_eq!(preview::claim_with_proof(&state, ea, &posted, ba, &bitmap, &forged), Err("InvalidMerkleProof"));
+    assert_eq!(preview::claim_with_proof(&state, ea, &epoch, ba, &bitmap, claim), Err("SettlementModeConflict"));
+    let taken = ClaimBitmap { bits: vec![1 << claim.index], ..bitmap.clone() };
+    assert_eq!(preview::claim_with_proof(&state, ea, &posted, ba, &taken, claim), Err("NothingToPayout"));
+    let nearly_paid = Epoch { total_payout_fp: dist.total_fp, ..posted.clone() };
+    assert_eq!(preview::claim_with_proof(&state, ea, &nearly_paid, ba, &bitmap, claim), Err("InsufficientPoolBalance"));
+
+    let closed = Epoch { closed: true, ..posted };
+    assert_eq!(preview::close_epoch(&state, &admin, ea, &closed, None), Err("SettlementModeConflict"));
+    let d = preview::close_epoch(&state, &admin, ea, &closed, Some(ba)).unwrap();
+    assert_eq!((d.len(), d[1].name, d[1].closed), (2, "ClaimBitmap", true));
+}
+
+#[test]
+fn legacy_accounts_decode_and_migrate() {
+    // Accounts written before layout versioning decode from their old layout as version 0
+    let state = State::decode(fixture!("state.bin")).unwrap();
+    let oracle = OracleList::decode(fixture!("oracle_list.bin")).unwrap();
+    let position = UserPosition::decode(fixture!("user_position.bin")).unwrap();
+    let epoch = Epoch::decode(fixture!("epoch.bin")).unwrap();
+    let claim = ClaimReceipt::decode(fixture!("claim_receipt.bin")).unwrap();
+    assert_eq!(fixture!("state.bin").len(), 279);
+    assert_eq!((state.version, state.claim_window_open, state.pause_flags, state.current_epoch_id), (0, false, 0, 0));
+    assert_eq!((state.total_deposited_fp, state.senior_total_fp, state.payout_policy), (3_000 * FP, 0, 1));
+    assert_eq!((oracle.count, oracle.bump, position.senior_deposited_fp, position.senior_lots.len), (2, 0, 2_000 * FP, 2));
+    assert_eq!((epoch.total_payout_fp, epoch.open_receipts, claim.claimed_fp), (150 * FP, 0, 150 * FP));
+    assert_eq!(position.bump, 0, "deposits never recorded the position bump");
+
+    let pdas = Pdas::new(PROGRAM_ID);
+    let (admin, owner, mint) = (state.admin, position.owner, state.usdc_mint);
+    let epochs = [(pdas.epoch(1), epoch.clone())];
+    let oracle_list = (pdas.oracle(), &oracle, pdas.oracle_bump());
+    let vault = (pdas.vault(&mint), 6, 2_850_000_000);
+    let run = |s: &State, who, epochs: &[(Pubkey, Epoch)]| {
+        preview::migrate_state((pdas.state(), s), oracle_list, (pdas.collateral(), None), vault, epochs, who, 1_800_000_000)
+    };
+    let d = run(&state, &admin, &epochs).unwrap();
+    assert_eq!(d.len(), 4, "state, oracle list, new registry, epoch");
+    let after = |i: usize, field: &str| d[i].changes.iter().find(|c| c.field == field).map(|c| c.after.clone());
+    assert_eq!(after(0, "pause_flags"), Some(PAUSE_ALL.to_string()));
+    assert_eq!(after(0, "current_epoch_id").as_deref(), Some("1"));
+    assert_eq!(after(1, "bump"), Some(pdas.oracle_bump().to_string()));
+    assert_eq!(d[2].address, pdas.collateral());
+    assert_eq!(after(3, "finalized_ts").as_deref(), Some("1800000000"), "closed legacy epochs count as finalized");
+    let stranger = Pubkey::new_unique();
+    assert_eq!(run(&state, &stranger, &[]), Err("Unauthorized"));
+    let current = State { version: ACCOUNT_VERSION, ..state.clone() };
+    assert!(run(&current, &stranger, &[]).unwrap()[0].changes.is_empty(), "current accounts are left as is");
+    assert_eq!(run(&State { version: ACCOUNT_VERSION + 1, ..state }, &admin, &[]), Err("UnsupportedAccountVersion"));
+
+    // Positions migrate against the migrated state and epochs
+    let migrated = State { version: ACCOUNT_VERSION, ..current };
+    let epoch = Epoch { version: ACCOUNT_VERSION, ..epoch };
+    let claims = [(pdas.claim(1, &owner), claim.clone(), pdas.epoch(1), epoch.clone())];
+    let bump = pdas.position_bump(&owner);
+    let migrate = |who, claims: &[_]| {
+        preview::migrate_position((pdas.position(&owner), &position), who, bump, (pdas.state(), &migrated), claims)
+    };
+    let d = migrate(&owner, &claims).unwrap();
+    assert_eq!(d.len(), 4, "position, state totals, receipt, epoch");
+    let after = |i: usize, field: &str| d[i].changes.iter().find(|c| c.field == field).map(|c| c.after.clone());
+    assert_eq!(after(0, "bump"), Some(bump.to_string()), "bump is repaired");
+    assert_eq!(after(0, "senior_withdrawable_fp").as_deref(), Some("0"), "principal still in lots is not withdrawable");
+    assert_eq!(after(1, "senior_total_fp"), Some((2_000 * FP).to_string()));
+    assert_eq!(after(2, "payer"), Some(format!("{owner:?}")), "the claimant paid a legacy receipt");
+    assert_eq!(after(3, "open_receipts").as_deref(), Some("1"));
+    assert_eq!(migrate(&stranger, &[]), Err("Unauthorized"));
+    let other = [(pdas.claim(1, &owner), claim, pdas.epoch(2), Epoch { epoch_id: 2, ..epoch })];
+    assert_eq!(migrate(&owner, &other), Err("InvalidRemainingAccounts"));
+
+    let ix = Builder::new(PROGRAM_ID).migrate_position(&stranger, &owner, &[1]);
+    assert_eq!(ix.accounts.len(), 7);
+    assert_eq!((ix.accounts[5].pubkey, ix.accounts[6].pubkey), (pdas.claim(1, &owner), pdas.epoch(1)));
+    assert!(ix.accounts[0].is_signer && !ix.accounts[1].is_signer && ix.accounts[3].is_writable);
+    let ix = Builder::new(PROGRAM_ID).migrate_state(&admin, &mint, &[1]);
+    assert_eq!((ix.accounts[4].pubkey, ix.accounts[5].pubkey), (mint, pdas.vault(&mint)));
+}
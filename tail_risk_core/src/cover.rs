@@ -0,0 +1,46 @@
+//! Cover sold to protection buyers.
+//!
+//! Each epoch the pool may sell cover (notional) up to `max_utilisation_bps`
+//! of its tranche capital. A claim on a triggered epoch pays the notional
+//! times the epoch's effective severity.
+
+use crate::{
+    math::{bps_of, mul_div_floor},
+    BPS_DENOM,
+};
+
+/// Capacity of the pool with some cover outstanding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capacity {
+    /// Most cover that may be outstanding.
+    pub max_fp: u128,
+    /// Cover still for sale (0 once capital has shrunk below what was sold).
+    pub available_fp: u128,
+    /// Outstanding cover in bps of capital (`u128::MAX` for cover without capital).
+    pub utilisation_bps: u128,
+}
+
+/// `sold_fp` in bps of `capital_fp`.
+pub fn utilisation_bps(capital_fp: u128, sold_fp: u128) -> Option<u128> {
+    match (capital_fp, sold_fp) {
+        (_, 0) => Some(0),
+        (0, _) => Some(u128::MAX),
+        _ => mul_div_floor(sold_fp, BPS_DENOM, capital_fp),
+    }
+}
+
+/// Capacity with `sold_fp` of cover outstanding against `capital_fp`.
+pub fn capacity(capital_fp: u128, sold_fp: u128, max_utilisation_bps: u16) -> Option<Capacity> {
+    let max_fp = bps_of(capital_fp, max_utilisation_bps as u128)?;
+    Some(Capacity {
+        max_fp,
+        available_fp: max_fp.saturating_sub(sold_fp),
+        utilisation_bps: utilisation_bps(capital_fp, sold_fp)?,
+    })
+}
+
+/// Payout on `notional_fp` of cover for an epoch triggered at `severity_bps`
+/// (capped at 100%).
+pub fn cover_payout_fp(notional_fp: u128, severity_bps: u16) -> Option<u128> {
+    bps_of(notional_fp, (severity_bps as u128).min(BPS_DENOM))
+}
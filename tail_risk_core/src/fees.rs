@@ -0,0 +1,68 @@
+//! Fee models and fee splits.
+
+use crate::{math::bps_of, BPS_DENOM};
+
+/// Where `protocol_fee_bps` / `referral_fee_bps` are charged (`State.fee_model`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FeeModel {
+    /// Charged on every deposit.
+    OnDeposit = 0,
+    /// Charged on premiums paid into the pool.
+    OnPremium = 1,
+    /// Charged on realised strategy yield.
+    OnYield = 2,
+}
+
+impl FeeModel {
+    /// Decode the on-chain `u8` representation.
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::OnDeposit),
+            1 => Some(Self::OnPremium),
+            2 => Some(Self::OnYield),
+            _ => None,
+        }
+    }
+}
+
+/// Breakdown of an amount after protocol and referral fees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepositFees {
+    /// Protocol fee (fixed-point).
+    pub protocol_fp: u128,
+    /// Referral fee (fixed-point).
+    pub referral_fp: u128,
+    /// Amount left after both fees (fixed-point).
+    pub net_fp: u128,
+}
+
+/// Fee rates charged at deposit time: the configured rates under
+/// [`FeeModel::OnDeposit`], zero otherwise.
+pub fn deposit_fee_bps(model: FeeModel, protocol_fee_bps: u16, referral_fee_bps: u16) -> (u16, u16) {
+    match model {
+        FeeModel::OnDeposit => (protocol_fee_bps, referral_fee_bps),
+        _ => (0, 0),
+    }
+}
+
+/// Split `amount_fp` into protocol fee, referral fee and net (each fee floored).
+pub fn split_fees(amount_fp: u128, protocol_bps: u16, referral_bps: u16) -> Option<DepositFees> {
+    let protocol_fp = bps_of(amount_fp, protocol_bps as u128)?;
+    let referral_fp = bps_of(amount_fp, referral_bps as u128)?;
+    Some(DepositFees {
+        protocol_fp,
+        referral_fp,
+        net_fp: amount_fp.saturating_sub(protocol_fp.saturating_add(referral_fp)),
+    })
+}
+
+/// Protocol share of realised yield: the performance fee, plus the protocol
+/// fee under [`FeeModel::OnYield`] (capped at 100%).
+pub fn yield_fee_fp(model: FeeModel, protocol_fee_bps: u16, performance_fee_bps: u16, yield_fp: u128) -> Option<u128> {
+    let mut bps = performance_fee_bps as u128;
+    if model == FeeModel::OnYield {
+        bps = bps.saturating_add(protocol_fee_bps as u128);
+    }
+    bps_of(yield_fp, core::cmp::min(bps, BPS_DENOM))
+}
@@ -0,0 +1,38 @@
+//! Pure math core of the tail-risk insurance pool.
+//!
+//! Everything here is `no_std`, allocation-free and Anchor-independent, so the
+//! on-chain program, off-chain services and fuzzers all run the same code.
+//!
+//! Conventions (same as the program):
+//! - amounts are `u128` fixed-point with [`SCALE`] = 1e6 (`*_fp`)
+//! - rates are basis points out of [`BPS_DENOM`] (`*_bps`)
+//! - every division floors; checked operations return `None` on overflow or
+//!   division by zero
+
+#![no_std]
+#![warn(missing_docs)]
+
+pub mod cover;
+pub mod fees;
+pub mod lots;
+pub mod math;
+pub mod merkle;
+pub mod outflow;
+pub mod payout;
+pub mod pricing;
+pub mod severity;
+pub mod yields;
+
+pub use cover::Capacity;
+pub use fees::{DepositFees, FeeModel};
+pub use lots::{Lot, LotEntry};
+pub use outflow::{OutflowLimits, OutflowWindow};
+pub use payout::{EpochLiability, PayoutPolicy, PayoutQuote};
+pub use pricing::{PremiumCurve, PremiumQuote};
+
+/// Fixed-point scale (1e6).
+pub const SCALE: u128 = 1_000_000;
+/// Basis-point denominator (100%).
+pub const BPS_DENOM: u128 = 10_000;
+/// Common accounting decimals for all collateral (USDC-style).
+pub const COMMON_DECIMALS: u8 = 6;
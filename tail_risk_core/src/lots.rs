@@ -0,0 +1,88 @@
+//! FIFO deposit lots kept in a fixed-size ring buffer.
+//!
+//! The ring is borrowed as separate `head` / `len` / `buf` fields so the
+//! program can keep its own serialisable `Lots` layout.
+
+/// Anything stored in a lot ring: an amount and the time it was deposited.
+pub trait LotEntry: Copy {
+    /// Lot amount (fixed-point).
+    fn amount_fp(&self) -> u128;
+    /// Deposit timestamp (unix seconds).
+    fn ts(&self) -> i64;
+}
+
+/// Plain lot for off-chain use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Lot {
+    /// Lot amount (fixed-point).
+    pub amount_fp: u128,
+    /// Deposit timestamp (unix seconds).
+    pub ts: i64,
+}
+
+impl LotEntry for Lot {
+    fn amount_fp(&self) -> u128 {
+        self.amount_fp
+    }
+    fn ts(&self) -> i64 {
+        self.ts
+    }
+}
+
+/// Mutable view over a ring buffer of lots; capacity is `buf.len()`.
+pub struct LotRing<'a, L> {
+    /// Index of the oldest lot.
+    pub head: &'a mut u8,
+    /// Number of live lots.
+    pub len: &'a mut u8,
+    /// Backing storage.
+    pub buf: &'a mut [L],
+}
+
+impl<L: LotEntry> LotRing<'_, L> {
+    /// Append a lot; returns `false` (and leaves the ring untouched) when full.
+    pub fn push(&mut self, lot: L) -> bool {
+        let cap = self.buf.len();
+        if (*self.len as usize) >= cap {
+            return false;
+        }
+        let idx = ((*self.head as usize) + (*self.len as usize)) % cap;
+        self.buf[idx] = lot;
+        *self.len += 1;
+        true
+    }
+
+    /// Pop the oldest lot if it has been held for at least `lockup_secs`.
+    pub fn pop_matured(&mut self, lockup_secs: i64, now: i64) -> Option<L> {
+        if *self.len == 0 {
+            return None;
+        }
+        let lot = self.buf[*self.head as usize];
+        if now.saturating_sub(lot.ts()) >= lockup_secs {
+            *self.head = ((*self.head as usize + 1) % self.buf.len()) as u8;
+            *self.len -= 1;
+            Some(lot)
+        } else {
+            None
+        }
+    }
+
+    /// Move every matured lot into `withdrawable_fp`, then consume as much of
+    /// `remaining` as the withdrawable balance allows.
+    pub fn mature_and_consume(&mut self, lockup_secs: i64, now: i64, withdrawable_fp: &mut u128, remaining: &mut u128) {
+        while let Some(l) = self.pop_matured(lockup_secs, now) {
+            *withdrawable_fp = withdrawable_fp.saturating_add(l.amount_fp());
+        }
+        let take = core::cmp::min(*withdrawable_fp, *remaining);
+        *withdrawable_fp = withdrawable_fp.saturating_sub(take);
+        *remaining = remaining.saturating_sub(take);
+    }
+
+    /// Sum of all live (not yet matured-out) lots.
+    pub fn locked_fp(&self) -> u128 {
+        let cap = self.buf.len();
+        (0..*self.len as usize)
+            .map(|i| self.buf[(*self.head as usize + i) % cap].amount_fp())
+            .fold(0u128, |acc, x| acc.saturating_add(x))
+    }
+}
@@ -0,0 +1,57 @@
+//! Fixed-point and decimal conversions.
+
+use crate::{BPS_DENOM, COMMON_DECIMALS, SCALE};
+
+/// Whole units -> fixed-point.
+pub fn to_fp(amount: u64) -> Option<u128> {
+    (amount as u128).checked_mul(SCALE)
+}
+
+/// Fixed-point -> whole units (floored).
+pub fn from_fp(amount_fp: u128) -> Option<u64> {
+    u64::try_from(amount_fp / SCALE).ok()
+}
+
+/// Fixed-point -> whole units, rounded up.
+pub fn from_fp_ceil(amount_fp: u128) -> Option<u64> {
+    u64::try_from(amount_fp.div_ceil(SCALE)).ok()
+}
+
+/// `floor(a * b / denom)`.
+pub fn mul_div_floor(a: u128, b: u128, denom: u128) -> Option<u128> {
+    a.checked_mul(b)?.checked_div(denom)
+}
+
+/// `floor(amount * bps / 10_000)`.
+pub fn bps_of(amount_fp: u128, bps: u128) -> Option<u128> {
+    mul_div_floor(amount_fp, bps, BPS_DENOM)
+}
+
+/// Rescale a value between decimal precisions (floors when reducing).
+pub fn rescale_decimals(x: u128, from: u8, to: u8) -> Option<u128> {
+    if from == to {
+        return Some(x);
+    }
+    let factor = 10u128.checked_pow(from.abs_diff(to) as u32)?;
+    if from > to {
+        x.checked_div(factor)
+    } else {
+        x.checked_mul(factor)
+    }
+}
+
+/// Native units of an asset with `decimals` -> common-unit fixed-point.
+pub fn asset_to_fp(amount: u64, decimals: u8) -> Option<u128> {
+    rescale_decimals(to_fp(amount)?, decimals, COMMON_DECIMALS)
+}
+
+/// Common-unit fixed-point -> native units of an asset with `decimals` (floored).
+pub fn fp_to_asset(amount_fp: u128, decimals: u8) -> Option<u64> {
+    from_fp(rescale_decimals(amount_fp, COMMON_DECIMALS, decimals)?)
+}
+
+/// Value credited for `par_fp` of an asset after its haircut.
+pub fn apply_haircut(par_fp: u128, haircut_bps: u16) -> Option<u128> {
+    let keep_bps = BPS_DENOM.saturating_sub(haircut_bps as u128);
+    bps_of(par_fp, keep_bps)
+}
@@ -0,0 +1,53 @@
+//! Merkle proofs and claim bitmaps for epochs settled by a posted root.
+//!
+//! A leaf commits to `(index, owner, amount_fp)`. Interior nodes hash the
+//! sorted pair of their children, so a proof is just the sibling hashes from
+//! the leaf up. Leaves and nodes carry distinct prefixes so that one can never
+//! pass for the other. The hash function (sha256 on chain) is supplied by the
+//! caller as `hashv`, which hashes the concatenation of its inputs.
+
+/// A 32-byte hash.
+pub type Hash = [u8; 32];
+
+/// Domain prefix of leaf hashes.
+pub const LEAF_PREFIX: u8 = 0;
+/// Domain prefix of interior node hashes.
+pub const NODE_PREFIX: u8 = 1;
+
+/// Leaf for claimant `index` (position in the distribution) owed `amount_fp`.
+pub fn leaf(index: u32, owner: &[u8; 32], amount_fp: u128, hashv: impl Fn(&[&[u8]]) -> Hash) -> Hash {
+    hashv(&[&[LEAF_PREFIX], &index.to_le_bytes(), owner, &amount_fp.to_le_bytes()])
+}
+
+/// Parent of two nodes (order-independent).
+pub fn node(a: &Hash, b: &Hash, hashv: impl Fn(&[&[u8]]) -> Hash) -> Hash {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[NODE_PREFIX], lo, hi])
+}
+
+/// Whether `proof` leads from `leaf` to `root`.
+pub fn verify(leaf: Hash, proof: &[Hash], root: &Hash, hashv: impl Fn(&[&[u8]]) -> Hash) -> bool {
+    let computed = proof.iter().fold(leaf, |acc, sibling| node(&acc, sibling, &hashv));
+    computed == *root
+}
+
+/// Bytes needed for a bitmap of `claimants` bits.
+pub fn bitmap_len(claimants: u32) -> usize {
+    (claimants as usize).div_ceil(8)
+}
+
+/// Whether bit `index` is set (out of range reads as set, so it can never be claimed).
+pub fn is_claimed(bits: &[u8], index: u32) -> bool {
+    !matches!(bits.get(index as usize / 8), Some(b) if b & (1 << (index % 8)) == 0)
+}
+
+/// Set bit `index`; `false` if it is out of range or already set.
+pub fn set_claimed(bits: &mut [u8], index: u32) -> bool {
+    match bits.get_mut(index as usize / 8) {
+        Some(b) if *b & (1 << (index % 8)) == 0 => {
+            *b |= 1 << (index % 8);
+            true
+        }
+        _ => false,
+    }
+}
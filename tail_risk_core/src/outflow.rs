@@ -0,0 +1,84 @@
+//! Vault outflow circuit breaker.
+//!
+//! Outflows (withdrawals, payouts, claims, sweeps) are counted over a sliding
+//! window of `window_secs` and capped at an absolute amount and/or a share of
+//! the vault value before the outflows still counted.
+
+use crate::math::{bps_of, mul_div_floor};
+
+/// Breaker settings (`State.outflow_*`). A cap of 0 is off; with both caps off
+/// (or `window_secs == 0`) outflows are unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutflowLimits {
+    /// Window length in seconds.
+    pub window_secs: i64,
+    /// Absolute cap per window.
+    pub cap_fp: u128,
+    /// Cap per window in bps of the vault value before the outflows in it.
+    pub cap_bps: u16,
+}
+
+impl OutflowLimits {
+    /// Limit for a window measured against `base_fp`: the tighter of the
+    /// configured caps, `None` if the breaker is off.
+    pub fn limit_fp(&self, base_fp: u128) -> Option<u128> {
+        if self.window_secs <= 0 {
+            return None;
+        }
+        let absolute = (self.cap_fp > 0).then_some(self.cap_fp);
+        let relative = (self.cap_bps > 0).then(|| bps_of(base_fp, self.cap_bps as u128).unwrap_or(u128::MAX));
+        match (absolute, relative) {
+            (Some(a), Some(r)) => Some(a.min(r)),
+            (a, r) => a.or(r),
+        }
+    }
+}
+
+/// The sliding window (`State.outflow_window_*`). Admitted outflows drain out
+/// of it linearly, at the limit per `window_secs`, so no span of `window_secs`
+/// lets much more than the limit through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutflowWindow {
+    /// When `used_fp` was last brought up to date.
+    pub updated_ts: i64,
+    /// Vault value the relative cap was last measured against: the vault plus
+    /// the outflows still counted.
+    pub base_fp: u128,
+    /// Outflows still counted against the limit.
+    pub used_fp: u128,
+}
+
+impl OutflowWindow {
+    /// Outflows still counted at `now`: `used_fp` less the limit for every
+    /// `window_secs` since `updated_ts`, pro rata.
+    pub fn used_at(&self, limits: &OutflowLimits, now: i64) -> u128 {
+        let Some(limit) = limits.limit_fp(self.base_fp) else {
+            return 0;
+        };
+        let elapsed = now.saturating_sub(self.updated_ts).max(0);
+        if elapsed >= limits.window_secs {
+            return 0;
+        }
+        let drained = mul_div_floor(limit, elapsed as u128, limits.window_secs as u128).unwrap_or(u128::MAX);
+        self.used_fp.saturating_sub(drained)
+    }
+
+    /// Admit `amount_fp` leaving the vaults at `now`, with `vault_fp` in them
+    /// beforehand. The window is first brought up to `now`; on success the
+    /// amount is counted, `Err(limit_fp)` means it would cross the limit and
+    /// nothing is counted.
+    pub fn admit(&mut self, limits: &OutflowLimits, vault_fp: u128, amount_fp: u128, now: i64) -> Result<(), u128> {
+        if limits.limit_fp(vault_fp).is_none() {
+            return Ok(());
+        }
+        let used = self.used_at(limits, now);
+        *self = OutflowWindow { updated_ts: now, base_fp: vault_fp.saturating_add(used), used_fp: used };
+        let limit = limits.limit_fp(self.base_fp).unwrap_or(u128::MAX);
+        let used = used.saturating_add(amount_fp);
+        if used > limit {
+            return Err(limit);
+        }
+        self.used_fp = used;
+        Ok(())
+    }
+}
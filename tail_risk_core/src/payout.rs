@@ -0,0 +1,138 @@
+//! Epoch liability, per-user payouts and payout quotes.
+
+use crate::math::{bps_of, mul_div_floor};
+
+/// How an epoch's liability is bounded (`State.payout_policy`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayoutPolicy {
+    /// Pro-rata share of `snapshot * severity`.
+    Proportional = 0,
+    /// Pro-rata, with an additional per-user cap in bps of the user's stake.
+    Capped = 1,
+    /// Pro-rata, with the whole epoch bounded by `epoch_cap_fp`.
+    EpochBounded = 2,
+}
+
+impl PayoutPolicy {
+    /// Decode the on-chain `u8` representation.
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::Proportional),
+            1 => Some(Self::Capped),
+            2 => Some(Self::EpochBounded),
+            _ => None,
+        }
+    }
+}
+
+/// Liability of a triggered epoch against the current pool balance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EpochLiability {
+    /// `snapshot * severity`, before any cap.
+    pub base_fp: u128,
+    /// Base liability after the policy cap.
+    pub capped_fp: u128,
+    /// What can actually be paid: capped liability bounded by the pool balance.
+    pub max_fp: u128,
+    /// Unfunded part of the base liability (0 when fully funded).
+    pub shortfall_fp: u128,
+}
+
+/// Result of quoting a user's payout for an epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayoutQuote {
+    /// Payout the user would receive.
+    pub user_payout_fp: u128,
+    /// Maximum the epoch can pay out in total.
+    pub max_epoch_liability_fp: u128,
+}
+
+/// Tranche-weighted stake: `senior * w_senior + junior * w_junior` (weights in bps).
+pub fn weighted_stake_fp(senior_fp: u128, junior_fp: u128, w_senior_bps: u128, w_junior_bps: u128) -> Option<u128> {
+    bps_of(senior_fp, w_senior_bps)?.checked_add(bps_of(junior_fp, w_junior_bps)?)
+}
+
+/// Epoch liability for `severity_bps` of the stake snapshot. `epoch_cap_fp`
+/// only applies under [`PayoutPolicy::EpochBounded`].
+pub fn epoch_liability(
+    snapshot_fp: u128,
+    severity_bps: u16,
+    policy: PayoutPolicy,
+    epoch_cap_fp: u128,
+    pool_balance_fp: u128,
+) -> Option<EpochLiability> {
+    let base_fp = bps_of(snapshot_fp, severity_bps as u128)?;
+    let capped_fp = if policy == PayoutPolicy::EpochBounded {
+        core::cmp::min(base_fp, epoch_cap_fp)
+    } else {
+        base_fp
+    };
+    Some(EpochLiability {
+        base_fp,
+        capped_fp,
+        max_fp: core::cmp::min(capped_fp, pool_balance_fp),
+        shortfall_fp: base_fp.saturating_sub(pool_balance_fp),
+    })
+}
+
+/// A user's pro-rata share of `max_liability_fp`, with the per-user cap under
+/// [`PayoutPolicy::Capped`] (`user_cap_bps == 0` = uncapped). Zero stake or
+/// snapshot yields 0.
+pub fn user_payout_fp(
+    max_liability_fp: u128,
+    user_stake_fp: u128,
+    snapshot_fp: u128,
+    policy: PayoutPolicy,
+    user_cap_bps: u16,
+) -> Option<u128> {
+    if user_stake_fp == 0 || snapshot_fp == 0 {
+        return Some(0);
+    }
+    let share = mul_div_floor(max_liability_fp, user_stake_fp, snapshot_fp)?;
+    if policy == PayoutPolicy::Capped && user_cap_bps > 0 {
+        Some(core::cmp::min(share, bps_of(user_stake_fp, user_cap_bps as u128)?))
+    } else {
+        Some(share)
+    }
+}
+
+/// Amount actually paid on a claim: the user's payout bounded by the room left
+/// in the epoch.
+pub fn claimable_fp(user_payout_fp: u128, max_liability_fp: u128, total_paid_fp: u128) -> u128 {
+    core::cmp::min(user_payout_fp, max_liability_fp.saturating_sub(total_paid_fp))
+}
+
+/// Quote a user's payout for a triggered epoch (same math as the claim path).
+pub fn quote_user_payout(
+    snapshot_fp: u128,
+    severity_bps: u16,
+    policy: PayoutPolicy,
+    epoch_cap_fp: u128,
+    user_cap_bps: u16,
+    pool_balance_fp: u128,
+    user_stake_fp: u128,
+) -> Option<PayoutQuote> {
+    let l = epoch_liability(snapshot_fp, severity_bps, policy, epoch_cap_fp, pool_balance_fp)?;
+    Some(PayoutQuote {
+        user_payout_fp: user_payout_fp(l.max_fp, user_stake_fp, snapshot_fp, policy, user_cap_bps)?,
+        max_epoch_liability_fp: l.max_fp,
+    })
+}
+
+/// A position's share of `available_fp` (what the vaults hold beyond other claims) in
+/// shutdown. Senior principal is repaid first; the junior tranche takes the rest. Within
+/// a tranche the share is pro-rata to principal, so exits in any order pay the same.
+pub fn emergency_share_fp(
+    senior_fp: u128,
+    junior_fp: u128,
+    senior_total_fp: u128,
+    junior_total_fp: u128,
+    available_fp: u128,
+) -> Option<u128> {
+    let senior_pool = core::cmp::min(senior_total_fp, available_fp);
+    let junior_pool = available_fp - senior_pool;
+    let senior = if senior_total_fp == 0 { 0 } else { mul_div_floor(senior_fp, senior_pool, senior_total_fp)? };
+    let junior = if junior_total_fp == 0 { 0 } else { mul_div_floor(junior_fp, junior_pool, junior_total_fp)? };
+    senior.checked_add(junior)
+}
@@ -0,0 +1,135 @@
+//! Premium pricing for cover.
+//!
+//! The annual rate is a base rate, plus a utilisation charge on a kinked curve
+//! (as in lending rate models: `slope1` is reached at the kink, `slope2` is
+//! added from the kink to full utilisation), plus the expected loss: the
+//! annual trigger probability times the severity the curve gives a reference
+//! input. A premium is that rate on the notional, pro rata over the duration.
+//! A purchase is priced along the curve, at its average rate between the
+//! utilisation before and after it, so splitting it up does not change its price.
+
+use crate::{cover::utilisation_bps, math::mul_div_floor, BPS_DENOM};
+
+/// Seconds in the (365-day) year the rates are quoted over.
+pub const SECS_PER_YEAR: u128 = 365 * 86_400;
+
+/// Sub-bps precision rates are averaged at.
+const RATE_PRECISION: u128 = 1_000_000;
+
+/// Annual rate curve over utilisation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PremiumCurve {
+    /// Rate at zero utilisation.
+    pub base_rate_bps: u16,
+    /// Utilisation where the second slope starts.
+    pub kink_utilisation_bps: u16,
+    /// Added between zero utilisation and the kink.
+    pub slope1_bps: u16,
+    /// Added between the kink and full utilisation.
+    pub slope2_bps: u16,
+}
+
+impl PremiumCurve {
+    /// Annual rate at `utilisation_bps` (capped at 100%), without expected loss.
+    pub fn rate_bps(&self, utilisation_bps: u128) -> Option<u128> {
+        let u = utilisation_bps.min(BPS_DENOM);
+        let kink = (self.kink_utilisation_bps as u128).min(BPS_DENOM);
+        let (slope1, slope2) = (self.slope1_bps as u128, self.slope2_bps as u128);
+        let charge = if u <= kink {
+            if kink == 0 { 0 } else { mul_div_floor(slope1, u, kink)? }
+        } else {
+            slope1.checked_add(mul_div_floor(slope2, u - kink, BPS_DENOM - kink)?)?
+        };
+        (self.base_rate_bps as u128).checked_add(charge)
+    }
+
+    /// Utilisation charge, in bps scaled by `RATE_PRECISION`, at `utilisation`
+    /// (in bps scaled by `RATE_PRECISION`, capped at 100%).
+    fn charge_scaled(&self, utilisation: u128) -> Option<u128> {
+        let u = utilisation.min(BPS_DENOM * RATE_PRECISION);
+        let kink = (self.kink_utilisation_bps as u128).min(BPS_DENOM) * RATE_PRECISION;
+        let slope1 = self.slope1_bps as u128 * RATE_PRECISION;
+        let slope2 = self.slope2_bps as u128 * RATE_PRECISION;
+        if u <= kink {
+            if kink == 0 { Some(0) } else { mul_div_floor(slope1, u, kink) }
+        } else {
+            slope1.checked_add(mul_div_floor(slope2, u - kink, BPS_DENOM * RATE_PRECISION - kink)?)
+        }
+    }
+
+    /// Utilisation charge on cover sold from `from_fp` to `to_fp` against `capital_fp`,
+    /// in fixed point times bps scaled by `RATE_PRECISION` (a year's premium on it).
+    /// The charge is linear below the kink, between the kink and 100% and flat above
+    /// it, so each piece is its width times the charge at its midpoint.
+    fn charge_integral(&self, capital_fp: u128, from_fp: u128, to_fp: u128) -> Option<u128> {
+        let kink_fp = mul_div_floor(capital_fp, (self.kink_utilisation_bps as u128).min(BPS_DENOM), BPS_DENOM)?;
+        let mut total = 0u128;
+        let mut start = from_fp;
+        for bound in [kink_fp, capital_fp, u128::MAX] {
+            let end = to_fp.min(bound);
+            if end > start {
+                let mid_utilisation = if capital_fp == 0 {
+                    u128::MAX
+                } else {
+                    mul_div_floor(start.checked_add(end)?, BPS_DENOM * RATE_PRECISION, capital_fp.checked_mul(2)?)?
+                };
+                total = total.checked_add((end - start).checked_mul(self.charge_scaled(mid_utilisation)?)?)?;
+                start = end;
+            }
+        }
+        Some(total)
+    }
+}
+
+/// Expected annual loss in bps of notional: a trigger with `probability_bps` a
+/// year that pays `severity_bps` (capped at 100%).
+pub fn expected_loss_bps(probability_bps: u16, severity_bps: u128) -> Option<u128> {
+    mul_div_floor(probability_bps as u128, severity_bps.min(BPS_DENOM), BPS_DENOM)
+}
+
+/// Premium on `notional_fp` at `annual_rate_bps` for `duration_secs`.
+pub fn premium_fp(notional_fp: u128, annual_rate_bps: u128, duration_secs: u64) -> Option<u128> {
+    mul_div_floor(notional_fp, annual_rate_bps.checked_mul(duration_secs as u128)?, BPS_DENOM * SECS_PER_YEAR)
+}
+
+/// Price of one purchase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PremiumQuote {
+    /// Utilisation once the cover is sold.
+    pub utilisation_bps: u128,
+    /// Expected annual loss included in the rate.
+    pub expected_loss_bps: u128,
+    /// Annual rate, averaged over the purchase (the marginal rate for no notional).
+    pub rate_bps: u128,
+    /// Premium for the duration.
+    pub premium_fp: u128,
+}
+
+/// Quote `notional_fp` of cover for `duration_secs`, with `sold_fp` already
+/// outstanding against `capital_fp`. The utilisation charge is integrated along
+/// the curve from `sold_fp` to `sold_fp + notional_fp`, so one purchase costs what
+/// the same cover bought in parts would (up to rounding).
+pub fn quote(
+    curve: &PremiumCurve,
+    capital_fp: u128,
+    sold_fp: u128,
+    notional_fp: u128,
+    duration_secs: u64,
+    expected_loss_bps: u128,
+) -> Option<PremiumQuote> {
+    let end_fp = sold_fp.checked_add(notional_fp)?;
+    let utilisation_bps = utilisation_bps(capital_fp, end_fp)?;
+    let flat_bps = (curve.base_rate_bps as u128).checked_add(expected_loss_bps)?;
+    if notional_fp == 0 {
+        let rate_bps = curve.rate_bps(utilisation_bps)?.checked_add(expected_loss_bps)?;
+        return Some(PremiumQuote { utilisation_bps, expected_loss_bps, rate_bps, premium_fp: 0 });
+    }
+
+    // A year's premium: the flat part on the whole notional plus the integrated charge
+    let charge = curve.charge_integral(capital_fp, sold_fp, end_fp)?;
+    let annual_fp = mul_div_floor(notional_fp, flat_bps, BPS_DENOM)?
+        .checked_add(charge / (BPS_DENOM * RATE_PRECISION))?;
+    let rate_bps = flat_bps.checked_add(charge / notional_fp.checked_mul(RATE_PRECISION)?)?;
+    let premium_fp = mul_div_floor(annual_fp, duration_secs as u128, SECS_PER_YEAR)?;
+    Some(PremiumQuote { utilisation_bps, expected_loss_bps, rate_bps, premium_fp })
+}
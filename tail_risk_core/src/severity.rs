@@ -0,0 +1,16 @@
+//! Severity curve.
+
+use crate::{math::mul_div_floor, BPS_DENOM, SCALE};
+
+/// Quadratic severity curve `a*x^2 + b*x + c` (x and result in bps, coefficients
+/// in fixed-point), raised to `floor_bps` and clamped to 100%.
+pub fn effective_severity_bps(x_bps: u128, a_fp: u128, b_fp: u128, c_fp: u128, floor_bps: u16) -> Option<u128> {
+    let x_fp = x_bps.checked_mul(SCALE)?;
+    let x2 = mul_div_floor(x_fp, x_fp, SCALE)?; // x^2 in SCALE
+    let ax2 = mul_div_floor(a_fp, x2, SCALE)?;
+    let bx = mul_div_floor(b_fp, x_fp, SCALE)?;
+    let sum = ax2.checked_add(bx)?.checked_add(c_fp)?;
+    let bps = sum / SCALE;
+    let floored = core::cmp::max(bps, floor_bps as u128);
+    Some(core::cmp::min(floored, BPS_DENOM))
+}
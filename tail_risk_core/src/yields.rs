@@ -0,0 +1,42 @@
+//! Tranche income: yield split and reward-index accounting.
+//!
+//! Each tranche keeps a cumulative index (income per unit of principal, in
+//! [`SCALE`]); a position's accrued income is `principal * index - debt`.
+
+use crate::{
+    math::{bps_of, mul_div_floor},
+    SCALE,
+};
+
+/// Split `yield_fp` into `(senior, junior)` shares. An empty tranche's share
+/// goes to the other one; with no principal at all both shares are 0.
+pub fn split_yield(yield_fp: u128, junior_share_bps: u16, senior_total_fp: u128, junior_total_fp: u128) -> Option<(u128, u128)> {
+    let mut junior_fp = bps_of(yield_fp, junior_share_bps as u128)?;
+    let mut senior_fp = yield_fp.saturating_sub(junior_fp);
+    if junior_total_fp == 0 {
+        senior_fp = senior_fp.saturating_add(junior_fp);
+        junior_fp = 0;
+    }
+    if senior_total_fp == 0 {
+        junior_fp = junior_fp.saturating_add(senior_fp);
+        senior_fp = 0;
+    }
+    Some((
+        if senior_total_fp > 0 { senior_fp } else { 0 },
+        if junior_total_fp > 0 { junior_fp } else { 0 },
+    ))
+}
+
+/// Index increase from crediting `amount_fp` to `principal_fp` (0 if no principal).
+pub fn index_increment(amount_fp: u128, principal_fp: u128) -> Option<u128> {
+    if principal_fp == 0 {
+        return Some(0);
+    }
+    mul_div_floor(amount_fp, SCALE, principal_fp)
+}
+
+/// Income accumulated by `principal_fp` at `index_fp` (the reward debt when
+/// taken at the current index).
+pub fn accrued_fp(principal_fp: u128, index_fp: u128) -> Option<u128> {
+    mul_div_floor(principal_fp, index_fp, SCALE)
+}
@@ -0,0 +1,217 @@
+use tail_risk_core::{
+    cover::{capacity, cover_payout_fp, utilisation_bps},
+    fees::{deposit_fee_bps, split_fees, yield_fee_fp},
+    lots::{Lot, LotRing},
+    math::{apply_haircut, asset_to_fp, fp_to_asset, from_fp, from_fp_ceil, to_fp},
+    merkle::{self, Hash},
+    outflow::{OutflowLimits, OutflowWindow},
+    payout::{claimable_fp, emergency_share_fp, epoch_liability, quote_user_payout, user_payout_fp, weighted_stake_fp},
+    pricing::{expected_loss_bps, premium_fp, quote, SECS_PER_YEAR},
+    severity::effective_severity_bps,
+    Capacity, FeeModel, PayoutPolicy, PremiumCurve, PremiumQuote, BPS_DENOM, SCALE,
+};
+
+#[test]
+fn decimals_round_trip_and_haircut() {
+    // Native units are scaled by SCALE, then rescaled to COMMON_DECIMALS
+    assert_eq!(asset_to_fp(1, 6), Some(SCALE));
+    assert_eq!(asset_to_fp(1_000, 9), Some(SCALE));
+    assert_eq!(fp_to_asset(SCALE, 9), Some(1_000));
+    assert_eq!(fp_to_asset(to_fp(1_234_567).unwrap(), 2), Some(123));
+    assert_eq!((from_fp(SCALE + 1), from_fp_ceil(SCALE + 1), from_fp_ceil(SCALE)), (Some(1), Some(2), Some(1)));
+    assert_eq!(apply_haircut(10_000, 250), Some(9_750));
+}
+
+#[test]
+fn severity_curve_floor_and_clamp() {
+    // Linear curve (b = 1): severity passes through
+    assert_eq!(effective_severity_bps(5_000, 0, SCALE, 0, 0), Some(5_000));
+    // Floor lifts small inputs
+    assert_eq!(effective_severity_bps(10, 0, SCALE, 0, 500), Some(500));
+    // Steep quadratic clamps at 100%
+    assert_eq!(effective_severity_bps(9_000, SCALE, 0, 0, 0), Some(BPS_DENOM));
+}
+
+#[test]
+fn fee_split_and_models() {
+    let f = split_fees(to_fp(10_000).unwrap(), 50, 25).unwrap();
+    assert_eq!(f.protocol_fp, to_fp(50).unwrap());
+    assert_eq!(f.referral_fp, to_fp(25).unwrap());
+    assert_eq!(f.net_fp + f.protocol_fp + f.referral_fp, to_fp(10_000).unwrap());
+
+    assert_eq!(deposit_fee_bps(FeeModel::OnDeposit, 50, 25), (50, 25));
+    assert_eq!(deposit_fee_bps(FeeModel::OnYield, 50, 25), (0, 0));
+    assert_eq!(yield_fee_fp(FeeModel::OnYield, 500, 1_000, 1_000), Some(150));
+    assert_eq!(yield_fee_fp(FeeModel::OnPremium, 500, 1_000, 1_000), Some(100));
+}
+
+#[test]
+fn lots_mature_fifo() {
+    let (mut head, mut len) = (0u8, 0u8);
+    let mut buf = [Lot::default(); 2];
+    let mut ring = LotRing { head: &mut head, len: &mut len, buf: &mut buf };
+    assert!(ring.push(Lot { amount_fp: 100, ts: 0 }));
+    assert!(ring.push(Lot { amount_fp: 50, ts: 10 }));
+    assert!(!ring.push(Lot { amount_fp: 1, ts: 20 }));
+
+    let (mut withdrawable, mut remaining) = (0u128, 120u128);
+    ring.mature_and_consume(10, 15, &mut withdrawable, &mut remaining);
+    // Only the first lot has matured
+    assert_eq!((withdrawable, remaining), (0, 20));
+    assert_eq!(ring.locked_fp(), 50);
+    assert!(ring.push(Lot { amount_fp: 1, ts: 20 }));
+}
+
+#[test]
+fn payouts_respect_policy_caps() {
+    let l = epoch_liability(1_000, 5_000, PayoutPolicy::EpochBounded, 300, 400).unwrap();
+    assert_eq!((l.base_fp, l.capped_fp, l.max_fp, l.shortfall_fp), (500, 300, 300, 100));
+
+    assert_eq!(weighted_stake_fp(100, 100, 10_000, 5_000), Some(150));
+    assert_eq!(user_payout_fp(500, 250, 1_000, PayoutPolicy::Proportional, 0), Some(125));
+    assert_eq!(user_payout_fp(500, 250, 1_000, PayoutPolicy::Capped, 2_000), Some(50));
+    assert_eq!(user_payout_fp(500, 0, 1_000, PayoutPolicy::Proportional, 0), Some(0));
+    assert_eq!(claimable_fp(125, 500, 450), 50);
+
+    let q = quote_user_payout(1_000, 5_000, PayoutPolicy::Proportional, 0, 0, 10_000, 250).unwrap();
+    assert_eq!((q.user_payout_fp, q.max_epoch_liability_fp), (125, 500));
+}
+
+#[test]
+fn emergency_shares_follow_tranche_priority() {
+    // Senior 600, junior 400; 800 left: senior whole, junior takes the remaining 200
+    assert_eq!(emergency_share_fp(300, 0, 600, 400, 800), Some(300));
+    assert_eq!(emergency_share_fp(0, 100, 600, 400, 800), Some(50));
+    assert_eq!(emergency_share_fp(300, 100, 600, 400, 800), Some(350));
+    // 450 left: junior is wiped out, senior shares pro-rata
+    assert_eq!(emergency_share_fp(300, 100, 600, 400, 450), Some(225));
+    // Surplus goes to the junior tranche
+    assert_eq!(emergency_share_fp(0, 400, 600, 400, 1_100), Some(500));
+
+    // Exit order does not matter: after one senior leaves, the next gets the same ratio
+    let first = emergency_share_fp(200, 0, 600, 400, 450).unwrap();
+    assert_eq!(emergency_share_fp(200, 0, 400, 400, 450 - first), Some(first));
+    assert_eq!(emergency_share_fp(0, 0, 0, 0, 100), Some(0));
+}
+
+#[test]
+fn outflow_breaker_caps_each_window() {
+    let off = OutflowLimits { window_secs: 0, cap_fp: 100, cap_bps: 0 };
+    assert_eq!(off.limit_fp(1_000), None);
+    let limits = OutflowLimits { window_secs: 60, cap_fp: 300, cap_bps: 2_000 };
+    assert_eq!((limits.limit_fp(1_000), limits.limit_fp(10_000)), (Some(200), Some(300)), "tighter cap wins");
+    assert_eq!(OutflowLimits { cap_fp: 0, ..limits }.limit_fp(10_000), Some(2_000));
+
+    // The share is of the vault value before the counted outflows, not what is left
+    let mut w = OutflowWindow::default();
+    assert_eq!(w.admit(&limits, 1_000, 150, 100), Ok(()));
+    assert_eq!(w, OutflowWindow { updated_ts: 100, base_fp: 1_000, used_fp: 150 });
+    assert_eq!(w.admit(&limits, 850, 50, 100), Ok(()));
+    assert_eq!(w.admit(&limits, 800, 1, 100), Err(200));
+    assert_eq!(w.used_fp, 200, "rejected outflows are not counted");
+
+    // The window slides: outflows drain out at the limit per window, so there is no
+    // boundary at which a second full limit becomes available at once
+    assert_eq!(w.used_at(&limits, 115), 150);
+    assert_eq!(w.admit(&limits, 800, 41, 115), Err(190));
+    assert_eq!(w.admit(&limits, 800, 40, 115), Ok(()));
+    assert_eq!(w, OutflowWindow { updated_ts: 115, base_fp: 950, used_fp: 190 });
+    assert_eq!(w.used_at(&limits, 160), 48);
+    assert_eq!(w.used_at(&limits, 175), 0, "a whole window later nothing is counted");
+    assert_eq!(w.admit(&limits, 760, 150, 175), Ok(()));
+    assert_eq!(w, OutflowWindow { updated_ts: 175, base_fp: 760, used_fp: 150 });
+    assert_eq!(OutflowWindow::default().admit(&off, 0, u128::MAX, 0), Ok(()));
+}
+
+#[test]
+fn cover_capacity_and_payouts() {
+    // 80% of 1,000 capital may be sold
+    assert_eq!(capacity(1_000, 300, 8_000), Some(Capacity { max_fp: 800, available_fp: 500, utilisation_bps: 3_000 }));
+    let shrunk = capacity(200, 300, 8_000).unwrap();
+    assert_eq!((shrunk.available_fp, shrunk.utilisation_bps), (0, 15_000), "withdrawn capital leaves cover over the limit");
+    assert_eq!(utilisation_bps(0, 0), Some(0));
+    assert_eq!(utilisation_bps(0, 1), Some(u128::MAX));
+    assert_eq!(capacity(u128::MAX, 0, 8_000), None);
+
+    assert_eq!(cover_payout_fp(1_000 * SCALE, 2_500), Some(250 * SCALE));
+    assert_eq!(cover_payout_fp(1_000, u16::MAX), Some(1_000), "severity is capped at 100%");
+}
+
+#[test]
+fn premium_rate_follows_the_kink_and_duration() {
+    let curve = PremiumCurve { base_rate_bps: 100, kink_utilisation_bps: 8_000, slope1_bps: 400, slope2_bps: 6_000 };
+    let rates: Vec<_> = [0, 4_000, 8_000, 9_000, 10_000, u128::MAX].iter().map(|&u| curve.rate_bps(u).unwrap()).collect();
+    assert_eq!(rates, [100, 300, 500, 3_500, 6_500, 6_500], "steep past the kink, flat past 100%");
+    let no_kink = PremiumCurve { kink_utilisation_bps: 0, ..curve };
+    assert_eq!((no_kink.rate_bps(0), no_kink.rate_bps(5_000)), (Some(100), Some(3_500)));
+
+    assert_eq!(expected_loss_bps(1_000, 5_000), Some(500));
+    assert_eq!(expected_loss_bps(10_000, 20_000), Some(10_000), "severity is capped at 100%");
+    assert_eq!(premium_fp(1_000 * SCALE, 1_000, SECS_PER_YEAR as u64), Some(100 * SCALE));
+    assert_eq!(premium_fp(1_000 * SCALE, 1_000, SECS_PER_YEAR as u64 / 2), Some(50 * SCALE));
+
+    // 100 more on 300 of 1,000 is priced at its average, 35% utilisation, plus 5% expected loss
+    let q = quote(&curve, 1_000 * SCALE, 300 * SCALE, 100 * SCALE, SECS_PER_YEAR as u64, 500).unwrap();
+    assert_eq!(q, PremiumQuote { utilisation_bps: 4_000, expected_loss_bps: 500, rate_bps: 775, premium_fp: 775 * SCALE / 100 });
+    // Across the kink: 70%-80% averages 375, 80%-90% averages 1,900
+    let q = quote(&curve, 1_000 * SCALE, 700 * SCALE, 200 * SCALE, SECS_PER_YEAR as u64, 0).unwrap();
+    assert_eq!((q.rate_bps, q.premium_fp), (1_237, 24_750 * SCALE / 1_000));
+    assert_eq!(quote(&curve, 1_000 * SCALE, 400 * SCALE, 0, 1, 0).unwrap().rate_bps, 300, "no notional: the marginal rate");
+    assert_eq!(quote(&curve, 0, 0, 1, 1, 0).unwrap().rate_bps, 6_500, "cover without capital is priced at the top");
+}
+
+#[test]
+fn a_purchase_costs_what_its_parts_do() {
+    let curve = PremiumCurve { base_rate_bps: 100, kink_utilisation_bps: 8_000, slope1_bps: 400, slope2_bps: 6_000 };
+    let capital = 1_000_000 * SCALE;
+    for (sold, notional, duration) in [
+        (0, 400_000 * SCALE, SECS_PER_YEAR as u64),
+        (300_000 * SCALE, 600_000 * SCALE, 86_400 * 30),
+        (750_000 * SCALE, 100_001 * SCALE + 7, 86_400 * 91 + 13),
+        (950_000 * SCALE, 300_000 * SCALE, 86_400),
+        (123_456 * SCALE + 789, 654_321 * SCALE + 3, 1_000_003),
+    ] {
+        let whole = quote(&curve, capital, sold, notional, duration, 250).unwrap().premium_fp;
+        let half = notional / 2;
+        let first = quote(&curve, capital, sold, half, duration, 250).unwrap().premium_fp;
+        let second = quote(&curve, capital, sold + half, notional - half, duration, 250).unwrap().premium_fp;
+        assert!(whole.abs_diff(first + second) <= 1, "{sold} + {notional}: {whole} vs {first} + {second}");
+    }
+}
+
+// Toy stand-in for sha256: order- and length-sensitive, good enough to tell nodes apart
+fn toy_hashv(parts: &[&[u8]]) -> Hash {
+    let mut h = [0u8; 32];
+    for (i, byte) in parts.iter().flat_map(|p| p.iter()).enumerate() {
+        h[i % 32] = h[i % 32].wrapping_mul(31).wrapping_add(*byte ^ i as u8);
+        h[(i * 7 + 3) % 32] ^= h[i % 32].rotate_left(3);
+    }
+    h
+}
+
+#[test]
+fn merkle_proofs_and_bitmap() {
+    let (alice, bob, carol) = ([1u8; 32], [2u8; 32], [3u8; 32]);
+    let leaves = [
+        merkle::leaf(0, &alice, 100, toy_hashv),
+        merkle::leaf(1, &bob, 200, toy_hashv),
+        merkle::leaf(2, &carol, 300, toy_hashv),
+    ];
+    // Odd level: carol's leaf is carried up unchanged
+    let ab = merkle::node(&leaves[0], &leaves[1], toy_hashv);
+    let root = merkle::node(&ab, &leaves[2], toy_hashv);
+    assert_eq!(merkle::node(&leaves[1], &leaves[0], toy_hashv), ab, "pairs are sorted");
+    assert!(merkle::verify(leaves[0], &[leaves[1], leaves[2]], &root, toy_hashv));
+    assert!(merkle::verify(leaves[2], &[ab], &root, toy_hashv));
+    // Wrong amount or wrong index fail
+    assert!(!merkle::verify(merkle::leaf(0, &alice, 101, toy_hashv), &[leaves[1], leaves[2]], &root, toy_hashv));
+    assert!(!merkle::verify(merkle::leaf(1, &alice, 100, toy_hashv), &[leaves[1], leaves[2]], &root, toy_hashv));
+
+    let mut bits = [0u8; 2];
+    assert_eq!(merkle::bitmap_len(9), 2);
+    assert!(!merkle::is_claimed(&bits, 8));
+    assert!(merkle::set_claimed(&mut bits, 8));
+    assert!(!merkle::set_claimed(&mut bits, 8), "second claim is rejected");
+    assert!(merkle::is_claimed(&bits, 8) && !merkle::is_claimed(&bits, 7));
+    assert!(merkle::is_claimed(&bits, 16) && !merkle::set_claimed(&mut bits, 16), "out of range");
+}
@@ -0,0 +1,134 @@
+//! Borsh mirrors of the program's `#[event]` structs.
+//!
+//! `emit!` logs `Program data: <base64>` where the payload is
+//! `sha256("event:<Name>")[..8]` followed by the Borsh-encoded event.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{hash::hash, pubkey::Pubkey};
+
+/// Anchor discriminator for event `name`.
+pub fn discriminator(name: &str) -> [u8; 8] {
+    let mut d = [0u8; 8];
+    d.copy_from_slice(&hash(format!("event:{name}").as_bytes()).to_bytes()[..8]);
+    d
+}
+
+macro_rules! events {
+    ($($name:ident { $($field:ident : $ty:ty),* $(,)? })*) => {
+        $(
+            #[allow(missing_docs)]
+            #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+            pub struct $name { $(pub $field: $ty),* }
+        )*
+
+        /// Any event emitted by the program.
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub enum Event {
+            $(#[allow(missing_docs)] $name($name),)*
+        }
+
+        impl Event {
+            /// Event name (the program's struct name).
+            pub fn kind(&self) -> &'static str {
+                match self {
+                    $(Event::$name(_) => stringify!($name),)*
+                }
+            }
+
+            /// Decode `discriminator || borsh`; `None` for unknown or malformed data.
+            pub fn decode(data: &[u8]) -> Option<Self> {
+                let (disc, mut rest) = (data.get(..8)?, data.get(8..)?);
+                $(
+                    if disc == discriminator(stringify!($name)) {
+                        return $name::deserialize(&mut rest).ok().map(Event::$name);
+                    }
+                )*
+                None
+            }
+
+            /// Encode as the program would (used by tests and fixtures).
+            pub fn encode(&self) -> Vec<u8> {
+                let mut out = discriminator(self.kind()).to_vec();
+                match self {
+                    $(Event::$name(e) => e.serialize(&mut out),)*
+                }
+                .expect("writing to a Vec cannot fail");
+                out
+            }
+        }
+    };
+}
+
+events! {
+    Initialized { admin: Pubkey, usdc_mint: Pubkey }
+    Deposited { owner: Pubkey, amount_fp: u128, referrer: Pubkey, tranche: u8, mint: Pubkey }
+    ProtocolFeeTaken { amount_u64: u64 }
+    ReferralRewardAccrued { referrer: Pubkey, amount_fp: u128, tier: u8 }
+    ReferralRewardsClaimed { referrer: Pubkey, amount_fp: u128, mint: Pubkey }
+    EpochStarted { epoch_id: u64, start_ts: i64, end_ts: i64 }
+    EpochEnded { epoch_id: u64, end_ts: i64 }
+    EventTriggered { epoch_id: u64, severity_bps: u16, user_cap_bps: u16, policy: u8, evidence_hash: [u8; 32] }
+    UserPayout { epoch_id: u64, owner: Pubkey, payout_fp: u128, mint: Pubkey }
+    Withdrawn { owner: Pubkey, amount_fp: u128, tranche: u8, mint: Pubkey }
+    EpochFinalized { epoch_id: u64 }
+    Paused { flags: u8 }
+    GuardianUpdated { guardian: Pubkey }
+    StrategyDeployed { amount_u64: u64, deployed_fp: u128 }
+    StrategyRecalled { received_u64: u64, yield_fp: u128, fee_fp: u128, loss_fp: u128, deployed_fp: u128 }
+    PremiumPaid { payer: Pubkey, amount_fp: u128, net_fp: u128, protocol_fee_u64: u64, referral_fee_fp: u128 }
+    YieldClaimed { owner: Pubkey, amount_fp: u128 }
+    CollateralUpdated { mint: Pubkey, haircut_bps: u16, concentration_cap_bps: u16, enabled: bool }
+    PositionClosed { owner: Pubkey }
+    ClaimReceiptClosed { epoch_id: u64, owner: Pubkey }
+    EpochClosed { epoch_id: u64 }
+    PoolShutdown { shutdown_ts: i64 }
+    EmergencyWithdrawn { owner: Pubkey, senior_fp: u128, junior_fp: u128, amount_fp: u128, mint: Pubkey }
+    OutflowLimitTripped { window_updated_ts: i64, used_fp: u128, attempted_fp: u128, limit_fp: u128 }
+    PayoutsCranked { epoch_id: u64, cranker: Pubkey, paid: u32, skipped: u32, reward_u64: u64 }
+    MerkleRootPosted { epoch_id: u64, root: [u8; 32], total_fp: u128, claimants: u32 }
+    CoverPurchased { epoch_id: u64, mint: Pubkey, buyer: Pubkey, notional_fp: u128, premium_fp: u128 }
+    CoverClaimed { epoch_id: u64, mint: Pubkey, holder: Pubkey, payout_fp: u128 }
+    CoverClosed { epoch_id: u64, mint: Pubkey, buyer: Pubkey }
+}
+
+/// Indexed columns of an event: `(epoch_id, owner, mint, tranche, amount_fp)`.
+pub type Columns = (Option<u64>, Option<Pubkey>, Option<Pubkey>, Option<u8>, Option<u128>);
+
+impl Event {
+    /// Columns the store indexes for ledger queries.
+    pub fn columns(&self) -> Columns {
+        match self {
+            Event::Deposited(e) => (None, Some(e.owner), Some(e.mint), Some(e.tranche), Some(e.amount_fp)),
+            Event::Withdrawn(e) => (None, Some(e.owner), Some(e.mint), Some(e.tranche), Some(e.amount_fp)),
+            Event::UserPayout(e) => (Some(e.epoch_id), Some(e.owner), Some(e.mint), None, Some(e.payout_fp)),
+            Event::YieldClaimed(e) => (None, Some(e.owner), None, None, Some(e.amount_fp)),
+            Event::ReferralRewardAccrued(e) => (None, Some(e.referrer), None, None, Some(e.amount_fp)),
+            Event::ReferralRewardsClaimed(e) => (None, Some(e.referrer), Some(e.mint), None, Some(e.amount_fp)),
+            Event::PremiumPaid(e) => (None, Some(e.payer), None, None, Some(e.amount_fp)),
+            Event::ProtocolFeeTaken(e) => (None, None, None, None, Some(e.amount_u64 as u128)),
+            Event::StrategyRecalled(e) => (None, None, None, None, Some(e.fee_fp)),
+            Event::EpochStarted(e) => (Some(e.epoch_id), None, None, None, None),
+            Event::EpochEnded(e) => (Some(e.epoch_id), None, None, None, None),
+            Event::EventTriggered(e) => (Some(e.epoch_id), None, None, None, None),
+            Event::EpochFinalized(e) => (Some(e.epoch_id), None, None, None, None),
+            Event::EpochClosed(e) => (Some(e.epoch_id), None, None, None, None),
+            Event::ClaimReceiptClosed(e) => (Some(e.epoch_id), Some(e.owner), None, None, None),
+            Event::PositionClosed(e) => (None, Some(e.owner), None, None, None),
+            Event::EmergencyWithdrawn(e) => (None, Some(e.owner), Some(e.mint), None, Some(e.amount_fp)),
+            Event::OutflowLimitTripped(e) => (None, None, None, None, Some(e.attempted_fp)),
+            Event::PayoutsCranked(e) => (Some(e.epoch_id), Some(e.cranker), None, None, Some(e.reward_u64 as u128)),
+            Event::MerkleRootPosted(e) => (Some(e.epoch_id), None, None, None, Some(e.total_fp)),
+            Event::CoverPurchased(e) => (Some(e.epoch_id), Some(e.buyer), Some(e.mint), None, Some(e.notional_fp)),
+            Event::CoverClaimed(e) => (Some(e.epoch_id), Some(e.holder), Some(e.mint), None, Some(e.payout_fp)),
+            Event::CoverClosed(e) => (Some(e.epoch_id), Some(e.buyer), Some(e.mint), None, None),
+            Event::CollateralUpdated(e) => (None, None, Some(e.mint), None, None),
+            Event::Initialized(_)
+            | Event::Paused(_)
+            | Event::GuardianUpdated(_)
+            | Event::StrategyDeployed(_)
+            | Event::PoolShutdown(_) => {
+                (None, None, None, None, None)
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Per-user, per-epoch and fee ledgers rebuilt from events.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::events::{Event, OutflowLimitTripped};
+
+/// Everything the events say about one user.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UserLedger {
+    /// Referrer recorded on the latest deposit (`None` = default pubkey).
+    pub referrer: Option<Pubkey>,
+    /// Number of deposits.
+    pub deposits: u32,
+    /// Net deposits per tranche (0 = senior, 1 = junior).
+    pub deposited_fp: [u128; 2],
+    /// Withdrawals per tranche (principal, including emergency exits).
+    pub withdrawn_fp: [u128; 2],
+    /// Paid out by `EmergencyWithdrawn` (may be less than the principal it retired).
+    pub emergency_paid_fp: u128,
+    /// Payout per epoch id.
+    pub payouts_fp: BTreeMap<u64, u128>,
+    /// Strategy yield claimed.
+    pub yield_claimed_fp: u128,
+    /// Referral rewards accrued as a referrer.
+    pub referral_accrued_fp: u128,
+    /// Referral rewards claimed as a referrer.
+    pub referral_claimed_fp: u128,
+    /// `PositionClosed` seen since the last deposit.
+    pub position_closed: bool,
+    /// Epoch ids whose claim receipt was closed.
+    pub closed_receipts: BTreeSet<u64>,
+}
+
+impl UserLedger {
+    /// Principal implied by the ledger for `tranche` (`*_deposited_fp` on the position).
+    pub fn principal_fp(&self, tranche: usize) -> u128 {
+        self.deposited_fp[tranche].saturating_sub(self.withdrawn_fp[tranche])
+    }
+
+    /// Sum of payouts across epochs.
+    pub fn total_payouts_fp(&self) -> u128 {
+        self.payouts_fp.values().sum()
+    }
+}
+
+/// Trigger parameters from `EventTriggered`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trigger {
+    /// Effective severity.
+    pub severity_bps: u16,
+    /// Per-user cap.
+    pub user_cap_bps: u16,
+    /// Payout policy at trigger time.
+    pub policy: u8,
+    /// Evidence hash.
+    pub evidence_hash: [u8; 32],
+}
+
+/// Everything the events say about one epoch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpochLedger {
+    /// `(start_ts, end_ts)` from `EpochStarted`.
+    pub window: Option<(i64, i64)>,
+    /// Set once triggered.
+    pub trigger: Option<Trigger>,
+    /// Sum of `UserPayout`.
+    pub payouts_fp: u128,
+    /// Number of payouts.
+    pub claimants: u32,
+    /// Root from `MerkleRootPosted` (payouts are then claimed by proof, without receipts).
+    pub merkle_root: Option<[u8; 32]>,
+    /// Sum of `CoverPurchased` notional.
+    pub cover_sold_fp: u128,
+    /// Sum of `CoverClaimed` (paid to cover NFT holders, not through `UserPayout`).
+    pub cover_claims_fp: u128,
+    /// Number of `CoverClosed` (cover accounts reclaimed).
+    pub covers_closed: u32,
+    /// `EpochFinalized` seen.
+    pub finalized: bool,
+    /// `EpochEnded` time (ended by the admin without a trigger).
+    pub ended_ts: Option<i64>,
+    /// `EpochClosed` seen (the account is gone).
+    pub account_closed: bool,
+}
+
+/// Pool-wide fee flows.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeeLedger {
+    /// `ProtocolFeeTaken` (native units of the charged asset).
+    pub protocol_fees_u64: u128,
+    /// `ReferralRewardAccrued`.
+    pub referral_accrued_fp: u128,
+    /// `ReferralRewardsClaimed`.
+    pub referral_claimed_fp: u128,
+    /// `PremiumPaid.amount_fp`.
+    pub premiums_fp: u128,
+    /// `StrategyRecalled.fee_fp` (performance fees).
+    pub performance_fees_fp: u128,
+    /// `PayoutsCranked.reward_u64` (base USDC paid to crankers out of uncollected fees).
+    pub crank_rewards_u64: u128,
+}
+
+/// Ledgers rebuilt from an ordered event stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ledger {
+    /// By owner.
+    pub users: BTreeMap<Pubkey, UserLedger>,
+    /// By epoch id.
+    pub epochs: BTreeMap<u64, EpochLedger>,
+    /// Fee flows.
+    pub fees: FeeLedger,
+    /// `PoolShutdown` time, once shut down.
+    pub shutdown_ts: Option<i64>,
+    /// Pause mask from the latest `Paused`.
+    pub pause_flags: u8,
+    /// Guardian from the latest `GuardianUpdated` (`None` = none appointed).
+    pub guardian: Option<Pubkey>,
+    /// Outflow circuit breaker alerts, in order.
+    pub outflow_alerts: Vec<OutflowLimitTripped>,
+}
+
+impl Ledger {
+    /// Fold events (in ledger order).
+    pub fn build<'a>(events: impl IntoIterator<Item = &'a Event>) -> Self {
+        let mut l = Self::default();
+        for ev in events {
+            l.apply(ev);
+        }
+        l
+    }
+
+    /// Apply one event.
+    pub fn apply(&mut self, ev: &Event) {
+        match ev {
+            Event::Deposited(e) => {
+                let u = self.users.entry(e.owner).or_default();
+                u.deposits += 1;
+                u.position_closed = false;
+                u.deposited_fp[tranche(e.tranche)] += e.amount_fp;
+                u.referrer = (e.referrer != Pubkey::default()).then_some(e.referrer);
+            }
+            Event::Withdrawn(e) => {
+                self.users.entry(e.owner).or_default().withdrawn_fp[tranche(e.tranche)] += e.amount_fp;
+            }
+            Event::EmergencyWithdrawn(e) => {
+                let u = self.users.entry(e.owner).or_default();
+                u.withdrawn_fp[0] += e.senior_fp;
+                u.withdrawn_fp[1] += e.junior_fp;
+                u.emergency_paid_fp += e.amount_fp;
+            }
+            Event::UserPayout(e) => {
+                *self.users.entry(e.owner).or_default().payouts_fp.entry(e.epoch_id).or_default() += e.payout_fp;
+                let ep = self.epochs.entry(e.epoch_id).or_default();
+                ep.payouts_fp += e.payout_fp;
+                ep.claimants += 1;
+            }
+            Event::YieldClaimed(e) => self.users.entry(e.owner).or_default().yield_claimed_fp += e.amount_fp,
+            Event::ReferralRewardAccrued(e) => {
+                self.users.entry(e.referrer).or_default().referral_accrued_fp += e.amount_fp;
+                self.fees.referral_accrued_fp += e.amount_fp;
+            }
+            Event::ReferralRewardsClaimed(e) => {
+                self.users.entry(e.referrer).or_default().referral_claimed_fp += e.amount_fp;
+                self.fees.referral_claimed_fp += e.amount_fp;
+            }
+            Event::EpochStarted(e) => self.epochs.entry(e.epoch_id).or_default().window = Some((e.start_ts, e.end_ts)),
+            Event::EventTriggered(e) => {
+                self.epochs.entry(e.epoch_id).or_default().trigger = Some(Trigger {
+                    severity_bps: e.severity_bps,
+                    user_cap_bps: e.user_cap_bps,
+                    policy: e.policy,
+                    evidence_hash: e.evidence_hash,
+                })
+            }
+            Event::EpochFinalized(e) => self.epochs.entry(e.epoch_id).or_default().finalized = true,
+            Event::EpochEnded(e) => self.epochs.entry(e.epoch_id).or_default().ended_ts = Some(e.end_ts),
+            Event::MerkleRootPosted(e) => self.epochs.entry(e.epoch_id).or_default().merkle_root = Some(e.root),
+            Event::CoverPurchased(e) => self.epochs.entry(e.epoch_id).or_default().cover_sold_fp += e.notional_fp,
+            Event::CoverClaimed(e) => self.epochs.entry(e.epoch_id).or_default().cover_claims_fp += e.payout_fp,
+            Event::CoverClosed(e) => self.epochs.entry(e.epoch_id).or_default().covers_closed += 1,
+            Event::PositionClosed(e) => self.users.entry(e.owner).or_default().position_closed = true,
+            Event::ClaimReceiptClosed(e) => {
+                self.users.entry(e.owner).or_default().closed_receipts.insert(e.epoch_id);
+            }
+            Event::EpochClosed(e) => self.epochs.entry(e.epoch_id).or_default().account_closed = true,
+            Event::ProtocolFeeTaken(e) => self.fees.protocol_fees_u64 += e.amount_u64 as u128,
+            Event::PremiumPaid(e) => self.fees.premiums_fp += e.amount_fp,
+            Event::StrategyRecalled(e) => self.fees.performance_fees_fp += e.fee_fp,
+            Event::PoolShutdown(e) => self.shutdown_ts = Some(e.shutdown_ts),
+            Event::Paused(e) => self.pause_flags = e.flags,
+            Event::GuardianUpdated(e) => self.guardian = (e.guardian != Pubkey::default()).then_some(e.guardian),
+            Event::OutflowLimitTripped(e) => self.outflow_alerts.push(e.clone()),
+            Event::PayoutsCranked(e) => self.fees.crank_rewards_u64 += e.reward_u64 as u128,
+            Event::Initialized(_)
+            | Event::StrategyDeployed(_)
+            | Event::CollateralUpdated(_) => {}
+        }
+    }
+}
+
+fn tranche(t: u8) -> usize {
+    usize::from(t != 0)
+}
@@ -0,0 +1,15 @@
+//! Event indexer for the tail-risk insurance pool.
+//!
+//! - [`events`]: decode Anchor `emit!` payloads
+//! - [`logs`]: pick this program's events out of transaction logs
+//! - [`source`]: logs from `getTransaction` JSON files or an RPC node
+//! - [`store`]: idempotent SQLite persistence and queries
+//! - [`ledger`]: per-user, per-epoch and fee ledgers
+//! - [`reconcile`]: ledger vs `UserPosition` / `Epoch` / `ClaimReceipt` accounts
+
+pub mod events;
+pub mod ledger;
+pub mod logs;
+pub mod reconcile;
+pub mod source;
+pub mod store;
@@ -0,0 +1,61 @@
+//! Transaction logs -> program events.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::events::Event;
+
+/// Logs of one transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxLogs {
+    /// First signature (transaction id).
+    pub signature: String,
+    /// Slot.
+    pub slot: u64,
+    /// Block time, if known.
+    pub block_time: Option<i64>,
+    /// Transaction failed (its events were rolled back).
+    pub failed: bool,
+    /// `meta.logMessages`.
+    pub logs: Vec<String>,
+}
+
+/// Events emitted by `program_id` itself, in log order.
+///
+/// Invocations are tracked so `Program data:` lines from other programs (CPI
+/// targets, or callers of this program) are skipped. Failed transactions
+/// yield nothing.
+pub fn program_events(program_id: &Pubkey, tx: &TxLogs) -> Vec<Event> {
+    if tx.failed {
+        return Vec::new();
+    }
+    let id = program_id.to_string();
+    let mut stack: Vec<&str> = Vec::new();
+    let mut out = Vec::new();
+    for line in &tx.logs {
+        if let Some(data) = line.strip_prefix("Program data: ") {
+            if stack.last() == Some(&id.as_str()) {
+                // One base64 chunk per emitted event
+                if let Some(ev) = data.split(' ').next().and_then(|b| BASE64.decode(b).ok()).and_then(|d| Event::decode(&d)) {
+                    out.push(ev);
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("Program ") {
+            let mut parts = rest.split(' ');
+            let (Some(pid), Some(verb)) = (parts.next(), parts.next()) else { continue };
+            match verb {
+                "invoke" => stack.push(pid),
+                "success" | "failed:" => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    out
+}
+
+/// `Program data:` line for an event (fixtures and tests).
+pub fn data_line(event: &Event) -> String {
+    format!("Program data: {}", BASE64.encode(event.encode()))
+}
@@ -0,0 +1,144 @@
+//! `tail-risk-indexer`: ingest program logs into SQLite, print ledgers and
+//! reconcile them against on-chain accounts. Run with `--help` for usage.
+
+use std::{fs, path::PathBuf, process};
+
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
+use solana_sdk::pubkey::Pubkey;
+use tail_risk_cli::pda::{Pdas, PROGRAM_ID};
+use tail_risk_indexer::{
+    ledger::Ledger,
+    reconcile::reconcile,
+    source::{from_json, RpcSource},
+    store::{Filter, Store},
+};
+
+#[derive(Parser)]
+#[command(name = "tail-risk-indexer", about = "Event ledger for the tail-risk insurance pool")]
+struct Cli {
+    /// SQLite database
+    #[arg(long, default_value = "tail_risk_events.sqlite")]
+    db: PathBuf,
+    #[arg(long, default_value_t = PROGRAM_ID)]
+    program_id: Pubkey,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Ingest `getTransaction` JSON files, or every new transaction from an RPC node
+    Ingest {
+        #[arg(long, required_unless_present = "rpc")]
+        file: Vec<PathBuf>,
+        #[arg(long, conflicts_with = "file")]
+        rpc: Option<String>,
+    },
+    /// List stored events
+    Events {
+        #[arg(long)]
+        kind: Option<String>,
+        #[arg(long)]
+        owner: Option<Pubkey>,
+        #[arg(long)]
+        epoch: Option<u64>,
+    },
+    /// Print user / epoch / fee ledgers
+    Ledger {
+        #[arg(long)]
+        user: Option<Pubkey>,
+        #[arg(long)]
+        epoch: Option<u64>,
+    },
+    /// Compare ledgers with UserPosition / Epoch / ClaimReceipt accounts
+    Reconcile {
+        #[arg(long)]
+        rpc: String,
+    },
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let mut store = Store::open(&cli.db, cli.program_id)?;
+    match cli.command {
+        Command::Ingest { file, rpc } => {
+            let txs = match rpc {
+                Some(url) => RpcSource::new(&url, cli.program_id).fetch_since(store.last_signature()?.as_deref())?,
+                None => file.iter().map(|f| from_json(&fs::read_to_string(f)?)).collect::<Result<Vec<_>>>()?.concat(),
+            };
+            let added = store.ingest_all(&txs)?;
+            let (t, e) = store.counts()?;
+            println!("ingested {} transactions, {added} new events ({t} transactions, {e} events stored)", txs.len());
+        }
+        Command::Events { kind, owner, epoch } => {
+            let filter = Filter { kind: kind.as_deref(), owner: owner.as_ref(), epoch_id: epoch };
+            for e in store.events(filter)? {
+                println!("{} {}#{} {:?}", e.slot, e.signature, e.idx, e.event);
+            }
+        }
+        Command::Ledger { user, epoch } => {
+            let events = store.events(Filter::default())?;
+            let ledger = Ledger::build(events.iter().map(|e| &e.event));
+            match (user, epoch) {
+                (Some(u), _) => match ledger.users.get(&u) {
+                    Some(l) => println!("{u} {l:#?}"),
+                    None => bail!("no events for {u}"),
+                },
+                (None, Some(id)) => match ledger.epochs.get(&id) {
+                    Some(l) => println!("epoch {id} {l:#?}"),
+                    None => bail!("no events for epoch {id}"),
+                },
+                (None, None) => {
+                    for (owner, l) in &ledger.users {
+                        println!(
+                            "user {owner} senior={} junior={} payouts={} yield={}",
+                            l.principal_fp(0),
+                            l.principal_fp(1),
+                            l.total_payouts_fp(),
+                            l.yield_claimed_fp
+                        );
+                    }
+                    for (id, l) in &ledger.epochs {
+                        let sev = l.trigger.as_ref().map(|t| t.severity_bps);
+                        println!(
+                            "epoch {id} severity={sev:?} payouts={} claimants={} finalized={} ended={:?}",
+                            l.payouts_fp, l.claimants, l.finalized, l.ended_ts
+                        );
+                    }
+                    println!("fees {:?}", ledger.fees);
+                    println!("pause_flags={:#07b} shutdown_ts={:?}", ledger.pause_flags, ledger.shutdown_ts);
+                    for a in &ledger.outflow_alerts {
+                        println!(
+                            "outflow breaker tripped: window_updated={} used={} attempted={} limit={}",
+                            a.window_updated_ts, a.used_fp, a.attempted_fp, a.limit_fp
+                        );
+                    }
+                }
+            }
+        }
+        Command::Reconcile { rpc } => {
+            let events = store.events(Filter::default())?;
+            let ledger = Ledger::build(events.iter().map(|e| &e.event));
+            let source = RpcSource::new(&rpc, cli.program_id);
+            let client = source.client();
+            let mismatches = reconcile(&ledger, &Pdas::new(cli.program_id), |addr| {
+                Ok(client.get_account_with_commitment(addr, client.commitment())?.value.map(|a| a.data))
+            })?;
+            for m in &mismatches {
+                println!("{} {} {}: ledger={} chain={}", m.account, m.address, m.field, m.ledger, m.chain);
+            }
+            if !mismatches.is_empty() {
+                bail!("{} mismatches", mismatches.len());
+            }
+            println!("ledger matches chain ({} users, {} epochs)", ledger.users.len(), ledger.epochs.len());
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("error: {e:#}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,131 @@
+//! Ledger vs on-chain accounts.
+
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use tail_risk_cli::{
+    accounts::{ClaimBitmap, ClaimReceipt, Epoch, ProgramAccount, UserPosition},
+    pda::Pdas,
+};
+
+use crate::ledger::{EpochLedger, Ledger, UserLedger};
+
+/// One disagreement between the ledger and an account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Account type (`UserPosition`, `Epoch`, `ClaimReceipt`, `ClaimBitmap`).
+    pub account: &'static str,
+    /// Address.
+    pub address: Pubkey,
+    /// Field (`exists` when the account is missing, or present after a close event).
+    pub field: &'static str,
+    /// Value implied by the events.
+    pub ledger: String,
+    /// Value on chain.
+    pub chain: String,
+}
+
+fn check<T: PartialEq + std::fmt::Debug>(out: &mut Vec<Mismatch>, account: &'static str, address: Pubkey, field: &'static str, ledger: T, chain: T) {
+    if ledger != chain {
+        out.push(Mismatch { account, address, field, ledger: format!("{ledger:?}"), chain: format!("{chain:?}") });
+    }
+}
+
+/// Compare a user's ledger with their `UserPosition`.
+pub fn reconcile_position(address: Pubkey, l: &UserLedger, p: &UserPosition) -> Vec<Mismatch> {
+    let mut out = Vec::new();
+    let a = UserPosition::NAME;
+    check(&mut out, a, address, "senior_deposited_fp", l.principal_fp(0), p.senior_deposited_fp);
+    check(&mut out, a, address, "junior_deposited_fp", l.principal_fp(1), p.junior_deposited_fp);
+    check(&mut out, a, address, "referrer", l.referrer.unwrap_or_default(), p.referrer);
+    out
+}
+
+/// Compare an epoch's ledger with its `Epoch` account.
+pub fn reconcile_epoch(address: Pubkey, l: &EpochLedger, e: &Epoch) -> Vec<Mismatch> {
+    let mut out = Vec::new();
+    let a = Epoch::NAME;
+    if let Some((start, end)) = l.window {
+        check(&mut out, a, address, "start_ts", start, e.start_ts);
+        check(&mut out, a, address, "end_ts", end, e.end_ts);
+    }
+    check(&mut out, a, address, "triggered", l.trigger.is_some(), e.triggered);
+    if let Some(t) = &l.trigger {
+        check(&mut out, a, address, "severity_bps", t.severity_bps, e.severity_bps);
+        check(&mut out, a, address, "user_cap_bps", t.user_cap_bps, e.user_cap_bps);
+        check(&mut out, a, address, "evidence_hash", t.evidence_hash, e.evidence_hash);
+    }
+    check(&mut out, a, address, "merkle_root", l.merkle_root.unwrap_or_default(), e.merkle_root);
+    check(&mut out, a, address, "total_payout_fp", l.payouts_fp, e.total_payout_fp);
+    check(&mut out, a, address, "closed", l.finalized || l.ended_ts.is_some(), e.closed);
+    if let Some(ended_ts) = l.ended_ts {
+        check(&mut out, a, address, "finalized_ts", ended_ts, e.finalized_ts);
+    }
+    out
+}
+
+/// Compare a Merkle-settled epoch's payouts with its `ClaimBitmap`.
+pub fn reconcile_bitmap(address: Pubkey, l: &EpochLedger, b: &ClaimBitmap) -> Vec<Mismatch> {
+    let mut out = Vec::new();
+    check(&mut out, ClaimBitmap::NAME, address, "claimed", l.claimants, b.claimed);
+    out
+}
+
+/// Compare one payout with its `ClaimReceipt`.
+pub fn reconcile_claim(address: Pubkey, payout_fp: u128, c: &ClaimReceipt) -> Vec<Mismatch> {
+    let mut out = Vec::new();
+    check(&mut out, ClaimReceipt::NAME, address, "claimed_fp", payout_fp, c.claimed_fp);
+    out
+}
+
+fn existence(account: &'static str, address: Pubkey, ledger: bool) -> Mismatch {
+    Mismatch { account, address, field: "exists", ledger: ledger.to_string(), chain: (!ledger).to_string() }
+}
+
+/// Compare an account the ledger expects open, or check that a closed one is gone.
+fn expect<T: ProgramAccount>(
+    out: &mut Vec<Mismatch>,
+    address: Pubkey,
+    data: Option<Vec<u8>>,
+    closed: bool,
+    compare: impl FnOnce(&T) -> Vec<Mismatch>,
+) -> Result<()> {
+    match (data, closed) {
+        (Some(data), false) => out.extend(compare(&T::decode(&data)?)),
+        (None, false) => out.push(existence(T::NAME, address, true)),
+        (Some(_), true) => out.push(existence(T::NAME, address, false)),
+        (None, true) => {}
+    }
+    Ok(())
+}
+
+/// Reconcile every depositor, epoch and claim in `ledger`. Accounts closed by
+/// `close_position`, `close_claim_receipt` or `close_epoch` must no longer exist.
+/// Payouts from Merkle-settled epochs have no receipts; the epoch's claim bitmap
+/// is checked instead.
+///
+/// `fetch` returns raw account data (`None` if the account does not exist), so
+/// the same code runs against an RPC node or a test fixture.
+pub fn reconcile(ledger: &Ledger, pdas: &Pdas, mut fetch: impl FnMut(&Pubkey) -> Result<Option<Vec<u8>>>) -> Result<Vec<Mismatch>> {
+    let mut out = Vec::new();
+    for (owner, u) in ledger.users.iter().filter(|(_, u)| u.deposits > 0) {
+        let addr = pdas.position(owner);
+        expect(&mut out, addr, fetch(&addr)?, u.position_closed, |p| reconcile_position(addr, u, p))?;
+        for (&epoch_id, &paid) in &u.payouts_fp {
+            if ledger.epochs.get(&epoch_id).is_some_and(|e| e.merkle_root.is_some()) {
+                continue;
+            }
+            let addr = pdas.claim(epoch_id, owner);
+            let closed = u.closed_receipts.contains(&epoch_id);
+            expect(&mut out, addr, fetch(&addr)?, closed, |c| reconcile_claim(addr, paid, c))?;
+        }
+    }
+    for (&epoch_id, e) in &ledger.epochs {
+        let addr = pdas.epoch(epoch_id);
+        expect(&mut out, addr, fetch(&addr)?, e.account_closed, |a| reconcile_epoch(addr, e, a))?;
+        if e.merkle_root.is_some() {
+            let addr = pdas.bitmap(epoch_id);
+            expect(&mut out, addr, fetch(&addr)?, e.account_closed, |b| reconcile_bitmap(addr, e, b))?;
+        }
+    }
+    Ok(out)
+}
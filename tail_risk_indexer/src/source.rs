@@ -0,0 +1,99 @@
+//! Where transaction logs come from: JSON files or an RPC node (e.g. a local validator).
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use solana_rpc_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_rpc_client_api::config::RpcTransactionConfig;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+
+use crate::logs::TxLogs;
+
+/// Parse `getTransaction` results: a JSON array, JSON lines, or a single object.
+/// Each entry may be the bare result or the full `{"jsonrpc", "result"}` response.
+pub fn from_json(text: &str) -> Result<Vec<TxLogs>> {
+    let trimmed = text.trim_start();
+    let values: Vec<Value> = if trimmed.starts_with('[') {
+        serde_json::from_str(trimmed)?
+    } else {
+        serde_json::Deserializer::from_str(trimmed).into_iter().collect::<Result<_, _>>()?
+    };
+    values.iter().enumerate().map(|(i, v)| parse_tx(v).with_context(|| format!("entry {i}"))).collect()
+}
+
+fn parse_tx(v: &Value) -> Result<TxLogs> {
+    let v = v.get("result").unwrap_or(v);
+    let meta = v.get("meta").ok_or_else(|| anyhow!("missing meta"))?;
+    let signature = v
+        .pointer("/transaction/signatures/0")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("missing transaction.signatures"))?;
+    let logs = meta
+        .get("logMessages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("missing meta.logMessages"))?
+        .iter()
+        .map(|l| l.as_str().map(str::to_owned).ok_or_else(|| anyhow!("non-string log line")))
+        .collect::<Result<_>>()?;
+    Ok(TxLogs {
+        signature: signature.to_owned(),
+        slot: v.get("slot").and_then(Value::as_u64).ok_or_else(|| anyhow!("missing slot"))?,
+        block_time: v.get("blockTime").and_then(Value::as_i64),
+        failed: !meta.get("err").is_none_or(Value::is_null),
+        logs,
+    })
+}
+
+/// Pulls the program's transactions from an RPC node.
+pub struct RpcSource {
+    client: RpcClient,
+    program_id: Pubkey,
+}
+
+impl RpcSource {
+    /// Source for `program_id` at `url` (confirmed commitment).
+    pub fn new(url: &str, program_id: Pubkey) -> Self {
+        Self { client: RpcClient::new_with_commitment(url.to_owned(), CommitmentConfig::confirmed()), program_id }
+    }
+
+    /// The underlying client (for account reads during reconciliation).
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+
+    /// Transactions newer than `until` (all history if `None`), oldest first.
+    pub fn fetch_since(&self, until: Option<&str>) -> Result<Vec<TxLogs>> {
+        let until = until.map(Signature::from_str).transpose()?;
+        let mut sigs = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self.client.get_signatures_for_address_with_config(
+                &self.program_id,
+                GetConfirmedSignaturesForAddress2Config { before, until, limit: None, commitment: None },
+            )?;
+            let Some(last) = page.last() else { break };
+            before = Some(Signature::from_str(&last.signature)?);
+            sigs.extend(page);
+        }
+        sigs.reverse();
+
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        sigs.iter()
+            .map(|s| {
+                let tx = self.client.get_transaction_with_config(&Signature::from_str(&s.signature)?, config)?;
+                let meta = tx.transaction.meta.ok_or_else(|| anyhow!("{}: no meta", s.signature))?;
+                let logs = match meta.log_messages {
+                    OptionSerializer::Some(l) => l,
+                    _ => Vec::new(),
+                };
+                Ok(TxLogs { signature: s.signature.clone(), slot: tx.slot, block_time: tx.block_time, failed: meta.err.is_some(), logs })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,171 @@
+//! Embedded SQLite store.
+//!
+//! `txs` records every ingested transaction (so re-ingestion is a no-op) and
+//! `events` one row per decoded event, with the raw payload plus indexed
+//! columns. `u128` amounts are stored as decimal text (SQLite integers are i64).
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    events::Event,
+    logs::{program_events, TxLogs},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS txs (
+    signature  TEXT PRIMARY KEY,
+    slot       INTEGER NOT NULL,
+    block_time INTEGER,
+    failed     INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    signature  TEXT NOT NULL REFERENCES txs(signature),
+    idx        INTEGER NOT NULL,
+    slot       INTEGER NOT NULL,
+    block_time INTEGER,
+    kind       TEXT NOT NULL,
+    epoch_id   INTEGER,
+    owner      TEXT,
+    mint       TEXT,
+    tranche    INTEGER,
+    amount_fp  TEXT,
+    payload    BLOB NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+CREATE INDEX IF NOT EXISTS events_owner ON events(owner);
+CREATE INDEX IF NOT EXISTS events_epoch ON events(epoch_id);
+CREATE INDEX IF NOT EXISTS events_kind ON events(kind);
+";
+
+/// An event with its position in the ledger.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredEvent {
+    /// Transaction signature.
+    pub signature: String,
+    /// Index among the program's events in the transaction.
+    pub idx: u32,
+    /// Slot.
+    pub slot: u64,
+    /// Block time, if known.
+    pub block_time: Option<i64>,
+    /// Decoded event.
+    pub event: Event,
+}
+
+/// Event filter for [`Store::events`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Filter<'a> {
+    /// Only this event kind.
+    pub kind: Option<&'a str>,
+    /// Only events about this owner / referrer / payer.
+    pub owner: Option<&'a Pubkey>,
+    /// Only events about this epoch.
+    pub epoch_id: Option<u64>,
+}
+
+/// SQLite-backed event store for one program.
+pub struct Store {
+    conn: Connection,
+    program_id: Pubkey,
+}
+
+impl Store {
+    /// Open (or create) a database file.
+    pub fn open(path: &Path, program_id: Pubkey) -> Result<Self> {
+        Self::init(Connection::open(path)?, program_id)
+    }
+
+    /// In-memory database (tests, one-off reconciliation).
+    pub fn in_memory(program_id: Pubkey) -> Result<Self> {
+        Self::init(Connection::open_in_memory()?, program_id)
+    }
+
+    fn init(conn: Connection, program_id: Pubkey) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn, program_id })
+    }
+
+    /// Record a transaction and its events. Returns the number of events stored
+    /// (0 if the transaction was already ingested).
+    pub fn ingest(&mut self, tx: &TxLogs) -> Result<usize> {
+        let events = program_events(&self.program_id, tx);
+        let db = self.conn.transaction()?;
+        let inserted = db.execute(
+            "INSERT OR IGNORE INTO txs (signature, slot, block_time, failed) VALUES (?1, ?2, ?3, ?4)",
+            params![tx.signature, tx.slot as i64, tx.block_time, tx.failed],
+        )?;
+        if inserted == 0 {
+            return Ok(0);
+        }
+        for (idx, ev) in events.iter().enumerate() {
+            let (epoch_id, owner, mint, tranche, amount_fp) = ev.columns();
+            db.execute(
+                "INSERT INTO events (signature, idx, slot, block_time, kind, epoch_id, owner, mint, tranche, amount_fp, payload)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    tx.signature,
+                    idx as i64,
+                    tx.slot as i64,
+                    tx.block_time,
+                    ev.kind(),
+                    epoch_id.map(|e| e as i64),
+                    owner.map(|o| o.to_string()),
+                    mint.map(|m| m.to_string()),
+                    tranche,
+                    amount_fp.map(|a| a.to_string()),
+                    ev.encode(),
+                ],
+            )?;
+        }
+        db.commit()?;
+        Ok(events.len())
+    }
+
+    /// Ingest many transactions; returns the number of new events.
+    pub fn ingest_all<'a>(&mut self, txs: impl IntoIterator<Item = &'a TxLogs>) -> Result<usize> {
+        txs.into_iter().try_fold(0, |n, tx| Ok(n + self.ingest(tx)?))
+    }
+
+    /// Signature of the most recent ingested transaction (resume point for RPC backfill).
+    pub fn last_signature(&self) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row("SELECT signature FROM txs ORDER BY slot DESC, rowid DESC LIMIT 1", [], |r| r.get(0))
+            .optional()?)
+    }
+
+    /// Events matching `filter`, in ledger order (slot, then ingestion, then log order).
+    pub fn events(&self, filter: Filter<'_>) -> Result<Vec<StoredEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.signature, e.idx, e.slot, e.block_time, e.payload FROM events e JOIN txs t USING (signature)
+             WHERE (?1 IS NULL OR e.kind = ?1) AND (?2 IS NULL OR e.owner = ?2) AND (?3 IS NULL OR e.epoch_id = ?3)
+             ORDER BY e.slot, t.rowid, e.idx",
+        )?;
+        let rows = stmt.query_map(
+            params![filter.kind, filter.owner.map(|o| o.to_string()), filter.epoch_id.map(|e| e as i64)],
+            |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?, r.get::<_, i64>(2)?, r.get::<_, Option<i64>>(3)?, r.get::<_, Vec<u8>>(4)?)),
+        )?;
+        rows.map(|row| {
+            let (signature, idx, slot, block_time, payload) = row?;
+            let event = Event::decode(&payload).ok_or_else(|| anyhow!("{signature}#{idx}: undecodable payload"))?;
+            Ok(StoredEvent { signature, idx: idx as u32, slot: slot as u64, block_time, event })
+        })
+        .collect()
+    }
+
+    /// Number of ingested transactions and stored events.
+    pub fn counts(&self) -> Result<(u64, u64)> {
+        let txs: i64 = self.conn.query_row("SELECT COUNT(*) FROM txs", [], |r| r.get(0))?;
+        let events: i64 = self.conn.query_row("SELECT COUNT(*) FROM events", [], |r| r.get(0))?;
+        Ok((txs as u64, events as u64))
+    }
+
+    /// Raw connection for ad-hoc SQL.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use solana_sdk::pubkey::Pubkey;
+use tail_risk_cli::{
+    accounts::{discriminator, ClaimBitmap, ClaimReceipt, Epoch, ProgramAccount, UserPosition},
+    pda::{Pdas, PROGRAM_ID},
+};
+use tail_risk_indexer::{
+    events::*,
+    ledger::Ledger,
+    logs::{data_line, program_events, TxLogs},
+    reconcile::reconcile,
+    source::from_json,
+    store::{Filter, Store},
+};
+
+const FP: u128 = 1_000_000_000_000;
+
+fn tx(sig: &str, slot: u64, events: &[Event]) -> TxLogs {
+    let id = PROGRAM_ID.to_string();
+    let mut logs = vec![format!("Program {id} invoke [1]"), "Program log: Instruction: X".into()];
+    logs.extend(events.iter().map(data_line));
+    logs.push(format!("Program {id} consumed 1000 of 200000 compute units"));
+    logs.push(format!("Program {id} success"));
+    TxLogs { signature: sig.into(), slot, block_time: Some(slot as i64), failed: false, logs }
+}
+
+fn deposit(owner: Pubkey, amount_fp: u128, tranche: u8) -> Event {
+    Event::Deposited(Deposited { owner, amount_fp, referrer: Pubkey::default(), tranche, mint: Pubkey::default() })
+}
+
+fn payout(epoch_id: u64, owner: Pubkey, payout_fp: u128) -> Event {
+    Event::UserPayout(UserPayout { epoch_id, owner, payout_fp, mint: Pubkey::default() })
+}
+
+fn history(alice: Pubkey, bob: Pubkey) -> Vec<TxLogs> {
+    vec![
+        tx("s1", 10, &[Event::ProtocolFeeTaken(ProtocolFeeTaken { amount_u64: 5 }), deposit(alice, 995 * FP, 0)]),
+        tx("s2", 11, &[deposit(bob, 500 * FP, 1)]),
+        tx("s3", 12, &[Event::EpochStarted(EpochStarted { epoch_id: 1, start_ts: 100, end_ts: 200 })]),
+        tx("s4", 13, &[Event::Withdrawn(Withdrawn { owner: alice, amount_fp: 95 * FP, tranche: 0, mint: Pubkey::default() })]),
+        tx(
+            "s5",
+            14,
+            &[Event::EventTriggered(EventTriggered { epoch_id: 1, severity_bps: 1_000, user_cap_bps: 0, policy: 0, evidence_hash: [7; 32] })],
+        ),
+        tx("s6", 15, &[payout(1, alice, 90 * FP)]),
+        tx("s7", 15, &[payout(1, bob, 50 * FP)]),
+        tx("s8", 16, &[Event::EpochFinalized(EpochFinalized { epoch_id: 1 })]),
+    ]
+}
+
+#[test]
+fn only_this_programs_successful_events_are_decoded() {
+    let id = PROGRAM_ID.to_string();
+    let other = Pubkey::new_unique().to_string();
+    let ev = payout(3, Pubkey::new_unique(), 42);
+    let mut t = tx("a", 1, std::slice::from_ref(&ev));
+    // CPI into another program that also logs data, then back in ours
+    t.logs.splice(
+        2..2,
+        [format!("Program {other} invoke [2]"), data_line(&ev), format!("Program {other} success")],
+    );
+    assert_eq!(program_events(&PROGRAM_ID, &t), vec![ev.clone()]);
+    assert_eq!(Event::decode(&ev.encode()), Some(ev.clone()));
+    assert_eq!(Event::decode(&[0; 16]), None);
+
+    t.failed = true;
+    assert!(program_events(&PROGRAM_ID, &t).is_empty());
+    // Events of this program invoked via CPI still count
+    t.failed = false;
+    t.logs[0] = format!("Program {other} invoke [1]");
+    t.logs.insert(1, format!("Program {id} invoke [2]"));
+    assert_eq!(program_events(&PROGRAM_ID, &t), vec![ev]);
+}
+
+#[test]
+fn json_feeds_parse() {
+    let t = tx("sig1", 9, &[Event::Paused(Paused { flags: 1 })]);
+    let result = serde_json::json!({
+        "slot": 9, "blockTime": 1_700_000_000,
+        "meta": { "err": null, "logMessages": t.logs },
+        "transaction": { "signatures": ["sig1"], "message": {} }
+    });
+    let failed = serde_json::json!({
+        "jsonrpc": "2.0", "id": 1,
+        "result": { "slot": 10, "blockTime": null, "meta": { "err": { "InstructionError": [0, "Custom"] }, "logMessages": [] },
+                    "transaction": { "signatures": ["sig2"] } }
+    });
+    let array = from_json(&serde_json::to_string(&[&result, &failed]).unwrap()).unwrap();
+    let lines = from_json(&format!("{result}\n{failed}\n")).unwrap();
+    assert_eq!(array, lines);
+    assert_eq!((array[0].signature.as_str(), array[0].slot, array[0].block_time, array[0].failed), ("sig1", 9, Some(1_700_000_000), false));
+    assert!(array[1].failed && array[1].block_time.is_none());
+    assert!(from_json("{\"slot\": 1}").is_err());
+}
+
+#[test]
+fn store_is_idempotent_and_builds_ledgers() {
+    let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let txs = history(alice, bob);
+    let mut store = Store::in_memory(PROGRAM_ID).unwrap();
+    assert_eq!(store.ingest_all(&txs).unwrap(), 9);
+    assert_eq!(store.ingest_all(&txs).unwrap(), 0, "re-ingestion adds nothing");
+    assert_eq!(store.counts().unwrap(), (8, 9));
+    assert_eq!(store.last_signature().unwrap().as_deref(), Some("s8"));
+
+    assert_eq!(store.events(Filter { owner: Some(&alice), ..Default::default() }).unwrap().len(), 3);
+    assert_eq!(store.events(Filter { epoch_id: Some(1), ..Default::default() }).unwrap().len(), 5);
+    assert_eq!(store.events(Filter { kind: Some("UserPayout"), ..Default::default() }).unwrap().len(), 2);
+    let total: i64 = store
+        .connection()
+        .query_row("SELECT SUM(CAST(amount_fp AS INTEGER)) FROM events WHERE kind = 'Deposited'", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(total as u128, 1_495 * FP);
+
+    let events = store.events(Filter::default()).unwrap();
+    let ledger = Ledger::build(events.iter().map(|e| &e.event));
+    let a = &ledger.users[&alice];
+    assert_eq!((a.principal_fp(0), a.principal_fp(1), a.total_payouts_fp()), (900 * FP, 0, 90 * FP));
+    assert_eq!(ledger.users[&bob].principal_fp(1), 500 * FP);
+    let e = &ledger.epochs[&1];
+    assert_eq!((e.payouts_fp, e.claimants, e.finalized), (140 * FP, 2, true));
+    assert_eq!(e.trigger.as_ref().unwrap().severity_bps, 1_000);
+    assert_eq!(ledger.fees.protocol_fees_u64, 5);
+    assert_eq!(ledger.shutdown_ts, None);
+
+    // Emergency exits retire the whole principal, whatever they pay
+    let exit = Event::EmergencyWithdrawn(EmergencyWithdrawn {
+        owner: bob, senior_fp: 0, junior_fp: 500 * FP, amount_fp: 450 * FP, mint: Pubkey::default(),
+    });
+    assert_eq!(exit.columns(), (None, Some(bob), Some(Pubkey::default()), None, Some(450 * FP)));
+    let tail = [Event::PoolShutdown(PoolShutdown { shutdown_ts: 300 }), exit];
+    let ledger = Ledger::build(events.iter().map(|e| &e.event).chain(&tail));
+    let b = &ledger.users[&bob];
+    assert_eq!((b.principal_fp(1), b.emergency_paid_fp, ledger.shutdown_ts), (0, 450 * FP, Some(300)));
+
+    // The breaker's alert is kept, and the pause it set is the latest mask
+    let trip = OutflowLimitTripped { window_updated_ts: 400, used_fp: 90 * FP, attempted_fp: 20 * FP, limit_fp: 100 * FP };
+    let tail = [Event::OutflowLimitTripped(trip.clone()), Event::Paused(Paused { flags: 0b11010 })];
+    let ledger = Ledger::build(events.iter().map(|e| &e.event).chain(&tail));
+    assert_eq!((ledger.outflow_alerts, ledger.pause_flags), (vec![trip], 0b11010));
+
+    // Guardian appointments are tracked; the default key removes the guardian
+    let guardian = Pubkey::new_unique();
+    let appointed = Event::GuardianUpdated(GuardianUpdated { guardian });
+    assert_eq!(Event::decode(&appointed.encode()), Some(appointed.clone()));
+    let ledger = Ledger::build(events.iter().map(|e| &e.event).chain([&appointed]));
+    assert_eq!(ledger.guardian, Some(guardian));
+    let removed = Event::GuardianUpdated(GuardianUpdated { guardian: Pubkey::default() });
+    assert_eq!(Ledger::build(events.iter().map(|e| &e.event).chain([&appointed, &removed])).guardian, None);
+
+    // Cranked payouts arrive as ordinary `UserPayout`s; only the reward is new
+    let keeper = Pubkey::new_unique();
+    let crank = Event::PayoutsCranked(PayoutsCranked { epoch_id: 1, cranker: keeper, paid: 2, skipped: 1, reward_u64: 2_000_000 });
+    assert_eq!(Event::decode(&crank.encode()), Some(crank.clone()));
+    assert_eq!(crank.columns(), (Some(1), Some(keeper), None, None, Some(2_000_000)));
+    let ledger = Ledger::build(events.iter().map(|e| &e.event).chain([&crank]));
+    assert_eq!((ledger.fees.crank_rewards_u64, ledger.epochs[&1].claimants), (2_000_000, 2));
+
+    // Cover is tracked per epoch, apart from LP payouts; its NFT may change hands before the claim
+    let (nft, buyer, holder) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let bought =
+        Event::CoverPurchased(CoverPurchased { epoch_id: 1, mint: nft, buyer, notional_fp: 400 * FP, premium_fp: 8 * FP });
+    let claimed = Event::CoverClaimed(CoverClaimed { epoch_id: 1, mint: nft, holder, payout_fp: 40 * FP });
+    assert_eq!(Event::decode(&claimed.encode()), Some(claimed.clone()));
+    assert_eq!(bought.columns(), (Some(1), Some(buyer), Some(nft), None, Some(400 * FP)));
+    assert_eq!(claimed.columns(), (Some(1), Some(holder), Some(nft), None, Some(40 * FP)));
+    let closed = Event::CoverClosed(CoverClosed { epoch_id: 1, mint: nft, buyer });
+    assert_eq!(Event::decode(&closed.encode()), Some(closed.clone()));
+    assert_eq!(closed.columns(), (Some(1), Some(buyer), Some(nft), None, None));
+    let ledger = Ledger::build(events.iter().map(|e| &e.event).chain([&bought, &claimed, &closed]));
+    let e = &ledger.epochs[&1];
+    assert_eq!((e.cover_sold_fp, e.cover_claims_fp, e.payouts_fp), (400 * FP, 40 * FP, 140 * FP));
+    assert_eq!(e.covers_closed, 1);
+}
+
+fn zeroed<T: ProgramAccount>(size: usize) -> T {
+    let mut data = discriminator(T::NAME).to_vec();
+    data.resize(8 + size, 0);
+    T::decode(&data).unwrap()
+}
+
+#[test]
+fn reconciles_against_accounts() {
+    let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let events: Vec<Event> = history(alice, bob).iter().flat_map(|t| program_events(&PROGRAM_ID, t)).collect();
+    let ledger = Ledger::build(&events);
+    let pdas = Pdas::new(PROGRAM_ID);
+
+    let mut chain: HashMap<Pubkey, Vec<u8>> = HashMap::new();
+    for (owner, senior, junior, paid) in [(alice, 900 * FP, 0, 90 * FP), (bob, 0, 500 * FP, 50 * FP)] {
+        let mut p: UserPosition = zeroed(32 + 16 * 4 + 2 * (2 + 16 * 24) + 8 + 32 + 16 * 3 + 1 + 1 + 128);
+        (p.owner, p.senior_deposited_fp, p.junior_deposited_fp) = (owner, senior, junior);
+        chain.insert(pdas.position(&owner), p.encode());
+        let mut c: ClaimReceipt = zeroed(8 + 32 + 16 + 1 + 1 + 32 + 16);
+        (c.epoch_id, c.owner, c.claimed_fp) = (1, owner, paid);
+        chain.insert(pdas.claim(1, &owner), c.encode());
+    }
+    let mut e: Epoch = zeroed(340);
+    (e.epoch_id, e.start_ts, e.end_ts, e.severity_bps, e.evidence_hash) = (1, 100, 200, 1_000, [7; 32]);
+    (e.triggered, e.closed, e.total_payout_fp) = (true, true, 140 * FP);
+    chain.insert(pdas.epoch(1), e.encode());
+
+    assert_eq!(reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap(), vec![]);
+
+    e.total_payout_fp += 1;
+    chain.insert(pdas.epoch(1), e.encode());
+    chain.remove(&pdas.claim(1, &bob));
+    let m = reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap();
+    assert_eq!(m.len(), 2);
+    assert!(m.iter().any(|m| m.account == "ClaimReceipt" && m.field == "exists"));
+    assert!(m.iter().any(|m| m.account == "Epoch" && m.field == "total_payout_fp" && m.chain == (140 * FP + 1).to_string()));
+
+    // Once closed, the receipt and epoch must be gone
+    let mut closed = events.clone();
+    closed.push(Event::ClaimReceiptClosed(ClaimReceiptClosed { epoch_id: 1, owner: bob }));
+    closed.push(Event::EpochClosed(EpochClosed { epoch_id: 1 }));
+    let ledger = Ledger::build(&closed);
+    assert!(ledger.users[&bob].closed_receipts.contains(&1) && ledger.epochs[&1].account_closed);
+    let m = reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap();
+    assert_eq!((m.len(), m[0].account, m[0].field, m[0].ledger.as_str()), (1, "Epoch", "exists", "false"));
+    chain.remove(&pdas.epoch(1));
+    assert_eq!(reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap(), vec![]);
+
+    // A Merkle-settled epoch pays without receipts; its claim bitmap counts the claims
+    let posted = Event::MerkleRootPosted(MerkleRootPosted { epoch_id: 1, root: [9; 32], total_fp: 140 * FP, claimants: 2 });
+    assert_eq!(Event::decode(&posted.encode()), Some(posted.clone()));
+    assert_eq!(posted.columns(), (Some(1), None, None, None, Some(140 * FP)));
+    let ledger = Ledger::build(events.iter().chain([&posted]));
+    assert_eq!(ledger.epochs[&1].merkle_root, Some([9; 32]));
+    (e.merkle_root, e.merkle_total_fp, e.total_payout_fp) = ([9; 32], 140 * FP, 140 * FP);
+    chain.insert(pdas.epoch(1), e.encode());
+    chain.remove(&pdas.claim(1, &alice));
+    let m = reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap();
+    assert_eq!((m.len(), m[0].account, m[0].field, m[0].ledger.as_str()), (1, "ClaimBitmap", "exists", "true"));
+    let bitmap = ClaimBitmap { epoch_id: 1, claimants: 2, claimed: 2, bump: 255, version: 1, reserved: [0; 16], bits: vec![0b11] };
+    chain.insert(pdas.bitmap(1), bitmap.encode());
+    assert_eq!(reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap(), vec![]);
+}
+
+#[test]
+fn an_epoch_ended_untriggered_reconciles_as_closed() {
+    let started = Event::EpochStarted(EpochStarted { epoch_id: 2, start_ts: 300, end_ts: 0 });
+    let ended = Event::EpochEnded(EpochEnded { epoch_id: 2, end_ts: 900 });
+    assert_eq!(Event::decode(&ended.encode()), Some(ended.clone()));
+    assert_eq!(ended.columns(), (Some(2), None, None, None, None));
+    let ledger = Ledger::build([&started, &ended]);
+    assert_eq!((ledger.epochs[&2].finalized, ledger.epochs[&2].ended_ts), (false, Some(900)));
+
+    let pdas = Pdas::new(PROGRAM_ID);
+    let mut e: Epoch = zeroed(340);
+    (e.epoch_id, e.start_ts, e.closed, e.finalized_ts) = (2, 300, true, 900);
+    let mut chain = HashMap::from([(pdas.epoch(2), e.encode())]);
+    assert_eq!(reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap(), vec![]);
+    e.closed = false;
+    chain.insert(pdas.epoch(2), e.encode());
+    let m = reconcile(&ledger, &pdas, |k| Ok(chain.get(k).cloned())).unwrap();
+    assert_eq!((m.len(), m[0].field), (1, "closed"));
+}
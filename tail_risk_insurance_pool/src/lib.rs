@@ -1,17 +1,88 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+};
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{self, Mint, Token, TokenAccount, Transfer},
+    associated_token::{get_associated_token_address, AssociatedToken},
+    token::{
+        self, spl_token::instruction::AuthorityType, Burn, CloseAccount, Mint, MintTo, SetAuthority, Token, TokenAccount,
+        Transfer,
+    },
+};
+use tail_risk_core::{
+    cover, fees, math, merkle, payout, pricing, severity, yields,
+    lots::{LotEntry, LotRing},
+    outflow::{OutflowLimits, OutflowWindow},
+    BPS_DENOM,
 };
 
 declare_id!("9XjXYmL9TLB3FuszEuXCTkjC6a4vHZ5TPWczyNMLKHRg");
 
-const SCALE: u128 = 1_000_000; // 1e6 fixed-point
-const BPS_DENOM: u128 = 10_000;
 
 // Storage bounds (tune for your needs)
 const MAX_LOTS: usize = 16;
 const MAX_ORACLES: usize = 16;
+const MAX_COLLATERALS: usize = 4;
+const MAX_REFERRAL_TIERS: usize = 3;
+
+// Collateral decimals are rescaled to COMMON_DECIMALS (USDC-style 6) for accounting
+const MAX_COLLATERAL_DECIMALS: u8 = 12;
+
+// Account layouts: every account ends with `version` and zeroed `reserved` bytes, and new
+// fields are carved out of `reserved`. Accounts written before versioning have the older
+// layouts below (`StateV0` ...), which migrate_state / migrate_position decode and rewrite.
+// `State` is boxed in every context: with its reserve it is too large for the SBF stack.
+const ACCOUNT_VERSION: u8 = 1;
+const STATE_RESERVED: usize = 640 - 144; // shutdown, pause control, outflow breaker, crank reward, rolling epochs, cover claims
+const POSITION_RESERVED: usize = 128 - 40; // rolling-epoch deposits
+const EPOCH_RESERVED: usize = 192 - 120; // merkle settlement, rolling-epoch deposits, cover claims
+const CLAIM_RESERVED: usize = 48 - 32; // rent payer
+const ORACLE_RESERVED: usize = 32;
+const REFERRER_RESERVED: usize = 32;
+const ATTRIBUTION_RESERVED: usize = 16;
+const COLLATERAL_RESERVED: usize = 64;
+const BITMAP_RESERVED: usize = 16;
+const HISTORY_RESERVED: usize = 64;
+const COVER_BOOK_RESERVED: usize = 46;
+const COVER_RESERVED: usize = 32;
+
+// Pause bits (`State::pause_flags`), set by the admin or guardian per operation
+pub const PAUSE_DEPOSITS: u8 = 1 << 0; // deposit_insurance, pay_premium
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 1; // withdraw, emergency_withdraw, strategy_deploy
+pub const PAUSE_TRIGGERS: u8 = 1 << 2; // trigger_event
+pub const PAUSE_CLAIMS: u8 = 1 << 3; // payout_user, crank_payouts, claim_yield, claim_referral_rewards
+pub const PAUSE_FEE_SWEEPS: u8 = 1 << 4; // collect_fees, finalize_epoch dust sweep
+pub const PAUSE_ALL: u8 = PAUSE_DEPOSITS | PAUSE_WITHDRAWALS | PAUSE_TRIGGERS | PAUSE_CLAIMS | PAUSE_FEE_SWEEPS;
+// What the outflow circuit breaker pauses when it trips
+pub const PAUSE_OUTFLOWS: u8 = PAUSE_WITHDRAWALS | PAUSE_CLAIMS | PAUSE_FEE_SWEEPS;
+
+// Ceiling on the per-claimant crank reward (base units, 10 USDC)
+const MAX_CRANK_REWARD_U64: u64 = 10_000_000;
+// Smallest crank payout (base units, 10 USDC) that earns the reward, so dust claims do not
+const MIN_CRANK_PAYOUT_U64: u64 = 10_000_000;
+
+// Leaves per Merkle distribution: keeps the claim bitmap within the 10 KiB an account can be
+// created with from a program
+const MAX_MERKLE_CLAIMANTS: u32 = 65_536;
+
+// Finalized epochs kept in the `EpochHistory` ring; the whole ring fits in one view's
+// return data (1 KiB)
+const EPOCH_HISTORY_LEN: usize = 16;
+
+// Claim receipts stay on chain this long after their epoch is finalized (audit trail)
+const CLAIM_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+
+// Cover holders get at least this long after a trigger to claim: until then finalize_epoch
+// waits for every cover sold on the epoch to be claimed
+const COVER_CLAIM_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+// Strategy adapter interface: Anchor-style `deposit(u64)` / `withdraw(u64)` on the lending program
+// (discriminators = sha256("global:<name>")[..8]); `u64::MAX` on withdraw means "everything".
+const STRATEGY_DEPOSIT_IX: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+const STRATEGY_WITHDRAW_IX: [u8; 8] = [183, 18, 70, 156, 148, 109, 161, 34];
+const STRATEGY_WITHDRAW_ALL: u64 = u64::MAX;
 
 // ----------------------------- Program ------------------------------------
 
@@ -28,13 +99,18 @@ pub mod tail_risk_insurance_pool {
         state.admin = ctx.accounts.admin.key();
         state.usdc_mint = ctx.accounts.usdc_mint.key();
         state.protocol_treasury = params.protocol_treasury;
-        state.paused = false;
+        state.claim_window_open = false;
         state.processing = false;
-        state.payout_policy = params.payout_policy as u8;
+        state.payout_policy = params.payout_policy;
 
         // Fees / bounds
         state.protocol_fee_bps = params.protocol_fee_bps;
         state.referral_fee_bps = params.referral_fee_bps;
+        state.fee_model = params.fee_model;
+        state.performance_fee_bps = params.performance_fee_bps;
+        state.protocol_fees_accrued_fp = 0;
+        state.referral_tiers = [ReferralTier::default(); MAX_REFERRAL_TIERS];
+        state.referral_rewards_owed_fp = 0;
 
         // Limits / UX
         state.user_deposit_cap_fp = params.user_deposit_cap_fp;
@@ -62,19 +138,229 @@ pub mod tail_risk_insurance_pool {
         state.total_deposited_fp = 0;
         state.carryover_shortfall_fp = 0;
 
+        // Strategy (configured later via set_strategy)
+        state.strategy_program = Pubkey::default();
+        state.strategy_max_bps = 0;
+        state.strategy_junior_yield_bps = 0;
+        state.strategy_deployed_fp = 0;
+        state.senior_total_fp = 0;
+        state.junior_total_fp = 0;
+        state.senior_yield_index_fp = 0;
+        state.junior_yield_index_fp = 0;
+        state.yield_reserve_fp = 0;
+
         state.bump = ctx.bumps.state;
+        state.version = ACCOUNT_VERSION;
+        state.shutdown = false;
+        state.shutdown_ts = 0;
+        state.guardian = Pubkey::default();
+        state.pause_flags = 0;
+        // Outflow breaker off until set_outflow_limits
+        state.outflow_window_secs = 0;
+        state.outflow_cap_fp = 0;
+        state.outflow_cap_bps = 0;
+        state.crank_reward_u64 = 0;
 
         // Param sanity
         assert_param_bounds(state)?;
 
+        // Oracle allowlist: enabled, empty (admin may always trigger)
+        let list = &mut ctx.accounts.oracle_list;
+        list.enabled = true;
+        list.count = 0;
+        list.bump = ctx.bumps.oracle_list;
+        list.version = ACCOUNT_VERSION;
+
+        // Collateral registry: base asset is always entry 0 (no haircut, uncapped)
+        let reg = &mut ctx.accounts.collateral_registry;
+        reg.count = 0;
+        reg.bump = ctx.bumps.collateral_registry;
+        reg.version = ACCOUNT_VERSION;
+        register_collateral(
+            reg,
+            CollateralAsset {
+                mint: ctx.accounts.usdc_mint.key(),
+                vault: ctx.accounts.vault_ata.key(),
+                decimals: ctx.accounts.usdc_mint.decimals,
+                haircut_bps: 0,
+                concentration_cap_bps: BPS_DENOM as u16,
+                enabled: true,
+                balance_fp: 0,
+            },
+        )?;
+
         emit!(Initialized { admin: state.admin, usdc_mint: state.usdc_mint });
         Ok(())
     }
 
-    pub fn set_paused(ctx: Context<AdminOnly>, paused: bool) -> Result<()> {
+    /// Register an additional stablecoin as collateral and create its vault ATA.
+    pub fn add_collateral(
+        ctx: Context<AddCollateral>,
+        haircut_bps: u16,
+        concentration_cap_bps: u16,
+    ) -> Result<()> {
+        let mint = &ctx.accounts.mint;
+        require!(mint.decimals <= MAX_COLLATERAL_DECIMALS, ErrorCode::ParamOutOfBounds);
+        assert_collateral_bounds(haircut_bps, concentration_cap_bps)?;
+
+        let reg = &mut ctx.accounts.collateral_registry;
+        require!(find_collateral(reg, mint.key()).is_none(), ErrorCode::ParamOutOfBounds);
+        register_collateral(
+            reg,
+            CollateralAsset {
+                mint: mint.key(),
+                vault: ctx.accounts.vault_ata.key(),
+                decimals: mint.decimals,
+                haircut_bps,
+                concentration_cap_bps,
+                enabled: true,
+                balance_fp: 0,
+            },
+        )?;
+
+        emit!(CollateralUpdated { mint: mint.key(), haircut_bps, concentration_cap_bps, enabled: true });
+        Ok(())
+    }
+
+    /// Update haircut / concentration cap, or disable new deposits of an asset.
+    pub fn set_collateral_params(
+        ctx: Context<AdminCollateral>,
+        mint: Pubkey,
+        haircut_bps: u16,
+        concentration_cap_bps: u16,
+        enabled: bool,
+    ) -> Result<()> {
+        assert_collateral_bounds(haircut_bps, concentration_cap_bps)?;
+
+        let reg = &mut ctx.accounts.collateral_registry;
+        let idx = collateral_index(reg, mint)?;
+        // Base asset stays at par and always accepts deposits
+        if idx == 0 {
+            require!(haircut_bps == 0 && enabled, ErrorCode::ParamOutOfBounds);
+        }
+        let a = &mut reg.assets[idx];
+        a.haircut_bps = haircut_bps;
+        a.concentration_cap_bps = concentration_cap_bps;
+        a.enabled = enabled;
+
+        emit!(CollateralUpdated { mint, haircut_bps, concentration_cap_bps, enabled });
+        Ok(())
+    }
+
+    /// Replace the pause mask (`PAUSE_*` bits); 0 resumes everything.
+    pub fn set_paused(ctx: Context<AdminOnly>, flags: u8) -> Result<()> {
+        require!(flags & !PAUSE_ALL == 0, ErrorCode::ParamOutOfBounds);
+        let state = &mut ctx.accounts.state;
+        state.pause_flags = flags;
+        emit!(Paused { flags });
+        Ok(())
+    }
+
+    /// Appoint the guardian (`Pubkey::default()` = none).
+    pub fn set_guardian(ctx: Context<AdminOnly>, guardian: Pubkey) -> Result<()> {
+        ctx.accounts.state.guardian = guardian;
+        emit!(GuardianUpdated { guardian });
+        Ok(())
+    }
+
+    /// Pause `flags` on top of whatever is already paused. Open to the guardian as well as
+    /// the admin; only the admin can resume operations (`set_paused`).
+    pub fn guardian_pause(ctx: Context<GuardianPause>, flags: u8) -> Result<()> {
+        require!(flags != 0 && flags & !PAUSE_ALL == 0, ErrorCode::ParamOutOfBounds);
+        let state = &mut ctx.accounts.state;
+        state.pause_flags |= flags;
+        emit!(Paused { flags: state.pause_flags });
+        Ok(())
+    }
+
+    /// Configure the outflow circuit breaker: at most `cap_fp`, and at most `cap_bps` of the
+    /// vault value before the outflows counted, may leave the vaults per sliding `window_secs`
+    /// (0 = cap off). Empties the current window.
+    pub fn set_outflow_limits(ctx: Context<AdminOnly>, window_secs: i64, cap_fp: u128, cap_bps: u16) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.outflow_window_secs = window_secs;
+        state.outflow_cap_fp = cap_fp;
+        state.outflow_cap_bps = cap_bps;
+        state.outflow_window_updated_ts = 0;
+        state.outflow_window_base_fp = 0;
+        state.outflow_window_used_fp = 0;
+        assert_param_bounds(state)
+    }
+
+    /// Set the reward `crank_payouts` pays per claimant (base USDC units, out of the
+    /// treasury's uncollected protocol fees). 0 disables it.
+    pub fn set_crank_reward(ctx: Context<AdminOnly>, reward_u64: u64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.crank_reward_u64 = reward_u64;
+        assert_param_bounds(state)
+    }
+
+    /// Turn rolling mode on or off. In rolling mode epochs run back to back for
+    /// `epoch_duration_secs` each, with sequential ids, and anyone may start the next one
+    /// once the current one has ended (see `start_epoch`).
+    pub fn set_rolling_mode(ctx: Context<AdminOnly>, enabled: bool, epoch_duration_secs: u32) -> Result<()> {
+        require!(!enabled || epoch_duration_secs > 0, ErrorCode::ParamOutOfBounds);
+        let state = &mut ctx.accounts.state;
+        state.rolling_mode = enabled;
+        state.epoch_duration_secs = epoch_duration_secs;
+        Ok(())
+    }
+
+    /// Create the `EpochHistory` registry `start_epoch` and `finalize_epoch` keep. A pool with
+    /// an epoch already running passes it as `current_epoch`, so the next epoch cannot
+    /// overlap it.
+    pub fn init_epoch_history(ctx: Context<InitEpochHistory>) -> Result<()> {
+        let history = &mut ctx.accounts.epoch_history;
+        history.last_epoch_id = 0;
+        history.last_end_ts = ctx.accounts.current_epoch.as_ref().map_or(0, |e| coverage_end_ts(e));
+        history.count = 0;
+        history.records = [EpochRecord::default(); EPOCH_HISTORY_LEN];
+        history.bump = ctx.bumps.epoch_history;
+        history.version = ACCOUNT_VERSION;
+        Ok(())
+    }
+
+    /// Create the `CoverBook`: protection buyers may hold cover (notional) of up to
+    /// `max_utilisation_bps` of tranche capital per epoch, priced by the premium curve.
+    pub fn init_cover_book(ctx: Context<InitCoverBook>, params: CoverParams) -> Result<()> {
+        assert_cover_bounds(&params)?;
+        let book = &mut ctx.accounts.cover_book;
+        apply_cover_params(book, &params);
+        book.epoch_id = 0;
+        book.sold_fp = 0;
+        book.bump = ctx.bumps.cover_book;
+        book.version = ACCOUNT_VERSION;
+        Ok(())
+    }
+
+    /// Update the cover book's limit and premium curve. A limit below what is already
+    /// sold only stops new sales.
+    pub fn set_cover_params(ctx: Context<SetCoverParams>, params: CoverParams) -> Result<()> {
+        assert_cover_bounds(&params)?;
+        apply_cover_params(&mut ctx.accounts.cover_book, &params);
+        Ok(())
+    }
+
+    /// Permanently wind the pool down: no more deposits, premiums, epochs, triggers or
+    /// strategy deployments, and LPs leave through `emergency_withdraw`. An open claim
+    /// window is finalized on the spot, what it has not paid booked as shortfall.
+    /// Strategy capital must be recalled first.
+    pub fn shutdown(ctx: Context<Shutdown>) -> Result<()> {
         let state = &mut ctx.accounts.state;
-        state.paused = paused;
-        emit!(Paused { paused });
+        require!(!state.shutdown, ErrorCode::PoolShutdown);
+        require!(state.strategy_deployed_fp == 0, ErrorCode::StrategyActive);
+        let now = Clock::get()?.unix_timestamp;
+        if state.claim_window_open {
+            let epoch = ctx.accounts.epoch.as_mut().ok_or(ErrorCode::EpochNotActive)?;
+            let history = ctx.accounts.epoch_history.as_mut().ok_or(ErrorCode::EpochNotActive)?;
+            require!(epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
+            require_cover_claims_done(epoch, now)?;
+            book_unpaid_liability(state, epoch)?;
+            settle_epoch(state, epoch, history, now);
+        }
+        state.shutdown = true;
+        state.shutdown_ts = now;
+        emit!(PoolShutdown { shutdown_ts: state.shutdown_ts });
         Ok(())
     }
 
@@ -88,6 +374,34 @@ pub mod tail_risk_insurance_pool {
         Ok(())
     }
 
+    pub fn set_fee_model(
+        ctx: Context<AdminOnly>,
+        fee_model: u8,
+        protocol_fee_bps: u16,
+        referral_fee_bps: u16,
+        performance_fee_bps: u16,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.fee_model = fee_model;
+        state.protocol_fee_bps = protocol_fee_bps;
+        state.referral_fee_bps = referral_fee_bps;
+        state.performance_fee_bps = performance_fee_bps;
+        assert_param_bounds(state)?;
+        Ok(())
+    }
+
+    /// Configure volume-based referral tiers above the base `referral_fee_bps`.
+    pub fn set_referral_tiers(ctx: Context<AdminOnly>, tiers: Vec<ReferralTier>) -> Result<()> {
+        require!(tiers.len() <= MAX_REFERRAL_TIERS, ErrorCode::ParamOutOfBounds);
+        let state = &mut ctx.accounts.state;
+        state.referral_tiers = [ReferralTier::default(); MAX_REFERRAL_TIERS];
+        for (i, t) in tiers.into_iter().enumerate() {
+            state.referral_tiers[i] = t;
+        }
+        assert_param_bounds(state)?;
+        Ok(())
+    }
+
     pub fn set_curve_and_weights(
         ctx: Context<AdminOnly>,
         sev_quad_a_fp: u128,
@@ -108,22 +422,61 @@ pub mod tail_risk_insurance_pool {
         Ok(())
     }
 
+    /// Start epoch `epoch_id` over `[start_ts, end_ts]` (admin; `end_ts` 0 = open-ended).
+    ///
+    /// In rolling mode the window is fixed instead (`start_ts` / `end_ts` must be 0): ids are
+    /// `current_epoch_id + 1`, and the epoch starts where the previous one (`previous_epoch`)
+    /// ended, skipping whole durations nobody rolled, and lasts `epoch_duration_secs`. Once
+    /// the previous epoch has ended anyone may start it; without one (the first epoch, or
+    /// the previous one closed or open-ended) only the admin can, and it starts now.
     pub fn start_epoch(
         ctx: Context<StartEpoch>,
         epoch_id: u64,
         start_ts: i64,
-        end_ts: i64, // may be 0 for "open/rolling" mode
+        end_ts: i64,
     ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(!state.shutdown, ErrorCode::PoolShutdown);
         let now = Clock::get()?.unix_timestamp;
-        require!(start_ts <= now, ErrorCode::EpochNotActive);
-        if end_ts != 0 {
-            require!(end_ts > start_ts, ErrorCode::EpochNotActive);
-        }
+        let is_admin = ctx.accounts.payer.key() == state.admin;
+        // Ids only go up; epochs never overlap (each starts once the previous one's cover ended)
+        let (start_ts, end_ts) = if state.rolling_mode {
+            require!(start_ts == 0 && end_ts == 0, ErrorCode::ParamOutOfBounds);
+            require!(epoch_id == state.current_epoch_id.saturating_add(1), ErrorCode::ParamOutOfBounds);
+            let duration = state.epoch_duration_secs as i64;
+            require!(duration > 0, ErrorCode::ParamOutOfBounds);
+            let start = match ctx.accounts.previous_epoch.as_ref().filter(|e| e.end_ts != 0) {
+                Some(prev) => {
+                    require!(prev.end_ts <= now, ErrorCode::EpochNotActive);
+                    let missed = now.saturating_sub(prev.end_ts) / duration;
+                    prev.end_ts.saturating_add(missed.saturating_mul(duration))
+                }
+                None => {
+                    require!(is_admin, ErrorCode::Unauthorized);
+                    now
+                }
+            };
+            (start, start.saturating_add(duration))
+        } else {
+            require!(is_admin, ErrorCode::Unauthorized);
+            require!(epoch_id > state.current_epoch_id, ErrorCode::ParamOutOfBounds);
+            // 0 = now; otherwise now or later
+            let start_ts = if start_ts == 0 { now } else { start_ts };
+            require!(start_ts >= now, ErrorCode::EpochNotActive);
+            if end_ts != 0 {
+                require!(end_ts > start_ts, ErrorCode::EpochNotActive);
+            }
+            (start_ts, end_ts)
+        };
+        let history = &mut ctx.accounts.epoch_history;
+        require!(start_ts >= history.last_end_ts, ErrorCode::EpochOverlap);
+        history.last_end_ts = if end_ts == 0 { i64::MAX } else { end_ts };
+        ctx.accounts.state.current_epoch_id = epoch_id;
 
         let epoch = &mut ctx.accounts.epoch;
         epoch.epoch_id = epoch_id;
         epoch.start_ts = start_ts;
-        epoch.end_ts = end_ts; // 0 = rolling/open ended
+        epoch.end_ts = end_ts; // 0 = open-ended
         epoch.total_stake_snapshot_fp = 0;
         epoch.total_payout_fp = 0;
         epoch.severity_bps = 0;
@@ -134,333 +487,967 @@ pub mod tail_risk_insurance_pool {
         epoch.closed = false;
         epoch.evidence_hash = [0u8; 32];
         epoch.evidence_ts = 0;
+        epoch.finalized_ts = 0;
+        epoch.open_receipts = 0;
         epoch.bump = ctx.bumps.epoch;
+        epoch.version = ACCOUNT_VERSION;
 
         emit!(EpochStarted { epoch_id, start_ts, end_ts });
         Ok(())
     }
 
+    /// End an epoch that was never triggered (admin): an open-ended one once it has started,
+    /// a fixed one once its window has passed. It covers nothing more, so the next epoch may
+    /// start, and `close_epoch` can reclaim its rent.
+    pub fn end_epoch(ctx: Context<EndEpoch>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let epoch = &mut ctx.accounts.epoch;
+        require!(!epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
+        let earliest_ts = if epoch.end_ts == 0 { epoch.start_ts } else { epoch.end_ts };
+        require!(now >= earliest_ts, ErrorCode::EpochNotActive);
+        epoch.closed = true;
+        epoch.finalized_ts = now;
+        if epoch.epoch_id == ctx.accounts.state.current_epoch_id {
+            ctx.accounts.epoch_history.last_end_ts = coverage_end_ts(epoch);
+        }
+
+        emit!(EpochEnded { epoch_id: epoch.epoch_id, end_ts: now });
+        Ok(())
+    }
+
     // ----------------------------- user flow -----------------------------
 
-    /// Deposit into a chosen tranche (0 = senior, 1 = junior)
+    /// Deposit into a chosen tranche (0 = senior, 1 = junior).
+    /// `amount` is in native units of `asset_mint`, which must be a registered collateral.
     pub fn deposit_insurance(
         ctx: Context<DepositInsurance>,
-        amount_usdc: u64,
+        amount: u64,
         tranche: u8,
         referrer_opt: Option<Pubkey>,
     ) -> Result<()> {
-        // Snapshot read-only to avoid &mut during CPI
-        let (paused, min_deposit_fp, user_cap_fp, proto_bps, ref_bps, min_cd_secs) = {
-            let s = &ctx.accounts.state;
-            (s.paused, s.min_deposit_fp, s.user_deposit_cap_fp, s.protocol_fee_bps, s.referral_fee_bps, s.min_seconds_between_deposits)
-        };
-        require!(!paused, ErrorCode::Paused);
-
-        // Amount checks
-        let amount_fp = to_fp_u64(amount_usdc)?;
-        require!(amount_fp >= min_deposit_fp, ErrorCode::MinDeposit);
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            // Snapshot read-only to avoid &mut during CPI
+            let (min_deposit_fp, user_cap_fp, min_cd_secs) = {
+                let s = &ctx.accounts.state;
+                (s.min_deposit_fp, s.user_deposit_cap_fp, s.min_seconds_between_deposits)
+            };
+            // Fee rates that apply at deposit time under the configured fee model
+            let (proto_bps, _) = deposit_fee_bps(&ctx.accounts.state)?;
+            let referral_tiers = ctx.accounts.state.referral_tiers;
+            require_not_frozen(&ctx.accounts.state, PAUSE_DEPOSITS)?;
+            require!(!ctx.accounts.state.shutdown, ErrorCode::PoolShutdown);
+
+            // Collateral lookup (must be registered and accepting deposits)
+            let (asset_idx, decimals, haircut_bps) = {
+                let reg = &ctx.accounts.collateral_registry;
+                let idx = collateral_index(reg, ctx.accounts.asset_mint.key())?;
+                let a = &reg.assets[idx];
+                require!(a.enabled, ErrorCode::UnsupportedCollateral);
+                (idx, a.decimals, a.haircut_bps)
+            };
 
-        let position = &mut ctx.accounts.position;
+            // Amount checks (par value in common units)
+            let amount_fp = asset_to_fp(amount, decimals)?;
+            require!(amount_fp >= min_deposit_fp, ErrorCode::MinDeposit);
 
-        // Rate limit deposits
-        let now = Clock::get()?.unix_timestamp;
-        if position.last_deposit_ts != 0 && min_cd_secs > 0 {
-            require!(
-                now.saturating_sub(position.last_deposit_ts) >= min_cd_secs,
-                ErrorCode::DepositCooldown
-            );
-        }
+            let position = &mut ctx.accounts.position;
 
-        // Tranche routing
-        require!(tranche <= 1, ErrorCode::Unauthorized);
+            // Rate limit deposits
+            let now = Clock::get()?.unix_timestamp;
+            if position.last_deposit_ts != 0 && min_cd_secs > 0 {
+                require!(
+                    now.saturating_sub(position.last_deposit_ts) >= min_cd_secs,
+                    ErrorCode::DepositCooldown
+                );
+            }
 
-        // Run transfer (user -> vault)
-        transfer_tokens_user(
-            &ctx.accounts.user_ata,
-            &ctx.accounts.vault_ata,
-            &ctx.accounts.user,
-            &ctx.accounts.token_program,
-            amount_usdc,
-        )?;
+            // Tranche routing
+            require!(tranche <= 1, ErrorCode::Unauthorized);
 
-        // Fees
-        let proto_fee_fp = mul_div_floor_u128(amount_fp, proto_bps as u128, BPS_DENOM)?;
-        let ref_fee_fp   = mul_div_floor_u128(amount_fp, ref_bps as u128, BPS_DENOM)?;
-        let proto_fee_u64 = from_fp_to_u64(proto_fee_fp)?;
-        let ref_fee_u64   = from_fp_to_u64(ref_fee_fp)?;
+            // Referral attribution is first-touch: an existing referrer is never overwritten.
+            // It lives in the attribution account, which outlives a closed position.
+            let attribution = &mut ctx.accounts.attribution;
+            if attribution.referrer == Pubkey::default() {
+                // Positions referred before attributions existed carry theirs over
+                attribution.referrer = position.referrer;
+            }
+            if attribution.referrer == Pubkey::default() {
+                if let Some(refer) = referrer_opt {
+                    if refer != Pubkey::default() && refer != ctx.accounts.user.key() {
+                        attribution.referrer = refer;
+                    }
+                }
+            }
+            attribution.owner = ctx.accounts.user.key();
+            attribution.bump = ctx.bumps.attribution;
+            attribution.version = ACCOUNT_VERSION;
+            position.referrer = attribution.referrer;
+            let referrer_key = position.referrer;
+
+            // Referred deposits must carry the referrer's account (volume + reward accrual)
+            let referrer = if referrer_key != Pubkey::default() {
+                let r = ctx.accounts.referrer_account.as_deref().ok_or(ErrorCode::Unauthorized)?;
+                require_keys_eq!(r.owner, referrer_key, ErrorCode::Unauthorized);
+                Some(r)
+            } else {
+                None
+            };
+            let ref_bps = deposit_referral_bps(&ctx.accounts.state, referrer)?;
 
-        // Protocol fee transfer (vault -> protocol_ata) via PDA signer
-        if proto_fee_u64 > 0 {
-            transfer_tokens_pda(
+            // Run transfer (user -> vault)
+            transfer_tokens_user(
+                &ctx.accounts.user_ata,
                 &ctx.accounts.vault_ata,
-                &ctx.accounts.protocol_treasury_ata,
-                &ctx.accounts.state,
+                &ctx.accounts.user,
                 &ctx.accounts.token_program,
-                proto_fee_u64,
+                amount,
             )?;
-            emit!(ProtocolFeeTaken { amount_u64: proto_fee_u64 });
-        }
 
-        // Referral fee transfer (optional)
-        if let Some(refer) = referrer_opt {
-            if refer != Pubkey::default() && ref_fee_u64 > 0 {
-                let ref_ata = ctx.accounts.referrer_ata.as_ref().ok_or(ErrorCode::Unauthorized)?;
-                require_keys_eq!(refer, ref_ata.owner, ErrorCode::Unauthorized);
+            // Fees (charged in the deposited asset)
+            let split = split_fees(amount_fp, proto_bps, ref_bps)?;
+            let proto_fee_fp = split.protocol_fp;
+            let ref_fee_fp = split.referral_fp;
+
+            // No referrer: the referral fee is credited to existing LPs as tranche income
+            // (before this deposit joins); it is not charged at all if there are none yet.
+            if referrer_key == Pubkey::default() && ref_fee_fp > 0 {
+                distribute_yield(&mut ctx.accounts.state, ref_fee_fp)?;
+            }
+            let yield_idx = {
+                let s = &ctx.accounts.state;
+                (s.senior_yield_index_fp, s.junior_yield_index_fp)
+            };
+            let proto_fee_u64 = fp_to_asset(proto_fee_fp, decimals)?;
+            let mut fees_out_u64 = 0u64;
+
+            // Protocol fee transfer (vault -> protocol_ata) via PDA signer
+            if proto_fee_u64 > 0 {
                 transfer_tokens_pda(
                     &ctx.accounts.vault_ata,
-                    ref_ata,
+                    &ctx.accounts.protocol_treasury_ata,
                     &ctx.accounts.state,
                     &ctx.accounts.token_program,
-                    ref_fee_u64,
+                    proto_fee_u64,
                 )?;
-                emit!(ReferralFeeTaken { amount_u64: ref_fee_u64, referrer: refer });
-                position.referrer = refer;
+                fees_out_u64 = fees_out_u64.saturating_add(proto_fee_u64);
+                emit!(ProtocolFeeTaken { amount_u64: proto_fee_u64 });
             }
-        }
-
-        // Net credit
-        let net_fp = amount_fp.saturating_sub(proto_fee_fp.saturating_add(ref_fee_fp));
-        position.owner = ctx.accounts.user.key();
 
-        // Update lots (FIFO) for chosen tranche
-        if tranche == 0 {
-            push_lot(&mut position.senior_lots, Lot { amount_fp: net_fp, ts: now })?;
-            position.senior_deposited_fp = position.senior_deposited_fp.saturating_add(net_fp);
-            position.senior_withdrawable_fp = position.senior_withdrawable_fp.saturating_add(net_fp);
-        } else {
-            push_lot(&mut position.junior_lots, Lot { amount_fp: net_fp, ts: now })?;
-            position.junior_deposited_fp = position.junior_deposited_fp.saturating_add(net_fp);
-            position.junior_withdrawable_fp = position.junior_withdrawable_fp.saturating_add(net_fp);
-        }
+            // Referral fee stays in the vault and accrues to the referrer (claimed later)
+            if referrer_key != Pubkey::default() {
+                let r = ctx.accounts.referrer_account.as_mut().ok_or(ErrorCode::Unauthorized)?;
+                r.total_referred_fp = r.total_referred_fp.saturating_add(amount_fp);
+                credit_referral(r, ref_fee_fp, &referral_tiers);
+            }
 
-        position.last_deposit_ts = now;
+            // Net credit: par value after fees, then the asset haircut
+            let net_par_fp = amount_fp.saturating_sub(proto_fee_fp.saturating_add(ref_fee_fp));
+            let net_fp = apply_haircut(net_par_fp, haircut_bps)?;
+            position.owner = ctx.accounts.user.key();
+            position.bump = ctx.bumps.position;
+            position.version = ACCOUNT_VERSION;
+
+            // Accrue strategy yield on the old balances before they change
+            settle_yield(position, yield_idx)?;
+
+            // Update lots (FIFO) for chosen tranche; the lot only becomes
+            // withdrawable once it matures (see `withdraw`)
+            if tranche == 0 {
+                push_lot(&mut position.senior_lots, Lot { amount_fp: net_fp, ts: now })?;
+                position.senior_deposited_fp = position.senior_deposited_fp.saturating_add(net_fp);
+            } else {
+                push_lot(&mut position.junior_lots, Lot { amount_fp: net_fp, ts: now })?;
+                position.junior_deposited_fp = position.junior_deposited_fp.saturating_add(net_fp);
+            }
 
-        // Cap per-user (sum across tranches)
-        let user_total = position.senior_deposited_fp.saturating_add(position.junior_deposited_fp);
-        require!(user_total <= user_cap_fp, ErrorCode::UserCapExceeded);
+            position.last_deposit_ts = now;
+            sync_yield_debt(position, yield_idx)?;
+
+            // Rolling mode: principal deposited during an epoch is covered from the next one
+            let (rolling, current_epoch_id) = (ctx.accounts.state.rolling_mode, ctx.accounts.state.current_epoch_id);
+            roll_pending(position, current_epoch_id);
+            if rolling && current_epoch_id != 0 {
+                let epoch = ctx.accounts.current_epoch.as_mut().ok_or(ErrorCode::CurrentEpochRequired)?;
+                if now < epoch.end_ts {
+                    epoch.pending_deposits_fp = epoch.pending_deposits_fp.saturating_add(net_fp);
+                    if tranche == 0 {
+                        position.pending_senior_fp = position.pending_senior_fp.saturating_add(net_fp);
+                    } else {
+                        position.pending_junior_fp = position.pending_junior_fp.saturating_add(net_fp);
+                    }
+                }
+            }
 
-        // Update pool accounting after CPIs
-        let state = &mut ctx.accounts.state;
-        state.total_deposited_fp = state.total_deposited_fp.saturating_add(net_fp);
+            // Cap per-user (sum across tranches)
+            let user_total = position.senior_deposited_fp.saturating_add(position.junior_deposited_fp);
+            require!(user_total <= user_cap_fp, ErrorCode::UserCapExceeded);
+
+            // Track vault holdings (par) and enforce the per-asset concentration cap
+            let reg = &mut ctx.accounts.collateral_registry;
+            let kept_fp = asset_to_fp(amount.saturating_sub(fees_out_u64), decimals)?;
+            reg.assets[asset_idx].balance_fp = reg.assets[asset_idx].balance_fp.saturating_add(kept_fp);
+            assert_concentration(reg, asset_idx)?;
+
+            // Update pool accounting after CPIs
+            let state = &mut ctx.accounts.state;
+            state.total_deposited_fp = state.total_deposited_fp.saturating_add(net_fp);
+            if referrer_key != Pubkey::default() {
+                state.referral_rewards_owed_fp = state.referral_rewards_owed_fp.saturating_add(ref_fee_fp);
+            }
+            if tranche == 0 {
+                state.senior_total_fp = state.senior_total_fp.saturating_add(net_fp);
+            } else {
+                state.junior_total_fp = state.junior_total_fp.saturating_add(net_fp);
+            }
 
-        emit!(Deposited { owner: position.owner, amount_fp: net_fp, referrer: position.referrer, tranche });
-        Ok(())
+            emit!(Deposited {
+                owner: position.owner,
+                amount_fp: net_fp,
+                referrer: position.referrer,
+                tranche,
+                mint: ctx.accounts.asset_mint.key(),
+            });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
     }
 
-    /// Withdraw from a selected tranche (0 senior, 1 junior)
-    pub fn withdraw(ctx: Context<Withdraw>, amount_usdc: u64, tranche: u8) -> Result<()> {
-        let state_chk = &ctx.accounts.state;
-        require!(!state_chk.paused, ErrorCode::Paused);
-        require!(tranche <= 1, ErrorCode::Unauthorized);
-
-        let amount_fp = to_fp_u64(amount_usdc)?;
-        let position = &mut ctx.accounts.position;
+    /// Withdraw from a selected tranche (0 senior, 1 junior).
+    /// Paid at par in `asset_mint`, subject to that vault's available balance.
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, tranche: u8) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            let state_chk = &ctx.accounts.state;
+            require_not_frozen(state_chk, PAUSE_WITHDRAWALS)?;
+            // At par would let early exits take the shortfall out of later ones
+            require!(!state_chk.shutdown, ErrorCode::PoolShutdown);
+            require!(tranche <= 1, ErrorCode::Unauthorized);
+
+            let (asset_idx, decimals) = {
+                let reg = &ctx.accounts.collateral_registry;
+                let idx = collateral_index(reg, ctx.accounts.asset_mint.key())?;
+                (idx, reg.assets[idx].decimals)
+            };
+            let amount_fp = asset_to_fp(amount, decimals)?;
+            require!(
+                ctx.accounts.collateral_registry.assets[asset_idx].balance_fp >= amount_fp,
+                ErrorCode::InsufficientPoolBalance
+            );
+            let yield_idx = (state_chk.senior_yield_index_fp, state_chk.junior_yield_index_fp);
+            let lockup = state_chk.lockup_secs;
+            let position = &mut ctx.accounts.position;
+            settle_yield(position, yield_idx)?;
+
+            // Mature lots per lockup_secs (FIFO); the rest may only be waiting on lockup
+            let now = Clock::get()?.unix_timestamp;
+            let (lots_ref, withdrawable_ref) =
+                if tranche == 0 { senior_parts(position) } else { junior_parts(position) };
+            let mut ring = lot_ring(lots_ref);
+            ring.mature_and_consume(lockup, now, withdrawable_ref, &mut 0);
+            let (withdrawable_fp, locked_fp) = (*withdrawable_ref, ring.locked_fp());
+            if withdrawable_fp < amount_fp {
+                require!(amount_fp - withdrawable_fp > locked_fp, ErrorCode::LockupNotExpired);
+                return err!(ErrorCode::InsufficientPoolBalance);
+            }
 
-        // Consume matured lots per lockup_secs (FIFO)
-        let now = Clock::get()?.unix_timestamp;
-        let lockup = state_chk.lockup_secs;
+            // Only a withdrawal the position covers counts against the breaker
+            let liquid_fp = pool_value_fp(&ctx.accounts.collateral_registry)?;
+            if !admit_outflow(&mut ctx.accounts.state, liquid_fp, amount_fp)? {
+                return Ok(());
+            }
+            let position = &mut ctx.accounts.position;
+            if tranche == 0 {
+                position.senior_withdrawable_fp = withdrawable_fp - amount_fp;
+            } else {
+                position.junior_withdrawable_fp = withdrawable_fp - amount_fp;
+            }
 
-        let mut remaining = amount_fp;
+            // Bookkeeping: reduce deposited_fp and pool total
+            if tranche == 0 {
+                position.senior_deposited_fp = position.senior_deposited_fp.saturating_sub(amount_fp);
+            } else {
+                position.junior_deposited_fp = position.junior_deposited_fp.saturating_sub(amount_fp);
+            }
+            sync_yield_debt(position, yield_idx)?;
+
+            // Lots leave oldest first, so principal still awaiting cover is withdrawn last
+            roll_pending(position, ctx.accounts.state.current_epoch_id);
+            let deposited = if tranche == 0 { position.senior_deposited_fp } else { position.junior_deposited_fp };
+            let pending = if tranche == 0 { &mut position.pending_senior_fp } else { &mut position.pending_junior_fp };
+            let cut_fp = pending.saturating_sub(deposited);
+            if cut_fp > 0 {
+                *pending = deposited;
+                let epoch = ctx.accounts.current_epoch.as_mut().ok_or(ErrorCode::CurrentEpochRequired)?;
+                epoch.pending_deposits_fp = epoch.pending_deposits_fp.saturating_sub(cut_fp);
+            }
+            let state = &mut ctx.accounts.state;
+            state.total_deposited_fp = state.total_deposited_fp.saturating_sub(amount_fp);
+            if tranche == 0 {
+                state.senior_total_fp = state.senior_total_fp.saturating_sub(amount_fp);
+            } else {
+                state.junior_total_fp = state.junior_total_fp.saturating_sub(amount_fp);
+            }
 
-        if tranche == 0 {
-            // senior — split borrows via helper to satisfy borrow checker
-            let (lots_ref, withdrawable_ref) = senior_parts(position);
-            mature_and_consume(lots_ref, lockup, now, withdrawable_ref, &mut remaining)?;
-        } else {
-            // junior — split borrows via helper to satisfy borrow checker
-            let (lots_ref, withdrawable_ref) = junior_parts(position);
-            mature_and_consume(lots_ref, lockup, now, withdrawable_ref, &mut remaining)?;
-        }
-        // Must have fully satisfied desired amount
-        require!(remaining == 0, ErrorCode::InsufficientPoolBalance);
+            let reg = &mut ctx.accounts.collateral_registry;
+            reg.assets[asset_idx].balance_fp = reg.assets[asset_idx].balance_fp.saturating_sub(amount_fp);
+            assert_outflow_concentration(reg, asset_idx)?;
 
-        // Bookkeeping: reduce deposited_fp and pool total
-        if tranche == 0 {
-            position.senior_deposited_fp = position.senior_deposited_fp.saturating_sub(amount_fp);
-        } else {
-            position.junior_deposited_fp = position.junior_deposited_fp.saturating_sub(amount_fp);
-        }
-        let state = &mut ctx.accounts.state;
-        state.total_deposited_fp = state.total_deposited_fp.saturating_sub(amount_fp);
-
-        // Transfer vault -> user
-        transfer_tokens_pda(
-            &ctx.accounts.vault_ata,
-            &ctx.accounts.user_ata,
-            &ctx.accounts.state,
-            &ctx.accounts.token_program,
-            amount_usdc,
-        )?;
+            // Transfer vault -> user
+            transfer_tokens_pda(
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.user_ata,
+                &ctx.accounts.state,
+                &ctx.accounts.token_program,
+                amount,
+            )?;
 
-        emit!(Withdrawn { owner: position.owner, amount_fp, tranche });
-        Ok(())
+            emit!(Withdrawn { owner: position.owner, amount_fp, tranche, mint: ctx.accounts.asset_mint.key() });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
     }
 
-    // ----------------------------- event / payout -----------------------------
+    /// After `shutdown`: withdraw the caller's whole position, lockup or not, as their share
+    /// of what the vaults hold beyond fees, referral rewards and unclaimed yield. Senior
+    /// principal is repaid first and junior takes the remainder, pro-rata within each tranche.
+    /// Paid at par in `asset_mint`, subject to that vault's balance. Accrued yield stays
+    /// claimable through `claim_yield`.
+    pub fn emergency_withdraw(ctx: Context<Withdraw>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            let state = &ctx.accounts.state;
+            require!(state.shutdown, ErrorCode::NotShutdown);
+            // No claim window outlives shutdown, so only the admin / guardian pause applies
+            require_not_paused(state, PAUSE_WITHDRAWALS)?;
 
-    pub fn trigger_event(
-        ctx: Context<TriggerEvent>,
-        severity_input_bps: u16,               // input to curve
-        user_cap_bps: Option<u16>,
-        epoch_cap_fp_override: Option<u128>,
-        evidence_hash: Option<[u8; 32]>,
-        evidence_ts_opt: Option<i64>,          // if oracle data has timestamp
-    ) -> Result<()> {
-        // Oracle allowlist: either admin OR an allowed oracle
-        {
-            let signer = ctx.accounts.admin_or_oracle.key();
-            let list = &ctx.accounts.oracle_list;
-            require!(list.enabled, ErrorCode::Unauthorized);
-            // If admin is also allowed implicitly:
-            if signer != ctx.accounts.state.admin {
-                require!(oracle_is_allowed(list, signer), ErrorCode::Unauthorized);
+            let yield_idx = (state.senior_yield_index_fp, state.junior_yield_index_fp);
+            let position = &mut ctx.accounts.position;
+            settle_yield(position, yield_idx)?;
+            let (senior_fp, junior_fp) = (position.senior_deposited_fp, position.junior_deposited_fp);
+            require!(senior_fp.saturating_add(junior_fp) > 0, ErrorCode::NothingToPayout);
+
+            let reg = &mut ctx.accounts.collateral_registry;
+            let other_claims_fp = tracked_liabilities_fp(state).saturating_sub(state.total_deposited_fp);
+            let available_fp = total_pool_value_fp(state, reg)?.saturating_sub(other_claims_fp);
+            let share_fp = payout::emergency_share_fp(
+                senior_fp,
+                junior_fp,
+                state.senior_total_fp,
+                state.junior_total_fp,
+                available_fp,
+            )
+            .ok_or_else(math_overflow)?;
+
+            let asset_idx = collateral_index(reg, ctx.accounts.asset_mint.key())?;
+            require!(reg.assets[asset_idx].balance_fp >= share_fp, ErrorCode::InsufficientPoolBalance);
+            if !admit_outflow(&mut ctx.accounts.state, pool_value_fp(reg)?, share_fp)? {
+                return Ok(());
+            }
+            let asset = &mut reg.assets[asset_idx];
+            let pay_u64 = fp_to_asset(share_fp, asset.decimals)?;
+            let paid_fp = asset_to_fp(pay_u64, asset.decimals)?;
+            asset.balance_fp = asset.balance_fp.saturating_sub(paid_fp);
+            assert_outflow_concentration(reg, asset_idx)?;
+
+            // The whole position leaves; principal not covered by the share is written off
+            position.senior_deposited_fp = 0;
+            position.junior_deposited_fp = 0;
+            position.senior_withdrawable_fp = 0;
+            position.junior_withdrawable_fp = 0;
+            position.senior_lots = Lots::default();
+            position.junior_lots = Lots::default();
+            position.pending_senior_fp = 0;
+            position.pending_junior_fp = 0;
+            sync_yield_debt(position, yield_idx)?;
+
+            let state = &mut ctx.accounts.state;
+            state.total_deposited_fp = state.total_deposited_fp.saturating_sub(senior_fp.saturating_add(junior_fp));
+            state.senior_total_fp = state.senior_total_fp.saturating_sub(senior_fp);
+            state.junior_total_fp = state.junior_total_fp.saturating_sub(junior_fp);
+
+            if pay_u64 > 0 {
+                transfer_tokens_pda(
+                    &ctx.accounts.vault_ata,
+                    &ctx.accounts.user_ata,
+                    &ctx.accounts.state,
+                    &ctx.accounts.token_program,
+                    pay_u64,
+                )?;
             }
-        }
 
-        let state = &mut ctx.accounts.state;
-        let epoch = &mut ctx.accounts.epoch;
-        let now = Clock::get()?.unix_timestamp;
+            emit!(EmergencyWithdrawn {
+                owner: position.owner,
+                senior_fp,
+                junior_fp,
+                amount_fp: paid_fp,
+                mint: ctx.accounts.asset_mint.key(),
+            });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
 
-        require!(!epoch.triggered, ErrorCode::EpochAlreadyTriggered);
-        // Rolling mode allows end_ts == 0
-        if epoch.end_ts != 0 {
-            require!(now >= epoch.start_ts && now <= epoch.end_ts, ErrorCode::EpochNotActive);
-        } else {
-            require!(now >= epoch.start_ts, ErrorCode::EpochNotActive);
-        }
+    // ----------------------------- referrals -----------------------------
+
+    /// Create the caller's referrer account (needed before anyone can be referred by them).
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let r = &mut ctx.accounts.referrer_account;
+        r.owner = ctx.accounts.referrer.key();
+        r.total_referred_fp = 0;
+        r.lifetime_rewards_fp = 0;
+        r.pending_rewards_fp = 0;
+        r.tier = 0;
+        r.bump = ctx.bumps.referrer_account;
+        r.version = ACCOUNT_VERSION;
+        Ok(())
+    }
 
-        // Optional staleness check
-        if let Some(e_ts) = evidence_ts_opt {
-            if state.max_stale_secs > 0 {
-                require!(now.saturating_sub(e_ts) <= state.max_stale_secs, ErrorCode::EpochNotActive);
+    /// Withdraw accrued referral rewards, paid at par in `asset_mint` subject to availability.
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            require_not_paused(&ctx.accounts.state, PAUSE_CLAIMS)?;
+            let reg = &mut ctx.accounts.collateral_registry;
+            let asset_idx = collateral_index(reg, ctx.accounts.asset_mint.key())?;
+            let decimals = reg.assets[asset_idx].decimals;
+
+            let r = &mut ctx.accounts.referrer_account;
+            let pay_u64 = fp_to_asset(r.pending_rewards_fp, decimals)?;
+            require!(pay_u64 > 0, ErrorCode::NothingToPayout);
+            let pay_fp = asset_to_fp(pay_u64, decimals)?;
+            require!(reg.assets[asset_idx].balance_fp >= pay_fp, ErrorCode::InsufficientPoolBalance);
+            if !admit_outflow(&mut ctx.accounts.state, pool_value_fp(reg)?, pay_fp)? {
+                return Ok(());
             }
-            epoch.evidence_ts = e_ts;
-        }
-
-        // Snapshot pool
-        epoch.total_stake_snapshot_fp = state.total_deposited_fp;
 
-        // Effective severity via curve + floor
-        let sev_eff_bps = effective_severity_bps(
-            severity_input_bps as u128,
-            state.sev_quad_a_fp,
-            state.sev_quad_b_fp,
-            state.sev_quad_c_fp,
-            state.severity_floor_bps,
-        )?;
-        epoch.severity_bps = sev_eff_bps as u16;
-        epoch.user_cap_bps = user_cap_bps.unwrap_or(0);
+            let asset = &mut reg.assets[asset_idx];
+            asset.balance_fp = asset.balance_fp.saturating_sub(pay_fp);
+            assert_outflow_concentration(reg, asset_idx)?;
+            r.pending_rewards_fp = r.pending_rewards_fp.saturating_sub(pay_fp);
+            let state = &mut ctx.accounts.state;
+            state.referral_rewards_owed_fp = state.referral_rewards_owed_fp.saturating_sub(pay_fp);
 
-        // Policy-cap on epoch liability
-        if state.payout_policy == PayoutPolicy::EpochBounded as u8 {
-            epoch.epoch_cap_fp = epoch_cap_fp_override.unwrap_or(state.epoch_cap_fp);
-        } else {
-            epoch.epoch_cap_fp = 0;
-        }
+            transfer_tokens_pda(
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.referrer_ata,
+                &ctx.accounts.state,
+                &ctx.accounts.token_program,
+                pay_u64,
+            )?;
 
-        epoch.triggered = true;
-        state.last_event_ts = now;
+            emit!(ReferralRewardsClaimed {
+                referrer: r.owner,
+                amount_fp: pay_fp,
+                mint: ctx.accounts.asset_mint.key(),
+            });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
 
-        // Freeze pool during claims
-        state.paused = true;
+    // ----------------------------- strategy / yield -----------------------------
 
-        if let Some(h) = evidence_hash {
-            epoch.evidence_hash = h;
+    /// Configure the external lending program idle USDC may be deployed into.
+    pub fn set_strategy(
+        ctx: Context<AdminOnly>,
+        strategy_program: Pubkey,
+        strategy_max_bps: u16,
+        strategy_junior_yield_bps: u16,
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        // Switching programs would strand deployed principal
+        if strategy_program != state.strategy_program {
+            require!(state.strategy_deployed_fp == 0, ErrorCode::StrategyActive);
         }
-
-        emit!(EventTriggered {
-            epoch_id: epoch.epoch_id,
-            severity_bps: epoch.severity_bps,
-            user_cap_bps: epoch.user_cap_bps,
-            policy: state.payout_policy,
-            evidence_hash: epoch.evidence_hash,
-        });
-
+        state.strategy_program = strategy_program;
+        state.strategy_max_bps = strategy_max_bps;
+        state.strategy_junior_yield_bps = strategy_junior_yield_bps;
+        assert_param_bounds(state)?;
         Ok(())
     }
 
-    /// Per-user payout with claim receipt (prevents double claims)
-    pub fn payout_user(ctx: Context<PayoutUser>) -> Result<()> {
-        // Reentrancy-style guard
-        {
-            let s = &ctx.accounts.state;
-            require!(!s.processing, ErrorCode::Busy);
-        }
-        {
-            let s = &mut ctx.accounts.state;
-            s.processing = true;
-        }
-
+    /// Move idle base-asset USDC from the vault into the strategy (bounded by `strategy_max_bps`).
+    /// Lending-program accounts are forwarded from `remaining_accounts`.
+    pub fn strategy_deploy<'info>(
+        ctx: Context<'_, '_, 'info, 'info, StrategyOp<'info>>,
+        amount_usdc: u64,
+    ) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
         let res = (|| -> Result<()> {
             let state = &ctx.accounts.state;
-            let epoch = &mut ctx.accounts.epoch;
-            require!(epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
-
-            // Compute max epoch liability
-            let pool_balance_fp = vault_balance_fp(&ctx.accounts.vault_ata)?;
-            let policy = state.payout_policy;
-
-            let base_liability_fp = {
-                let sev_fp = epoch.severity_bps as u128;
-                mul_div_floor_u128(epoch.total_stake_snapshot_fp, sev_fp, BPS_DENOM)?
+            require_not_frozen(state, PAUSE_WITHDRAWALS)?;
+            require!(!state.shutdown, ErrorCode::PoolShutdown);
+            require!(state.strategy_program != Pubkey::default(), ErrorCode::StrategyNotConfigured);
+
+            let amount_fp = to_fp_u64(amount_usdc)?;
+            let liquid_fp = ctx.accounts.collateral_registry.assets[0].balance_fp;
+            require!(liquid_fp >= amount_fp, ErrorCode::InsufficientPoolBalance);
+
+            // Deployed share of (liquid + deployed) base capital must stay under the cap
+            let deployed_after = state.strategy_deployed_fp.saturating_add(amount_fp);
+            let base_total = liquid_fp.saturating_add(state.strategy_deployed_fp);
+            let max_deploy = mul_div_floor_u128(base_total, state.strategy_max_bps as u128, BPS_DENOM)?;
+            require!(deployed_after <= max_deploy, ErrorCode::StrategyCapExceeded);
+
+            // The strategy only ever sees the escrow: fund it from the vault, then let it pull
+            let escrow_before = ctx.accounts.strategy_escrow.amount;
+            transfer_tokens_pda(
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.strategy_escrow,
+                &ctx.accounts.state,
+                &ctx.accounts.token_program,
+                amount_usdc,
+            )?;
+            let bump = ctx.bumps.strategy_authority;
+            let strategy = StrategyCpi {
+                program: ctx.accounts.strategy_program.as_ref(),
+                authority: ctx.accounts.strategy_authority.as_ref(),
+                bump,
+                escrow: &mut ctx.accounts.strategy_escrow,
             };
+            invoke_strategy(&strategy, &ctx.accounts.token_program, ctx.remaining_accounts, STRATEGY_DEPOSIT_IX, amount_usdc)?;
 
-            // If underfunded, record shortfall (carryover)
-            if base_liability_fp > pool_balance_fp {
-                epoch.shortfall_fp = base_liability_fp.saturating_sub(pool_balance_fp);
+            // Whatever the strategy left in the escrow goes straight back to the vault
+            strategy.escrow.reload()?;
+            let leftover_u64 = strategy.escrow.amount.saturating_sub(escrow_before);
+            if leftover_u64 > 0 {
+                transfer_tokens_strategy(&strategy, &ctx.accounts.vault_ata, &ctx.accounts.token_program, leftover_u64)?;
             }
+            let taken_u64 = amount_usdc.saturating_sub(leftover_u64);
+            let taken_fp = to_fp_u64(taken_u64)?;
 
-            // Policy cap
-            let mut liability_cap_fp = base_liability_fp;
-            if policy == PayoutPolicy::EpochBounded as u8 {
-                liability_cap_fp = core::cmp::min(liability_cap_fp, epoch.epoch_cap_fp);
-            }
+            let reg = &mut ctx.accounts.collateral_registry;
+            reg.assets[0].balance_fp = reg.assets[0].balance_fp.saturating_sub(taken_fp);
+            assert_outflow_concentration(reg, 0)?;
+            let state = &mut ctx.accounts.state;
+            state.strategy_deployed_fp = state.strategy_deployed_fp.saturating_add(taken_fp);
 
-            // Never exceed current pool USDC balance
-            let max_liability_fp = core::cmp::min(liability_cap_fp, pool_balance_fp);
-            require!(max_liability_fp > 0, ErrorCode::NothingToPayout);
+            emit!(StrategyDeployed { amount_u64: taken_u64, deployed_fp: state.strategy_deployed_fp });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
 
-            // User position (uses tranche-weighted stake)
+    /// Pull capital back from the strategy. `None` recalls everything and realises yield.
+    pub fn strategy_recall<'info>(
+        ctx: Context<'_, '_, 'info, 'info, StrategyOp<'info>>,
+        amount_usdc: Option<u64>,
+    ) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            require!(
+                ctx.accounts.state.strategy_program != Pubkey::default(),
+                ErrorCode::StrategyNotConfigured
+            );
+            let bump = ctx.bumps.strategy_authority;
+            let mut strategy = StrategyCpi {
+                program: ctx.accounts.strategy_program.as_ref(),
+                authority: ctx.accounts.strategy_authority.as_ref(),
+                bump,
+                escrow: &mut ctx.accounts.strategy_escrow,
+            };
+            recall_strategy(
+                &mut strategy,
+                &mut ctx.accounts.state,
+                &mut ctx.accounts.collateral_registry,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.token_program,
+                ctx.remaining_accounts,
+                amount_usdc,
+            )
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    /// Claim realised strategy yield credited to the caller's tranches (paid in base USDC).
+    pub fn claim_yield(ctx: Context<ClaimYield>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            let yield_idx = {
+                let s = &ctx.accounts.state;
+                require_not_paused(s, PAUSE_CLAIMS)?;
+                (s.senior_yield_index_fp, s.junior_yield_index_fp)
+            };
             let position = &mut ctx.accounts.position;
-            require_keys_eq!(position.owner, ctx.accounts.user.key(), ErrorCode::Unauthorized);
+            settle_yield(position, yield_idx)?;
 
-            // Weighted stake at snapshot approximated by current (pool is paused)
-            let effective_user_stake_fp = weighted_stake_fp(
-                position.senior_deposited_fp,
-                position.junior_deposited_fp,
-                state.tranche_weight_senior_bps as u128,
-                state.tranche_weight_junior_bps as u128,
-            )?;
+            // Only whole base units leave the vault; the remainder stays accrued
+            let pay_u64 = from_fp_to_u64(position.yield_accrued_fp)?;
+            require!(pay_u64 > 0, ErrorCode::NothingToPayout);
+            let pay_fp = to_fp_u64(pay_u64)?;
 
-            if effective_user_stake_fp == 0 || epoch.total_stake_snapshot_fp == 0 {
-                return err!(ErrorCode::NothingToPayout);
+            // Fee-on-yield: the position's referrer accrues its tiered cut when yield is claimed
+            let (base_referral_bps, referral_tiers, on_yield) = {
+                let s = &ctx.accounts.state;
+                (s.referral_fee_bps, s.referral_tiers, fee_model(s)? == fees::FeeModel::OnYield)
+            };
+            let referred = on_yield && position.referrer != Pubkey::default();
+            let mut ref_fee_u64 = 0u64;
+            if referred {
+                let r = ctx.accounts.referrer_account.as_ref().ok_or(ErrorCode::Unauthorized)?;
+                require_keys_eq!(r.owner, position.referrer, ErrorCode::Unauthorized);
+                let bps = referral_bps_for_tier(base_referral_bps, &referral_tiers, r.tier);
+                ref_fee_u64 = from_fp_to_u64(mul_div_floor_u128(pay_fp, bps as u128, BPS_DENOM)?)?;
             }
+            let ref_fee_fp = to_fp_u64(ref_fee_u64)?;
 
-            // Pro-rata share
-            let user_share_fp = mul_div_floor_u128(max_liability_fp, effective_user_stake_fp, epoch.total_stake_snapshot_fp)?;
+            // The referral cut stays in the vault until the referrer claims it
+            let reg = &mut ctx.accounts.collateral_registry;
+            require!(reg.assets[0].balance_fp >= pay_fp, ErrorCode::InsufficientPoolBalance);
+            if !admit_outflow(&mut ctx.accounts.state, pool_value_fp(reg)?, pay_fp.saturating_sub(ref_fee_fp))? {
+                return Ok(());
+            }
+            if referred {
+                let r = ctx.accounts.referrer_account.as_mut().ok_or(ErrorCode::Unauthorized)?;
+                credit_referral(r, ref_fee_fp, &referral_tiers);
+            }
+            reg.assets[0].balance_fp = reg.assets[0].balance_fp.saturating_sub(pay_fp.saturating_sub(ref_fee_fp));
+            assert_outflow_concentration(reg, 0)?;
+            position.yield_accrued_fp = position.yield_accrued_fp.saturating_sub(pay_fp);
 
-            // Optional per-user cap (for Capped policy)
-            let final_user_payout_fp = if policy == PayoutPolicy::Capped as u8 && epoch.user_cap_bps > 0 {
-                let cap_fp = mul_div_floor_u128(effective_user_stake_fp, epoch.user_cap_bps as u128, BPS_DENOM)?;
-                core::cmp::min(user_share_fp, cap_fp)
-            } else {
-                user_share_fp
+            let state = &mut ctx.accounts.state;
+            state.yield_reserve_fp = state.yield_reserve_fp.saturating_sub(pay_fp);
+            state.referral_rewards_owed_fp = state.referral_rewards_owed_fp.saturating_add(ref_fee_fp);
+
+            transfer_tokens_pda(
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.user_ata,
+                &ctx.accounts.state,
+                &ctx.accounts.token_program,
+                pay_u64.saturating_sub(ref_fee_u64),
+            )?;
+
+            emit!(YieldClaimed { owner: position.owner, amount_fp: pay_fp });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    /// Pay a premium into the pool. Net of fee-on-premium charges it is credited to the
+    /// tranches exactly like realised strategy yield.
+    pub fn pay_premium(ctx: Context<PayPremium>, amount_usdc: u64) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            // Premiums only add to the vault, so they keep flowing during a claim window
+            require_not_paused(&ctx.accounts.state, PAUSE_DEPOSITS)?;
+            require!(!ctx.accounts.state.shutdown, ErrorCode::PoolShutdown);
+            require!(amount_usdc > 0, ErrorCode::MinDeposit);
+
+            transfer_tokens_user(
+                &ctx.accounts.payer_ata,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.payer,
+                &ctx.accounts.token_program,
+                amount_usdc,
+            )?;
+
+            // The referral cut only goes to the referrer attributed to the payer's position
+            // (first touch), never to the payer's own account
+            let a = &mut *ctx.accounts;
+            let referrer_key = a.position.as_ref().map_or(Pubkey::default(), |p| p.referrer);
+            let referrer = if referrer_key != Pubkey::default() {
+                let r = a.referrer_account.as_deref_mut().ok_or(ErrorCode::Unauthorized)?;
+                require_keys_eq!(r.owner, referrer_key, ErrorCode::Unauthorized);
+                require_keys_neq!(r.owner, a.payer.key(), ErrorCode::Unauthorized);
+                Some(r)
+            } else {
+                require!(a.referrer_account.is_none(), ErrorCode::Unauthorized);
+                None
             };
+            let (proto_fee_u64, ref_fee_fp, net_fp) = book_premium(
+                &mut a.state,
+                &mut a.collateral_registry,
+                &a.vault_ata,
+                &a.protocol_treasury_ata,
+                &a.token_program,
+                referrer,
+                amount_usdc,
+            )?;
+
+            emit!(PremiumPaid {
+                payer: ctx.accounts.payer.key(),
+                amount_fp: to_fp_u64(amount_usdc)?,
+                net_fp,
+                protocol_fee_u64: proto_fee_u64,
+                referral_fee_fp: ref_fee_fp,
+            });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    /// Buy `notional_u64` (base units) of cover on the current epoch, until its window ends
+    /// or it is triggered, within the cover book's remaining capacity. The premium covers
+    /// the rest of the window at the curve's rate averaged between the utilisation before and
+    /// after the purchase, rounded up to a base unit (a premium of zero is rejected); it (at most
+    /// `max_premium_u64`) is paid in base USDC and credited to the tranches as
+    /// by `pay_premium`. Each purchase is its own `Cover`, represented by a newly minted
+    /// NFT (supply 1, no mint authority) sent to the buyer; whoever holds it claims.
+    pub fn buy_cover(ctx: Context<BuyCover>, notional_u64: u64, max_premium_u64: u64) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            require_not_paused(&ctx.accounts.state, PAUSE_DEPOSITS)?;
+            require!(!ctx.accounts.state.shutdown, ErrorCode::PoolShutdown);
+            require!(notional_u64 > 0, ErrorCode::MinDeposit);
+            // Cover is sold for a known window, once it has opened
+            let now = Clock::get()?.unix_timestamp;
+            let epoch = &ctx.accounts.epoch;
+            require!(
+                !epoch.triggered && epoch.end_ts != 0 && now >= epoch.start_ts && now < epoch.end_ts,
+                ErrorCode::EpochNotActive
+            );
+            let epoch_id = epoch.epoch_id;
+
+            // Cover sold on an earlier epoch has expired
+            let book = &mut ctx.accounts.cover_book;
+            if book.epoch_id != epoch_id {
+                book.epoch_id = epoch_id;
+                book.sold_fp = 0;
+            }
+            let notional_fp = to_fp_u64(notional_u64)?;
+            let capacity = cover_capacity(&ctx.accounts.state, book)?;
+            require!(notional_fp <= capacity.available_fp, ErrorCode::CapacityExceeded);
+            let quote = quote_cover(&ctx.accounts.state, book, notional_fp, (epoch.end_ts - now) as u64)?;
+            let premium_u64 = from_fp_ceil_to_u64(quote.premium_fp)?;
+            require!(premium_u64 > 0, ErrorCode::ZeroPremium);
+            require!(premium_u64 <= max_premium_u64, ErrorCode::SlippageExceeded);
+            book.sold_fp = book.sold_fp.checked_add(notional_fp).ok_or_else(math_overflow)?;
+            let epoch = &mut ctx.accounts.epoch;
+            epoch.cover_sold_fp = epoch.cover_sold_fp.checked_add(notional_fp).ok_or_else(math_overflow)?;
+
+            transfer_tokens_user(
+                &ctx.accounts.buyer_ata,
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.buyer,
+                &ctx.accounts.token_program,
+                premium_u64,
+            )?;
+            let a = &mut *ctx.accounts;
+            let (proto_fee_u64, ref_fee_fp, net_fp) = book_premium(
+                &mut a.state,
+                &mut a.collateral_registry,
+                &a.vault_ata,
+                &a.protocol_treasury_ata,
+                &a.token_program,
+                None,
+                premium_u64,
+            )?;
+            emit!(PremiumPaid {
+                payer: ctx.accounts.buyer.key(),
+                amount_fp: to_fp_u64(premium_u64)?,
+                net_fp,
+                protocol_fee_u64: proto_fee_u64,
+                referral_fee_fp: ref_fee_fp,
+            });
+
+            let a = &mut *ctx.accounts;
+            mint_cover_token(&a.cover_mint, &a.buyer_nft, &a.state, &a.token_program)?;
+            a.cover_book.covers_minted = a.cover_book.covers_minted.checked_add(1).ok_or_else(math_overflow)?;
+
+            let premium_fp = to_fp_u64(premium_u64)?;
+            let cover = &mut a.cover;
+            cover.mint = a.cover_mint.key();
+            cover.buyer = a.buyer.key();
+            cover.epoch_id = epoch_id;
+            cover.notional_fp = notional_fp;
+            cover.premium_fp = premium_fp;
+            cover.bump = ctx.bumps.cover;
+            cover.version = ACCOUNT_VERSION;
+
+            emit!(CoverPurchased { epoch_id, mint: cover.mint, buyer: cover.buyer, notional_fp, premium_fp });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    /// Move fees accrued in the vault (fee-on-yield / performance fees) to the treasury.
+    pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            require_not_paused(&ctx.accounts.state, PAUSE_FEE_SWEEPS)?;
+            let amount_u64 = from_fp_to_u64(ctx.accounts.state.protocol_fees_accrued_fp)?;
+            require!(amount_u64 > 0, ErrorCode::NothingToPayout);
+            let amount_fp = to_fp_u64(amount_u64)?;
+
+            let reg = &mut ctx.accounts.collateral_registry;
+            require!(reg.assets[0].balance_fp >= amount_fp, ErrorCode::InsufficientPoolBalance);
+            if !admit_outflow(&mut ctx.accounts.state, pool_value_fp(reg)?, amount_fp)? {
+                return Ok(());
+            }
+            reg.assets[0].balance_fp = reg.assets[0].balance_fp.saturating_sub(amount_fp);
+            assert_outflow_concentration(reg, 0)?;
+            let state = &mut ctx.accounts.state;
+            state.protocol_fees_accrued_fp = state.protocol_fees_accrued_fp.saturating_sub(amount_fp);
+
+            transfer_tokens_pda(
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.protocol_treasury_ata,
+                &ctx.accounts.state,
+                &ctx.accounts.token_program,
+                amount_u64,
+            )?;
+
+            emit!(ProtocolFeeTaken { amount_u64 });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    // ----------------------------- event / payout -----------------------------
+
+    pub fn trigger_event<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TriggerEvent<'info>>,
+        severity_input_bps: u16,               // input to curve
+        user_cap_bps: Option<u16>,
+        epoch_cap_fp_override: Option<u128>,
+        evidence_hash: Option<[u8; 32]>,
+        evidence_ts_opt: Option<i64>,          // if oracle data has timestamp
+    ) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            // Oracle allowlist: either admin OR an allowed oracle
+            {
+                let signer = ctx.accounts.admin_or_oracle.key();
+                let list = &ctx.accounts.oracle_list;
+                require!(list.enabled, ErrorCode::Unauthorized);
+                // If admin is also allowed implicitly:
+                if signer != ctx.accounts.state.admin {
+                    require!(oracle_is_allowed(list, signer), ErrorCode::Unauthorized);
+                }
+            }
+
+            let state = &mut ctx.accounts.state;
+            let epoch = &mut ctx.accounts.epoch;
+            let now = Clock::get()?.unix_timestamp;
+
+            require!(!epoch.triggered, ErrorCode::EpochAlreadyTriggered);
+            require!(!epoch.closed, ErrorCode::EpochNotActive);
+            // One claim window at a time: finalizing an earlier epoch would unfreeze
+            // deposits while this one still pays out against its snapshot
+            require_not_frozen(state, PAUSE_TRIGGERS)?;
+            require!(!state.shutdown, ErrorCode::PoolShutdown);
+            // Open-ended epochs have end_ts == 0
+            if epoch.end_ts != 0 {
+                require!(now >= epoch.start_ts && now <= epoch.end_ts, ErrorCode::EpochNotActive);
+            } else {
+                require!(now >= epoch.start_ts, ErrorCode::EpochNotActive);
+            }
+
+            // Optional staleness check
+            if let Some(e_ts) = evidence_ts_opt {
+                if state.max_stale_secs > 0 {
+                    require!(now.saturating_sub(e_ts) <= state.max_stale_secs, ErrorCode::EpochNotActive);
+                }
+                epoch.evidence_ts = e_ts;
+            }
+
+            // Force-recall strategy capital so the whole pool is liquid for payouts
+            if state.strategy_deployed_fp > 0 {
+                let missing = || error!(ErrorCode::StrategyNotConfigured);
+                let mut strategy = StrategyCpi {
+                    program: ctx.accounts.strategy_program.as_ref().ok_or_else(missing)?.as_ref(),
+                    authority: ctx.accounts.strategy_authority.as_ref().ok_or_else(missing)?.as_ref(),
+                    bump: ctx.bumps.strategy_authority.ok_or_else(missing)?,
+                    escrow: ctx.accounts.strategy_escrow.as_mut().ok_or_else(missing)?,
+                };
+                recall_strategy(
+                    &mut strategy,
+                    state,
+                    &mut ctx.accounts.collateral_registry,
+                    &ctx.accounts.vault_ata,
+                    &ctx.accounts.token_program,
+                    ctx.remaining_accounts,
+                    None,
+                )?;
+            }
+
+            // Snapshot pool (principal deposited during this rolling epoch is not covered yet)
+            epoch.total_stake_snapshot_fp = state.total_deposited_fp.saturating_sub(epoch.pending_deposits_fp);
+
+            // Effective severity via curve + floor
+            let sev_eff_bps = effective_severity_bps(
+                severity_input_bps as u128,
+                state.sev_quad_a_fp,
+                state.sev_quad_b_fp,
+                state.sev_quad_c_fp,
+                state.severity_floor_bps,
+            )?;
+            epoch.severity_bps = sev_eff_bps as u16;
+            epoch.user_cap_bps = user_cap_bps.unwrap_or(0);
+
+            // Policy-cap on epoch liability
+            if payout_policy(state)? == payout::PayoutPolicy::EpochBounded {
+                epoch.epoch_cap_fp = epoch_cap_fp_override.unwrap_or(state.epoch_cap_fp);
+            } else {
+                epoch.epoch_cap_fp = 0;
+            }
+
+            epoch.triggered = true;
+            epoch.triggered_ts = now;
+            state.last_event_ts = now;
+            state.cover_owed_fp = cover_owed_fp(epoch)?;
+
+            // Freeze principal movements (the stake snapshot) during claims
+            state.claim_window_open = true;
+
+            if let Some(h) = evidence_hash {
+                epoch.evidence_hash = h;
+            }
+
+            emit!(EventTriggered {
+                epoch_id: epoch.epoch_id,
+                severity_bps: epoch.severity_bps,
+                user_cap_bps: epoch.user_cap_bps,
+                policy: state.payout_policy,
+                evidence_hash: epoch.evidence_hash,
+            });
+
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    /// Per-user payout with claim receipt (prevents double claims)
+    pub fn payout_user(ctx: Context<PayoutUser>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            require_not_paused(&ctx.accounts.state, PAUSE_CLAIMS)?;
+            let state = &ctx.accounts.state;
+            let epoch = &mut ctx.accounts.epoch;
+            require!(epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
+            require!(epoch.merkle_root == [0; 32], ErrorCode::SettlementModeConflict);
 
-            // Check claim receipt (no double claim)
-            let claim = &mut ctx.accounts.claim;
-            require!(claim.claimed_fp == 0, ErrorCode::NothingToPayout);
+            // User position (uses tranche-weighted stake)
+            let position = &ctx.accounts.position;
+            require_keys_eq!(position.owner, ctx.accounts.user.key(), ErrorCode::Unauthorized);
 
-            // Ensure room in epoch
-            let remaining_epoch_room_fp = max_liability_fp.saturating_sub(epoch.total_payout_fp);
-            let pay_fp = core::cmp::min(final_user_payout_fp, remaining_epoch_room_fp);
+            // Haircut value across all collateral vaults bounds the epoch liability
+            let pool_balance_fp = pool_value_fp(&ctx.accounts.collateral_registry)?;
+            let pay_fp = epoch_claim_fp(state, epoch, pool_balance_fp, position)?;
+
+            // A claim receipt means already claimed. It is only created once the payout is
+            // admitted, so a payout stopped by the breaker can be retried
+            let claim_info = ctx.accounts.claim.to_account_info();
+            require_keys_eq!(*claim_info.owner, System::id(), ErrorCode::NothingToPayout);
             require!(pay_fp > 0, ErrorCode::NothingToPayout);
+            if !admit_outflow(&mut ctx.accounts.state, pool_balance_fp, pay_fp)? {
+                return Ok(());
+            }
+
+            // Paid at par in the asset chosen by the claimant, subject to availability
+            let reg = &mut ctx.accounts.collateral_registry;
+            let asset_idx = collateral_index(reg, ctx.accounts.asset_mint.key())?;
+            let asset = &mut reg.assets[asset_idx];
+            require!(asset.balance_fp >= pay_fp, ErrorCode::InsufficientPoolBalance);
+            let pay_u64 = fp_to_asset(pay_fp, asset.decimals)?;
+            asset.balance_fp = asset.balance_fp.saturating_sub(asset_to_fp(pay_u64, asset.decimals)?);
+            assert_outflow_concentration(reg, asset_idx)?;
 
             // Update epoch (accumulator)
             epoch.total_payout_fp = epoch.total_payout_fp.saturating_add(pay_fp);
 
             // Transfer vault -> user
-            let pay_u64 = from_fp_to_u64(pay_fp)?;
             transfer_tokens_pda(
                 &ctx.accounts.vault_ata,
                 &ctx.accounts.user_ata,
@@ -470,63 +1457,569 @@ pub mod tail_risk_insurance_pool {
             )?;
 
             // Write receipt
-            claim.epoch_id = epoch.epoch_id;
-            claim.owner = ctx.accounts.user.key();
-            claim.claimed_fp = pay_fp;
-            claim.bump = ctx.bumps.claim;
+            epoch.open_receipts = epoch.open_receipts.checked_add(1).ok_or_else(math_overflow)?;
+            let receipt = ClaimReceipt {
+                epoch_id: epoch.epoch_id,
+                owner: ctx.accounts.user.key(),
+                claimed_fp: pay_fp,
+                bump: ctx.bumps.claim,
+                version: ACCOUNT_VERSION,
+                payer: ctx.accounts.user.key(),
+                reserved: [0; CLAIM_RESERVED],
+            };
+            let (user, system) = (ctx.accounts.user.to_account_info(), ctx.accounts.system_program.to_account_info());
+            create_claim_receipt(&claim_info, &user, &system, &receipt)?;
 
             emit!(UserPayout {
                 epoch_id: epoch.epoch_id,
                 owner: position.owner,
-                payout_fp: pay_fp
+                payout_fp: pay_fp,
+                mint: ctx.accounts.asset_mint.key(),
             });
             Ok(())
         })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
 
-        // Always clear the guard
-        let s = &mut ctx.accounts.state;
-        s.processing = false;
-        res
+    /// Permissionless batch of `payout_user` for holders who have not claimed. Remaining
+    /// accounts are `(position, claim receipt, holder's base-USDC ATA)` triples; each holder
+    /// is paid in base USDC and gets a claim receipt, whose rent the cranker fronts (and gets
+    /// back when it is closed). Triples whose receipt already exists, with nothing owed or
+    /// without an ATA are skipped. The cranker earns `crank_reward_u64` per holder paid at
+    /// least `MIN_CRANK_PAYOUT_U64`, out of the treasury's uncollected protocol fees.
+    pub fn crank_payouts<'info>(ctx: Context<'_, '_, 'info, 'info, CrankPayouts<'info>>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let remaining = ctx.remaining_accounts;
+        let res = (|| -> Result<()> {
+            require_not_paused(&ctx.accounts.state, PAUSE_CLAIMS)?;
+            let epoch_id = ctx.accounts.epoch.epoch_id;
+            require!(ctx.accounts.epoch.triggered && !ctx.accounts.epoch.closed, ErrorCode::EpochNotActive);
+            require!(ctx.accounts.epoch.merkle_root == [0; 32], ErrorCode::SettlementModeConflict);
+            let triples = remaining.chunks_exact(3);
+            require!(!remaining.is_empty() && triples.remainder().is_empty(), ErrorCode::InvalidRemainingAccounts);
+
+            let cranker = ctx.accounts.cranker.to_account_info();
+            let system = ctx.accounts.system_program.to_account_info();
+            let mint = ctx.accounts.vault_ata.mint;
+            let (mut paid, mut rewarded, mut skipped, mut tripped) = (0u32, 0u64, 0u32, false);
+            for triple in triples {
+                let (position_info, claim_info, ata_info) = (&triple[0], &triple[1], &triple[2]);
+                let position = Account::<UserPosition>::try_from(position_info)?;
+                let owner = position.owner;
+                let position_key = Pubkey::create_program_address(&[b"position", owner.as_ref(), &[position.bump]], &crate::ID)
+                    .map_err(|_| error!(ErrorCode::InvalidRemainingAccounts))?;
+                let (claim_key, claim_bump) =
+                    Pubkey::find_program_address(&[b"claim", epoch_id.to_le_bytes().as_ref(), owner.as_ref()], &crate::ID);
+                require!(
+                    position_info.key() == position_key
+                        && claim_info.key() == claim_key
+                        && ata_info.key() == get_associated_token_address(&owner, &mint),
+                    ErrorCode::InvalidRemainingAccounts
+                );
+
+                // Already claimed (receipt exists) or no token account to pay into
+                if *claim_info.owner == crate::ID || *ata_info.owner != Token::id() {
+                    skipped += 1;
+                    continue;
+                }
+                require_keys_eq!(*claim_info.owner, System::id(), ErrorCode::InvalidRemainingAccounts);
+                let user_ata = Account::<TokenAccount>::try_from(ata_info)?;
+                // The ATA address is derived from the owner, but its authority can be reassigned
+                require_keys_eq!(user_ata.owner, owner, ErrorCode::InvalidRemainingAccounts);
+
+                let pool_balance_fp = pool_value_fp(&ctx.accounts.collateral_registry)?;
+                let pay_fp = epoch_claim_fp(&ctx.accounts.state, &mut ctx.accounts.epoch, pool_balance_fp, &position)?;
+                if pay_fp == 0 {
+                    skipped += 1;
+                    continue;
+                }
+                if !admit_outflow(&mut ctx.accounts.state, pool_balance_fp, pay_fp)? {
+                    tripped = true;
+                    break;
+                }
+
+                let asset = &mut ctx.accounts.collateral_registry.assets[0];
+                require!(asset.balance_fp >= pay_fp, ErrorCode::InsufficientPoolBalance);
+                let pay_u64 = fp_to_asset(pay_fp, asset.decimals)?;
+                asset.balance_fp = asset.balance_fp.saturating_sub(asset_to_fp(pay_u64, asset.decimals)?);
+                assert_outflow_concentration(&ctx.accounts.collateral_registry, 0)?;
+
+                let epoch = &mut ctx.accounts.epoch;
+                epoch.total_payout_fp = epoch.total_payout_fp.saturating_add(pay_fp);
+                epoch.open_receipts = epoch.open_receipts.checked_add(1).ok_or_else(math_overflow)?;
+                let receipt = ClaimReceipt {
+                    epoch_id,
+                    owner,
+                    claimed_fp: pay_fp,
+                    bump: claim_bump,
+                    version: ACCOUNT_VERSION,
+                    payer: cranker.key(),
+                    reserved: [0; CLAIM_RESERVED],
+                };
+                create_claim_receipt(claim_info, &cranker, &system, &receipt)?;
+
+                transfer_tokens_pda(
+                    &ctx.accounts.vault_ata,
+                    &user_ata,
+                    &ctx.accounts.state,
+                    &ctx.accounts.token_program,
+                    pay_u64,
+                )?;
+                emit!(UserPayout { epoch_id, owner, payout_fp: pay_fp, mint });
+                paid += 1;
+                if pay_u64 >= MIN_CRANK_PAYOUT_U64 {
+                    rewarded += 1;
+                }
+            }
+
+            // Reward out of the treasury's share of the vault; none once the breaker trips
+            let fees_u64 = from_fp_to_u64(ctx.accounts.state.protocol_fees_accrued_fp)?;
+            let mut reward_u64 = ctx.accounts.state.crank_reward_u64.saturating_mul(rewarded).min(fees_u64);
+            if tripped {
+                reward_u64 = 0;
+            }
+            if reward_u64 > 0 {
+                let reward_fp = to_fp_u64(reward_u64)?;
+                let reg = &mut ctx.accounts.collateral_registry;
+                require!(reg.assets[0].balance_fp >= reward_fp, ErrorCode::InsufficientPoolBalance);
+                if admit_outflow(&mut ctx.accounts.state, pool_value_fp(reg)?, reward_fp)? {
+                    reg.assets[0].balance_fp = reg.assets[0].balance_fp.saturating_sub(reward_fp);
+                    assert_outflow_concentration(reg, 0)?;
+                    let state = &mut ctx.accounts.state;
+                    state.protocol_fees_accrued_fp = state.protocol_fees_accrued_fp.saturating_sub(reward_fp);
+                    transfer_tokens_pda(
+                        &ctx.accounts.vault_ata,
+                        &ctx.accounts.cranker_ata,
+                        &ctx.accounts.state,
+                        &ctx.accounts.token_program,
+                        reward_u64,
+                    )?;
+                } else {
+                    reward_u64 = 0;
+                }
+            }
+
+            emit!(PayoutsCranked { epoch_id, cranker: cranker.key(), paid, skipped, reward_u64 });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
     }
 
-    /// Finalize an epoch, unpause the pool, optionally sweep dust to treasury.
-    pub fn finalize_epoch(ctx: Context<FinalizeEpoch>, sweep_dust_u64: Option<u64>) -> Result<()> {
-        let state = &mut ctx.accounts.state;
+    /// Settle a triggered epoch off chain: post the root of a distribution paying `total_fp`
+    /// over `claimants` leaves (see `tail_risk_core::merkle`). Holders then claim with
+    /// `claim_with_proof`; `payout_user` and `crank_payouts` are closed for the epoch.
+    pub fn post_merkle_root(ctx: Context<PostMerkleRoot>, root: [u8; 32], total_fp: u128, claimants: u32) -> Result<()> {
         let epoch = &mut ctx.accounts.epoch;
-
         require!(epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
-
-        // Record shortfall to state for future make-good accounting
-        if epoch.shortfall_fp > 0 {
-            state.carryover_shortfall_fp = state.carryover_shortfall_fp.saturating_add(epoch.shortfall_fp);
+        // Only cover may have been claimed on chain so far
+        require!(
+            epoch.merkle_root == [0; 32] && epoch.total_payout_fp == epoch.cover_paid_fp,
+            ErrorCode::SettlementModeConflict
+        );
+        require!(root != [0; 32] && (1..=MAX_MERKLE_CLAIMANTS).contains(&claimants), ErrorCode::ParamOutOfBounds);
+
+        // The distribution may pay no more than the on-chain path would pay depositors in total
+        let policy = payout_policy(&ctx.accounts.state)?;
+        let liability = payout::epoch_liability(
+            claim_base_fp(epoch),
+            epoch.severity_bps,
+            policy,
+            epoch.epoch_cap_fp,
+            pool_value_fp(&ctx.accounts.collateral_registry)?,
+        )
+        .ok_or_else(math_overflow)?;
+        let depositors_fp = payout::user_payout_fp(liability.max_fp, epoch.total_stake_snapshot_fp, claim_base_fp(epoch), policy, 0)
+            .ok_or_else(math_overflow)?;
+        require!(total_fp > 0 && total_fp <= depositors_fp, ErrorCode::ParamOutOfBounds);
+        if liability.shortfall_fp > 0 {
+            epoch.shortfall_fp = liability.shortfall_fp;
         }
+        epoch.merkle_root = root;
+        epoch.merkle_total_fp = total_fp;
+
+        let bitmap = &mut ctx.accounts.claim_bitmap;
+        bitmap.epoch_id = epoch.epoch_id;
+        bitmap.claimants = claimants;
+        bitmap.claimed = 0;
+        bitmap.bump = ctx.bumps.claim_bitmap;
+        bitmap.version = ACCOUNT_VERSION;
+        bitmap.bits = vec![0; merkle::bitmap_len(claimants)];
+
+        emit!(MerkleRootPosted { epoch_id: epoch.epoch_id, root, total_fp, claimants });
+        Ok(())
+    }
+
+    /// Claim leaf `index` of a Merkle-settled epoch: `amount_fp` to the signer, paid at par
+    /// in the asset of their choice.
+    pub fn claim_with_proof(ctx: Context<ClaimWithProof>, index: u32, amount_fp: u128, proof: Vec<[u8; 32]>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            require_not_paused(&ctx.accounts.state, PAUSE_CLAIMS)?;
+            let epoch = &mut ctx.accounts.epoch;
+            require!(epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
+            require!(epoch.merkle_root != [0; 32], ErrorCode::SettlementModeConflict);
+
+            let owner = ctx.accounts.user.key();
+            let leaf = merkle::leaf(index, &owner.to_bytes(), amount_fp, sha256v);
+            require!(merkle::verify(leaf, &proof, &epoch.merkle_root, sha256v), ErrorCode::InvalidMerkleProof);
+            let bitmap = &mut ctx.accounts.claim_bitmap;
+            require!(amount_fp > 0 && !merkle::is_claimed(&bitmap.bits, index), ErrorCode::NothingToPayout);
+            // A root whose leaves overstate its total cannot pay past it (cover claims aside)
+            let paid_fp = epoch.total_payout_fp.checked_add(amount_fp).ok_or_else(math_overflow)?;
+            require!(paid_fp.saturating_sub(epoch.cover_paid_fp) <= epoch.merkle_total_fp, ErrorCode::InsufficientPoolBalance);
+
+            let pool_balance_fp = pool_value_fp(&ctx.accounts.collateral_registry)?;
+            if !admit_outflow(&mut ctx.accounts.state, pool_balance_fp, amount_fp)? {
+                return Ok(());
+            }
+
+            let reg = &mut ctx.accounts.collateral_registry;
+            let asset_idx = collateral_index(reg, ctx.accounts.asset_mint.key())?;
+            let asset = &mut reg.assets[asset_idx];
+            require!(asset.balance_fp >= amount_fp, ErrorCode::InsufficientPoolBalance);
+            let pay_u64 = fp_to_asset(amount_fp, asset.decimals)?;
+            asset.balance_fp = asset.balance_fp.saturating_sub(asset_to_fp(pay_u64, asset.decimals)?);
+            assert_outflow_concentration(reg, asset_idx)?;
+
+            merkle::set_claimed(&mut bitmap.bits, index);
+            bitmap.claimed = bitmap.claimed.saturating_add(1);
+            epoch.total_payout_fp = paid_fp;
+
+            transfer_tokens_pda(
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.user_ata,
+                &ctx.accounts.state,
+                &ctx.accounts.token_program,
+                pay_u64,
+            )?;
+            emit!(UserPayout {
+                epoch_id: epoch.epoch_id,
+                owner,
+                payout_fp: amount_fp,
+                mint: ctx.accounts.asset_mint.key(),
+            });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
 
-        // Optional dust sweep (any spare above total_deposited_fp is interpreted as fees/excess)
-        if let Some(sweep) = sweep_dust_u64 {
-            if sweep > 0 {
-                let pool_bal_fp = vault_balance_fp(&ctx.accounts.vault_ata)?;
-                let principal_fp = state.total_deposited_fp;
-                if pool_bal_fp > principal_fp {
-                    let dust_fp = pool_bal_fp.saturating_sub(principal_fp);
-                    let move_u64 = core::cmp::min(sweep, from_fp_to_u64(dust_fp)?);
-                    if move_u64 > 0 {
-                        transfer_tokens_pda(
-                            &ctx.accounts.vault_ata,
-                            &ctx.accounts.protocol_treasury_ata,
-                            state,
-                            &ctx.accounts.token_program,
-                            move_u64,
-                        )?;
+    /// Claim cover on a triggered epoch, once, in base USDC, until the epoch is finalized: its
+    /// notional times the epoch's effective severity, bounded like depositor payouts by the
+    /// policy, the epoch cap and the pool (cover and depositors share the liability pro rata).
+    /// Paid to the signer holding the cover's NFT, whoever bought it.
+    pub fn claim_cover(ctx: Context<ClaimCover>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            require_not_paused(&ctx.accounts.state, PAUSE_CLAIMS)?;
+            let epoch = &mut ctx.accounts.epoch;
+            require!(epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
+            let notional_fp = ctx.accounts.cover.notional_fp;
+            require!(ctx.accounts.cover.claimed_fp == 0, ErrorCode::NothingToPayout);
+            let pool_balance_fp = pool_value_fp(&ctx.accounts.collateral_registry)?;
+            let pay_fp = cover_claim_fp(&ctx.accounts.state, epoch, pool_balance_fp, notional_fp)?;
+            require!(pay_fp > 0, ErrorCode::NothingToPayout);
+
+            if !admit_outflow(&mut ctx.accounts.state, pool_balance_fp, pay_fp)? {
+                return Ok(());
+            }
+            let base = &mut ctx.accounts.collateral_registry.assets[0];
+            require!(base.balance_fp >= pay_fp, ErrorCode::InsufficientPoolBalance);
+            let pay_u64 = fp_to_asset(pay_fp, base.decimals)?;
+            base.balance_fp = base.balance_fp.saturating_sub(asset_to_fp(pay_u64, base.decimals)?);
+            assert_outflow_concentration(&ctx.accounts.collateral_registry, 0)?;
+
+            let epoch = &mut ctx.accounts.epoch;
+            epoch.total_payout_fp = epoch.total_payout_fp.saturating_add(pay_fp);
+            epoch.cover_paid_fp = epoch.cover_paid_fp.saturating_add(pay_fp);
+            epoch.cover_claimed_fp = epoch.cover_claimed_fp.saturating_add(notional_fp);
+            ctx.accounts.state.cover_owed_fp = cover_owed_fp(epoch)?;
+
+            let cover = &mut ctx.accounts.cover;
+            cover.claimed_fp = pay_fp;
+            transfer_tokens_pda(
+                &ctx.accounts.vault_ata,
+                &ctx.accounts.holder_ata,
+                &ctx.accounts.state,
+                &ctx.accounts.token_program,
+                pay_u64,
+            )?;
+            emit!(CoverClaimed {
+                epoch_id: cover.epoch_id,
+                mint: cover.mint,
+                holder: ctx.accounts.holder.key(),
+                payout_fp: pay_fp,
+            });
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    /// Finalize an epoch, close its claim window, optionally sweep dust to treasury.
+    pub fn finalize_epoch(ctx: Context<FinalizeEpoch>, sweep_dust_u64: Option<u64>) -> Result<()> {
+        begin_processing(&mut ctx.accounts.state)?;
+        let res = (|| -> Result<()> {
+            let state = &mut ctx.accounts.state;
+            let epoch = &mut ctx.accounts.epoch;
+
+            require!(epoch.triggered && !epoch.closed, ErrorCode::EpochNotActive);
+            let now = Clock::get()?.unix_timestamp;
+            require_cover_claims_done(epoch, now)?;
+
+            // Optional dust sweep (any spare above total_deposited_fp is interpreted as fees/excess),
+            // paid out of the `asset_mint` vault.
+            if sweep_dust_u64.is_some() {
+                require_not_paused(state, PAUSE_FEE_SWEEPS)?;
+            }
+            if let Some(sweep) = sweep_dust_u64 {
+                if sweep > 0 {
+                    let reg = &mut ctx.accounts.collateral_registry;
+                    let asset_idx = collateral_index(reg, ctx.accounts.asset_mint.key())?;
+                    let pool_bal_fp = total_pool_value_fp(state, reg)?;
+                    // Anything the pool owes (principal, yield, fees, referral rewards) is never dust
+                    let principal_fp = tracked_liabilities_fp(state);
+                    if pool_bal_fp > principal_fp {
+                        let liquid_fp = pool_value_fp(reg)?;
+                        let asset = &mut reg.assets[asset_idx];
+                        let dust_fp = core::cmp::min(pool_bal_fp.saturating_sub(principal_fp), asset.balance_fp);
+                        let move_u64 = core::cmp::min(sweep, fp_to_asset(dust_fp, asset.decimals)?);
+                        let moved_fp = asset_to_fp(move_u64, asset.decimals)?;
+                        // A tripped breaker skips the sweep; the epoch still finalizes
+                        if move_u64 > 0 && admit_outflow(state, liquid_fp, moved_fp)? {
+                            asset.balance_fp = asset.balance_fp.saturating_sub(moved_fp);
+                            assert_outflow_concentration(reg, asset_idx)?;
+                            transfer_tokens_pda(
+                                &ctx.accounts.vault_ata,
+                                &ctx.accounts.protocol_treasury_ata,
+                                state,
+                                &ctx.accounts.token_program,
+                                move_u64,
+                            )?;
+                        }
                     }
                 }
             }
-        }
 
-        epoch.closed = true;
-        state.paused = false;
+            settle_epoch(state, epoch, &mut ctx.accounts.epoch_history, now);
+            Ok(())
+        })();
+        end_processing(&mut ctx.accounts.state, res)
+    }
+
+    // ----------------------------- account closing -----------------------------
+
+    /// Close the caller's position once it holds nothing: no principal, no withdrawable
+    /// balance, no lots and no claimable yield. Yield below one base unit (which
+    /// `claim_yield` cannot pay) is forfeited to the pool. Rent goes back to the owner.
+    /// The referral attribution is a separate account and survives the close.
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        let yield_idx = {
+            let s = &ctx.accounts.state;
+            (s.senior_yield_index_fp, s.junior_yield_index_fp)
+        };
+        let position = &mut ctx.accounts.position;
+        settle_yield(position, yield_idx)?;
+        require!(
+            position.senior_deposited_fp == 0
+                && position.junior_deposited_fp == 0
+                && position.senior_withdrawable_fp == 0
+                && position.junior_withdrawable_fp == 0
+                && position.senior_lots.len == 0
+                && position.junior_lots.len == 0
+                && from_fp_to_u64(position.yield_accrued_fp)? == 0,
+            ErrorCode::AccountNotEmpty
+        );
+
+        let state = &mut ctx.accounts.state;
+        state.yield_reserve_fp = state.yield_reserve_fp.saturating_sub(position.yield_accrued_fp);
+
+        emit!(PositionClosed { owner: position.owner });
+        Ok(())
+    }
+
+    /// Close a cover that can pay nothing more: claimed, or its epoch finalized or ended
+    /// (unclaimed cover lapses then). The holder's NFT is burnt and its token account closed
+    /// to the holder; the cover's rent goes back to the buyer, who paid for it. An SPL mint
+    /// cannot be closed, so the NFT's mint stays behind with a supply of zero.
+    pub fn close_cover(ctx: Context<CloseCover>) -> Result<()> {
+        // `close_epoch` may already have removed a finalized epoch
+        let epoch: &AccountInfo = &ctx.accounts.epoch;
+        let settled = epoch.data_is_empty() || Epoch::try_deserialize(&mut &epoch.try_borrow_data()?[..])?.closed;
+        let cover = &ctx.accounts.cover;
+        require!(cover.claimed_fp > 0 || settled, ErrorCode::CoverActive);
+
+        let holder = ctx.accounts.holder.to_account_info();
+        let nft = ctx.accounts.holder_nft.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        token::burn(
+            CpiContext::new(
+                token_program.clone(),
+                Burn { mint: ctx.accounts.cover_mint.to_account_info(), from: nft.clone(), authority: holder.clone() },
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new(
+            token_program,
+            CloseAccount { account: nft, destination: holder.clone(), authority: holder },
+        ))?;
+
+        emit!(CoverClosed { epoch_id: cover.epoch_id, mint: cover.mint, buyer: cover.buyer });
+        Ok(())
+    }
+
+    /// Close a claim receipt `CLAIM_RETENTION_SECS` after its epoch was finalized.
+    /// Permissionless; rent goes back to whoever paid for the receipt: the claimant, or the
+    /// cranker that paid them.
+    pub fn close_claim_receipt(ctx: Context<CloseClaimReceipt>) -> Result<()> {
+        let epoch = &mut ctx.accounts.epoch;
+        require!(epoch.closed, ErrorCode::EpochNotActive);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= epoch.finalized_ts.saturating_add(CLAIM_RETENTION_SECS),
+            ErrorCode::RetentionPeriodActive
+        );
+        epoch.open_receipts = epoch.open_receipts.saturating_sub(1);
+
+        let claim = &ctx.accounts.claim;
+        emit!(ClaimReceiptClosed { epoch_id: claim.epoch_id, owner: claim.owner });
+        Ok(())
+    }
+
+    /// Close a finalized epoch once every claim receipt against it has been closed.
+    /// Rent goes back to the admin, who paid for the epoch.
+    pub fn close_epoch(ctx: Context<CloseEpoch>) -> Result<()> {
+        let epoch = &ctx.accounts.epoch;
+        require!(epoch.closed, ErrorCode::EpochNotActive);
+        require!(epoch.open_receipts == 0, ErrorCode::AccountNotEmpty);
+        // A Merkle-settled epoch takes its claim bitmap with it
+        require!(
+            epoch.merkle_root == [0; 32] || ctx.accounts.claim_bitmap.is_some(),
+            ErrorCode::SettlementModeConflict
+        );
+
+        emit!(EpochClosed { epoch_id: epoch.epoch_id });
+        Ok(())
+    }
+
+    // ----------------------------- migrations -----------------------------
+
+    /// Upgrade the global accounts (state, oracle list) and any `Epoch` accounts passed as
+    /// remaining accounts to the current layout, creating the collateral registry a pool from
+    /// before multi-collateral lacks. The admin tops up rent. A migrated legacy pool is left
+    /// fully paused: the tranche totals are rebuilt as `migrate_position` runs for each position.
+    pub fn migrate_state<'info>(ctx: Context<'_, '_, 'info, 'info, MigrateState<'info>>) -> Result<()> {
+        let admin = ctx.accounts.admin.to_account_info();
+        let system = ctx.accounts.system_program.to_account_info();
+        let admin_key = admin.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        // Epochs first: a legacy state takes its claim window and last epoch id from them
+        let (mut claim_window_open, mut last_epoch_id) = (false, 0u64);
+        for info in ctx.remaining_accounts {
+            let epoch = migrate_account::<Epoch>(info, &admin, &system, |v0| {
+                let mut e = Epoch::from(v0);
+                // Finalized before versioning: the receipt retention period runs from now
+                if e.closed {
+                    e.finalized_ts = now;
+                }
+                Ok(e)
+            })?;
+            claim_window_open |= epoch.triggered && !epoch.closed;
+            last_epoch_id = last_epoch_id.max(epoch.epoch_id);
+        }
+        let state = migrate_account::<State>(&ctx.accounts.state, &admin, &system, |v0| {
+            require_keys_eq!(v0.admin, admin_key, ErrorCode::Unauthorized);
+            let mut s = State::from(v0);
+            s.claim_window_open = claim_window_open;
+            s.current_epoch_id = last_epoch_id;
+            s.pause_flags = PAUSE_ALL;
+            Ok(s)
+        })?;
+        let oracle_bump = ctx.bumps.oracle_list;
+        migrate_account::<OracleList>(&ctx.accounts.oracle_list, &admin, &system, |v0| {
+            let mut list = OracleList::from(v0);
+            // Never recorded before versioning
+            list.bump = oracle_bump;
+            Ok(list)
+        })?;
+
+        // Before multi-collateral the base asset was the only one, all of it in its vault
+        let reg = &mut ctx.accounts.collateral_registry;
+        if reg.version == 0 {
+            let (mint, vault) = (&ctx.accounts.usdc_mint, &ctx.accounts.vault_ata);
+            require_keys_eq!(mint.key(), state.usdc_mint, ErrorCode::UnsupportedCollateral);
+            reg.bump = ctx.bumps.collateral_registry;
+            reg.version = ACCOUNT_VERSION;
+            register_collateral(
+                reg,
+                CollateralAsset {
+                    mint: mint.key(),
+                    vault: vault.key(),
+                    decimals: mint.decimals,
+                    haircut_bps: 0,
+                    concentration_cap_bps: BPS_DENOM as u16,
+                    enabled: true,
+                    balance_fp: asset_to_fp(vault.amount, mint.decimals)?,
+                },
+            )?;
+        }
+        Ok(())
+    }
 
-        emit!(EpochFinalized { epoch_id: epoch.epoch_id });
+    /// Upgrade `owner`'s position, plus their claim receipts passed as remaining accounts
+    /// `(claim_receipt, epoch)` pairs, to the current layout. Permissionless; `payer` tops up
+    /// rent. Run after `migrate_state`: a legacy position's principal is added back into the
+    /// tranche totals and a legacy receipt is counted on its (migrated) epoch.
+    pub fn migrate_position<'info>(ctx: Context<'_, '_, 'info, 'info, MigratePosition<'info>>) -> Result<()> {
+        let payer = ctx.accounts.payer.to_account_info();
+        let system = ctx.accounts.system_program.to_account_info();
+        let owner = ctx.accounts.owner.key();
+        let bump = ctx.bumps.position;
+        let state = &mut ctx.accounts.state;
+        let yield_idx = (state.senior_yield_index_fp, state.junior_yield_index_fp);
+
+        let mut legacy = false;
+        let position = migrate_account::<UserPosition>(&ctx.accounts.position, &payer, &system, |v0| {
+            require_keys_eq!(v0.owner, owner, ErrorCode::Unauthorized);
+            let mut p = UserPosition::from(v0);
+            // Positions created before versioning never recorded their bump
+            p.bump = bump;
+            // Deposits were credited as withdrawable as well as locked in a lot; only what no
+            // lot still holds is withdrawable, and lots never hold more than the principal
+            trim_lots(&mut p.senior_lots, p.senior_deposited_fp);
+            trim_lots(&mut p.junior_lots, p.junior_deposited_fp);
+            p.senior_withdrawable_fp = p.senior_deposited_fp - lot_ring(&mut p.senior_lots).locked_fp();
+            p.junior_withdrawable_fp = p.junior_deposited_fp - lot_ring(&mut p.junior_lots).locked_fp();
+            sync_yield_debt(&mut p, yield_idx)?;
+            legacy = true;
+            Ok(p)
+        })?;
+        if legacy {
+            state.senior_total_fp = state.senior_total_fp.checked_add(position.senior_deposited_fp).ok_or_else(math_overflow)?;
+            state.junior_total_fp = state.junior_total_fp.checked_add(position.junior_deposited_fp).ok_or_else(math_overflow)?;
+        }
 
+        let pairs = ctx.remaining_accounts.chunks_exact(2);
+        require!(pairs.remainder().is_empty(), ErrorCode::InvalidRemainingAccounts);
+        for pair in pairs {
+            let (claim_info, epoch_info) = (&pair[0], &pair[1]);
+            let mut epoch = Account::<Epoch>::try_from(epoch_info)?;
+            let mut legacy = false;
+            let mut claim = migrate_account::<ClaimReceipt>(claim_info, &payer, &system, |v0| {
+                require_keys_eq!(v0.owner, owner, ErrorCode::Unauthorized);
+                legacy = true;
+                Ok(ClaimReceipt::from(v0))
+            })?;
+            // Receipts written before the payer was recorded were paid by their claimant
+            if claim.payer == Pubkey::default() {
+                claim.payer = claim.owner;
+                claim.try_serialize(&mut &mut claim_info.try_borrow_mut_data()?[..])?;
+            }
+            require!(claim.epoch_id == epoch.epoch_id, ErrorCode::InvalidRemainingAccounts);
+            if legacy {
+                epoch.open_receipts = epoch.open_receipts.checked_add(1).ok_or_else(math_overflow)?;
+                epoch.exit(&crate::ID)?;
+            }
+        }
         Ok(())
     }
 
@@ -534,14 +2027,24 @@ pub mod tail_risk_insurance_pool {
 
     pub fn pool_stats(ctx: Context<ViewPoolStats>) -> Result<PoolStats> {
         let s = &ctx.accounts.state;
-        let bal = vault_balance_fp(&ctx.accounts.vault_ata)?;
+        let liquid = pool_value_fp(&ctx.accounts.collateral_registry)?;
+        let bal = total_pool_value_fp(s, &ctx.accounts.collateral_registry)?;
         Ok(PoolStats {
             total_deposited_fp: s.total_deposited_fp,
             pool_balance_fp: bal,
+            liquid_balance_fp: liquid,
+            strategy_deployed_fp: s.strategy_deployed_fp,
+            yield_reserve_fp: s.yield_reserve_fp,
+            tracked_liabilities_fp: tracked_liabilities_fp(s),
             payout_policy: s.payout_policy,
             epoch_cap_fp: s.epoch_cap_fp,
             carryover_shortfall_fp: s.carryover_shortfall_fp,
             rolling_mode: s.rolling_mode,
+            current_epoch_id: s.current_epoch_id,
+            epoch_duration_secs: s.epoch_duration_secs,
+            shutdown: s.shutdown,
+            pause_flags: s.pause_flags,
+            claim_window_open: s.claim_window_open,
         })
     }
 
@@ -555,6 +2058,7 @@ pub mod tail_risk_insurance_pool {
             junior_withdrawable_fp: p.junior_withdrawable_fp,
             last_deposit_ts: p.last_deposit_ts,
             referrer: p.referrer,
+            yield_accrued_fp: p.yield_accrued_fp,
         })
     }
 
@@ -577,6 +2081,56 @@ pub mod tail_risk_insurance_pool {
         })
     }
 
+    /// The current and last finalized epoch ids and the finalized epochs still in the
+    /// history ring, newest first.
+    pub fn epoch_history_view(ctx: Context<ViewEpochHistory>) -> Result<EpochHistoryView> {
+        let h = &ctx.accounts.epoch_history;
+        let kept = (h.count as usize).min(EPOCH_HISTORY_LEN);
+        let records = (1..=kept).map(|i| h.records[(h.count as usize - i) % EPOCH_HISTORY_LEN]).collect();
+        Ok(EpochHistoryView {
+            current_epoch_id: ctx.accounts.state.current_epoch_id,
+            last_epoch_id: h.last_epoch_id,
+            last_end_ts: h.last_end_ts,
+            finalized_count: h.count,
+            records,
+        })
+    }
+
+    /// Cover capacity: tranche capital, cover outstanding on the current epoch, what is
+    /// still for sale and the annual premium rate at the current utilisation.
+    pub fn capacity_view(ctx: Context<ViewCapacity>) -> Result<CapacityView> {
+        let (s, book) = (&ctx.accounts.state, &ctx.accounts.cover_book);
+        let capacity = cover_capacity(s, book)?;
+        let marginal = quote_cover(s, book, 0, 0)?;
+        Ok(CapacityView {
+            capital_fp: s.senior_total_fp.saturating_add(s.junior_total_fp),
+            sold_fp: outstanding_cover_fp(s, book),
+            max_utilisation_bps: book.max_utilisation_bps,
+            max_cover_fp: capacity.max_fp,
+            available_fp: capacity.available_fp,
+            utilisation_bps: capacity.utilisation_bps,
+            marginal_rate_bps: marginal.rate_bps,
+            expected_loss_bps: marginal.expected_loss_bps,
+        })
+    }
+
+    /// Premium `buy_cover` would charge (rounded up to a base unit) for `notional_u64` of
+    /// cover lasting `duration_secs` on top of the cover outstanding now.
+    pub fn quote_premium(ctx: Context<ViewCapacity>, notional_u64: u64, duration_secs: u64) -> Result<PremiumQuote> {
+        let (s, book) = (&ctx.accounts.state, &ctx.accounts.cover_book);
+        let notional_fp = to_fp_u64(notional_u64)?;
+        let q = quote_cover(s, book, notional_fp, duration_secs)?;
+        Ok(PremiumQuote {
+            notional_fp,
+            duration_secs,
+            utilisation_bps: q.utilisation_bps,
+            expected_loss_bps: q.expected_loss_bps,
+            rate_bps: q.rate_bps,
+            premium_fp: to_fp_u64(from_fp_ceil_to_u64(q.premium_fp)?)?,
+            within_capacity: notional_fp <= cover_capacity(s, book)?.available_fp,
+        })
+    }
+
     pub fn quote_user_payout(ctx: Context<QuoteUserPayout>) -> Result<QuoteOut> {
         let s = &ctx.accounts.state;
         let e = &ctx.accounts.epoch;
@@ -584,52 +2138,62 @@ pub mod tail_risk_insurance_pool {
 
         require!(e.triggered, ErrorCode::EpochNotActive);
 
-        let bal = vault_balance_fp(&ctx.accounts.vault_ata)?;
-        let base_liab = mul_div_floor_u128(e.total_stake_snapshot_fp, e.severity_bps as u128, BPS_DENOM)?;
-        let liab_cap = if s.payout_policy == PayoutPolicy::EpochBounded as u8 {
-            core::cmp::min(base_liab, e.epoch_cap_fp)
-        } else {
-            base_liab
-        };
-        let max_liab = core::cmp::min(liab_cap, bal);
-
-        let eff_user = weighted_stake_fp(
-            p.senior_deposited_fp,
-            p.junior_deposited_fp,
-            s.tranche_weight_senior_bps as u128,
-            s.tranche_weight_junior_bps as u128,
-        )?;
-
-        let mut user_share = 0u128;
-        if e.total_stake_snapshot_fp > 0 && eff_user > 0 {
-            user_share = mul_div_floor_u128(max_liab, eff_user, e.total_stake_snapshot_fp)?;
-            if s.payout_policy == PayoutPolicy::Capped as u8 && e.user_cap_bps > 0 {
-                let cap = mul_div_floor_u128(eff_user, e.user_cap_bps as u128, BPS_DENOM)?;
-                user_share = core::cmp::min(user_share, cap);
-            }
-        }
-
-        Ok(QuoteOut { user_payout_fp: user_share, max_epoch_liability_fp: max_liab })
+        let bal = pool_value_fp(&ctx.accounts.collateral_registry)?;
+        let eff_user = covered_stake_fp(s, p, e.epoch_id)?;
+
+        let q = payout::quote_user_payout(
+            claim_base_fp(e),
+            e.severity_bps,
+            payout_policy(s)?,
+            e.epoch_cap_fp,
+            e.user_cap_bps,
+            bal,
+            eff_user,
+        )
+        .ok_or_else(math_overflow)?;
+
+        Ok(QuoteOut { user_payout_fp: q.user_payout_fp, max_epoch_liability_fp: q.max_epoch_liability_fp })
     }
 
-    pub fn quote_deposit(ctx: Context<ViewPoolStats>, amount_usdc: u64) -> Result<DepositQuote> {
+    pub fn quote_deposit(ctx: Context<QuoteDeposit>, amount: u64) -> Result<DepositQuote> {
         let s = &ctx.accounts.state;
-        let fp = to_fp_u64(amount_usdc)?;
-        let pf = mul_div_floor_u128(fp, s.protocol_fee_bps as u128, BPS_DENOM)?;
-        let rf = mul_div_floor_u128(fp, s.referral_fee_bps as u128, BPS_DENOM)?;
-        let net = fp.saturating_sub(pf.saturating_add(rf));
+        let reg = &ctx.accounts.collateral_registry;
+        let a = &reg.assets[collateral_index(reg, ctx.accounts.asset_mint.key())?];
+        require!(a.enabled, ErrorCode::UnsupportedCollateral);
+        let fp = asset_to_fp(amount, a.decimals)?;
+        let referrer = ctx.accounts.referrer_account.as_deref();
+        let (proto_bps, _) = deposit_fee_bps(s)?;
+        let split = split_fees(fp, proto_bps, deposit_referral_bps(s, referrer)?)?;
+
+        // Rates deferred to premiums / yield under the other models (the referrer's tier rate)
+        let (deferred_proto_bps, deferred_ref_bps) = if fee_model(s)? == fees::FeeModel::OnDeposit {
+            (0, 0)
+        } else {
+            (s.protocol_fee_bps, referrer.map_or(s.referral_fee_bps, |r| referrer_fee_bps(s, r)))
+        };
         Ok(DepositQuote {
-            net_fp: net,
-            protocol_fee_u64: from_fp_to_u64(pf)?,
-            referral_fee_u64: from_fp_to_u64(rf)?,
+            net_fp: apply_haircut(split.net_fp, a.haircut_bps)?,
+            protocol_fee_u64: fp_to_asset(split.protocol_fp, a.decimals)?,
+            referral_fee_u64: fp_to_asset(split.referral_fp, a.decimals)?,
+            fee_model: s.fee_model,
+            deferred_protocol_fee_bps: deferred_proto_bps,
+            deferred_referral_fee_bps: deferred_ref_bps,
+            performance_fee_bps: s.performance_fee_bps,
         })
     }
 
-    pub fn quote_withdraw(ctx: Context<ViewUserPosition>, amount_usdc: u64, tranche: u8) -> Result<WithdrawQuote> {
+    pub fn quote_withdraw(ctx: Context<QuoteWithdraw>, amount_usdc: u64, tranche: u8) -> Result<WithdrawQuote> {
         require!(tranche <= 1, ErrorCode::Unauthorized);
         let p = &ctx.accounts.position;
         let need_fp = to_fp_u64(amount_usdc)?;
-        let avail_fp = if tranche == 0 { p.senior_withdrawable_fp } else { p.junior_withdrawable_fp };
+        // Withdrawable balance plus every lot that has matured by now
+        let (mut lots, mut avail_fp) = if tranche == 0 {
+            (p.senior_lots.clone(), p.senior_withdrawable_fp)
+        } else {
+            (p.junior_lots.clone(), p.junior_withdrawable_fp)
+        };
+        let now = Clock::get()?.unix_timestamp;
+        lot_ring(&mut lots).mature_and_consume(ctx.accounts.state.lockup_secs, now, &mut avail_fp, &mut 0);
         Ok(WithdrawQuote {
             can_withdraw: avail_fp >= need_fp,
             available_fp: avail_fp,
@@ -656,7 +2220,7 @@ pub struct Initialize<'info> {
         bump,
         space = 8 + State::SIZE
     )]
-    pub state: Account<'info, State>,
+    pub state: Box<Account<'info, State>>,
 
     /// Program-owned vault ATA (authority = state)
     #[account(
@@ -677,12 +2241,86 @@ pub struct Initialize<'info> {
     )]
     pub oracle_list: Account<'info, OracleList>,
 
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// Collateral registry (USDC registered as entry 0)
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"collateral", crate::id().as_ref()],
+        bump,
+        space = 8 + CollateralRegistry::SIZE
+    )]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
 }
 
+#[derive(Accounts)]
+pub struct InitEpochHistory<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    /// Epoch `current_epoch_id`, if one was started before the registry existed
+    #[account(seeds = [b"epoch", state.current_epoch_id.to_le_bytes().as_ref()], bump = current_epoch.bump)]
+    pub current_epoch: Option<Account<'info, Epoch>>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"epoch_history", crate::id().as_ref()],
+        bump,
+        space = 8 + EpochHistory::SIZE
+    )]
+    pub epoch_history: Account<'info, EpochHistory>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitCoverBook<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"cover_book", crate::id().as_ref()],
+        bump,
+        space = 8 + CoverBook::SIZE
+    )]
+    pub cover_book: Account<'info, CoverBook>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCoverParams<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(mut, seeds = [b"cover_book", crate::id().as_ref()], bump = cover_book.bump)]
+    pub cover_book: Account<'info, CoverBook>,
+}
+
 #[derive(Accounts)]
 pub struct AdminOnly<'info> {
     #[account(mut)]
@@ -693,12 +2331,23 @@ pub struct AdminOnly<'info> {
         bump = state.bump,
         has_one = admin @ ErrorCode::Unauthorized
     )]
-    pub state: Account<'info, State>,
+    pub state: Box<Account<'info, State>>,
 }
 
 #[derive(Accounts)]
-#[instruction(epoch_id: u64)]
-pub struct StartEpoch<'info> {
+pub struct GuardianPause<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        constraint = authority.key() == state.guardian || authority.key() == state.admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct AddCollateral<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
     #[account(
@@ -706,11 +2355,62 @@ pub struct StartEpoch<'info> {
         bump = state.bump,
         has_one = admin @ ErrorCode::Unauthorized
     )]
-    pub state: Account<'info, State>,
+    pub state: Box<Account<'info, State>>,
+
+    pub mint: Account<'info, Mint>,
 
+    /// Program-owned vault ATA for the new asset (authority = state)
     #[account(
         init,
         payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = state
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AdminCollateral<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch_id: u64)]
+pub struct StartEpoch<'info> {
+    /// Admin, or anyone rolling the next epoch in rolling mode
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    /// Epoch `current_epoch_id` (rolling mode)
+    #[account(seeds = [b"epoch", state.current_epoch_id.to_le_bytes().as_ref()], bump = previous_epoch.bump)]
+    pub previous_epoch: Option<Account<'info, Epoch>>,
+
+    #[account(mut, seeds = [b"epoch_history", crate::id().as_ref()], bump = epoch_history.bump)]
+    pub epoch_history: Account<'info, EpochHistory>,
+
+    #[account(
+        init,
+        payer = payer,
         seeds = [b"epoch", epoch_id.to_le_bytes().as_ref()],
         bump,
         space = 8 + Epoch::SIZE
@@ -720,38 +2420,60 @@ pub struct StartEpoch<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct EndEpoch<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, Epoch>,
+
+    #[account(mut, seeds = [b"epoch_history", crate::id().as_ref()], bump = epoch_history.bump)]
+    pub epoch_history: Account<'info, EpochHistory>,
+}
+
 #[derive(Accounts)]
 pub struct DepositInsurance<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub usdc_mint: Account<'info, Mint>,
+    /// Collateral asset being moved (must be registered)
+    pub asset_mint: Account<'info, Mint>,
 
     #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
-    pub state: Account<'info, State>,
+    pub state: Box<Account<'info, State>>,
 
     /// Program-owned vault
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
+        associated_token::mint = asset_mint,
         associated_token::authority = state
     )]
     pub vault_ata: Account<'info, TokenAccount>,
 
-    /// User's USDC ATA (source)
+    /// User's ATA for the deposited asset (source)
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
+        associated_token::mint = asset_mint,
         associated_token::authority = user
     )]
     pub user_ata: Account<'info, TokenAccount>,
 
     /// Treasury ATA (destination for protocol fee)
-    #[account(mut)]
+    #[account(mut, constraint = protocol_treasury_ata.owner == state.protocol_treasury @ ErrorCode::Unauthorized)]
     pub protocol_treasury_ata: Account<'info, TokenAccount>,
 
-    /// Optional: Referrer ATA
+    /// Referrer account, required once the position has a referrer
     #[account(mut)]
-    pub referrer_ata: Option<Account<'info, TokenAccount>>,
+    pub referrer_account: Option<Account<'info, Referrer>>,
 
     #[account(
         init_if_needed,
@@ -762,6 +2484,23 @@ pub struct DepositInsurance<'info> {
     )]
     pub position: Account<'info, UserPosition>,
 
+    /// The user's referral attribution (never closed)
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referral", user.key().as_ref()],
+        bump,
+        space = 8 + Attribution::SIZE
+    )]
+    pub attribution: Box<Account<'info, Attribution>>,
+
+    /// Epoch `current_epoch_id`, required in rolling mode to track pending cover
+    #[account(mut, seeds = [b"epoch", state.current_epoch_id.to_le_bytes().as_ref()], bump = current_epoch.bump)]
+    pub current_epoch: Option<Account<'info, Epoch>>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
@@ -771,15 +2510,16 @@ pub struct DepositInsurance<'info> {
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub usdc_mint: Account<'info, Mint>,
+    /// Collateral asset being moved (must be registered)
+    pub asset_mint: Account<'info, Mint>,
 
-    #[account(seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
-    pub state: Account<'info, State>,
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
 
     /// Program-owned vault
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
+        associated_token::mint = asset_mint,
         associated_token::authority = state
     )]
     pub vault_ata: Account<'info, TokenAccount>,
@@ -787,7 +2527,7 @@ pub struct Withdraw<'info> {
     /// User ATA (destination)
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
+        associated_token::mint = asset_mint,
         associated_token::authority = user
     )]
     pub user_ata: Account<'info, TokenAccount>,
@@ -800,6 +2540,13 @@ pub struct Withdraw<'info> {
     )]
     pub position: Account<'info, UserPosition>,
 
+    /// Epoch `current_epoch_id`, required in rolling mode to track pending cover
+    #[account(mut, seeds = [b"epoch", state.current_epoch_id.to_le_bytes().as_ref()], bump = current_epoch.bump)]
+    pub current_epoch: Option<Account<'info, Epoch>>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
@@ -810,7 +2557,7 @@ pub struct TriggerEvent<'info> {
     pub admin_or_oracle: Signer<'info>,
 
     #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
-    pub state: Account<'info, State>,
+    pub state: Box<Account<'info, State>>,
 
     #[account(
         mut,
@@ -821,16 +2568,37 @@ pub struct TriggerEvent<'info> {
 
     #[account(seeds = [b"oracle", crate::id().as_ref()], bump = oracle_list.bump)]
     pub oracle_list: Account<'info, OracleList>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault, receives recalled strategy capital
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// CHECK: lending program, only required while capital is deployed (key checked on recall)
+    pub strategy_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: strategy signer PDA, only required while capital is deployed
+    #[account(seeds = [b"strategy", crate::id().as_ref()], bump)]
+    pub strategy_authority: Option<UncheckedAccount<'info>>,
+
+    /// Strategy escrow, only required while capital is deployed
+    #[account(mut, seeds = [b"strategy_escrow", crate::id().as_ref()], bump)]
+    pub strategy_escrow: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
 pub struct PayoutUser<'info> {
     #[account(mut)]
     pub user: Signer<'info>, // payer for claim
-    pub usdc_mint: Account<'info, Mint>,
+    /// Collateral asset being moved (must be registered)
+    pub asset_mint: Account<'info, Mint>,
 
-    #[account(seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
-    pub state: Account<'info, State>,
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
 
     #[account(
         mut,
@@ -842,79 +2610,693 @@ pub struct PayoutUser<'info> {
     /// Program-owned vault
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
+        associated_token::mint = asset_mint,
+        associated_token::authority = state
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// User ATA (destination)
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = user
+    )]
+    pub user_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"position", user.key().as_ref()], bump = position.bump)]
+    pub position: Account<'info, UserPosition>,
+
+    /// CHECK: claim receipt PDA, created once the payout is made (an existing one means
+    /// already claimed)
+    #[account(mut, seeds = [b"claim", epoch.epoch_id.to_le_bytes().as_ref(), user.key().as_ref()], bump)]
+    pub claim: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankPayouts<'info> {
+    /// Anyone; fronts the rent of the claim receipts it creates
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, Epoch>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// Cranker's base-asset token account (reward destination)
+    #[account(
+        mut,
+        token::mint = vault_ata.mint,
+        token::authority = cranker
+    )]
+    pub cranker_ata: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(root: [u8; 32], total_fp: u128, claimants: u32)]
+pub struct PostMerkleRoot<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, Epoch>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"bitmap", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + ClaimBitmap::size(claimants.min(MAX_MERKLE_CLAIMANTS))
+    )]
+    pub claim_bitmap: Account<'info, ClaimBitmap>,
+
+    #[account(seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithProof<'info> {
+    pub user: Signer<'info>,
+    /// Collateral asset being moved (must be registered)
+    pub asset_mint: Account<'info, Mint>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, Epoch>,
+
+    #[account(
+        mut,
+        seeds = [b"bitmap", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = claim_bitmap.bump
+    )]
+    pub claim_bitmap: Account<'info, ClaimBitmap>,
+
+    /// Program-owned vault
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = state
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// User ATA (destination)
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = user
+    )]
+    pub user_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeEpoch<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, Epoch>,
+
+    #[account(mut, seeds = [b"epoch_history", crate::id().as_ref()], bump = epoch_history.bump)]
+    pub epoch_history: Account<'info, EpochHistory>,
+
+    /// Program-owned vault
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
         associated_token::authority = state
     )]
-    pub vault_ata: Account<'info, TokenAccount>,
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// Treasury ATA for dust sweep
+    #[account(mut)]
+    pub protocol_treasury_ata: Account<'info, TokenAccount>,
+
+    /// Collateral asset being moved (must be registered)
+    pub asset_mint: Account<'info, Mint>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    #[account(
+        init,
+        payer = referrer,
+        seeds = [b"referrer", referrer.key().as_ref()],
+        bump,
+        space = 8 + Referrer::SIZE
+    )]
+    pub referrer_account: Account<'info, Referrer>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralRewards<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    /// Collateral asset to be paid in (must be registered)
+    pub asset_mint: Account<'info, Mint>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.key().as_ref()],
+        bump = referrer_account.bump,
+        constraint = referrer_account.owner == referrer.key() @ ErrorCode::Unauthorized
+    )]
+    pub referrer_account: Account<'info, Referrer>,
+
+    /// Program-owned vault
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = state
+    )]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// Referrer ATA (destination)
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = referrer
+    )]
+    pub referrer_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct StrategyOp<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// CHECK: external lending program, pinned to `state.strategy_program`
+    #[account(executable, address = state.strategy_program @ ErrorCode::StrategyNotConfigured)]
+    pub strategy_program: UncheckedAccount<'info>,
+
+    /// CHECK: PDA that signs strategy CPIs; it owns the escrow and no vault
+    #[account(seeds = [b"strategy", crate::id().as_ref()], bump)]
+    pub strategy_authority: UncheckedAccount<'info>,
+
+    /// Base USDC in flight to / from the strategy (authority = strategy_authority)
+    #[account(
+        init_if_needed,
+        payer = admin,
+        seeds = [b"strategy_escrow", crate::id().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = strategy_authority
+    )]
+    pub strategy_escrow: Box<Account<'info, TokenAccount>>,
+
+    #[account(address = state.usdc_mint)]
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimYield<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// User ATA (destination, base asset)
+    #[account(
+        mut,
+        token::mint = vault_ata.mint,
+        token::authority = user
+    )]
+    pub user_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"position", user.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub position: Account<'info, UserPosition>,
+
+    /// Referrer account, required when fee-on-yield credits the position's referrer
+    #[account(mut)]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct PayPremium<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// Payer's base-asset ATA (source)
+    #[account(
+        mut,
+        token::mint = vault_ata.mint,
+        token::authority = payer
+    )]
+    pub payer_ata: Account<'info, TokenAccount>,
+
+    /// Treasury ATA (destination for protocol fee)
+    #[account(mut, constraint = protocol_treasury_ata.owner == state.protocol_treasury @ ErrorCode::Unauthorized)]
+    pub protocol_treasury_ata: Account<'info, TokenAccount>,
+
+    /// Payer's position, if any: its referrer is the only one a premium can credit
+    #[account(seeds = [b"position", payer.key().as_ref()], bump = position.bump)]
+    pub position: Option<Account<'info, UserPosition>>,
+
+    /// Referrer account, required when the payer's position has a referrer
+    #[account(mut)]
+    pub referrer_account: Option<Account<'info, Referrer>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct BuyCover<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    /// Epoch `current_epoch_id`
+    #[account(mut, seeds = [b"epoch", state.current_epoch_id.to_le_bytes().as_ref()], bump = epoch.bump)]
+    pub epoch: Account<'info, Epoch>,
+
+    #[account(mut, seeds = [b"cover_book", crate::id().as_ref()], bump = cover_book.bump)]
+    pub cover_book: Account<'info, CoverBook>,
+
+    /// The cover's NFT: mint number `covers_minted` of the book
+    #[account(
+        init,
+        payer = buyer,
+        seeds = [b"cover_mint", cover_book.covers_minted.to_le_bytes().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = state
+    )]
+    pub cover_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = buyer,
+        seeds = [b"cover", cover_mint.key().as_ref()],
+        bump,
+        space = 8 + Cover::SIZE
+    )]
+    pub cover: Account<'info, Cover>,
+
+    /// Buyer's token account for the NFT
+    #[account(
+        init,
+        payer = buyer,
+        associated_token::mint = cover_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_nft: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// Buyer's base-asset ATA (source)
+    #[account(
+        mut,
+        token::mint = vault_ata.mint,
+        token::authority = buyer
+    )]
+    pub buyer_ata: Account<'info, TokenAccount>,
+
+    /// Treasury ATA (destination for protocol fee)
+    #[account(mut, constraint = protocol_treasury_ata.owner == state.protocol_treasury @ ErrorCode::Unauthorized)]
+    pub protocol_treasury_ata: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCover<'info> {
+    pub holder: Signer<'info>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(mut, seeds = [b"cover", cover.mint.as_ref()], bump = cover.bump)]
+    pub cover: Account<'info, Cover>,
+
+    /// Holder's token account with the cover's NFT
+    #[account(
+        token::mint = cover.mint,
+        token::authority = holder,
+        constraint = holder_nft.amount == 1 @ ErrorCode::NotCoverHolder
+    )]
+    pub holder_nft: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"epoch", cover.epoch_id.to_le_bytes().as_ref()], bump = epoch.bump)]
+    pub epoch: Account<'info, Epoch>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    /// Holder's base-asset account (destination)
+    #[account(
+        mut,
+        token::mint = vault_ata.mint,
+        token::authority = holder
+    )]
+    pub holder_ata: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(mut, seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+
+    /// Base (USDC) vault
+    #[account(mut, address = collateral_registry.assets[0].vault)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = vault_ata.mint,
+        constraint = protocol_treasury_ata.owner == state.protocol_treasury @ ErrorCode::Unauthorized
+    )]
+    pub protocol_treasury_ata: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// ----------------------------- view contexts -----------------------------
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"position", user.key().as_ref()],
+        bump = position.bump,
+        constraint = position.owner == user.key() @ ErrorCode::Unauthorized
+    )]
+    pub position: Account<'info, UserPosition>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCover<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+
+    /// Original purchaser (rent destination)
+    #[account(mut, address = cover.buyer @ ErrorCode::Unauthorized)]
+    pub buyer: SystemAccount<'info>,
+
+    #[account(mut, close = buyer, seeds = [b"cover", cover.mint.as_ref()], bump = cover.bump)]
+    pub cover: Account<'info, Cover>,
+
+    #[account(mut, address = cover.mint)]
+    pub cover_mint: Account<'info, Mint>,
+
+    /// Holder's token account with the cover's NFT (burnt, then closed)
+    #[account(
+        mut,
+        token::mint = cover.mint,
+        token::authority = holder,
+        constraint = holder_nft.amount == 1 @ ErrorCode::NotCoverHolder
+    )]
+    pub holder_nft: Account<'info, TokenAccount>,
+
+    /// CHECK: epoch the cover was sold on, read while it exists (`close_epoch` removes it)
+    #[account(seeds = [b"epoch", cover.epoch_id.to_le_bytes().as_ref()], bump)]
+    pub epoch: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CloseClaimReceipt<'info> {
+    #[account(
+        mut,
+        seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = epoch.bump
+    )]
+    pub epoch: Account<'info, Epoch>,
 
-    /// User ATA (destination)
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = user
+        close = payer,
+        seeds = [b"claim", epoch.epoch_id.to_le_bytes().as_ref(), claim.owner.as_ref()],
+        bump = claim.bump,
+        has_one = payer @ ErrorCode::Unauthorized
     )]
-    pub user_ata: Account<'info, TokenAccount>,
+    pub claim: Account<'info, ClaimReceipt>,
 
-    #[account(mut, seeds = [b"position", user.key().as_ref()], bump = position.bump)]
-    pub position: Account<'info, UserPosition>,
+    /// Whoever paid the receipt's rent (rent destination)
+    #[account(mut)]
+    pub payer: SystemAccount<'info>,
+}
 
+#[derive(Accounts)]
+pub struct Shutdown<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"claim", epoch.epoch_id.to_le_bytes().as_ref(), user.key().as_ref()],
-        bump,
-        space = 8 + ClaimReceipt::SIZE
+        mut,
+        seeds = [b"state", crate::id().as_ref()],
+        bump = state.bump,
+        has_one = admin @ ErrorCode::Unauthorized
     )]
-    pub claim: Account<'info, ClaimReceipt>,
+    pub state: Box<Account<'info, State>>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+    /// The epoch whose claim window is open, if any (settled by the shutdown)
+    #[account(mut, seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()], bump = epoch.bump)]
+    pub epoch: Option<Account<'info, Epoch>>,
+
+    /// Required with `epoch`
+    #[account(mut, seeds = [b"epoch_history", crate::id().as_ref()], bump = epoch_history.bump)]
+    pub epoch_history: Option<Account<'info, EpochHistory>>,
 }
 
 #[derive(Accounts)]
-pub struct FinalizeEpoch<'info> {
+pub struct CloseEpoch<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
     #[account(
-        mut,
         seeds = [b"state", crate::id().as_ref()],
         bump = state.bump,
         has_one = admin @ ErrorCode::Unauthorized
     )]
-    pub state: Account<'info, State>,
+    pub state: Box<Account<'info, State>>,
 
     #[account(
         mut,
+        close = admin,
         seeds = [b"epoch", epoch.epoch_id.to_le_bytes().as_ref()],
         bump = epoch.bump
     )]
     pub epoch: Account<'info, Epoch>,
 
-    /// Program-owned vault
+    /// Required once a Merkle root was posted for the epoch
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = state
+        close = admin,
+        seeds = [b"bitmap", epoch.epoch_id.to_le_bytes().as_ref()],
+        bump = claim_bitmap.bump
     )]
-    pub vault_ata: Account<'info, TokenAccount>,
+    pub claim_bitmap: Option<Account<'info, ClaimBitmap>>,
+}
 
-    /// Treasury ATA for dust sweep
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
     #[account(mut)]
-    pub protocol_treasury_ata: Account<'info, TokenAccount>,
+    pub admin: Signer<'info>,
+
+    /// CHECK: may hold a legacy layout; checked and deserialized in `migrate_account`
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump)]
+    pub state: UncheckedAccount<'info>,
+
+    /// CHECK: as `state`
+    #[account(mut, seeds = [b"oracle", crate::id().as_ref()], bump)]
+    pub oracle_list: UncheckedAccount<'info>,
+
+    // Created here for pools from before multi-collateral, with the base asset (checked
+    // against the migrated state) and its vault balance
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + CollateralRegistry::SIZE,
+        seeds = [b"collateral", crate::id().as_ref()],
+        bump
+    )]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
 
     pub usdc_mint: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
+    #[account(associated_token::mint = usdc_mint, associated_token::authority = state)]
+    pub vault_ata: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
 }
 
-// ----------------------------- view contexts -----------------------------
+#[derive(Accounts)]
+pub struct MigratePosition<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: position owner (seed only)
+    pub owner: UncheckedAccount<'info>,
+
+    /// CHECK: may hold a legacy layout; checked and deserialized in `migrate_account`
+    #[account(mut, seeds = [b"position", owner.key().as_ref()], bump)]
+    pub position: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"state", crate::id().as_ref()], bump = state.bump)]
+    pub state: Box<Account<'info, State>>,
+
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
 pub struct ViewPoolStats<'info> {
-    pub state: Account<'info, State>,
-    #[account(associated_token::mint = usdc_mint, associated_token::authority = state)]
-    pub vault_ata: Account<'info, TokenAccount>,
-    pub usdc_mint: Account<'info, Mint>,
+    pub state: Box<Account<'info, State>>,
+    #[account(seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteDeposit<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
+    pub asset_mint: Account<'info, Mint>,
+    /// Referrer the deposit would be attributed to, for its tier rate (none = unreferred)
+    pub referrer_account: Option<Account<'info, Referrer>>,
 }
 
 #[derive(Accounts)]
@@ -923,39 +3305,52 @@ pub struct ViewUserPosition<'info> {
     pub position: Account<'info, UserPosition>,
 }
 
+#[derive(Accounts)]
+pub struct QuoteWithdraw<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(seeds = [b"position", position.owner.as_ref()], bump = position.bump)]
+    pub position: Account<'info, UserPosition>,
+}
+
 #[derive(Accounts)]
 pub struct ViewEpochStats<'info> {
     pub epoch: Account<'info, Epoch>,
 }
 
+#[derive(Accounts)]
+pub struct ViewEpochHistory<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(seeds = [b"epoch_history", crate::id().as_ref()], bump = epoch_history.bump)]
+    pub epoch_history: Account<'info, EpochHistory>,
+}
+
+#[derive(Accounts)]
+pub struct ViewCapacity<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(seeds = [b"cover_book", crate::id().as_ref()], bump = cover_book.bump)]
+    pub cover_book: Account<'info, CoverBook>,
+}
+
 #[derive(Accounts)]
 pub struct QuoteUserPayout<'info> {
-    pub state: Account<'info, State>,
+    pub state: Box<Account<'info, State>>,
     pub epoch: Account<'info, Epoch>,
-    #[account(associated_token::mint = usdc_mint, associated_token::authority = state)]
-    pub vault_ata: Account<'info, TokenAccount>,
-    pub usdc_mint: Account<'info, Mint>,
+    #[account(seeds = [b"collateral", crate::id().as_ref()], bump = collateral_registry.bump)]
+    pub collateral_registry: Account<'info, CollateralRegistry>,
     pub position: Account<'info, UserPosition>,
 }
 
 // ---------------------------------------------------------------------------
-// State & Enums
+// State
 // ---------------------------------------------------------------------------
 
-#[repr(u8)]
-pub enum PayoutPolicy {
-    Proportional = 0,
-    Capped = 1,
-    EpochBounded = 2,
-}
-
 #[account]
 pub struct State {
     pub admin: Pubkey,
     pub usdc_mint: Pubkey,
     pub protocol_treasury: Pubkey,
 
-    pub paused: bool,
+    pub claim_window_open: bool, // set by trigger_event until finalize_epoch
     pub processing: bool, // reentrancy-style guard
     pub payout_policy: u8, // 0=Proportional,1=Capped,2=EpochBounded
 
@@ -966,6 +3361,13 @@ pub struct State {
     pub referral_fee_bps: u16,
     pub lockup_secs: i64,
     pub min_seconds_between_deposits: i64,
+    pub fee_model: u8,                    // 0=OnDeposit,1=OnPremium,2=OnYield
+    pub performance_fee_bps: u16,         // protocol cut of realised strategy yield
+    pub protocol_fees_accrued_fp: u128,   // fees owed to treasury, held in base vault
+
+    // Referrals (tier 0 = referral_fee_bps, tiers 1..=3 unlock by referred volume)
+    pub referral_tiers: [ReferralTier; MAX_REFERRAL_TIERS],
+    pub referral_rewards_owed_fp: u128,   // accrued, unclaimed referral rewards held in vaults
 
     // Epoch policy
     pub epoch_cap_fp: u128,
@@ -987,18 +3389,71 @@ pub struct State {
     pub total_deposited_fp: u128,
     pub carryover_shortfall_fp: u128,
 
+    // Idle-capital strategy
+    pub strategy_program: Pubkey,         // default = no strategy configured
+    pub strategy_max_bps: u16,            // max share of base vault deployable
+    pub strategy_junior_yield_bps: u16,   // junior share of realised yield
+    pub strategy_deployed_fp: u128,       // principal currently deployed (base asset)
+
+    // Tranche totals & yield indices
+    pub senior_total_fp: u128,
+    pub junior_total_fp: u128,
+    pub senior_yield_index_fp: u128,      // cumulative yield per unit of principal (SCALE)
+    pub junior_yield_index_fp: u128,
+    pub yield_reserve_fp: u128,           // realised yield owed to LPs, held in base vault
+
     pub bump: u8,
+    // Layout version (0 = written before versioning); new fields are carved out of `reserved`
+    pub version: u8,
+
+    // Shutdown (permanent; LPs exit through emergency_withdraw)
+    pub shutdown: bool,
+    pub shutdown_ts: i64,
+
+    // Pause control (PAUSE_* bits; the guardian can pause but not resume)
+    pub guardian: Pubkey,
+    pub pause_flags: u8,
+
+    // Outflow circuit breaker (limits; 0 = off) and its sliding window
+    pub outflow_window_secs: i64,
+    pub outflow_cap_fp: u128,
+    pub outflow_cap_bps: u16,             // of liquid vault value before the outflows counted
+    pub outflow_window_updated_ts: i64,
+    pub outflow_window_base_fp: u128,
+    pub outflow_window_used_fp: u128,
+    pub crank_reward_u64: u64,            // base units per claimant paid by crank_payouts
+
+    // Rolling epochs (start_epoch): last epoch started and the fixed length in rolling mode
+    pub current_epoch_id: u64,
+    pub epoch_duration_secs: u32,
+
+    // Cover sold on the epoch in its claim window and not claimed yet (notional x severity)
+    pub cover_owed_fp: u128,
+
+    pub reserved: [u8; STATE_RESERVED],
 }
 impl State {
     pub const SIZE: usize =
         32 + 32 + 32 +
         1 + 1 + 1 +
         16 + 16 + 2 + 2 + 8 + 8 +
+        1 + 2 + 16 +
+        (MAX_REFERRAL_TIERS * ReferralTier::SIZE) + 16 +
         16 + 1 + 8 +
         16 + 16 + 16 + 2 +
         2 + 2 +
         8 + 16 + 16 +
-        1;
+        32 + 2 + 2 + 16 +
+        16 + 16 + 16 + 16 + 16 +
+        1 +
+        1 +
+        1 + 8 +
+        32 + 1 +
+        8 + 16 + 2 + 8 + 16 + 16 +
+        8 +
+        8 + 4 +
+        16 +
+        STATE_RESERVED;
 }
 
 #[account]
@@ -1015,13 +3470,32 @@ pub struct UserPosition {
 
     pub last_deposit_ts: i64,
     pub referrer: Pubkey,
+
+    // Strategy yield (reward-debt accounting against State yield indices)
+    pub senior_yield_debt_fp: u128,
+    pub junior_yield_debt_fp: u128,
+    pub yield_accrued_fp: u128,
+
     pub bump: u8,
+    pub version: u8,
+
+    // Principal deposited during rolling epoch `pending_epoch_id`, covered from the next one
+    pub pending_epoch_id: u64,
+    pub pending_senior_fp: u128,
+    pub pending_junior_fp: u128,
+
+    pub reserved: [u8; POSITION_RESERVED],
 }
 impl UserPosition {
     pub const SIZE: usize =
         32 + 16 + 16 + 16 + 16 +
         Lots::SIZE + Lots::SIZE +
-        8 + 32 + 1;
+        8 + 32 +
+        16 + 16 + 16 +
+        1 +
+        1 +
+        8 + 16 + 16 +
+        POSITION_RESERVED;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
@@ -1044,7 +3518,7 @@ impl Lots {
 pub struct Epoch {
     pub epoch_id: u64,
     pub start_ts: i64,
-    pub end_ts: i64, // 0 = open/rolling
+    pub end_ts: i64, // 0 = open-ended
     pub total_stake_snapshot_fp: u128,
     pub total_payout_fp: u128,
     pub shortfall_fp: u128,
@@ -1062,7 +3536,29 @@ pub struct Epoch {
     pub evidence_hash: [u8; 32],
     pub evidence_ts: i64,
 
+    // Account closing: receipts close CLAIM_RETENTION_SECS after finalize, then the epoch
+    pub finalized_ts: i64,
+    pub open_receipts: u32,
+
     pub bump: u8,
+    pub version: u8,
+
+    // Merkle settlement (post_merkle_root); a zero root means payouts are computed on chain
+    pub merkle_root: [u8; 32],
+    pub merkle_total_fp: u128,
+
+    // Rolling mode: principal deposited while this epoch runs, left out of its snapshot
+    pub pending_deposits_fp: u128,
+
+    // Cover sold on this epoch and the notional claimed. Claims share the epoch liability with
+    // depositors pro rata (notional next to the stake snapshot); `cover_paid_fp` is their part
+    // of `total_payout_fp`
+    pub cover_sold_fp: u128,
+    pub cover_claimed_fp: u128,
+    pub cover_paid_fp: u128,
+    pub triggered_ts: i64,
+
+    pub reserved: [u8; EPOCH_RESERVED],
 }
 impl Epoch {
     pub const SIZE: usize =
@@ -1070,7 +3566,13 @@ impl Epoch {
         2 + 2 + 16 +
         1 + 1 +
         32 + 8 +
-        1;
+        8 + 4 +
+        1 +
+        1 +
+        32 + 16 +
+        16 +
+        16 + 16 + 16 + 8 +
+        EPOCH_RESERVED;
 }
 
 #[account]
@@ -1079,9 +3581,103 @@ pub struct ClaimReceipt {
     pub owner: Pubkey,
     pub claimed_fp: u128,
     pub bump: u8,
+    pub version: u8,
+
+    // Who paid the rent (the claimant, or a cranker), refunded by close_claim_receipt
+    pub payer: Pubkey,
+
+    pub reserved: [u8; CLAIM_RESERVED],
 }
 impl ClaimReceipt {
-    pub const SIZE: usize = 8 + 32 + 16 + 1;
+    pub const SIZE: usize = 8 + 32 + 16 + 1 + 1 + 32 + CLAIM_RESERVED;
+}
+
+/// One bit per leaf of a Merkle-settled epoch, set when that leaf is claimed. The bitmap is
+/// sized at creation, so it follows `version` / `reserved` instead of preceding them.
+#[account]
+pub struct ClaimBitmap {
+    pub epoch_id: u64,
+    pub claimants: u32,
+    pub claimed: u32,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; BITMAP_RESERVED],
+    pub bits: Vec<u8>,
+}
+impl ClaimBitmap {
+    pub fn size(claimants: u32) -> usize {
+        8 + 4 + 4 + 1 + 1 + BITMAP_RESERVED + 4 + merkle::bitmap_len(claimants)
+    }
+}
+
+/// Epoch registry: where the latest epoch's cover ends, so the next cannot overlap it, and
+/// a ring of the last `EPOCH_HISTORY_LEN` finalized epochs.
+#[account]
+pub struct EpochHistory {
+    pub last_epoch_id: u64, // last finalized
+    pub last_end_ts: i64,   // cover of the latest started epoch ends (i64::MAX while an open-ended one runs)
+    pub count: u32,         // epochs recorded so far; record `count - 1` is the newest
+    pub records: [EpochRecord; EPOCH_HISTORY_LEN],
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; HISTORY_RESERVED],
+}
+impl EpochHistory {
+    pub const SIZE: usize = 8 + 8 + 4 + (EPOCH_HISTORY_LEN * EpochRecord::SIZE) + 1 + 1 + HISTORY_RESERVED;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug)]
+pub struct EpochRecord {
+    pub epoch_id: u64,
+    pub severity_bps: u16,
+    pub total_payout_fp: u128,
+    pub shortfall_fp: u128,
+}
+impl EpochRecord {
+    pub const SIZE: usize = 8 + 2 + 16 + 16;
+}
+
+/// Cover sold to protection buyers on the current epoch, and its limit and price.
+#[account]
+pub struct CoverBook {
+    pub max_utilisation_bps: u16, // cover outstanding per unit of tranche capital
+    pub base_rate_bps: u16,       // annual, at zero utilisation
+    pub epoch_id: u64,            // epoch `sold_fp` was sold on
+    pub sold_fp: u128,
+    pub bump: u8,
+    pub version: u8,
+
+    // Premium curve (annual rates): utilisation kink and slopes, and the expected loss of a
+    // trigger at `reference_input_bps` on the severity curve, `event_probability_bps` a year
+    pub kink_utilisation_bps: u16,
+    pub slope1_bps: u16,
+    pub slope2_bps: u16,
+    pub event_probability_bps: u16,
+    pub reference_input_bps: u16,
+
+    pub covers_minted: u64, // cover NFTs minted; the next is `["cover_mint", covers_minted]`
+
+    pub reserved: [u8; COVER_BOOK_RESERVED],
+}
+impl CoverBook {
+    pub const SIZE: usize = 2 + 2 + 8 + 16 + 1 + 1 + 2 * 5 + 8 + COVER_BOOK_RESERVED;
+}
+
+/// One cover purchase on one epoch (`["cover", mint]`), owned by whoever holds its NFT.
+#[account]
+pub struct Cover {
+    pub mint: Pubkey,  // the cover's NFT
+    pub buyer: Pubkey, // original purchaser
+    pub epoch_id: u64,
+    pub notional_fp: u128,
+    pub premium_fp: u128,
+    pub claimed_fp: u128,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; COVER_RESERVED],
+}
+impl Cover {
+    pub const SIZE: usize = 32 + 32 + 8 + 16 + 16 + 16 + 1 + 1 + COVER_RESERVED;
 }
 
 #[account]
@@ -1090,9 +3686,337 @@ pub struct OracleList {
     pub count: u8,
     pub keys: [Pubkey; MAX_ORACLES],
     pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ORACLE_RESERVED],
 }
 impl OracleList {
-    pub const SIZE: usize = 1 + 1 + (MAX_ORACLES * 32) + 1;
+    pub const SIZE: usize = 1 + 1 + (MAX_ORACLES * 32) + 1 + 1 + ORACLE_RESERVED;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug)]
+pub struct ReferralTier {
+    pub min_volume_fp: u128, // referred deposit volume needed (0 = tier unused)
+    pub fee_bps: u16,
+}
+impl ReferralTier {
+    pub const SIZE: usize = 16 + 2;
+}
+
+#[account]
+pub struct Referrer {
+    pub owner: Pubkey,
+    pub total_referred_fp: u128,   // deposit volume from referred positions
+    pub lifetime_rewards_fp: u128,
+    pub pending_rewards_fp: u128,  // claimable via claim_referral_rewards
+    pub tier: u8,
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; REFERRER_RESERVED],
+}
+impl Referrer {
+    pub const SIZE: usize = 32 + 16 + 16 + 16 + 1 + 1 + 1 + REFERRER_RESERVED;
+}
+
+/// A user's first-touch referrer. Kept apart from `UserPosition` and never closed, so
+/// closing and reopening a position cannot re-attribute it.
+#[account]
+pub struct Attribution {
+    pub owner: Pubkey,
+    pub referrer: Pubkey, // default = unreferred
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; ATTRIBUTION_RESERVED],
+}
+impl Attribution {
+    pub const SIZE: usize = 32 + 32 + 1 + 1 + ATTRIBUTION_RESERVED;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct CollateralAsset {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub decimals: u8,
+    pub haircut_bps: u16,           // valuation discount applied on deposit
+    pub concentration_cap_bps: u16, // max share of pool value (10_000 = uncapped)
+    pub enabled: bool,              // false = no new deposits (exits still allowed)
+    pub balance_fp: u128,           // par amount held in vault, common units
+}
+impl CollateralAsset {
+    pub const SIZE: usize = 32 + 32 + 1 + 2 + 2 + 1 + 16;
+}
+
+#[account]
+pub struct CollateralRegistry {
+    pub count: u8,
+    pub assets: [CollateralAsset; MAX_COLLATERALS], // [0] = state.usdc_mint
+    pub bump: u8,
+    pub version: u8,
+    pub reserved: [u8; COLLATERAL_RESERVED],
+}
+impl CollateralRegistry {
+    pub const SIZE: usize = 1 + (MAX_COLLATERALS * CollateralAsset::SIZE) + 1 + 1 + COLLATERAL_RESERVED;
+}
+
+// ---------------------------------------------------------------------------
+// Layouts written before versioning (decoded by migrate_state / migrate_position)
+// ---------------------------------------------------------------------------
+
+#[derive(AnchorDeserialize)]
+struct StateV0 {
+    admin: Pubkey,
+    usdc_mint: Pubkey,
+    protocol_treasury: Pubkey,
+    _paused: bool, // set by trigger_event until finalize, and by the admin
+    processing: bool,
+    payout_policy: u8,
+    user_deposit_cap_fp: u128,
+    min_deposit_fp: u128,
+    protocol_fee_bps: u16,
+    referral_fee_bps: u16,
+    lockup_secs: i64,
+    min_seconds_between_deposits: i64,
+    epoch_cap_fp: u128,
+    rolling_mode: bool,
+    max_stale_secs: i64,
+    sev_quad_a_fp: u128,
+    sev_quad_b_fp: u128,
+    sev_quad_c_fp: u128,
+    severity_floor_bps: u16,
+    tranche_weight_senior_bps: u16,
+    tranche_weight_junior_bps: u16,
+    last_event_ts: i64,
+    total_deposited_fp: u128,
+    carryover_shortfall_fp: u128,
+    bump: u8,
+}
+impl StateV0 {
+    const SIZE: usize =
+        32 + 32 + 32 +
+        1 + 1 + 1 +
+        16 + 16 + 2 + 2 + 8 + 8 +
+        16 + 1 + 8 +
+        16 + 16 + 16 + 2 +
+        2 + 2 +
+        8 + 16 + 16 +
+        1;
+}
+
+// Fees were charged on deposit and paid out at once; `paused` is replaced by the claim window
+// (set from the migrated epochs) and the pause mask, and the tranche totals are rebuilt by
+// migrate_position
+impl From<StateV0> for State {
+    fn from(v0: StateV0) -> Self {
+        State {
+            admin: v0.admin,
+            usdc_mint: v0.usdc_mint,
+            protocol_treasury: v0.protocol_treasury,
+            claim_window_open: false,
+            processing: v0.processing,
+            payout_policy: v0.payout_policy,
+            user_deposit_cap_fp: v0.user_deposit_cap_fp,
+            min_deposit_fp: v0.min_deposit_fp,
+            protocol_fee_bps: v0.protocol_fee_bps,
+            referral_fee_bps: v0.referral_fee_bps,
+            lockup_secs: v0.lockup_secs,
+            min_seconds_between_deposits: v0.min_seconds_between_deposits,
+            fee_model: fees::FeeModel::OnDeposit as u8,
+            performance_fee_bps: 0,
+            protocol_fees_accrued_fp: 0,
+            referral_tiers: [ReferralTier::default(); MAX_REFERRAL_TIERS],
+            referral_rewards_owed_fp: 0,
+            epoch_cap_fp: v0.epoch_cap_fp,
+            rolling_mode: v0.rolling_mode,
+            max_stale_secs: v0.max_stale_secs,
+            sev_quad_a_fp: v0.sev_quad_a_fp,
+            sev_quad_b_fp: v0.sev_quad_b_fp,
+            sev_quad_c_fp: v0.sev_quad_c_fp,
+            severity_floor_bps: v0.severity_floor_bps,
+            tranche_weight_senior_bps: v0.tranche_weight_senior_bps,
+            tranche_weight_junior_bps: v0.tranche_weight_junior_bps,
+            last_event_ts: v0.last_event_ts,
+            total_deposited_fp: v0.total_deposited_fp,
+            carryover_shortfall_fp: v0.carryover_shortfall_fp,
+            strategy_program: Pubkey::default(),
+            strategy_max_bps: 0,
+            strategy_junior_yield_bps: 0,
+            strategy_deployed_fp: 0,
+            senior_total_fp: 0,
+            junior_total_fp: 0,
+            senior_yield_index_fp: 0,
+            junior_yield_index_fp: 0,
+            yield_reserve_fp: 0,
+            bump: v0.bump,
+            version: ACCOUNT_VERSION,
+            shutdown: false,
+            shutdown_ts: 0,
+            guardian: Pubkey::default(),
+            pause_flags: 0,
+            outflow_window_secs: 0,
+            outflow_cap_fp: 0,
+            outflow_cap_bps: 0,
+            outflow_window_updated_ts: 0,
+            outflow_window_base_fp: 0,
+            outflow_window_used_fp: 0,
+            crank_reward_u64: 0,
+            current_epoch_id: 0,
+            epoch_duration_secs: 0,
+            cover_owed_fp: 0,
+            reserved: [0; STATE_RESERVED],
+        }
+    }
+}
+
+#[derive(AnchorDeserialize)]
+struct UserPositionV0 {
+    owner: Pubkey,
+    senior_deposited_fp: u128,
+    junior_deposited_fp: u128,
+    senior_withdrawable_fp: u128,
+    junior_withdrawable_fp: u128,
+    senior_lots: Lots,
+    junior_lots: Lots,
+    last_deposit_ts: i64,
+    referrer: Pubkey,
+    bump: u8, // never set by deposit_insurance
+}
+impl UserPositionV0 {
+    const SIZE: usize =
+        32 + 16 + 16 + 16 + 16 +
+        Lots::SIZE + Lots::SIZE +
+        8 + 32 + 1;
+}
+
+impl From<UserPositionV0> for UserPosition {
+    fn from(v0: UserPositionV0) -> Self {
+        UserPosition {
+            owner: v0.owner,
+            senior_deposited_fp: v0.senior_deposited_fp,
+            junior_deposited_fp: v0.junior_deposited_fp,
+            senior_withdrawable_fp: v0.senior_withdrawable_fp,
+            junior_withdrawable_fp: v0.junior_withdrawable_fp,
+            senior_lots: v0.senior_lots,
+            junior_lots: v0.junior_lots,
+            last_deposit_ts: v0.last_deposit_ts,
+            referrer: v0.referrer,
+            senior_yield_debt_fp: 0,
+            junior_yield_debt_fp: 0,
+            yield_accrued_fp: 0,
+            bump: v0.bump,
+            version: ACCOUNT_VERSION,
+            pending_epoch_id: 0,
+            pending_senior_fp: 0,
+            pending_junior_fp: 0,
+            reserved: [0; POSITION_RESERVED],
+        }
+    }
+}
+
+#[derive(AnchorDeserialize)]
+struct EpochV0 {
+    epoch_id: u64,
+    start_ts: i64,
+    end_ts: i64,
+    total_stake_snapshot_fp: u128,
+    total_payout_fp: u128,
+    shortfall_fp: u128,
+    severity_bps: u16,
+    user_cap_bps: u16,
+    epoch_cap_fp: u128,
+    triggered: bool,
+    closed: bool,
+    evidence_hash: [u8; 32],
+    evidence_ts: i64,
+    bump: u8,
+}
+impl EpochV0 {
+    const SIZE: usize =
+        8 + 8 + 8 + 16 + 16 + 16 +
+        2 + 2 + 16 +
+        1 + 1 +
+        32 + 8 +
+        1;
+}
+
+// Receipts are counted in `open_receipts` as migrate_position migrates them
+impl From<EpochV0> for Epoch {
+    fn from(v0: EpochV0) -> Self {
+        Epoch {
+            epoch_id: v0.epoch_id,
+            start_ts: v0.start_ts,
+            end_ts: v0.end_ts,
+            total_stake_snapshot_fp: v0.total_stake_snapshot_fp,
+            total_payout_fp: v0.total_payout_fp,
+            shortfall_fp: v0.shortfall_fp,
+            severity_bps: v0.severity_bps,
+            user_cap_bps: v0.user_cap_bps,
+            epoch_cap_fp: v0.epoch_cap_fp,
+            triggered: v0.triggered,
+            closed: v0.closed,
+            evidence_hash: v0.evidence_hash,
+            evidence_ts: v0.evidence_ts,
+            finalized_ts: 0,
+            open_receipts: 0,
+            bump: v0.bump,
+            version: ACCOUNT_VERSION,
+            merkle_root: [0; 32],
+            merkle_total_fp: 0,
+            pending_deposits_fp: 0,
+            cover_sold_fp: 0,
+            cover_claimed_fp: 0,
+            cover_paid_fp: 0,
+            triggered_ts: 0,
+            reserved: [0; EPOCH_RESERVED],
+        }
+    }
+}
+
+#[derive(AnchorDeserialize)]
+struct ClaimReceiptV0 {
+    epoch_id: u64,
+    owner: Pubkey,
+    claimed_fp: u128,
+    bump: u8,
+}
+impl ClaimReceiptV0 {
+    const SIZE: usize = 8 + 32 + 16 + 1;
+}
+
+impl From<ClaimReceiptV0> for ClaimReceipt {
+    fn from(v0: ClaimReceiptV0) -> Self {
+        ClaimReceipt {
+            epoch_id: v0.epoch_id,
+            owner: v0.owner,
+            claimed_fp: v0.claimed_fp,
+            bump: v0.bump,
+            version: ACCOUNT_VERSION,
+            payer: v0.owner,
+            reserved: [0; CLAIM_RESERVED],
+        }
+    }
+}
+
+#[derive(AnchorDeserialize)]
+struct OracleListV0 {
+    enabled: bool,
+    count: u8,
+    keys: [Pubkey; MAX_ORACLES],
+    bump: u8, // never set by initialize
+}
+impl OracleListV0 {
+    const SIZE: usize = 1 + 1 + (MAX_ORACLES * 32) + 1;
+}
+
+impl From<OracleListV0> for OracleList {
+    fn from(v0: OracleListV0) -> Self {
+        OracleList {
+            enabled: v0.enabled,
+            count: v0.count,
+            keys: v0.keys,
+            bump: v0.bump,
+            version: ACCOUNT_VERSION,
+            reserved: [0; ORACLE_RESERVED],
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -1103,17 +4027,23 @@ impl OracleList {
 pub struct Initialized { pub admin: Pubkey, pub usdc_mint: Pubkey }
 
 #[event]
-pub struct Deposited { pub owner: Pubkey, pub amount_fp: u128, pub referrer: Pubkey, pub tranche: u8 }
+pub struct Deposited { pub owner: Pubkey, pub amount_fp: u128, pub referrer: Pubkey, pub tranche: u8, pub mint: Pubkey }
 
 #[event]
 pub struct ProtocolFeeTaken { pub amount_u64: u64 }
 
 #[event]
-pub struct ReferralFeeTaken { pub amount_u64: u64, pub referrer: Pubkey }
+pub struct ReferralRewardAccrued { pub referrer: Pubkey, pub amount_fp: u128, pub tier: u8 }
+
+#[event]
+pub struct ReferralRewardsClaimed { pub referrer: Pubkey, pub amount_fp: u128, pub mint: Pubkey }
 
 #[event]
 pub struct EpochStarted { pub epoch_id: u64, pub start_ts: i64, pub end_ts: i64 }
 
+#[event]
+pub struct EpochEnded { pub epoch_id: u64, pub end_ts: i64 }
+
 #[event]
 pub struct EventTriggered {
     pub epoch_id: u64,
@@ -1124,16 +4054,73 @@ pub struct EventTriggered {
 }
 
 #[event]
-pub struct UserPayout { pub epoch_id: u64, pub owner: Pubkey, pub payout_fp: u128 }
+pub struct UserPayout { pub epoch_id: u64, pub owner: Pubkey, pub payout_fp: u128, pub mint: Pubkey }
+
+#[event]
+pub struct Withdrawn { pub owner: Pubkey, pub amount_fp: u128, pub tranche: u8, pub mint: Pubkey }
+
+#[event]
+pub struct EpochFinalized { pub epoch_id: u64 }
+
+#[event]
+pub struct Paused { pub flags: u8 }
+
+#[event]
+pub struct GuardianUpdated { pub guardian: Pubkey }
+
+#[event]
+pub struct StrategyDeployed { pub amount_u64: u64, pub deployed_fp: u128 }
+
+#[event]
+pub struct StrategyRecalled { pub received_u64: u64, pub yield_fp: u128, pub fee_fp: u128, pub loss_fp: u128, pub deployed_fp: u128 }
+
+#[event]
+pub struct PremiumPaid {
+    pub payer: Pubkey,
+    pub amount_fp: u128,
+    pub net_fp: u128,
+    pub protocol_fee_u64: u64,
+    pub referral_fee_fp: u128,
+}
+
+#[event]
+pub struct YieldClaimed { pub owner: Pubkey, pub amount_fp: u128 }
+
+#[event]
+pub struct PositionClosed { pub owner: Pubkey }
+
+#[event]
+pub struct ClaimReceiptClosed { pub epoch_id: u64, pub owner: Pubkey }
+
+#[event]
+pub struct EpochClosed { pub epoch_id: u64 }
+
+#[event]
+pub struct PoolShutdown { pub shutdown_ts: i64 }
+
+#[event]
+pub struct EmergencyWithdrawn { pub owner: Pubkey, pub senior_fp: u128, pub junior_fp: u128, pub amount_fp: u128, pub mint: Pubkey }
+
+#[event]
+pub struct OutflowLimitTripped { pub window_updated_ts: i64, pub used_fp: u128, pub attempted_fp: u128, pub limit_fp: u128 }
+
+#[event]
+pub struct PayoutsCranked { pub epoch_id: u64, pub cranker: Pubkey, pub paid: u32, pub skipped: u32, pub reward_u64: u64 }
+
+#[event]
+pub struct MerkleRootPosted { pub epoch_id: u64, pub root: [u8; 32], pub total_fp: u128, pub claimants: u32 }
 
 #[event]
-pub struct Withdrawn { pub owner: Pubkey, pub amount_fp: u128, pub tranche: u8 }
+pub struct CollateralUpdated { pub mint: Pubkey, pub haircut_bps: u16, pub concentration_cap_bps: u16, pub enabled: bool }
 
 #[event]
-pub struct EpochFinalized { pub epoch_id: u64 }
+pub struct CoverPurchased { pub epoch_id: u64, pub mint: Pubkey, pub buyer: Pubkey, pub notional_fp: u128, pub premium_fp: u128 }
+
+#[event]
+pub struct CoverClaimed { pub epoch_id: u64, pub mint: Pubkey, pub holder: Pubkey, pub payout_fp: u128 }
 
 #[event]
-pub struct Paused { pub paused: bool }
+pub struct CoverClosed { pub epoch_id: u64, pub mint: Pubkey, pub buyer: Pubkey }
 
 // ---------------------------------------------------------------------------
 // Return types for view/quote
@@ -1143,10 +4130,19 @@ pub struct Paused { pub paused: bool }
 pub struct PoolStats {
     pub total_deposited_fp: u128,
     pub pool_balance_fp: u128,
+    pub liquid_balance_fp: u128,
+    pub strategy_deployed_fp: u128,
+    pub yield_reserve_fp: u128,
+    pub tracked_liabilities_fp: u128,
     pub payout_policy: u8,
     pub epoch_cap_fp: u128,
     pub carryover_shortfall_fp: u128,
     pub rolling_mode: bool,
+    pub current_epoch_id: u64,
+    pub epoch_duration_secs: u32,
+    pub shutdown: bool,
+    pub pause_flags: u8,
+    pub claim_window_open: bool,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -1158,6 +4154,7 @@ pub struct UserPositionView {
     pub junior_withdrawable_fp: u128,
     pub last_deposit_ts: i64,
     pub referrer: Pubkey,
+    pub yield_accrued_fp: u128,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -1177,6 +4174,38 @@ pub struct EpochStats {
     pub evidence_ts: i64,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct EpochHistoryView {
+    pub current_epoch_id: u64,
+    pub last_epoch_id: u64,
+    pub last_end_ts: i64,
+    pub finalized_count: u32,
+    pub records: Vec<EpochRecord>, // newest first
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CapacityView {
+    pub capital_fp: u128,       // senior + junior principal
+    pub sold_fp: u128,          // cover outstanding on the current epoch
+    pub max_utilisation_bps: u16,
+    pub max_cover_fp: u128,
+    pub available_fp: u128,
+    pub utilisation_bps: u128,
+    pub marginal_rate_bps: u128, // annual, for the next unit of cover
+    pub expected_loss_bps: u128, // annual, included in the rate
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PremiumQuote {
+    pub notional_fp: u128,
+    pub duration_secs: u64,
+    pub utilisation_bps: u128, // after the purchase
+    pub expected_loss_bps: u128,
+    pub rate_bps: u128, // annual, averaged from the utilisation before the purchase to after it
+    pub premium_fp: u128,
+    pub within_capacity: bool,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct QuoteOut {
     pub user_payout_fp: u128,
@@ -1185,9 +4214,13 @@ pub struct QuoteOut {
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct DepositQuote {
-    pub net_fp: u128,
-    pub protocol_fee_u64: u64,
+    pub net_fp: u128,                // credited: after fees, then the asset haircut
+    pub protocol_fee_u64: u64,       // charged now (fee-on-deposit only)
     pub referral_fee_u64: u64,
+    pub fee_model: u8,
+    pub deferred_protocol_fee_bps: u16, // charged on premiums / yield instead
+    pub deferred_referral_fee_bps: u16,
+    pub performance_fee_bps: u16,       // on realised strategy yield, any model
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -1211,6 +4244,8 @@ pub struct InitializeParams {
     pub referral_fee_bps: u16,
     pub lockup_secs: i64,
     pub min_seconds_between_deposits: i64,
+    pub fee_model: u8,
+    pub performance_fee_bps: u16,
 
     pub epoch_cap_fp: u128,
     pub rolling_mode: bool,
@@ -1225,104 +4260,721 @@ pub struct InitializeParams {
     pub tranche_weight_junior_bps: u16,
 }
 
+/// `CoverBook` limit and premium curve (`init_cover_book` / `set_cover_params`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CoverParams {
+    pub max_utilisation_bps: u16,
+    pub base_rate_bps: u16,
+    pub kink_utilisation_bps: u16,
+    pub slope1_bps: u16,
+    pub slope2_bps: u16,
+    pub event_probability_bps: u16,
+    pub reference_input_bps: u16,
+}
+
 // ---------------------------------------------------------------------------
 // Helpers & Math
 // ---------------------------------------------------------------------------
 
+// Thin wrappers over tail_risk_core: same math on- and off-chain, mapped to program errors
+fn math_overflow() -> Error {
+    error!(ErrorCode::MathOverflow)
+}
+
+// sha256 of the concatenated parts: the hash Merkle distributions are built with
+fn sha256v(parts: &[&[u8]]) -> [u8; 32] {
+    hashv(parts).to_bytes()
+}
+
 fn to_fp_u64(amount_u64: u64) -> Result<u128> {
-    let a = amount_u64 as u128;
-    a.checked_mul(SCALE).ok_or_else(|| error!(ErrorCode::MathOverflow))
+    math::to_fp(amount_u64).ok_or_else(math_overflow)
 }
 
 fn from_fp_to_u64(amount_fp: u128) -> Result<u64> {
-    let x = amount_fp
-        .checked_div(SCALE)
-        .ok_or_else(|| error!(ErrorCode::MathOverflow))?;
-    u64::try_from(x).map_err(|_| error!(ErrorCode::MathOverflow))
+    math::from_fp(amount_fp).ok_or_else(math_overflow)
+}
+
+fn from_fp_ceil_to_u64(amount_fp: u128) -> Result<u64> {
+    math::from_fp_ceil(amount_fp).ok_or_else(math_overflow)
 }
 
 fn mul_div_floor_u128(a: u128, b: u128, denom: u128) -> Result<u128> {
-    let num = a.checked_mul(b).ok_or_else(|| error!(ErrorCode::MathOverflow))?;
-    num.checked_div(denom).ok_or_else(|| error!(ErrorCode::MathOverflow))
+    math::mul_div_floor(a, b, denom).ok_or_else(math_overflow)
 }
 
-fn vault_balance_fp(vault: &Account<TokenAccount>) -> Result<u128> {
-    let bal = vault.amount as u128;
-    bal.checked_mul(SCALE).ok_or_else(|| error!(ErrorCode::MathOverflow))
+// Native asset units -> common-unit fixed-point (and back), rescaling decimals
+fn asset_to_fp(amount: u64, decimals: u8) -> Result<u128> {
+    math::asset_to_fp(amount, decimals).ok_or_else(math_overflow)
+}
+
+fn fp_to_asset(amount_fp: u128, decimals: u8) -> Result<u64> {
+    math::fp_to_asset(amount_fp, decimals).ok_or_else(math_overflow)
+}
+
+fn apply_haircut(par_fp: u128, haircut_bps: u16) -> Result<u128> {
+    math::apply_haircut(par_fp, haircut_bps).ok_or_else(math_overflow)
 }
 
 fn weighted_stake_fp(senior_fp: u128, junior_fp: u128, w_senior_bps: u128, w_junior_bps: u128) -> Result<u128> {
-    let s_w = mul_div_floor_u128(senior_fp, w_senior_bps, BPS_DENOM)?;
-    let j_w = mul_div_floor_u128(junior_fp, w_junior_bps, BPS_DENOM)?;
-    s_w.checked_add(j_w).ok_or_else(|| error!(ErrorCode::MathOverflow))
+    payout::weighted_stake_fp(senior_fp, junior_fp, w_senior_bps, w_junior_bps).ok_or_else(math_overflow)
 }
 
 // Quadratic severity: a*x^2 + b*x + c (x in bps), coefficients in SCALE
 fn effective_severity_bps(x_bps: u128, a_fp: u128, b_fp: u128, c_fp: u128, floor_bps: u16) -> Result<u128> {
-    // Convert x_bps to fixed-point
-    let x_fp = x_bps.checked_mul(SCALE).ok_or_else(|| error!(ErrorCode::MathOverflow))?;
-    let x2 = mul_div_floor_u128(x_fp, x_fp, SCALE)?; // x^2 in SCALE
-    let ax2 = mul_div_floor_u128(a_fp, x2, SCALE)?;
-    let bx  = mul_div_floor_u128(b_fp, x_fp, SCALE)?;
-    let sum = ax2.checked_add(bx).ok_or_else(|| error!(ErrorCode::MathOverflow))?
-                 .checked_add(c_fp).ok_or_else(|| error!(ErrorCode::MathOverflow))?;
-    // Convert back to bps (divide by SCALE)
-    let bps = sum.checked_div(SCALE).ok_or_else(|| error!(ErrorCode::MathOverflow))?;
-    let floored = core::cmp::max(bps, floor_bps as u128);
-    Ok(core::cmp::min(floored, BPS_DENOM)) // clamp to 10000 bps
-}
-
-// Lots helpers
+    severity::effective_severity_bps(x_bps, a_fp, b_fp, c_fp, floor_bps).ok_or_else(math_overflow)
+}
+
+fn payout_policy(s: &State) -> Result<payout::PayoutPolicy> {
+    payout::PayoutPolicy::from_u8(s.payout_policy).ok_or_else(|| error!(ErrorCode::ParamOutOfBounds))
+}
+
+fn fee_model(s: &State) -> Result<fees::FeeModel> {
+    fees::FeeModel::from_u8(s.fee_model).ok_or_else(|| error!(ErrorCode::ParamOutOfBounds))
+}
+
+// Lots helpers (ring-buffer logic lives in tail_risk_core::lots)
+impl LotEntry for Lot {
+    fn amount_fp(&self) -> u128 { self.amount_fp }
+    fn ts(&self) -> i64 { self.ts }
+}
+
+fn lot_ring(lots: &mut Lots) -> LotRing<'_, Lot> {
+    LotRing { head: &mut lots.head, len: &mut lots.len, buf: &mut lots.buf }
+}
+
 fn push_lot(lots: &mut Lots, lot: Lot) -> Result<()> {
-    if (lots.len as usize) < MAX_LOTS {
-        let idx = ((lots.head as usize) + (lots.len as usize)) % MAX_LOTS;
-        lots.buf[idx] = lot;
-        lots.len += 1;
-        Ok(())
+    // simple back-pressure: reject if ring full
+    require!(lot_ring(lots).push(lot), ErrorCode::TooManyLots);
+    Ok(())
+}
+
+// ---- split helpers to avoid double mutable borrows on the same struct ----
+fn senior_parts(p: &mut UserPosition) -> (&mut Lots, &mut u128) {
+    (&mut p.senior_lots, &mut p.senior_withdrawable_fp)
+}
+fn junior_parts(p: &mut UserPosition) -> (&mut Lots, &mut u128) {
+    (&mut p.junior_lots, &mut p.junior_withdrawable_fp)
+}
+
+// Layout versioning (see ACCOUNT_VERSION); `V0` is the layout written before versioning
+trait Versioned: AccountSerialize + AccountDeserialize + Discriminator {
+    const SPACE: usize;
+    const V0_SPACE: usize;
+    type V0: AnchorDeserialize;
+    fn version(&self) -> u8;
+}
+
+macro_rules! versioned {
+    ($($t:ty => $v0:ty),*) => {$(
+        impl Versioned for $t {
+            const SPACE: usize = 8 + <$t>::SIZE;
+            const V0_SPACE: usize = 8 + <$v0>::SIZE;
+            type V0 = $v0;
+            fn version(&self) -> u8 { self.version }
+        }
+    )*};
+}
+versioned!(
+    State => StateV0,
+    UserPosition => UserPositionV0,
+    Epoch => EpochV0,
+    ClaimReceipt => ClaimReceiptV0,
+    OracleList => OracleListV0
+);
+
+fn has_discriminator<T: Discriminator>(info: &AccountInfo) -> bool {
+    info.try_borrow_data().is_ok_and(|d| d.starts_with(T::DISCRIMINATOR))
+}
+
+// Bring a `T` account to the current layout and return it; `payer` tops up rent. A legacy
+// account (exactly `T::V0_SPACE` bytes) is decoded as `T::V0`, rewritten by `upgrade` and
+// grown; a versioned one written before its reserve grew is zero-extended.
+fn migrate_account<'info, T: Versioned>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system: &AccountInfo<'info>,
+    upgrade: impl FnOnce(T::V0) -> Result<T>,
+) -> Result<T> {
+    require_keys_eq!(*info.owner, crate::ID, anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram);
+    require!(has_discriminator::<T>(info), anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+    let legacy = if info.data_len() == T::V0_SPACE {
+        let v0 = T::V0::deserialize(&mut &info.try_borrow_data()?[8..])
+            .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotDeserialize))?;
+        Some(upgrade(v0)?)
+    } else {
+        None
+    };
+    if info.data_len() < T::SPACE {
+        let top_up = Rent::get()?.minimum_balance(T::SPACE).saturating_sub(info.lamports());
+        if top_up > 0 {
+            let accounts = anchor_lang::system_program::Transfer { from: payer.clone(), to: info.clone() };
+            anchor_lang::system_program::transfer(CpiContext::new(system.clone(), accounts), top_up)?;
+        }
+        info.resize(T::SPACE)?;
+    }
+
+    match legacy {
+        Some(acc) => {
+            acc.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+            Ok(acc)
+        }
+        None => {
+            let acc = T::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            require!(acc.version() == ACCOUNT_VERSION, ErrorCode::UnsupportedAccountVersion);
+            Ok(acc)
+        }
+    }
+}
+
+// Shrink the oldest lots until they add up to at most `cap_fp`
+fn trim_lots(lots: &mut Lots, cap_fp: u128) {
+    let mut excess = lot_ring(lots).locked_fp().saturating_sub(cap_fp);
+    for i in 0..lots.len as usize {
+        let lot = &mut lots.buf[(lots.head as usize + i) % MAX_LOTS];
+        let cut = excess.min(lot.amount_fp);
+        lot.amount_fp -= cut;
+        excess -= cut;
+    }
+}
+
+// Collateral helpers
+fn find_collateral(reg: &CollateralRegistry, mint: Pubkey) -> Option<usize> {
+    (0..(reg.count as usize)).find(|&i| reg.assets[i].mint == mint)
+}
+
+fn collateral_index(reg: &CollateralRegistry, mint: Pubkey) -> Result<usize> {
+    find_collateral(reg, mint).ok_or_else(|| error!(ErrorCode::UnsupportedCollateral))
+}
+
+fn register_collateral(reg: &mut CollateralRegistry, asset: CollateralAsset) -> Result<()> {
+    require!((reg.count as usize) < MAX_COLLATERALS, ErrorCode::TooManyCollaterals);
+    reg.assets[reg.count as usize] = asset;
+    reg.count += 1;
+    Ok(())
+}
+
+// Pool value = haircut value of every vault, in common units
+fn pool_value_fp(reg: &CollateralRegistry) -> Result<u128> {
+    let mut total = 0u128;
+    for a in reg.assets.iter().take(reg.count as usize) {
+        let v = apply_haircut(a.balance_fp, a.haircut_bps)?;
+        total = total.checked_add(v).ok_or_else(|| error!(ErrorCode::MathOverflow))?;
+    }
+    Ok(total)
+}
+
+fn assert_concentration(reg: &CollateralRegistry, idx: usize) -> Result<()> {
+    let a = &reg.assets[idx];
+    if a.concentration_cap_bps as u128 >= BPS_DENOM {
+        return Ok(());
+    }
+    let value = apply_haircut(a.balance_fp, a.haircut_bps)?;
+    let limit = mul_div_floor_u128(pool_value_fp(reg)?, a.concentration_cap_bps as u128, BPS_DENOM)?;
+    require!(value <= limit, ErrorCode::ConcentrationCapExceeded);
+    Ok(())
+}
+
+// An outflow from asset `idx` only lowers that asset's share of the pool; every other asset
+// must still be within its cap afterwards
+fn assert_outflow_concentration(reg: &CollateralRegistry, idx: usize) -> Result<()> {
+    for i in (0..reg.count as usize).filter(|&i| i != idx) {
+        assert_concentration(reg, i)?;
+    }
+    Ok(())
+}
+
+// Pro-rata payout `position` is owed by a triggered `epoch`: its tranche-weighted stake
+// against the trigger snapshot, capped by policy and by what the epoch has left (0 = nothing
+// owed). Records the shortfall of an underfunded epoch.
+fn epoch_claim_fp(state: &State, epoch: &mut Epoch, pool_balance_fp: u128, position: &UserPosition) -> Result<u128> {
+    let policy = payout_policy(state)?;
+    let liability = payout::epoch_liability(
+        claim_base_fp(epoch),
+        epoch.severity_bps,
+        policy,
+        epoch.epoch_cap_fp,
+        pool_balance_fp,
+    )
+    .ok_or_else(math_overflow)?;
+
+    // If underfunded, record shortfall (carryover)
+    if liability.shortfall_fp > 0 {
+        epoch.shortfall_fp = liability.shortfall_fp;
+    }
+
+    // Weighted stake at snapshot approximated by current (principal is frozen)
+    let stake_fp = covered_stake_fp(state, position, epoch.epoch_id)?;
+    if liability.max_fp == 0 || stake_fp == 0 || epoch.total_stake_snapshot_fp == 0 {
+        return Ok(0);
+    }
+
+    // Pro-rata share, with the optional per-user cap (for Capped policy), within the room left
+    let user_fp = payout::user_payout_fp(
+        liability.max_fp,
+        stake_fp,
+        claim_base_fp(epoch),
+        policy,
+        epoch.user_cap_bps,
+    )
+    .ok_or_else(math_overflow)?;
+    Ok(payout::claimable_fp(user_fp, liability.max_fp, epoch.total_payout_fp))
+}
+
+// What `notional_fp` of cover on a triggered epoch is paid now: its pro-rata share of the epoch
+// liability (no per-user cap), within what the epoch has left. Records the shortfall like
+// `epoch_claim_fp`.
+fn cover_claim_fp(state: &State, epoch: &mut Epoch, pool_balance_fp: u128, notional_fp: u128) -> Result<u128> {
+    let policy = payout_policy(state)?;
+    let liability = payout::epoch_liability(
+        claim_base_fp(epoch),
+        epoch.severity_bps,
+        policy,
+        epoch.epoch_cap_fp,
+        pool_balance_fp,
+    )
+    .ok_or_else(math_overflow)?;
+    if liability.shortfall_fp > 0 {
+        epoch.shortfall_fp = liability.shortfall_fp;
+    }
+    let cover_fp = payout::user_payout_fp(liability.max_fp, notional_fp, claim_base_fp(epoch), policy, 0)
+        .ok_or_else(math_overflow)?;
+    Ok(payout::claimable_fp(cover_fp, liability.max_fp, epoch.total_payout_fp))
+}
+
+// What a triggered epoch pays out on, pro rata: its stake snapshot and the cover sold on it
+fn claim_base_fp(epoch: &Epoch) -> u128 {
+    epoch.total_stake_snapshot_fp.saturating_add(epoch.cover_sold_fp)
+}
+
+// Full payout of the cover on a triggered epoch not claimed yet
+fn cover_owed_fp(epoch: &Epoch) -> Result<u128> {
+    cover::cover_payout_fp(epoch.cover_sold_fp.saturating_sub(epoch.cover_claimed_fp), epoch.severity_bps)
+        .ok_or_else(math_overflow)
+}
+
+// Tranche-weighted stake `position` holds in epoch `epoch_id`: principal deposited during
+// that (rolling) epoch is only covered from the next one
+fn covered_stake_fp(state: &State, position: &UserPosition, epoch_id: u64) -> Result<u128> {
+    let (senior_pending_fp, junior_pending_fp) = if position.pending_epoch_id == epoch_id {
+        (position.pending_senior_fp, position.pending_junior_fp)
     } else {
-        // simple back-pressure: reject if ring full
-        err!(ErrorCode::TooManyLots)
+        (0, 0)
+    };
+    weighted_stake_fp(
+        position.senior_deposited_fp.saturating_sub(senior_pending_fp),
+        position.junior_deposited_fp.saturating_sub(junior_pending_fp),
+        state.tranche_weight_senior_bps as u128,
+        state.tranche_weight_junior_bps as u128,
+    )
+}
+
+// When `e` stops covering: its end, or its finalization if earlier (open-ended epochs run
+// until then)
+fn coverage_end_ts(e: &Epoch) -> i64 {
+    let end_ts = if e.end_ts == 0 { i64::MAX } else { e.end_ts };
+    if e.closed { end_ts.min(e.finalized_ts) } else { end_ts }
+}
+
+// Close a triggered epoch's claim window at `now`, carrying its shortfall over for future
+// make-good accounting; cover still unclaimed lapses. A finalized epoch covers nothing more,
+// so the next one may start
+fn settle_epoch(state: &mut State, epoch: &mut Epoch, history: &mut EpochHistory, now: i64) {
+    state.carryover_shortfall_fp = state.carryover_shortfall_fp.saturating_add(epoch.shortfall_fp);
+    epoch.closed = true;
+    epoch.finalized_ts = now;
+    state.claim_window_open = false;
+    state.cover_owed_fp = 0;
+    if epoch.epoch_id == state.current_epoch_id {
+        history.last_end_ts = coverage_end_ts(epoch);
     }
+    record_epoch(history, epoch);
+    emit!(EpochFinalized { epoch_id: epoch.epoch_id });
 }
 
-fn pop_matured(lots: &mut Lots, lockup_secs: i64, now: i64) -> Option<Lot> {
-    if lots.len == 0 { return None; }
-    let lot = lots.buf[lots.head as usize];
-    if now.saturating_sub(lot.ts) >= lockup_secs {
-        lots.head = ((lots.head as usize + 1) % MAX_LOTS) as u8;
-        lots.len -= 1;
-        Some(lot)
+// Cover paid for is not voided early: a triggered epoch settles only once every cover is
+// claimed or its claim window is over
+fn require_cover_claims_done(epoch: &Epoch, now: i64) -> Result<()> {
+    require!(
+        epoch.cover_claimed_fp >= epoch.cover_sold_fp
+            || now >= epoch.triggered_ts.saturating_add(COVER_CLAIM_WINDOW_SECS),
+        ErrorCode::CoverClaimWindowActive
+    );
+    Ok(())
+}
+
+// Claims against `epoch` stop for good: what it still owes (the policy-capped liability, or
+// the Merkle total plus the cover's share) beyond what it paid becomes shortfall
+fn book_unpaid_liability(state: &State, epoch: &mut Epoch) -> Result<()> {
+    let policy = payout_policy(state)?;
+    let capped_fp = payout::epoch_liability(claim_base_fp(epoch), epoch.severity_bps, policy, epoch.epoch_cap_fp, 0)
+        .ok_or_else(math_overflow)?
+        .capped_fp;
+    let owed_fp = if epoch.merkle_root != [0; 32] {
+        let cover_fp = payout::user_payout_fp(capped_fp, epoch.cover_sold_fp, claim_base_fp(epoch), policy, 0)
+            .ok_or_else(math_overflow)?;
+        epoch.merkle_total_fp.saturating_add(cover_fp)
     } else {
-        None
+        capped_fp
+    };
+    epoch.shortfall_fp = epoch.shortfall_fp.max(owed_fp.saturating_sub(epoch.total_payout_fp));
+    Ok(())
+}
+
+fn record_epoch(history: &mut EpochHistory, e: &Epoch) {
+    history.records[history.count as usize % EPOCH_HISTORY_LEN] = EpochRecord {
+        epoch_id: e.epoch_id,
+        severity_bps: e.severity_bps,
+        total_payout_fp: e.total_payout_fp,
+        shortfall_fp: e.shortfall_fp,
+    };
+    history.count = history.count.saturating_add(1);
+    history.last_epoch_id = e.epoch_id;
+}
+
+// Pending principal of an earlier epoch is covered by now; start tracking `current_epoch_id`
+fn roll_pending(position: &mut UserPosition, current_epoch_id: u64) {
+    if position.pending_epoch_id != current_epoch_id {
+        position.pending_epoch_id = current_epoch_id;
+        position.pending_senior_fp = 0;
+        position.pending_junior_fp = 0;
     }
 }
 
-fn mature_and_consume(
-    lots: &mut Lots,
-    lockup_secs: i64,
-    now: i64,
-    withdrawable_fp: &mut u128,
-    remaining: &mut u128,
+// Create `receipt` at its PDA `info`, rent paid by `payer` (an address someone already sent
+// lamports to is topped up, allocated and assigned instead)
+fn create_claim_receipt<'info>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system: &AccountInfo<'info>,
+    receipt: &ClaimReceipt,
 ) -> Result<()> {
-    // Make withdrawable by maturing lots
-    loop {
-        if let Some(l) = pop_matured(lots, lockup_secs, now) {
-            *withdrawable_fp = withdrawable_fp.saturating_add(l.amount_fp);
-        } else { break; }
-    }
-    // Consume withdrawable toward target
-    let take = core::cmp::min(*withdrawable_fp, *remaining);
-    *withdrawable_fp = withdrawable_fp.saturating_sub(take);
-    *remaining = remaining.saturating_sub(take);
+    use anchor_lang::system_program::{allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer};
+    let epoch_bytes = receipt.epoch_id.to_le_bytes();
+    let seeds: &[&[u8]] = &[b"claim", epoch_bytes.as_ref(), receipt.owner.as_ref(), &[receipt.bump]];
+    let signer = &[seeds];
+    let space = <ClaimReceipt as Versioned>::SPACE;
+    let rent = Rent::get()?.minimum_balance(space);
+    if info.lamports() == 0 {
+        let accounts = CreateAccount { from: payer.clone(), to: info.clone() };
+        create_account(CpiContext::new_with_signer(system.clone(), accounts, signer), rent, space as u64, &crate::ID)?;
+    } else {
+        let top_up = rent.saturating_sub(info.lamports());
+        if top_up > 0 {
+            let accounts = Transfer { from: payer.clone(), to: info.clone() };
+            transfer(CpiContext::new(system.clone(), accounts), top_up)?;
+        }
+        let accounts = Allocate { account_to_allocate: info.clone() };
+        allocate(CpiContext::new_with_signer(system.clone(), accounts, signer), space as u64)?;
+        let accounts = Assign { account_to_assign: info.clone() };
+        assign(CpiContext::new_with_signer(system.clone(), accounts, signer), &crate::ID)?;
+    }
+    receipt.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+// Outflow circuit breaker: admit `amount_fp` leaving the vaults (`liquid_fp` before it).
+// Over the limit it pauses outflows, emits `OutflowLimitTripped` and returns false; the
+// caller then returns Ok without paying, since an error would roll the pause back.
+fn admit_outflow(state: &mut State, liquid_fp: u128, amount_fp: u128) -> Result<bool> {
+    let limits = OutflowLimits {
+        window_secs: state.outflow_window_secs,
+        cap_fp: state.outflow_cap_fp,
+        cap_bps: state.outflow_cap_bps,
+    };
+    let mut window = OutflowWindow {
+        updated_ts: state.outflow_window_updated_ts,
+        base_fp: state.outflow_window_base_fp,
+        used_fp: state.outflow_window_used_fp,
+    };
+    let admitted = window.admit(&limits, liquid_fp, amount_fp, Clock::get()?.unix_timestamp);
+    state.outflow_window_updated_ts = window.updated_ts;
+    state.outflow_window_base_fp = window.base_fp;
+    state.outflow_window_used_fp = window.used_fp;
+    match admitted {
+        Ok(()) => Ok(true),
+        Err(limit_fp) => {
+            state.pause_flags |= PAUSE_OUTFLOWS;
+            emit!(OutflowLimitTripped {
+                window_updated_ts: window.updated_ts,
+                used_fp: window.used_fp,
+                attempted_fp: amount_fp,
+                limit_fp,
+            });
+            emit!(Paused { flags: state.pause_flags });
+            Ok(false)
+        }
+    }
+}
+
+// Book a premium already transferred into the base vault: the fee-on-premium protocol cut
+// goes to the treasury, the referral cut accrues to `referrer`, the rest is credited to the
+// tranches like yield. Returns (protocol fee, referral fee, net credited).
+fn book_premium<'info>(
+    state: &mut Account<'info, State>,
+    reg: &mut CollateralRegistry,
+    vault_ata: &Account<'info, TokenAccount>,
+    treasury_ata: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    referrer: Option<&mut Referrer>,
+    amount_u64: u64,
+) -> Result<(u64, u128, u128)> {
+    let amount_fp = to_fp_u64(amount_u64)?;
+    let on_premium = fee_model(state)? == fees::FeeModel::OnPremium;
+    let proto_fee_u64 = if on_premium {
+        from_fp_to_u64(mul_div_floor_u128(amount_fp, state.protocol_fee_bps as u128, BPS_DENOM)?)?
+    } else {
+        0
+    };
+    if proto_fee_u64 > 0 {
+        transfer_tokens_pda(vault_ata, treasury_ata, state, token_program, proto_fee_u64)?;
+        emit!(ProtocolFeeTaken { amount_u64: proto_fee_u64 });
+    }
+
+    // Referred premiums count towards the referrer's volume; under fee-on-premium its cut
+    // accrues to the referrer's account (claimed later)
+    let mut ref_fee_fp = 0u128;
+    if let Some(r) = referrer {
+        if on_premium {
+            let bps = referrer_fee_bps(state, r);
+            ref_fee_fp = mul_div_floor_u128(amount_fp, bps as u128, BPS_DENOM)?;
+        }
+        r.total_referred_fp = r.total_referred_fp.saturating_add(amount_fp);
+        credit_referral(r, ref_fee_fp, &state.referral_tiers);
+    }
+
+    let kept_fp = to_fp_u64(amount_u64.saturating_sub(proto_fee_u64))?;
+    let net_fp = kept_fp.saturating_sub(ref_fee_fp);
+    reg.assets[0].balance_fp = reg.assets[0].balance_fp.saturating_add(kept_fp);
+    state.referral_rewards_owed_fp = state.referral_rewards_owed_fp.saturating_add(ref_fee_fp);
+    distribute_yield(state, net_fp)?;
+    Ok((proto_fee_u64, ref_fee_fp, net_fp))
+}
+
+// Cover outstanding: what was sold on the current epoch (earlier cover has expired)
+fn outstanding_cover_fp(state: &State, book: &CoverBook) -> u128 {
+    if book.epoch_id == state.current_epoch_id { book.sold_fp } else { 0 }
+}
+
+// Cover capacity against tranche capital
+fn cover_capacity(state: &State, book: &CoverBook) -> Result<cover::Capacity> {
+    let capital_fp = state.senior_total_fp.saturating_add(state.junior_total_fp);
+    cover::capacity(capital_fp, outstanding_cover_fp(state, book), book.max_utilisation_bps).ok_or_else(math_overflow)
+}
+
+// Price `notional_fp` of cover for `duration_secs` with the book's curve and the pool's
+// current severity curve
+fn quote_cover(state: &State, book: &CoverBook, notional_fp: u128, duration_secs: u64) -> Result<pricing::PremiumQuote> {
+    let curve = pricing::PremiumCurve {
+        base_rate_bps: book.base_rate_bps,
+        kink_utilisation_bps: book.kink_utilisation_bps,
+        slope1_bps: book.slope1_bps,
+        slope2_bps: book.slope2_bps,
+    };
+    let severity_bps = effective_severity_bps(
+        book.reference_input_bps as u128,
+        state.sev_quad_a_fp,
+        state.sev_quad_b_fp,
+        state.sev_quad_c_fp,
+        state.severity_floor_bps,
+    )?;
+    let expected_loss_bps = pricing::expected_loss_bps(book.event_probability_bps, severity_bps).ok_or_else(math_overflow)?;
+    let capital_fp = state.senior_total_fp.saturating_add(state.junior_total_fp);
+    pricing::quote(&curve, capital_fp, outstanding_cover_fp(state, book), notional_fp, duration_secs, expected_loss_bps)
+        .ok_or_else(math_overflow)
+}
+
+// Take the in-flight guard of an instruction that makes CPIs (`Busy` if it is already held).
+// The flag is written to the account at once, so a re-entrant call sees it.
+fn begin_processing(state: &mut Account<State>) -> Result<()> {
+    require!(!state.processing, ErrorCode::Busy);
+    state.processing = true;
+    state.exit(&crate::ID)
+}
+
+// Release the guard taken by `begin_processing`, passing the handler's result through
+fn end_processing<T>(state: &mut State, res: Result<T>) -> Result<T> {
+    state.processing = false;
+    res
+}
+
+// Fail with `Paused` while `op` (a PAUSE_* bit) is paused by the admin or guardian
+fn require_not_paused(state: &State, op: u8) -> Result<()> {
+    require!(state.pause_flags & op == 0, ErrorCode::Paused);
     Ok(())
 }
 
-// ---- split helpers to avoid double mutable borrows on the same struct ----
-fn senior_parts(p: &mut UserPosition) -> (&mut Lots, &mut u128) {
-    (&mut p.senior_lots, &mut p.senior_withdrawable_fp)
+// As `require_not_paused`, and also frozen while a claim window is open: principal and
+// the liquidity claims are paid from stay put until finalize_epoch
+fn require_not_frozen(state: &State, op: u8) -> Result<()> {
+    require_not_paused(state, op)?;
+    require!(!state.claim_window_open, ErrorCode::Paused);
+    Ok(())
 }
-fn junior_parts(p: &mut UserPosition) -> (&mut Lots, &mut u128) {
-    (&mut p.junior_lots, &mut p.junior_withdrawable_fp)
+
+// Everything the pool owes out of its vaults, in common units
+fn tracked_liabilities_fp(state: &State) -> u128 {
+    state
+        .total_deposited_fp
+        .saturating_add(state.yield_reserve_fp)
+        .saturating_add(state.protocol_fees_accrued_fp)
+        .saturating_add(state.referral_rewards_owed_fp)
+        .saturating_add(state.cover_owed_fp)
+}
+
+// Liquid vault value plus principal deployed in the strategy
+fn total_pool_value_fp(state: &State, reg: &CollateralRegistry) -> Result<u128> {
+    pool_value_fp(reg)?
+        .checked_add(state.strategy_deployed_fp)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+// Strategy yield helpers (reward-debt accounting, indices in SCALE per unit principal)
+fn settle_yield(p: &mut UserPosition, idx: (u128, u128)) -> Result<()> {
+    let s_acc = yields::accrued_fp(p.senior_deposited_fp, idx.0).ok_or_else(math_overflow)?;
+    let j_acc = yields::accrued_fp(p.junior_deposited_fp, idx.1).ok_or_else(math_overflow)?;
+    let pending = s_acc
+        .saturating_sub(p.senior_yield_debt_fp)
+        .saturating_add(j_acc.saturating_sub(p.junior_yield_debt_fp));
+    p.yield_accrued_fp = p.yield_accrued_fp.saturating_add(pending);
+    p.senior_yield_debt_fp = s_acc;
+    p.junior_yield_debt_fp = j_acc;
+    Ok(())
+}
+
+fn sync_yield_debt(p: &mut UserPosition, idx: (u128, u128)) -> Result<()> {
+    p.senior_yield_debt_fp = yields::accrued_fp(p.senior_deposited_fp, idx.0).ok_or_else(math_overflow)?;
+    p.junior_yield_debt_fp = yields::accrued_fp(p.junior_deposited_fp, idx.1).ok_or_else(math_overflow)?;
+    Ok(())
+}
+
+// Referral helpers: tier 0 = base rate, tier i = referral_tiers[i - 1]
+fn referral_bps_for_tier(base_bps: u16, tiers: &[ReferralTier; MAX_REFERRAL_TIERS], tier: u8) -> u16 {
+    match tier as usize {
+        0 => base_bps,
+        t => tiers.get(t - 1).map(|x| x.fee_bps).unwrap_or(base_bps),
+    }
+}
+
+fn referral_tier_for_volume(tiers: &[ReferralTier; MAX_REFERRAL_TIERS], volume_fp: u128) -> u8 {
+    let mut tier = 0u8;
+    for (i, t) in tiers.iter().enumerate() {
+        if t.min_volume_fp > 0 && volume_fp >= t.min_volume_fp {
+            tier = (i + 1) as u8;
+        }
+    }
+    tier
+}
+
+fn credit_referral(r: &mut Referrer, reward_fp: u128, tiers: &[ReferralTier; MAX_REFERRAL_TIERS]) {
+    r.pending_rewards_fp = r.pending_rewards_fp.saturating_add(reward_fp);
+    r.lifetime_rewards_fp = r.lifetime_rewards_fp.saturating_add(reward_fp);
+    r.tier = referral_tier_for_volume(tiers, r.total_referred_fp);
+    if reward_fp > 0 {
+        emit!(ReferralRewardAccrued { referrer: r.owner, amount_fp: reward_fp, tier: r.tier });
+    }
+}
+
+// Fee rates charged at deposit time (zero unless fee-on-deposit)
+fn deposit_fee_bps(s: &State) -> Result<(u16, u16)> {
+    Ok(fees::deposit_fee_bps(fee_model(s)?, s.protocol_fee_bps, s.referral_fee_bps))
+}
+
+// Referral rate a deposit pays (0 outside fee-on-deposit): `referrer`'s tier rate when
+// referred, else the base rate credited to LPs, waived while the pool has none
+fn deposit_referral_bps(s: &State, referrer: Option<&Referrer>) -> Result<u16> {
+    let (_, base_ref_bps) = deposit_fee_bps(s)?;
+    Ok(match referrer {
+        _ if base_ref_bps == 0 => 0,
+        Some(r) => referrer_fee_bps(s, r),
+        None if s.senior_total_fp.saturating_add(s.junior_total_fp) == 0 => 0,
+        None => base_ref_bps,
+    })
+}
+
+fn referrer_fee_bps(s: &State, r: &Referrer) -> u16 {
+    referral_bps_for_tier(s.referral_fee_bps, &s.referral_tiers, r.tier)
+}
+
+fn split_fees(amount_fp: u128, protocol_bps: u16, referral_bps: u16) -> Result<fees::DepositFees> {
+    fees::split_fees(amount_fp, protocol_bps, referral_bps).ok_or_else(math_overflow)
+}
+
+// Protocol share of realised strategy yield: performance fee, plus the protocol fee under fee-on-yield
+fn yield_fee_fp(s: &State, yield_fp: u128) -> Result<u128> {
+    fees::yield_fee_fp(fee_model(s)?, s.protocol_fee_bps, s.performance_fee_bps, yield_fp).ok_or_else(math_overflow)
+}
+
+// Split realised yield between tranches; an empty tranche's share goes to the other one.
+// With no principal at all the yield simply stays in the vault as surplus.
+fn distribute_yield(state: &mut State, yield_fp: u128) -> Result<()> {
+    let (senior_fp, junior_fp) = yields::split_yield(
+        yield_fp,
+        state.strategy_junior_yield_bps,
+        state.senior_total_fp,
+        state.junior_total_fp,
+    )
+    .ok_or_else(math_overflow)?;
+
+    let s_inc = yields::index_increment(senior_fp, state.senior_total_fp).ok_or_else(math_overflow)?;
+    let j_inc = yields::index_increment(junior_fp, state.junior_total_fp).ok_or_else(math_overflow)?;
+    state.senior_yield_index_fp = state.senior_yield_index_fp.saturating_add(s_inc);
+    state.junior_yield_index_fp = state.junior_yield_index_fp.saturating_add(j_inc);
+    state.yield_reserve_fp = state.yield_reserve_fp.saturating_add(senior_fp.saturating_add(junior_fp));
+    Ok(())
+}
+
+// Withdraw from the strategy into the escrow, move it on to the base vault and book
+// principal / yield / loss. `None` = full recall: anything above deployed principal is realised yield.
+fn recall_strategy<'info>(
+    strategy: &mut StrategyCpi<'_, 'info>,
+    state: &mut Account<'info, State>,
+    reg: &mut Account<'info, CollateralRegistry>,
+    vault: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    extra: &[AccountInfo<'info>],
+    amount_opt: Option<u64>,
+) -> Result<()> {
+    require_keys_eq!(strategy.program.key(), state.strategy_program, ErrorCode::StrategyNotConfigured);
+
+    let before = strategy.escrow.amount;
+    invoke_strategy(
+        strategy,
+        token_program,
+        extra,
+        STRATEGY_WITHDRAW_IX,
+        amount_opt.unwrap_or(STRATEGY_WITHDRAW_ALL),
+    )?;
+    strategy.escrow.reload()?;
+    let received_u64 = strategy.escrow.amount.saturating_sub(before);
+    if received_u64 > 0 {
+        transfer_tokens_strategy(strategy, vault, token_program, received_u64)?;
+    }
+    let received_fp = to_fp_u64(received_u64)?;
+    reg.assets[0].balance_fp = reg.assets[0].balance_fp.saturating_add(received_fp);
+
+    let principal_fp = core::cmp::min(received_fp, state.strategy_deployed_fp);
+    let yield_fp = received_fp.saturating_sub(principal_fp);
+    let loss_fp = if amount_opt.is_none() {
+        let loss = state.strategy_deployed_fp.saturating_sub(principal_fp);
+        state.strategy_deployed_fp = 0;
+        loss
+    } else {
+        state.strategy_deployed_fp = state.strategy_deployed_fp.saturating_sub(principal_fp);
+        0
+    };
+    let fee_fp = yield_fee_fp(state, yield_fp)?;
+    state.protocol_fees_accrued_fp = state.protocol_fees_accrued_fp.saturating_add(fee_fp);
+    if yield_fp > fee_fp {
+        distribute_yield(state, yield_fp - fee_fp)?;
+    }
+
+    emit!(StrategyRecalled {
+        received_u64,
+        yield_fp,
+        fee_fp,
+        loss_fp,
+        deployed_fp: state.strategy_deployed_fp,
+    });
+    Ok(())
 }
 
 // Oracle helpers
@@ -1337,11 +4989,57 @@ fn oracle_is_allowed(list: &OracleList, signer: Pubkey) -> bool {
 fn assert_param_bounds(s: &State) -> Result<()> {
     require!(s.protocol_fee_bps as u32 <= 1_000, ErrorCode::ParamOutOfBounds);
     require!(s.referral_fee_bps as u32 <= 1_000, ErrorCode::ParamOutOfBounds);
+    fee_model(s)?;
+    require!(s.performance_fee_bps as u32 <= 5_000, ErrorCode::ParamOutOfBounds);
+    // Active referral tiers: ascending volume thresholds, same fee ceiling as the base rate
+    let mut last_volume = 0u128;
+    for t in s.referral_tiers.iter().filter(|t| t.min_volume_fp > 0) {
+        require!(t.min_volume_fp > last_volume, ErrorCode::ParamOutOfBounds);
+        require!(t.fee_bps as u32 <= 1_000, ErrorCode::ParamOutOfBounds);
+        last_volume = t.min_volume_fp;
+    }
     require!((s.tranche_weight_senior_bps as u32) <= 10_000, ErrorCode::ParamOutOfBounds);
     require!((s.tranche_weight_junior_bps as u32) <= 10_000, ErrorCode::ParamOutOfBounds);
+    require!((s.strategy_max_bps as u32) <= 10_000, ErrorCode::ParamOutOfBounds);
+    require!((s.strategy_junior_yield_bps as u32) <= 10_000, ErrorCode::ParamOutOfBounds);
+    require!(s.outflow_window_secs >= 0 && (s.outflow_cap_bps as u32) <= 10_000, ErrorCode::ParamOutOfBounds);
+    require!(s.crank_reward_u64 <= MAX_CRANK_REWARD_U64, ErrorCode::ParamOutOfBounds);
+    Ok(())
+}
+
+fn assert_collateral_bounds(haircut_bps: u16, concentration_cap_bps: u16) -> Result<()> {
+    require!((haircut_bps as u128) < BPS_DENOM, ErrorCode::ParamOutOfBounds);
+    require!(
+        concentration_cap_bps > 0 && (concentration_cap_bps as u128) <= BPS_DENOM,
+        ErrorCode::ParamOutOfBounds
+    );
+    Ok(())
+}
+
+fn assert_cover_bounds(p: &CoverParams) -> Result<()> {
+    let all = [
+        p.max_utilisation_bps,
+        p.base_rate_bps,
+        p.kink_utilisation_bps,
+        p.slope1_bps,
+        p.slope2_bps,
+        p.event_probability_bps,
+        p.reference_input_bps,
+    ];
+    require!(all.iter().all(|&bps| (bps as u128) <= BPS_DENOM), ErrorCode::ParamOutOfBounds);
     Ok(())
 }
 
+fn apply_cover_params(book: &mut CoverBook, p: &CoverParams) {
+    book.max_utilisation_bps = p.max_utilisation_bps;
+    book.base_rate_bps = p.base_rate_bps;
+    book.kink_utilisation_bps = p.kink_utilisation_bps;
+    book.slope1_bps = p.slope1_bps;
+    book.slope2_bps = p.slope2_bps;
+    book.event_probability_bps = p.event_probability_bps;
+    book.reference_input_bps = p.reference_input_bps;
+}
+
 // user authority (Signer) transfer
 fn transfer_tokens_user<'info>(
     from: &Account<'info, TokenAccount>,
@@ -1389,6 +5087,118 @@ fn transfer_tokens_pda<'info>(
     token::transfer(cpi_ctx, amount)
 }
 
+// state PDA authority mints a cover NFT's single token, then drops the mint authority
+fn mint_cover_token<'info>(
+    mint: &Account<'info, Mint>,
+    to: &Account<'info, TokenAccount>,
+    state: &Account<'info, State>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+    let program_id_bytes = crate::id();
+    let seeds: &[&[u8]] = &[
+        b"state",
+        program_id_bytes.as_ref(),
+        &[state.bump],
+    ];
+    let signer = &[seeds];
+
+    token::mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo { mint: mint.to_account_info(), to: to.to_account_info(), authority: state.to_account_info() },
+            signer,
+        ),
+        1,
+    )?;
+    token::set_authority(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            SetAuthority { current_authority: state.to_account_info(), account_or_mint: mint.to_account_info() },
+            signer,
+        ),
+        AuthorityType::MintTokens,
+        None,
+    )
+}
+
+// Strategy program, its signer PDA (bump) and the escrow that PDA owns. The state PDA,
+// which owns every vault, is never handed to strategy code.
+struct StrategyCpi<'a, 'info> {
+    program: &'a AccountInfo<'info>,
+    authority: &'a AccountInfo<'info>,
+    bump: u8,
+    escrow: &'a mut Account<'info, TokenAccount>,
+}
+
+// strategy PDA authority transfer out of the escrow
+fn transfer_tokens_strategy<'info>(
+    strategy: &StrategyCpi<'_, 'info>,
+    to: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    let program_id_bytes = crate::id();
+    let seeds: &[&[u8]] = &[
+        b"strategy",
+        program_id_bytes.as_ref(),
+        &[strategy.bump],
+    ];
+    let signer = &[seeds];
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+            from: strategy.escrow.to_account_info(),
+            to: to.to_account_info(),
+            authority: strategy.authority.clone(),
+        },
+        signer,
+    );
+    token::transfer(cpi_ctx, amount)
+}
+
+// strategy PDA authority CPI into the strategy program:
+// accounts = [strategy authority (signer), escrow, token program, ...lending accounts]
+fn invoke_strategy<'info>(
+    strategy: &StrategyCpi<'_, 'info>,
+    token_program: &Program<'info, Token>,
+    extra: &[AccountInfo<'info>],
+    ix_tag: [u8; 8],
+    amount: u64,
+) -> Result<()> {
+    let mut metas = vec![
+        AccountMeta::new_readonly(strategy.authority.key(), true),
+        AccountMeta::new(strategy.escrow.key(), false),
+        AccountMeta::new_readonly(token_program.key(), false),
+    ];
+    let mut infos = vec![
+        strategy.authority.clone(),
+        strategy.escrow.to_account_info(),
+        token_program.to_account_info(),
+    ];
+    for a in extra {
+        metas.push(if a.is_writable {
+            AccountMeta::new(a.key(), a.is_signer)
+        } else {
+            AccountMeta::new_readonly(a.key(), a.is_signer)
+        });
+        infos.push(a.clone());
+    }
+    infos.push(strategy.program.clone());
+
+    let mut data = ix_tag.to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    let ix = Instruction { program_id: strategy.program.key(), accounts: metas, data };
+
+    let program_id_bytes = crate::id();
+    let seeds: &[&[u8]] = &[
+        b"strategy",
+        program_id_bytes.as_ref(),
+        &[strategy.bump],
+    ];
+    invoke_signed(&ix, &infos, &[seeds]).map_err(Into::into)
+}
+
 // ---------------------------------------------------------------------------
 // Errors
 // ---------------------------------------------------------------------------
@@ -1423,4 +5233,48 @@ pub enum ErrorCode {
     ParamOutOfBounds,
     #[msg("Operation busy (reentrancy guard)")]
     Busy,
+    #[msg("Collateral asset not registered or disabled")]
+    UnsupportedCollateral,
+    #[msg("Too many collateral assets")]
+    TooManyCollaterals,
+    #[msg("Collateral concentration cap exceeded")]
+    ConcentrationCapExceeded,
+    #[msg("No strategy configured or wrong strategy program")]
+    StrategyNotConfigured,
+    #[msg("Strategy allocation cap exceeded")]
+    StrategyCapExceeded,
+    #[msg("Strategy still holds deployed capital")]
+    StrategyActive,
+    #[msg("Account still holds balances or open claims")]
+    AccountNotEmpty,
+    #[msg("Claim receipt retention period not elapsed")]
+    RetentionPeriodActive,
+    #[msg("Account layout version is newer than this program")]
+    UnsupportedAccountVersion,
+    #[msg("Pool is shut down")]
+    PoolShutdown,
+    #[msg("Only available after shutdown")]
+    NotShutdown,
+    #[msg("Remaining accounts do not match the expected layout")]
+    InvalidRemainingAccounts,
+    #[msg("Not available in this epoch's settlement mode (on-chain or Merkle)")]
+    SettlementModeConflict,
+    #[msg("Merkle proof does not match the posted root")]
+    InvalidMerkleProof,
+    #[msg("Rolling mode: the current epoch account is required")]
+    CurrentEpochRequired,
+    #[msg("Epoch would overlap the previous epoch's cover")]
+    EpochOverlap,
+    #[msg("Cover would exceed the pool's maximum utilisation")]
+    CapacityExceeded,
+    #[msg("Premium above the buyer's maximum")]
+    SlippageExceeded,
+    #[msg("Signer does not hold the cover's NFT")]
+    NotCoverHolder,
+    #[msg("Cover sold on the epoch is still unclaimed within its claim window")]
+    CoverClaimWindowActive,
+    #[msg("Premium rounds to zero")]
+    ZeroPremium,
+    #[msg("Cover is unclaimed and its epoch not yet finalized")]
+    CoverActive,
 }
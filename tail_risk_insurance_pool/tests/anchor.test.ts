@@ -12,6 +12,9 @@ describe("Tail Risk Insurance Pool", () => {
   let statePda: web3.PublicKey;
   let vaultAta: web3.PublicKey;
   let oracleListPda: web3.PublicKey;
+  let collateralRegistryPda: web3.PublicKey;
+  let epochHistoryPda: web3.PublicKey;
+  let coverBookPda: web3.PublicKey;
   
   // USDC mock mint
   let usdcMint: web3.PublicKey;
@@ -71,6 +74,21 @@ describe("Tail Risk Insurance Pool", () => {
       pg.program.programId
     );
     
+    [collateralRegistryPda] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral"), pg.program.programId.toBuffer()],
+      pg.program.programId
+    );
+
+    [epochHistoryPda] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("epoch_history"), pg.program.programId.toBuffer()],
+      pg.program.programId
+    );
+
+    [coverBookPda] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("cover_book"), pg.program.programId.toBuffer()],
+      pg.program.programId
+    );
+
     // Derive vault ATA
     vaultAta = await getAssociatedTokenAddress(
       usdcMint,
@@ -104,6 +122,8 @@ describe("Tail Risk Insurance Pool", () => {
       referralFeeBps: 25, // 0.25%
       lockupSecs: new BN(60), // 60 seconds for testing
       minSecondsBetweenDeposits: new BN(10), // 10 seconds cooldown
+      feeModel: 0, // fees charged on deposit
+      performanceFeeBps: 1000, // 10% of realised strategy yield
       epochCapFp: toFp(500_000), // 500k USDC epoch cap
       rollingMode: false,
       maxStaleSecs: new BN(300), // 5 minutes
@@ -123,6 +143,7 @@ describe("Tail Risk Insurance Pool", () => {
         state: statePda,
         vaultAta: vaultAta,
         oracleList: oracleListPda,
+        collateralRegistry: collateralRegistryPda,
         systemProgram: web3.SystemProgram.programId,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
@@ -137,15 +158,126 @@ describe("Tail Risk Insurance Pool", () => {
     const state = await pg.program.account.state.fetch(statePda);
     assert(state.admin.equals(admin.publicKey));
     assert(state.usdcMint.equals(usdcMint));
-    assert.equal(state.paused, false);
+    assert.equal(state.claimWindowOpen, false);
+    assert.equal(state.pauseFlags, 0);
     assert.equal(state.protocolFeeBps, 50);
+
+    const registry = await pg.program.account.collateralRegistry.fetch(collateralRegistryPda);
+    assert.equal(registry.count, 1);
+    assert(registry.assets[0].mint.equals(usdcMint));
+    assert.equal(registry.assets[0].haircutBps, 0);
     console.log("✓ Pool initialized successfully");
   });
 
+  it("Create the epoch registry", async () => {
+    const txHash = await pg.program.methods
+      .initEpochHistory()
+      .accounts({
+        admin: admin.publicKey,
+        state: statePda,
+        currentEpoch: null,
+        epochHistory: epochHistoryPda,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Init epoch history tx: ${txHash}`);
+    await pg.connection.confirmTransaction(txHash);
+
+    const history = await pg.program.account.epochHistory.fetch(epochHistoryPda);
+    assert.equal(history.count, 0);
+    console.log("✓ Epoch registry created");
+  });
+
+  it("Create the cover book", async () => {
+    const txHash = await pg.program.methods
+      .initCoverBook({
+        maxUtilisationBps: 5000, // cover up to 50% of capital
+        baseRateBps: 100, // 1% a year at zero utilisation
+        kinkUtilisationBps: 4000,
+        slope1Bps: 200,
+        slope2Bps: 2000,
+        eventProbabilityBps: 500, // a 5% a year chance of a trigger
+        referenceInputBps: 5000, // paying what a 50% input would
+      })
+      .accounts({
+        admin: admin.publicKey,
+        state: statePda,
+        coverBook: coverBookPda,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Init cover book tx: ${txHash}`);
+    await pg.connection.confirmTransaction(txHash);
+
+    const book = await pg.program.account.coverBook.fetch(coverBookPda);
+    assert.equal(book.maxUtilisationBps, 5000);
+    assert.equal(book.baseRateBps, 100);
+    assert.equal(book.kinkUtilisationBps, 4000);
+    assert.equal(book.coversMinted.toNumber(), 0); // each purchase mints the next cover NFT
+    console.log("✓ Cover book created");
+  });
+
+  it("Register a secondary stablecoin with a haircut", async () => {
+    const usdtMint = await createMint(pg.connection, admin, admin.publicKey, null, 6);
+    const usdtVault = await getAssociatedTokenAddress(usdtMint, statePda, true);
+
+    const txHash = await pg.program.methods
+      .addCollateral(200, 4000) // 2% haircut, max 40% of pool value
+      .accounts({
+        admin: admin.publicKey,
+        state: statePda,
+        mint: usdtMint,
+        vaultAta: usdtVault,
+        collateralRegistry: collateralRegistryPda,
+        systemProgram: web3.SystemProgram.programId,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    console.log(`Add collateral tx: ${txHash}`);
+    await pg.connection.confirmTransaction(txHash);
+
+    const registry = await pg.program.account.collateralRegistry.fetch(collateralRegistryPda);
+    assert.equal(registry.count, 2);
+    assert(registry.assets[1].mint.equals(usdtMint));
+    assert.equal(registry.assets[1].haircutBps, 200);
+    assert.equal(registry.assets[1].concentrationCapBps, 4000);
+    console.log("✓ Secondary collateral registered");
+  });
+
+  it("Referrer registers a referral account", async () => {
+    const [referrerPda] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("referrer"), admin.publicKey.toBuffer()],
+      pg.program.programId
+    );
+
+    const txHash = await pg.program.methods
+      .registerReferrer()
+      .accounts({
+        referrer: admin.publicKey,
+        referrerAccount: referrerPda,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    console.log(`Register referrer tx: ${txHash}`);
+    await pg.connection.confirmTransaction(txHash);
+
+    const referrer = await pg.program.account.referrer.fetch(referrerPda);
+    assert(referrer.owner.equals(admin.publicKey));
+    assert.equal(referrer.tier, 0);
+    assert(referrer.pendingRewardsFp.eq(new BN(0)));
+    console.log("✓ Referrer account created");
+  });
+
   it("Start an epoch", async () => {
     const epochId = new BN(1);
     const now = Math.floor(Date.now() / 1000);
-    const startTs = new BN(now);
+    const startTs = new BN(0); // now, by the cluster clock
     const endTs = new BN(now + 3600); // 1 hour epoch
 
     const [epochPda] = web3.PublicKey.findProgramAddressSync(
@@ -156,8 +288,10 @@ describe("Tail Risk Insurance Pool", () => {
     const txHash = await pg.program.methods
       .startEpoch(epochId, startTs, endTs)
       .accounts({
-        admin: admin.publicKey,
+        payer: admin.publicKey,
         state: statePda,
+        previousEpoch: null,
+        epochHistory: epochHistoryPda,
         epoch: epochPda,
         systemProgram: web3.SystemProgram.programId,
       })
@@ -201,13 +335,19 @@ describe("Tail Risk Insurance Pool", () => {
       .depositInsurance(new BN(depositAmount), 0, null) // 0 = senior tranche
       .accounts({
         user: user1.publicKey,
-        usdcMint: usdcMint,
+        assetMint: usdcMint,
         state: statePda,
         vaultAta: vaultAta,
         userAta: user1Ata,
         protocolTreasuryAta: protocolTreasuryAta,
-        referrerAta: null,
+        referrerAccount: null,
         position: positionPda,
+        attribution: web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("referral"), user1.publicKey.toBuffer()],
+          pg.program.programId
+        )[0],
+        currentEpoch: null,
+        collateralRegistry: collateralRegistryPda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: web3.SystemProgram.programId,
@@ -253,13 +393,19 @@ describe("Tail Risk Insurance Pool", () => {
       .depositInsurance(new BN(depositAmount), 1, null) // 1 = junior tranche
       .accounts({
         user: user2.publicKey,
-        usdcMint: usdcMint,
+        assetMint: usdcMint,
         state: statePda,
         vaultAta: vaultAta,
         userAta: user2Ata,
         protocolTreasuryAta: protocolTreasuryAta,
-        referrerAta: null,
+        referrerAccount: null,
         position: positionPda,
+        attribution: web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("referral"), user2.publicKey.toBuffer()],
+          pg.program.programId
+        )[0],
+        currentEpoch: null,
+        collateralRegistry: collateralRegistryPda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: web3.SystemProgram.programId,
@@ -276,23 +422,130 @@ describe("Tail Risk Insurance Pool", () => {
     console.log("✓ User deposited into junior tranche");
   });
 
+  it("Vault balance equals the sum of tracked liabilities", async () => {
+    // user1 deposited into an empty pool without a referrer: no referral fee charged
+    const [position1Pda] = web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), user1.publicKey.toBuffer()],
+      pg.program.programId
+    );
+    const position1 = await pg.program.account.userPosition.fetch(position1Pda);
+    assert(position1.seniorDepositedFp.eq(toFp(10_000 - 50)));
+
+    // user2's unreferred referral fee was credited to user1's tranche as yield
+    const state = await pg.program.account.state.fetch(statePda);
+    assert(state.yieldReserveFp.eq(new BN(12_500_000))); // 0.25% of 5,000
+    assert(state.referralRewardsOwedFp.eq(new BN(0)));
+
+    const stats = await pg.program.methods
+      .poolStats()
+      .accounts({
+        state: statePda,
+        collateralRegistry: collateralRegistryPda,
+      })
+      .view();
+    const vault = await pg.connection.getTokenAccountBalance(vaultAta);
+    const vaultFp = toFp(parseInt(vault.value.amount));
+
+    const liabilities = state.totalDepositedFp
+      .add(state.yieldReserveFp)
+      .add(state.protocolFeesAccruedFp)
+      .add(state.referralRewardsOwedFp);
+    assert(stats.trackedLiabilitiesFp.eq(liabilities));
+    assert(vaultFp.eq(liabilities));
+    assert(stats.poolBalanceFp.eq(liabilities));
+    console.log(`✓ Vault holds exactly what the pool owes: ${vault.value.amount}`);
+  });
+
+  it("Admin configures the idle-capital strategy", async () => {
+    // Mock lending program (see mock_lending/), deployed separately
+    const strategyProgram = new web3.PublicKey("8zxEJYbCp2j8GsZ5M166FQ7b9BREEeQ6pwtkA1kgSZpF");
+
+    const txHash = await pg.program.methods
+      .setStrategy(strategyProgram, 5000, 7000) // up to 50% deployed, 70% of yield to junior
+      .accounts({
+        admin: admin.publicKey,
+        state: statePda,
+      })
+      .rpc();
+
+    console.log(`Set strategy tx: ${txHash}`);
+    await pg.connection.confirmTransaction(txHash);
+
+    const state = await pg.program.account.state.fetch(statePda);
+    assert(state.strategyProgram.equals(strategyProgram));
+    assert.equal(state.strategyMaxBps, 5000);
+    assert(state.strategyDeployedFp.eq(new BN(0)));
+    console.log("✓ Strategy configured");
+  });
+
+  it("Quote deposit returns the fee breakdown", async () => {
+    const quote = await pg.program.methods
+      .quoteDeposit(new BN(10_000))
+      .accounts({
+        state: statePda,
+        collateralRegistry: collateralRegistryPda,
+        assetMint: usdcMint,
+        referrerAccount: null,
+      })
+      .view();
+
+    assert.equal(quote.feeModel, 0);
+    assert.equal(quote.protocolFeeU64.toNumber(), 50); // 0.5% of 10,000
+    assert.equal(quote.referralFeeU64.toNumber(), 25);
+    assert.equal(quote.deferredProtocolFeeBps, 0);
+    assert.equal(quote.performanceFeeBps, 1000);
+    console.log("✓ Deposit quote matches fee-on-deposit model");
+  });
+
   it("View pool stats", async () => {
     const stats = await pg.program.methods
       .poolStats()
       .accounts({
         state: statePda,
-        vaultAta: vaultAta,
-        usdcMint: usdcMint,
+        collateralRegistry: collateralRegistryPda,
       })
       .view();
 
     console.log("Pool Stats:");
     console.log(`- Total Deposited: ${fromFp(stats.totalDepositedFp)} USDC`);
     console.log(`- Pool Balance: ${fromFp(stats.poolBalanceFp)} USDC`);
+    console.log(`- Deployed in strategy: ${fromFp(stats.strategyDeployedFp)} USDC`);
     console.log(`- Payout Policy: ${stats.payoutPolicy}`);
     console.log("✓ Pool stats retrieved");
   });
 
+  it("View cover capacity", async () => {
+    const capacity = await pg.program.methods
+      .capacityView()
+      .accounts({
+        state: statePda,
+        coverBook: coverBookPda,
+      })
+      .view();
+
+    console.log(`- Capital: ${fromFp(capacity.capitalFp)} USDC`);
+    console.log(`- Cover available: ${fromFp(capacity.availableFp)} USDC`);
+    assert(capacity.soldFp.eq(new BN(0)));
+    assert(capacity.maxCoverFp.eq(capacity.capitalFp.divn(2)));
+    console.log("✓ Half the capital is available as cover");
+  });
+
+  it("Quote a premium", async () => {
+    const quote = await pg.program.methods
+      .quotePremium(new BN(1_000), new BN(365 * 86_400)) // 1,000 units for a year
+      .accounts({
+        state: statePda,
+        coverBook: coverBookPda,
+      })
+      .view();
+
+    console.log(`- Annual rate: ${quote.rateBps.toString()} bps (expected loss ${quote.expectedLossBps.toString()} bps)`);
+    console.log(`- Premium: ${fromFp(quote.premiumFp)} USDC`);
+    assert(quote.rateBps.gte(new BN(100)));
+    assert.equal(quote.withinCapacity, true);
+    console.log("✓ Premium quoted from the curve");
+  });
+
   it("Trigger event (oracle)", async () => {
     const epochId = new BN(1);
     const [epochPda] = web3.PublicKey.findProgramAddressSync(
@@ -317,6 +570,12 @@ describe("Tail Risk Insurance Pool", () => {
         state: statePda,
         epoch: epochPda,
         oracleList: oracleListPda,
+        collateralRegistry: collateralRegistryPda,
+        vaultAta: vaultAta,
+        strategyProgram: null, // nothing deployed, no recall needed
+        strategyAuthority: null,
+        strategyEscrow: null,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
       })
       .rpc();
 
@@ -360,13 +619,14 @@ describe("Tail Risk Insurance Pool", () => {
       .payoutUser()
       .accounts({
         user: user1.publicKey,
-        usdcMint: usdcMint,
+        assetMint: usdcMint,
         state: statePda,
         epoch: epochPda,
         vaultAta: vaultAta,
         userAta: user1Ata,
         position: positionPda,
         claim: claimPda,
+        collateralRegistry: collateralRegistryPda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
         systemProgram: web3.SystemProgram.programId,
@@ -399,9 +659,11 @@ describe("Tail Risk Insurance Pool", () => {
         admin: admin.publicKey,
         state: statePda,
         epoch: epochPda,
+        epochHistory: epochHistoryPda,
         vaultAta: vaultAta,
         protocolTreasuryAta: protocolTreasuryAta,
-        usdcMint: usdcMint,
+        assetMint: usdcMint,
+        collateralRegistry: collateralRegistryPda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
       })
       .rpc();
@@ -410,11 +672,15 @@ describe("Tail Risk Insurance Pool", () => {
     await pg.connection.confirmTransaction(txHash);
 
     const state = await pg.program.account.state.fetch(statePda);
-    assert.equal(state.paused, false);
+    assert.equal(state.claimWindowOpen, false);
     
     const epoch = await pg.program.account.epoch.fetch(epochPda);
     assert.equal(epoch.closed, true);
-    console.log("✓ Epoch finalized and pool unpaused");
+
+    const history = await pg.program.account.epochHistory.fetch(epochHistoryPda);
+    assert(history.lastEpochId.eq(epochId));
+    assert.equal(history.count, 1);
+    console.log("✓ Epoch finalized and claim window closed");
   });
 
   it("User withdraws from senior tranche", async () => {
@@ -440,11 +706,13 @@ describe("Tail Risk Insurance Pool", () => {
       .withdraw(new BN(withdrawAmount), 0) // 0 = senior tranche
       .accounts({
         user: user1.publicKey,
-        usdcMint: usdcMint,
+        assetMint: usdcMint,
         state: statePda,
         vaultAta: vaultAta,
         userAta: user1Ata,
         position: positionPda,
+        currentEpoch: null,
+        collateralRegistry: collateralRegistryPda,
         tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
       })
@@ -0,0 +1,1399 @@
+//! Shared harness: boots the pool (and `mock_lending`) in solana-program-test,
+//! creates mints and funded users, and wraps every instruction.
+//!
+//! Programs are loaded from `target/deploy/*.so`, so run `anchor build` first.
+
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::AccountMeta, program_pack::Pack, sysvar},
+    AccountDeserialize, AccountSerialize, AnchorDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{get_associated_token_address, spl_associated_token_account},
+    token::spl_token,
+};
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    clock::Clock,
+    hash::hashv,
+    instruction::{Instruction, InstructionError},
+    rent::Rent,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+use tail_risk_core::merkle;
+use tail_risk_insurance_pool::{
+    accounts as acc, instruction as ix, Attribution, ClaimBitmap, CollateralRegistry, Cover, CoverBook, CoverParams, Epoch, EpochHistory,
+    ErrorCode, InitializeParams, OracleList, ReferralTier, Referrer, State, UserPosition,
+};
+
+pub const SCALE: u128 = 1_000_000;
+pub const DECIMALS: u8 = 6;
+pub const DAY: i64 = 86_400;
+/// The year premium rates are quoted over.
+pub const YEAR: i64 = 365 * DAY;
+
+pub type TxResult = Result<(), BanksClientError>;
+
+/// Whole USDC -> native units.
+pub fn usdc(n: u64) -> u64 {
+    n * 10u64.pow(DECIMALS as u32)
+}
+
+/// Whole USDC -> common-unit fixed point (`*_fp`).
+pub fn fp(n: u64) -> u128 {
+    usdc(n) as u128 * SCALE
+}
+
+/// Pool parameters used unless a test overrides them (same values as the TS suite,
+/// with tranche weights inside bounds).
+pub fn default_params(protocol_treasury: Pubkey) -> InitializeParams {
+    InitializeParams {
+        protocol_treasury,
+        payout_policy: 0,
+        user_deposit_cap_fp: fp(1_000_000),
+        min_deposit_fp: fp(100),
+        protocol_fee_bps: 50,
+        referral_fee_bps: 25,
+        lockup_secs: 60,
+        min_seconds_between_deposits: 10,
+        fee_model: 0,
+        performance_fee_bps: 1_000,
+        epoch_cap_fp: fp(500_000),
+        rolling_mode: false,
+        max_stale_secs: 300,
+        sev_quad_a_fp: 0,
+        sev_quad_b_fp: SCALE,
+        sev_quad_c_fp: 0,
+        severity_floor_bps: 100,
+        tranche_weight_senior_bps: 5_000,
+        tranche_weight_junior_bps: 10_000,
+    }
+}
+
+/// Cover book without a premium curve: a flat annual `base_rate_bps`.
+pub fn flat_cover(max_utilisation_bps: u16, base_rate_bps: u16) -> CoverParams {
+    CoverParams {
+        max_utilisation_bps,
+        base_rate_bps,
+        kink_utilisation_bps: 0,
+        slope1_bps: 0,
+        slope2_bps: 0,
+        event_probability_bps: 0,
+        reference_input_bps: 0,
+    }
+}
+
+/// Assert that a transaction failed with a program `ErrorCode`.
+#[track_caller]
+pub fn assert_error(res: TxResult, code: ErrorCode) {
+    let expected = u32::from(code);
+    match res.expect_err("transaction should fail").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(got)) => assert_eq!(got, expected),
+        other => panic!("expected custom error {expected}, got {other:?}"),
+    }
+}
+
+fn pda(seeds: &[&[u8]], program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(seeds, program_id).0
+}
+
+fn sha256v(parts: &[&[u8]]) -> [u8; 32] {
+    hashv(parts).to_bytes()
+}
+
+/// Merkle distribution over `(owner, amount_fp)` leaves, indexed in order (what the
+/// CLI's `merkle-build` produces from positions).
+pub struct Distribution {
+    pub leaves: Vec<(Pubkey, u128)>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl Distribution {
+    pub fn new(leaves: &[(Pubkey, u128)]) -> Self {
+        let mut level: Vec<_> = leaves
+            .iter()
+            .enumerate()
+            .map(|(i, (owner, amount_fp))| merkle::leaf(i as u32, &owner.to_bytes(), *amount_fp, sha256v))
+            .collect();
+        let mut levels = vec![level.clone()];
+        while level.len() > 1 {
+            level = level.chunks(2).map(|p| if p.len() == 2 { merkle::node(&p[0], &p[1], sha256v) } else { p[0] }).collect();
+            levels.push(level.clone());
+        }
+        Self { leaves: leaves.to_vec(), levels }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    pub fn total_fp(&self) -> u128 {
+        self.leaves.iter().map(|(_, a)| a).sum()
+    }
+
+    /// Sibling hashes from leaf `index` up to the root.
+    pub fn proof(&self, index: u32) -> Vec<[u8; 32]> {
+        let mut i = index as usize;
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(i ^ 1) {
+                proof.push(*sibling);
+            }
+            i /= 2;
+        }
+        proof
+    }
+}
+
+/// Strategy wiring for `mock_lending` (obligation owned by the pool's strategy PDA).
+pub struct Strategy {
+    pub obligation: Pubkey,
+    pub reserve_authority: Pubkey,
+    pub reserve_ata: Pubkey,
+}
+
+impl Strategy {
+    /// Lending accounts forwarded as remaining accounts.
+    pub fn remaining(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.obligation, false),
+            AccountMeta::new(self.reserve_ata, false),
+            AccountMeta::new_readonly(self.reserve_authority, false),
+        ]
+    }
+}
+
+/// An initialized pool plus its test context. The context payer is the admin.
+pub struct Pool {
+    pub ctx: ProgramTestContext,
+    pub usdc_mint: Pubkey,
+    pub treasury: Keypair,
+    pub treasury_ata: Pubkey,
+    sent: HashSet<Signature>,
+}
+
+impl Pool {
+    /// Pool initialized with [`default_params`].
+    pub async fn start() -> Self {
+        Self::start_with(|_| {}).await
+    }
+
+    /// Pool initialized with [`default_params`] adjusted by `tweak`.
+    pub async fn start_with(tweak: impl FnOnce(&mut InitializeParams)) -> Self {
+        let mut pool = Self::boot().await;
+        let mut params = default_params(pool.treasury.pubkey());
+        tweak(&mut params);
+        pool.initialize(params).await.unwrap();
+        pool.init_epoch_history().await.unwrap();
+        pool
+    }
+
+    /// Programs loaded, USDC mint and treasury ATA created, pool not initialized.
+    pub async fn boot() -> Self {
+        let mut pt = ProgramTest::new("tail_risk_insurance_pool", tail_risk_insurance_pool::ID, None);
+        pt.add_program("mock_lending", mock_lending::ID, None);
+        let ctx = pt.start_with_context().await;
+        let mut pool = Self {
+            ctx,
+            usdc_mint: Pubkey::default(),
+            treasury: Keypair::new(),
+            treasury_ata: Pubkey::default(),
+            sent: HashSet::new(),
+        };
+        pool.usdc_mint = pool.create_mint(DECIMALS).await;
+        let treasury = pool.treasury.pubkey();
+        pool.treasury_ata = pool.create_ata(&treasury, &pool.usdc_mint.clone()).await;
+        pool
+    }
+
+    // ----------------------------- plumbing -----------------------------
+
+    pub fn admin(&self) -> Pubkey {
+        self.ctx.payer.pubkey()
+    }
+
+    /// Sign with the payer (admin) plus `signers` and process. Identical retries
+    /// get a fresh blockhash so they are not deduplicated.
+    pub async fn send(&mut self, ixs: &[Instruction], signers: &[&Keypair]) -> TxResult {
+        let mut tx = self.sign(ixs, signers);
+        if self.sent.contains(&tx.signatures[0]) {
+            self.ctx.last_blockhash = self.ctx.get_new_latest_blockhash().await.unwrap();
+            tx = self.sign(ixs, signers);
+        }
+        self.sent.insert(tx.signatures[0]);
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    fn sign(&self, ixs: &[Instruction], signers: &[&Keypair]) -> Transaction {
+        let payer = self.ctx.payer.pubkey();
+        let all: Vec<&Keypair> = std::iter::once(&self.ctx.payer)
+            .chain(signers.iter().copied().filter(|k| k.pubkey() != payer))
+            .collect();
+        Transaction::new_signed_with_payer(ixs, Some(&payer), &all, self.ctx.last_blockhash)
+    }
+
+    /// Run a view instruction in simulation and decode its return data.
+    pub async fn view<T: AnchorDeserialize>(&mut self, ix: Instruction) -> T {
+        let tx = self.sign(&[ix], &[]);
+        let sim = self.ctx.banks_client.simulate_transaction(tx).await.unwrap();
+        if let Some(Err(e)) = sim.result {
+            panic!("view failed: {e:?}");
+        }
+        let data = sim.simulation_details.and_then(|d| d.return_data).expect("return data").data;
+        T::deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    pub async fn now(&mut self) -> i64 {
+        self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    /// Move the cluster clock forward by `secs`.
+    pub async fn warp(&mut self, secs: i64) {
+        let mut clock: Clock = self.ctx.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += secs;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    pub async fn account<T: AccountDeserialize>(&mut self, address: Pubkey) -> T {
+        let a = self.ctx.banks_client.get_account(address).await.unwrap().expect("account exists");
+        T::try_deserialize(&mut a.data.as_slice()).unwrap()
+    }
+
+    pub async fn exists(&mut self, address: Pubkey) -> bool {
+        self.ctx.banks_client.get_account(address).await.unwrap().is_some()
+    }
+
+    pub async fn lamports(&mut self, address: Pubkey) -> u64 {
+        self.ctx.banks_client.get_balance(address).await.unwrap()
+    }
+
+    // ----------------------------- tokens -----------------------------
+
+    pub async fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        let mint = Keypair::new();
+        let admin = self.admin();
+        let ixs = [
+            system_instruction::create_account(
+                &admin,
+                &mint.pubkey(),
+                Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), &admin, None, decimals).unwrap(),
+        ];
+        self.send(&ixs, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    pub async fn create_ata(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let admin = self.admin();
+        let ix = spl_associated_token_account::instruction::create_associated_token_account(
+            &admin,
+            owner,
+            mint,
+            &spl_token::ID,
+        );
+        self.send(&[ix], &[]).await.unwrap();
+        get_associated_token_address(owner, mint)
+    }
+
+    pub async fn mint_to(&mut self, mint: &Pubkey, owner: &Pubkey, amount: u64) {
+        let admin = self.admin();
+        let ata = get_associated_token_address(owner, mint);
+        let ix = spl_token::instruction::mint_to(&spl_token::ID, mint, &ata, &admin, &[], amount).unwrap();
+        self.send(&[ix], &[]).await.unwrap();
+    }
+
+    pub async fn balance(&mut self, token_account: Pubkey) -> u64 {
+        let a = self.ctx.banks_client.get_account(token_account).await.unwrap().expect("token account");
+        spl_token::state::Account::unpack(&a.data).unwrap().amount
+    }
+
+    /// New keypair with SOL for rent and an ATA holding `usdc_amount` native USDC.
+    pub async fn user(&mut self, usdc_amount: u64) -> Keypair {
+        let user = Keypair::new();
+        let admin = self.admin();
+        self.send(&[system_instruction::transfer(&admin, &user.pubkey(), 1_000_000_000)], &[])
+            .await
+            .unwrap();
+        let mint = self.usdc_mint;
+        self.create_ata(&user.pubkey(), &mint).await;
+        if usdc_amount > 0 {
+            self.mint_to(&mint, &user.pubkey(), usdc_amount).await;
+        }
+        user
+    }
+
+    // ----------------------------- addresses -----------------------------
+
+    pub fn state(&self) -> Pubkey {
+        pda(&[b"state", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn oracle_list(&self) -> Pubkey {
+        pda(&[b"oracle", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn registry(&self) -> Pubkey {
+        pda(&[b"collateral", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn epoch(&self, epoch_id: u64) -> Pubkey {
+        pda(&[b"epoch", &epoch_id.to_le_bytes()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn position(&self, owner: &Pubkey) -> Pubkey {
+        pda(&[b"position", owner.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn claim(&self, epoch_id: u64, owner: &Pubkey) -> Pubkey {
+        pda(&[b"claim", &epoch_id.to_le_bytes(), owner.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn epoch_history(&self) -> Pubkey {
+        pda(&[b"epoch_history", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn bitmap(&self, epoch_id: u64) -> Pubkey {
+        pda(&[b"bitmap", &epoch_id.to_le_bytes()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn cover_book(&self) -> Pubkey {
+        pda(&[b"cover_book", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    /// NFT mint of the book's `id`-th cover (from 0).
+    pub fn cover_mint(&self, id: u64) -> Pubkey {
+        pda(&[b"cover_mint", &id.to_le_bytes()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn cover(&self, mint: &Pubkey) -> Pubkey {
+        pda(&[b"cover", mint.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    pub fn referrer(&self, owner: &Pubkey) -> Pubkey {
+        pda(&[b"referrer", owner.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    /// First-touch referral attribution of `owner` (never closed).
+    pub fn attribution(&self, owner: &Pubkey) -> Pubkey {
+        pda(&[b"referral", owner.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    /// Signer of strategy CPIs.
+    pub fn strategy_authority(&self) -> Pubkey {
+        pda(&[b"strategy", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    /// Token account USDC moves through on its way to / from the strategy.
+    pub fn strategy_escrow(&self) -> Pubkey {
+        pda(&[b"strategy_escrow", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID)
+    }
+
+    /// Program-owned vault for `mint`.
+    pub fn vault(&self, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address(&self.state(), mint)
+    }
+
+    pub fn ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address(owner, &self.usdc_mint)
+    }
+
+    /// `owner`'s ATA for the cover NFT `mint`.
+    pub fn cover_nft(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address(owner, mint)
+    }
+
+    // ----------------------------- accounts -----------------------------
+
+    pub async fn state_account(&mut self) -> State {
+        let a = self.state();
+        self.account(a).await
+    }
+
+    /// Epoch `current_epoch_id`, if it is still open: deposits, withdrawals and the next
+    /// `start_epoch` pass it.
+    pub async fn current_epoch(&mut self) -> Option<Pubkey> {
+        let id = self.state_account().await.current_epoch_id;
+        let address = self.epoch(id);
+        (id != 0 && self.exists(address).await).then_some(address)
+    }
+
+    /// Rewrite `State.processing`, as a re-entrant call would find it while another
+    /// instruction holds the in-flight guard.
+    pub async fn set_processing(&mut self, processing: bool) {
+        let address = self.state();
+        let mut account = self.ctx.banks_client.get_account(address).await.unwrap().expect("account exists");
+        let mut state: State = State::try_deserialize(&mut account.data.as_slice()).unwrap();
+        state.processing = processing;
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        account.data[..data.len()].copy_from_slice(&data);
+        self.ctx.set_account(&address, &account.into());
+    }
+
+    pub async fn registry_account(&mut self) -> CollateralRegistry {
+        let a = self.registry();
+        self.account(a).await
+    }
+
+    pub async fn oracle_list_account(&mut self) -> OracleList {
+        let a = self.oracle_list();
+        self.account(a).await
+    }
+
+    pub async fn epoch_account(&mut self, epoch_id: u64) -> Epoch {
+        let a = self.epoch(epoch_id);
+        self.account(a).await
+    }
+
+    pub async fn epoch_history_account(&mut self) -> EpochHistory {
+        let a = self.epoch_history();
+        self.account(a).await
+    }
+
+    pub async fn bitmap_account(&mut self, epoch_id: u64) -> ClaimBitmap {
+        let a = self.bitmap(epoch_id);
+        self.account(a).await
+    }
+
+    pub async fn cover_book_account(&mut self) -> CoverBook {
+        let a = self.cover_book();
+        self.account(a).await
+    }
+
+    pub async fn cover_account(&mut self, mint: &Pubkey) -> Cover {
+        let a = self.cover(mint);
+        self.account(a).await
+    }
+
+    pub async fn position_account(&mut self, owner: &Pubkey) -> UserPosition {
+        let a = self.position(owner);
+        self.account(a).await
+    }
+
+    pub async fn referrer_account(&mut self, owner: &Pubkey) -> Referrer {
+        let a = self.referrer(owner);
+        self.account(a).await
+    }
+
+    pub async fn attribution_account(&mut self, owner: &Pubkey) -> Attribution {
+        let a = self.attribution(owner);
+        self.account(a).await
+    }
+
+    // ----------------------------- admin -----------------------------
+
+    fn ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+        Instruction {
+            program_id: tail_risk_insurance_pool::ID,
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    fn admin_only(&self) -> acc::AdminOnly {
+        acc::AdminOnly { admin: self.admin(), state: self.state() }
+    }
+
+    pub async fn initialize(&mut self, params: InitializeParams) -> TxResult {
+        let ix = Self::ix(
+            acc::Initialize {
+                admin: self.admin(),
+                usdc_mint: self.usdc_mint,
+                state: self.state(),
+                vault_ata: self.vault(&self.usdc_mint),
+                oracle_list: self.oracle_list(),
+                collateral_registry: self.registry(),
+                system_program: anchor_lang::system_program::ID,
+                token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                rent: sysvar::rent::ID,
+            },
+            ix::Initialize { params },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn add_collateral(&mut self, mint: &Pubkey, haircut_bps: u16, concentration_cap_bps: u16) -> TxResult {
+        let ix = Self::ix(
+            acc::AddCollateral {
+                admin: self.admin(),
+                state: self.state(),
+                mint: *mint,
+                vault_ata: self.vault(mint),
+                collateral_registry: self.registry(),
+                system_program: anchor_lang::system_program::ID,
+                token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                rent: sysvar::rent::ID,
+            },
+            ix::AddCollateral { haircut_bps, concentration_cap_bps },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_collateral_params(
+        &mut self,
+        mint: &Pubkey,
+        haircut_bps: u16,
+        concentration_cap_bps: u16,
+        enabled: bool,
+    ) -> TxResult {
+        let ix = Self::ix(
+            acc::AdminCollateral { admin: self.admin(), state: self.state(), collateral_registry: self.registry() },
+            ix::SetCollateralParams { mint: *mint, haircut_bps, concentration_cap_bps, enabled },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_paused(&mut self, flags: u8) -> TxResult {
+        let ix = Self::ix(self.admin_only(), ix::SetPaused { flags });
+        self.send(&[ix], &[]).await
+    }
+
+    /// `set_paused` signed by someone other than the admin.
+    pub async fn set_paused_as(&mut self, signer: &Keypair, flags: u8) -> TxResult {
+        let ix = Self::ix(acc::AdminOnly { admin: signer.pubkey(), state: self.state() }, ix::SetPaused { flags });
+        self.send(&[ix], &[signer]).await
+    }
+
+    pub async fn set_guardian(&mut self, guardian: Pubkey) -> TxResult {
+        let ix = Self::ix(self.admin_only(), ix::SetGuardian { guardian });
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn guardian_pause(&mut self, signer: &Keypair, flags: u8) -> TxResult {
+        let ix = Self::ix(acc::GuardianPause { authority: signer.pubkey(), state: self.state() }, ix::GuardianPause { flags });
+        self.send(&[ix], &[signer]).await
+    }
+
+    pub async fn set_outflow_limits(&mut self, window_secs: i64, cap_fp: u128, cap_bps: u16) -> TxResult {
+        let ix = Self::ix(self.admin_only(), ix::SetOutflowLimits { window_secs, cap_fp, cap_bps });
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_crank_reward(&mut self, reward_u64: u64) -> TxResult {
+        let ix = Self::ix(self.admin_only(), ix::SetCrankReward { reward_u64 });
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn shutdown(&mut self) -> TxResult {
+        self.shutdown_with(None).await
+    }
+
+    /// `shutdown` settling `open_epoch_id`'s claim window.
+    pub async fn shutdown_with(&mut self, open_epoch_id: Option<u64>) -> TxResult {
+        let ix = Self::ix(
+            acc::Shutdown {
+                admin: self.admin(),
+                state: self.state(),
+                epoch: open_epoch_id.map(|id| self.epoch(id)),
+                epoch_history: open_epoch_id.map(|_| self.epoch_history()),
+            },
+            ix::Shutdown {},
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_policy(&mut self, payout_policy: u8, epoch_cap_fp: Option<u128>) -> TxResult {
+        let ix = Self::ix(self.admin_only(), ix::SetPolicy { payout_policy, epoch_cap_fp });
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_fee_model(
+        &mut self,
+        fee_model: u8,
+        protocol_fee_bps: u16,
+        referral_fee_bps: u16,
+        performance_fee_bps: u16,
+    ) -> TxResult {
+        let ix = Self::ix(
+            self.admin_only(),
+            ix::SetFeeModel { fee_model, protocol_fee_bps, referral_fee_bps, performance_fee_bps },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_referral_tiers(&mut self, tiers: Vec<ReferralTier>) -> TxResult {
+        let ix = Self::ix(self.admin_only(), ix::SetReferralTiers { tiers });
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_curve_and_weights(&mut self, a: u128, b: u128, c: u128, floor: u16, senior: u16, junior: u16) -> TxResult {
+        let ix = Self::ix(
+            self.admin_only(),
+            ix::SetCurveAndWeights {
+                sev_quad_a_fp: a,
+                sev_quad_b_fp: b,
+                sev_quad_c_fp: c,
+                severity_floor_bps: floor,
+                tranche_weight_senior_bps: senior,
+                tranche_weight_junior_bps: junior,
+            },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_strategy(&mut self, program: Pubkey, max_bps: u16, junior_yield_bps: u16) -> TxResult {
+        let ix = Self::ix(
+            self.admin_only(),
+            ix::SetStrategy { strategy_program: program, strategy_max_bps: max_bps, strategy_junior_yield_bps: junior_yield_bps },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_rolling_mode(&mut self, enabled: bool, epoch_duration_secs: u32) -> TxResult {
+        let ix = Self::ix(self.admin_only(), ix::SetRollingMode { enabled, epoch_duration_secs });
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn init_epoch_history(&mut self) -> TxResult {
+        let ix = self.init_epoch_history_ix(self.admin()).await;
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn init_epoch_history_as(&mut self, signer: &Keypair) -> TxResult {
+        let ix = self.init_epoch_history_ix(signer.pubkey()).await;
+        self.send(&[ix], &[signer]).await
+    }
+
+    async fn init_epoch_history_ix(&mut self, admin: Pubkey) -> Instruction {
+        Self::ix(
+            acc::InitEpochHistory {
+                admin,
+                state: self.state(),
+                current_epoch: self.current_epoch().await,
+                epoch_history: self.epoch_history(),
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::InitEpochHistory {},
+        )
+    }
+
+    fn start_epoch_ix(&self, payer: Pubkey, epoch_id: u64, start_ts: i64, end_ts: i64, previous: Option<Pubkey>) -> Instruction {
+        Self::ix(
+            acc::StartEpoch {
+                payer,
+                state: self.state(),
+                previous_epoch: previous,
+                epoch_history: self.epoch_history(),
+                epoch: self.epoch(epoch_id),
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::StartEpoch { epoch_id, start_ts, end_ts },
+        )
+    }
+
+    /// `start_epoch` by the admin, passing the current epoch (if any) as the previous one.
+    pub async fn start_epoch(&mut self, epoch_id: u64, start_ts: i64, end_ts: i64) -> TxResult {
+        let previous = self.current_epoch().await;
+        let ix = self.start_epoch_ix(self.admin(), epoch_id, start_ts, end_ts, previous);
+        self.send(&[ix], &[]).await
+    }
+
+    /// Rolling mode: `payer` starts `epoch_id` after the current epoch.
+    pub async fn roll_epoch(&mut self, payer: &Keypair, epoch_id: u64) -> TxResult {
+        let previous = self.current_epoch().await;
+        let ix = self.start_epoch_ix(payer.pubkey(), epoch_id, 0, 0, previous);
+        self.send(&[ix], &[payer]).await
+    }
+
+    /// Start `epoch_id` now, open for a day.
+    pub async fn open_epoch(&mut self, epoch_id: u64) {
+        let now = self.now().await;
+        self.start_epoch(epoch_id, now, now + DAY).await.unwrap();
+    }
+
+    pub async fn end_epoch(&mut self, epoch_id: u64) -> TxResult {
+        let ix = Self::ix(
+            acc::EndEpoch {
+                admin: self.admin(),
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                epoch_history: self.epoch_history(),
+            },
+            ix::EndEpoch {},
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn finalize_epoch(&mut self, epoch_id: u64, sweep_dust_u64: Option<u64>) -> TxResult {
+        let ix = Self::ix(
+            acc::FinalizeEpoch {
+                admin: self.admin(),
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                epoch_history: self.epoch_history(),
+                vault_ata: self.vault(&self.usdc_mint),
+                protocol_treasury_ata: self.treasury_ata,
+                asset_mint: self.usdc_mint,
+                collateral_registry: self.registry(),
+                token_program: spl_token::ID,
+            },
+            ix::FinalizeEpoch { sweep_dust_u64 },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn collect_fees(&mut self) -> TxResult {
+        let ix = Self::ix(
+            acc::CollectFees {
+                admin: self.admin(),
+                state: self.state(),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                protocol_treasury_ata: self.treasury_ata,
+                token_program: spl_token::ID,
+            },
+            ix::CollectFees {},
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    // ----------------------------- users -----------------------------
+
+    /// Deposit USDC (native units) into `tranche`.
+    pub async fn deposit(&mut self, user: &Keypair, amount: u64, tranche: u8) -> TxResult {
+        let mint = self.usdc_mint;
+        self.deposit_asset(user, &mint, amount, tranche, None).await
+    }
+
+    /// Deposit any registered asset, optionally naming a referrer (whose account is passed).
+    pub async fn deposit_asset(
+        &mut self,
+        user: &Keypair,
+        mint: &Pubkey,
+        amount: u64,
+        tranche: u8,
+        referrer: Option<Pubkey>,
+    ) -> TxResult {
+        let current_epoch = self.current_epoch().await;
+        self.deposit_passing(user, mint, amount, tranche, referrer, current_epoch).await
+    }
+
+    /// Deposit with an explicit `current_epoch` account (or none).
+    pub async fn deposit_passing(
+        &mut self,
+        user: &Keypair,
+        mint: &Pubkey,
+        amount: u64,
+        tranche: u8,
+        referrer: Option<Pubkey>,
+        current_epoch: Option<Pubkey>,
+    ) -> TxResult {
+        let ix = self.deposit_ix(user.pubkey(), mint, amount, tranche, referrer, referrer, current_epoch);
+        self.send(&[ix], &[user]).await
+    }
+
+    /// Deposit USDC offering `offered` as referrer while passing `referrer`'s account.
+    pub async fn deposit_offering(&mut self, user: &Keypair, amount: u64, offered: Pubkey, referrer: Pubkey) -> TxResult {
+        let (mint, current_epoch) = (self.usdc_mint, self.current_epoch().await);
+        let ix = self.deposit_ix(user.pubkey(), &mint, amount, 0, Some(offered), Some(referrer), current_epoch);
+        self.send(&[ix], &[user]).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn deposit_ix(
+        &self,
+        owner: Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+        tranche: u8,
+        referrer: Option<Pubkey>,
+        referrer_account: Option<Pubkey>,
+        current_epoch: Option<Pubkey>,
+    ) -> Instruction {
+        Self::ix(
+            acc::DepositInsurance {
+                user: owner,
+                asset_mint: *mint,
+                state: self.state(),
+                vault_ata: self.vault(mint),
+                user_ata: get_associated_token_address(&owner, mint),
+                protocol_treasury_ata: get_associated_token_address(&self.treasury.pubkey(), mint),
+                referrer_account: referrer_account.map(|r| self.referrer(&r)),
+                position: self.position(&owner),
+                attribution: self.attribution(&owner),
+                current_epoch,
+                collateral_registry: self.registry(),
+                token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::DepositInsurance { amount, tranche, referrer_opt: referrer },
+        )
+    }
+
+    fn withdraw_accounts(&self, owner: Pubkey, current_epoch: Option<Pubkey>) -> acc::Withdraw {
+        acc::Withdraw {
+            user: owner,
+            asset_mint: self.usdc_mint,
+            state: self.state(),
+            vault_ata: self.vault(&self.usdc_mint),
+            user_ata: self.ata(&owner),
+            position: self.position(&owner),
+            current_epoch,
+            collateral_registry: self.registry(),
+            token_program: spl_token::ID,
+            associated_token_program: spl_associated_token_account::ID,
+        }
+    }
+
+    pub async fn withdraw(&mut self, user: &Keypair, amount: u64, tranche: u8) -> TxResult {
+        let current_epoch = self.current_epoch().await;
+        self.withdraw_passing(user, amount, tranche, current_epoch).await
+    }
+
+    /// Withdraw with an explicit `current_epoch` account (or none).
+    pub async fn withdraw_passing(&mut self, user: &Keypair, amount: u64, tranche: u8, current_epoch: Option<Pubkey>) -> TxResult {
+        let ix = Self::ix(self.withdraw_accounts(user.pubkey(), current_epoch), ix::Withdraw { amount, tranche });
+        self.send(&[ix], &[user]).await
+    }
+
+    /// Whole position, paid in base USDC.
+    pub async fn emergency_withdraw(&mut self, user: &Keypair) -> TxResult {
+        let current_epoch = self.current_epoch().await;
+        let ix = Self::ix(self.withdraw_accounts(user.pubkey(), current_epoch), ix::EmergencyWithdraw {});
+        self.send(&[ix], &[user]).await
+    }
+
+    pub async fn register_referrer(&mut self, referrer: &Keypair) -> TxResult {
+        let ix = Self::ix(
+            acc::RegisterReferrer {
+                referrer: referrer.pubkey(),
+                referrer_account: self.referrer(&referrer.pubkey()),
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::RegisterReferrer {},
+        );
+        self.send(&[ix], &[referrer]).await
+    }
+
+    pub async fn claim_referral_rewards(&mut self, referrer: &Keypair) -> TxResult {
+        let owner = referrer.pubkey();
+        let ix = Self::ix(
+            acc::ClaimReferralRewards {
+                referrer: owner,
+                asset_mint: self.usdc_mint,
+                state: self.state(),
+                referrer_account: self.referrer(&owner),
+                vault_ata: self.vault(&self.usdc_mint),
+                referrer_ata: self.ata(&owner),
+                collateral_registry: self.registry(),
+                token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+            },
+            ix::ClaimReferralRewards {},
+        );
+        self.send(&[ix], &[referrer]).await
+    }
+
+    /// `payer` pays a premium; its position is passed when it has one.
+    pub async fn pay_premium(&mut self, payer: &Keypair, amount_usdc: u64, referrer: Option<Pubkey>) -> TxResult {
+        let position = self.position(&payer.pubkey());
+        let position = self.exists(position).await.then_some(position);
+        let ix = Self::ix(
+            acc::PayPremium {
+                payer: payer.pubkey(),
+                state: self.state(),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                payer_ata: self.ata(&payer.pubkey()),
+                protocol_treasury_ata: self.treasury_ata,
+                position,
+                referrer_account: referrer.map(|r| self.referrer(&r)),
+                token_program: spl_token::ID,
+            },
+            ix::PayPremium { amount_usdc },
+        );
+        self.send(&[ix], &[payer]).await
+    }
+
+    // ----------------------------- cover -----------------------------
+
+    pub async fn init_cover_book(&mut self, params: CoverParams) -> TxResult {
+        let ix = Self::ix(
+            acc::InitCoverBook {
+                admin: self.admin(),
+                state: self.state(),
+                cover_book: self.cover_book(),
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::InitCoverBook { params },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_cover_params(&mut self, params: CoverParams) -> TxResult {
+        let ix = Self::ix(
+            acc::SetCoverParams { admin: self.admin(), state: self.state(), cover_book: self.cover_book() },
+            ix::SetCoverParams { params },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    /// `buyer` buys cover on the current epoch; its NFT is `cover_mint(covers_minted)`.
+    pub async fn buy_cover(&mut self, buyer: &Keypair, notional_u64: u64, max_premium_u64: u64) -> TxResult {
+        let owner = buyer.pubkey();
+        let epoch_id = self.state_account().await.current_epoch_id;
+        let minted = self.cover_book_account().await.covers_minted;
+        let mint = self.cover_mint(minted);
+        let ix = Self::ix(
+            acc::BuyCover {
+                buyer: owner,
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                cover_book: self.cover_book(),
+                cover_mint: mint,
+                cover: self.cover(&mint),
+                buyer_nft: self.cover_nft(&owner, &mint),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                buyer_ata: self.ata(&owner),
+                protocol_treasury_ata: self.treasury_ata,
+                token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::BuyCover { notional_u64, max_premium_u64 },
+        );
+        self.send(&[ix], &[buyer]).await
+    }
+
+    /// `holder` claims the cover of NFT `mint` from their ATA for it.
+    pub async fn claim_cover(&mut self, holder: &Keypair, mint: &Pubkey) -> TxResult {
+        let epoch_id = self.cover_account(mint).await.epoch_id;
+        let ix = Self::ix(
+            acc::ClaimCover {
+                holder: holder.pubkey(),
+                state: self.state(),
+                cover: self.cover(mint),
+                holder_nft: self.cover_nft(&holder.pubkey(), mint),
+                epoch: self.epoch(epoch_id),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                holder_ata: self.ata(&holder.pubkey()),
+                token_program: spl_token::ID,
+            },
+            ix::ClaimCover {},
+        );
+        self.send(&[ix], &[holder]).await
+    }
+
+    /// `holder` burns the cover NFT `mint` from their ATA for it and closes the cover.
+    pub async fn close_cover(&mut self, holder: &Keypair, mint: &Pubkey) -> TxResult {
+        let cover = self.cover_account(mint).await;
+        let ix = Self::ix(
+            acc::CloseCover {
+                holder: holder.pubkey(),
+                buyer: cover.buyer,
+                cover: self.cover(mint),
+                cover_mint: *mint,
+                holder_nft: self.cover_nft(&holder.pubkey(), mint),
+                epoch: self.epoch(cover.epoch_id),
+                token_program: spl_token::ID,
+            },
+            ix::CloseCover {},
+        );
+        self.send(&[ix], &[holder]).await
+    }
+
+    /// Hand the cover NFT `mint` from `from` to a new ATA of `to`.
+    pub async fn transfer_cover(&mut self, from: &Keypair, to: &Pubkey, mint: &Pubkey) -> TxResult {
+        let to_nft = self.create_ata(to, mint).await;
+        let from_nft = self.cover_nft(&from.pubkey(), mint);
+        let ix = spl_token::instruction::transfer(&spl_token::ID, &from_nft, &to_nft, &from.pubkey(), &[], 1).unwrap();
+        self.send(&[ix], &[from]).await
+    }
+
+    pub async fn claim_yield(&mut self, user: &Keypair) -> TxResult {
+        let owner = user.pubkey();
+        let referrer = self.position_account(&owner).await.referrer;
+        let ix = Self::ix(
+            acc::ClaimYield {
+                user: owner,
+                state: self.state(),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                user_ata: self.ata(&owner),
+                position: self.position(&owner),
+                referrer_account: (referrer != Pubkey::default()).then(|| self.referrer(&referrer)),
+                token_program: spl_token::ID,
+            },
+            ix::ClaimYield {},
+        );
+        self.send(&[ix], &[user]).await
+    }
+
+    // ----------------------------- events / payouts -----------------------------
+
+    /// Trigger as the admin with no strategy capital deployed.
+    pub async fn trigger(&mut self, epoch_id: u64, severity_bps: u16) -> TxResult {
+        let admin = self.ctx.payer.insecure_clone();
+        self.trigger_with(&admin, epoch_id, severity_bps, TriggerArgs::default(), None).await
+    }
+
+    pub async fn trigger_with(
+        &mut self,
+        signer: &Keypair,
+        epoch_id: u64,
+        severity_input_bps: u16,
+        args: TriggerArgs,
+        strategy: Option<&Strategy>,
+    ) -> TxResult {
+        let mut ix = Self::ix(
+            acc::TriggerEvent {
+                admin_or_oracle: signer.pubkey(),
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                oracle_list: self.oracle_list(),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                strategy_program: strategy.map(|_| mock_lending::ID),
+                strategy_authority: strategy.map(|_| self.strategy_authority()),
+                strategy_escrow: strategy.map(|_| self.strategy_escrow()),
+                token_program: spl_token::ID,
+            },
+            ix::TriggerEvent {
+                severity_input_bps,
+                user_cap_bps: args.user_cap_bps,
+                epoch_cap_fp_override: args.epoch_cap_fp_override,
+                evidence_hash: args.evidence_hash,
+                evidence_ts_opt: args.evidence_ts,
+            },
+        );
+        if let Some(s) = strategy {
+            ix.accounts.extend(s.remaining());
+        }
+        self.send(&[ix], &[signer]).await
+    }
+
+    pub async fn payout(&mut self, user: &Keypair, epoch_id: u64) -> TxResult {
+        let owner = user.pubkey();
+        let ix = Self::ix(
+            acc::PayoutUser {
+                user: owner,
+                asset_mint: self.usdc_mint,
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                vault_ata: self.vault(&self.usdc_mint),
+                user_ata: self.ata(&owner),
+                position: self.position(&owner),
+                claim: self.claim(epoch_id, &owner),
+                collateral_registry: self.registry(),
+                token_program: spl_token::ID,
+                associated_token_program: spl_associated_token_account::ID,
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::PayoutUser {},
+        );
+        self.send(&[ix], &[user]).await
+    }
+
+    pub async fn crank_payouts(&mut self, cranker: &Keypair, epoch_id: u64, owners: &[Pubkey]) -> TxResult {
+        let ix = self.crank_payouts_ix(cranker, epoch_id, owners);
+        self.send(&[ix], &[cranker]).await
+    }
+
+    /// `crank_payouts` for `owners` (their positions, claim receipts and ATAs).
+    pub fn crank_payouts_ix(&self, cranker: &Keypair, epoch_id: u64, owners: &[Pubkey]) -> Instruction {
+        let mut ix = Self::ix(
+            acc::CrankPayouts {
+                cranker: cranker.pubkey(),
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                cranker_ata: self.ata(&cranker.pubkey()),
+                token_program: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::CrankPayouts {},
+        );
+        for owner in owners {
+            ix.accounts.extend([
+                AccountMeta::new_readonly(self.position(owner), false),
+                AccountMeta::new(self.claim(epoch_id, owner), false),
+                AccountMeta::new(self.ata(owner), false),
+            ]);
+        }
+        ix
+    }
+
+    /// `post_merkle_root` for `dist` (admin pays the bitmap).
+    pub async fn post_merkle_root(&mut self, epoch_id: u64, dist: &Distribution) -> TxResult {
+        self.post_merkle_root_with(epoch_id, dist.root(), dist.total_fp(), dist.leaves.len() as u32).await
+    }
+
+    pub async fn post_merkle_root_with(&mut self, epoch_id: u64, root: [u8; 32], total_fp: u128, claimants: u32) -> TxResult {
+        let ix = Self::ix(
+            acc::PostMerkleRoot {
+                admin: self.admin(),
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                claim_bitmap: self.bitmap(epoch_id),
+                collateral_registry: self.registry(),
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::PostMerkleRoot { root, total_fp, claimants },
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    /// `claim_with_proof` of leaf `index` as `user`, in USDC.
+    pub async fn claim_with_proof(
+        &mut self,
+        user: &Keypair,
+        epoch_id: u64,
+        index: u32,
+        amount_fp: u128,
+        proof: Vec<[u8; 32]>,
+    ) -> TxResult {
+        let owner = user.pubkey();
+        let ix = Self::ix(
+            acc::ClaimWithProof {
+                user: owner,
+                asset_mint: self.usdc_mint,
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                claim_bitmap: self.bitmap(epoch_id),
+                vault_ata: self.vault(&self.usdc_mint),
+                user_ata: self.ata(&owner),
+                collateral_registry: self.registry(),
+                token_program: spl_token::ID,
+            },
+            ix::ClaimWithProof { index, amount_fp, proof },
+        );
+        self.send(&[ix], &[user]).await
+    }
+
+    // ----------------------------- account closing -----------------------------
+
+    pub async fn close_position(&mut self, user: &Keypair) -> TxResult {
+        let owner = user.pubkey();
+        let ix = Self::ix(
+            acc::ClosePosition { user: owner, state: self.state(), position: self.position(&owner) },
+            ix::ClosePosition {},
+        );
+        self.send(&[ix], &[user]).await
+    }
+
+    /// Permissionless: sent (and paid for) by the admin, refunding `payer`.
+    pub async fn close_claim_receipt(&mut self, epoch_id: u64, owner: &Pubkey, payer: &Pubkey) -> TxResult {
+        let ix = Self::ix(
+            acc::CloseClaimReceipt { epoch: self.epoch(epoch_id), claim: self.claim(epoch_id, owner), payer: *payer },
+            ix::CloseClaimReceipt {},
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    /// `close_epoch`, passing the claim bitmap when the epoch has one.
+    pub async fn close_epoch(&mut self, epoch_id: u64) -> TxResult {
+        let bitmap = self.bitmap(epoch_id);
+        let claim_bitmap = self.exists(bitmap).await.then_some(bitmap);
+        self.close_epoch_with(epoch_id, claim_bitmap).await
+    }
+
+    pub async fn close_epoch_with(&mut self, epoch_id: u64, claim_bitmap: Option<Pubkey>) -> TxResult {
+        let ix = Self::ix(
+            acc::CloseEpoch { admin: self.admin(), state: self.state(), epoch: self.epoch(epoch_id), claim_bitmap },
+            ix::CloseEpoch {},
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    // ----------------------------- migration -----------------------------
+
+    /// `migrate_state` signed (and paid for) by `admin`; `epoch_ids` go in remaining accounts.
+    pub async fn migrate_state(&mut self, admin: &Keypair, epoch_ids: &[u64]) -> TxResult {
+        let mut ix = Self::ix(
+            acc::MigrateState {
+                admin: admin.pubkey(),
+                state: self.state(),
+                oracle_list: self.oracle_list(),
+                collateral_registry: self.registry(),
+                usdc_mint: self.usdc_mint,
+                vault_ata: self.vault(&self.usdc_mint),
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::MigrateState {},
+        );
+        ix.accounts.extend(epoch_ids.iter().map(|&id| AccountMeta::new(self.epoch(id), false)));
+        self.send(&[ix], &[admin]).await
+    }
+
+    /// `migrate_position` paid for by the admin; `extra` (claim receipt, epoch pairs) go in
+    /// remaining accounts.
+    pub async fn migrate_position(&mut self, owner: &Pubkey, extra: &[Pubkey]) -> TxResult {
+        let mut ix = Self::ix(
+            acc::MigratePosition {
+                payer: self.admin(),
+                owner: *owner,
+                position: self.position(owner),
+                state: self.state(),
+                system_program: anchor_lang::system_program::ID,
+            },
+            ix::MigratePosition {},
+        );
+        ix.accounts.extend(extra.iter().map(|&a| AccountMeta::new(a, false)));
+        self.send(&[ix], &[]).await
+    }
+
+    // ----------------------------- strategy -----------------------------
+
+    /// Point the pool at `mock_lending` and open an obligation for the strategy PDA.
+    pub async fn setup_strategy(&mut self, max_bps: u16, junior_yield_bps: u16) -> Strategy {
+        self.set_strategy(mock_lending::ID, max_bps, junior_yield_bps).await.unwrap();
+        let authority = self.strategy_authority();
+        let obligation = pda(&[b"obligation", authority.as_ref()], &mock_lending::ID);
+        let reserve_authority = pda(&[b"reserve"], &mock_lending::ID);
+        let init = Instruction {
+            program_id: mock_lending::ID,
+            accounts: mock_lending::accounts::InitObligation {
+                payer: self.admin(),
+                obligation,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: mock_lending::instruction::InitObligation { authority }.data(),
+        };
+        self.send(&[init], &[]).await.unwrap();
+        let mint = self.usdc_mint;
+        let reserve_ata = self.create_ata(&reserve_authority, &mint).await;
+        Strategy { obligation, reserve_authority, reserve_ata }
+    }
+
+    fn strategy_op(&self, strategy: &Strategy, data: impl InstructionData) -> Instruction {
+        let mut ix = Self::ix(
+            acc::StrategyOp {
+                admin: self.admin(),
+                state: self.state(),
+                collateral_registry: self.registry(),
+                vault_ata: self.vault(&self.usdc_mint),
+                strategy_program: mock_lending::ID,
+                strategy_authority: self.strategy_authority(),
+                strategy_escrow: self.strategy_escrow(),
+                usdc_mint: self.usdc_mint,
+                token_program: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            },
+            data,
+        );
+        ix.accounts.extend(strategy.remaining());
+        ix
+    }
+
+    pub async fn strategy_deploy(&mut self, strategy: &Strategy, amount_usdc: u64) -> TxResult {
+        let ix = self.strategy_op(strategy, ix::StrategyDeploy { amount_usdc });
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn strategy_recall(&mut self, strategy: &Strategy, amount_usdc: Option<u64>) -> TxResult {
+        let ix = self.strategy_op(strategy, ix::StrategyRecall { amount_usdc });
+        self.send(&[ix], &[]).await
+    }
+
+    /// Simulate lending interest: the admin funds the reserve and credits the obligation.
+    pub async fn accrue(&mut self, strategy: &Strategy, amount: u64) {
+        let admin = self.admin();
+        let mint = self.usdc_mint;
+        if !self.exists(self.ata(&admin)).await {
+            self.create_ata(&admin, &mint).await;
+        }
+        self.mint_to(&mint, &admin, amount).await;
+        let ix = Instruction {
+            program_id: mock_lending::ID,
+            accounts: mock_lending::accounts::Accrue {
+                funder: admin,
+                funder_token: self.ata(&admin),
+                reserve_ata: strategy.reserve_ata,
+                obligation: strategy.obligation,
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: mock_lending::instruction::Accrue { amount }.data(),
+        };
+        self.send(&[ix], &[]).await.unwrap();
+    }
+
+    // ----------------------------- views -----------------------------
+
+    pub fn pool_stats_ix(&self) -> Instruction {
+        Self::ix(acc::ViewPoolStats { state: self.state(), collateral_registry: self.registry() }, ix::PoolStats {})
+    }
+
+    /// Quote depositing `amount` of `mint`, referred by `referrer` (whose account is passed).
+    pub fn quote_deposit_ix(&self, mint: &Pubkey, amount: u64, referrer: Option<Pubkey>) -> Instruction {
+        Self::ix(
+            acc::QuoteDeposit {
+                state: self.state(),
+                collateral_registry: self.registry(),
+                asset_mint: *mint,
+                referrer_account: referrer.map(|r| self.referrer(&r)),
+            },
+            ix::QuoteDeposit { amount },
+        )
+    }
+
+    pub fn user_position_view_ix(&self, owner: &Pubkey) -> Instruction {
+        Self::ix(acc::ViewUserPosition { position: self.position(owner) }, ix::UserPositionView {})
+    }
+
+    pub fn quote_withdraw_ix(&self, owner: &Pubkey, amount_usdc: u64, tranche: u8) -> Instruction {
+        Self::ix(
+            acc::QuoteWithdraw { state: self.state(), position: self.position(owner) },
+            ix::QuoteWithdraw { amount_usdc, tranche },
+        )
+    }
+
+    pub fn epoch_stats_ix(&self, epoch_id: u64) -> Instruction {
+        Self::ix(acc::ViewEpochStats { epoch: self.epoch(epoch_id) }, ix::EpochStats {})
+    }
+
+    pub fn epoch_history_view_ix(&self) -> Instruction {
+        Self::ix(
+            acc::ViewEpochHistory { state: self.state(), epoch_history: self.epoch_history() },
+            ix::EpochHistoryView {},
+        )
+    }
+
+    pub fn capacity_view_ix(&self) -> Instruction {
+        Self::ix(acc::ViewCapacity { state: self.state(), cover_book: self.cover_book() }, ix::CapacityView {})
+    }
+
+    pub fn quote_premium_ix(&self, notional_u64: u64, duration_secs: u64) -> Instruction {
+        Self::ix(
+            acc::ViewCapacity { state: self.state(), cover_book: self.cover_book() },
+            ix::QuotePremium { notional_u64, duration_secs },
+        )
+    }
+
+    pub fn quote_user_payout_ix(&self, epoch_id: u64, owner: &Pubkey) -> Instruction {
+        Self::ix(
+            acc::QuoteUserPayout {
+                state: self.state(),
+                epoch: self.epoch(epoch_id),
+                collateral_registry: self.registry(),
+                position: self.position(owner),
+            },
+            ix::QuoteUserPayout {},
+        )
+    }
+}
+
+/// Optional `trigger_event` arguments.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TriggerArgs {
+    pub user_cap_bps: Option<u16>,
+    pub epoch_cap_fp_override: Option<u128>,
+    pub evidence_hash: Option<[u8; 32]>,
+    pub evidence_ts: Option<i64>,
+}
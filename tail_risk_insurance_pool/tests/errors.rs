@@ -0,0 +1,486 @@
+//! Every reachable `ErrorCode`.
+//!
+//! Not covered: `MathOverflow` needs amounts beyond any u64 token supply.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token;
+use common::{assert_error, default_params, flat_cover, fp, usdc, Distribution, Pool, Strategy, TriggerArgs, DAY, YEAR};
+use solana_sdk::signature::{Keypair, Signer};
+use tail_risk_insurance_pool::{CoverParams, ErrorCode, ReferralTier, PAUSE_ALL, PAUSE_DEPOSITS, PAUSE_WITHDRAWALS};
+
+#[tokio::test]
+async fn unauthorized() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(5_000)).await;
+    let rita = pool.user(0).await;
+
+    assert_error(pool.set_paused_as(&alice, PAUSE_ALL).await, ErrorCode::Unauthorized);
+    assert_error(pool.guardian_pause(&alice, PAUSE_DEPOSITS).await, ErrorCode::Unauthorized);
+    assert_error(pool.deposit(&alice, usdc(1_000), 2).await, ErrorCode::Unauthorized);
+    // Deposit fees only go to the protocol treasury
+    let mut ix = pool.deposit_ix(alice.pubkey(), &pool.usdc_mint, usdc(1_000), 0, None, None, None);
+    let treasury = ix.accounts.iter_mut().find(|a| a.pubkey == pool.treasury_ata).unwrap();
+    treasury.pubkey = pool.ata(&alice.pubkey());
+    assert_error(pool.send(&[ix], &[&alice]).await, ErrorCode::Unauthorized);
+
+    // Only the admin or an allowlisted oracle may trigger
+    pool.open_epoch(1).await;
+    assert_error(pool.trigger_with(&alice, 1, 1_000, TriggerArgs::default(), None).await, ErrorCode::Unauthorized);
+
+    // A referred position must carry the referrer's account on every deposit
+    pool.register_referrer(&rita).await.unwrap();
+    let mint = pool.usdc_mint;
+    pool.deposit_asset(&alice, &mint, usdc(1_000), 0, Some(rita.pubkey())).await.unwrap();
+    pool.warp(10).await;
+    assert_error(pool.deposit(&alice, usdc(1_000), 0).await, ErrorCode::Unauthorized);
+
+    // ... and on premiums, which may only credit that referrer: no self-referral rebates
+    let mallory = pool.user(usdc(100)).await;
+    pool.register_referrer(&mallory).await.unwrap();
+    assert_error(pool.pay_premium(&alice, usdc(10), None).await, ErrorCode::Unauthorized);
+    assert_error(pool.pay_premium(&alice, usdc(10), Some(mallory.pubkey())).await, ErrorCode::Unauthorized);
+    assert_error(pool.pay_premium(&mallory, usdc(10), Some(mallory.pubkey())).await, ErrorCode::Unauthorized);
+}
+
+#[tokio::test]
+async fn paused() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(2_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    assert_error(pool.set_paused(PAUSE_ALL + 1).await, ErrorCode::ParamOutOfBounds);
+    pool.set_paused(PAUSE_ALL).await.unwrap();
+
+    pool.warp(10).await;
+    assert_error(pool.deposit(&alice, usdc(500), 0).await, ErrorCode::Paused);
+    assert_error(pool.withdraw(&alice, usdc(100), 0).await, ErrorCode::Paused);
+    assert_error(pool.pay_premium(&alice, usdc(100), None).await, ErrorCode::Paused);
+    assert_error(pool.claim_yield(&alice).await, ErrorCode::Paused);
+    assert_error(pool.collect_fees().await, ErrorCode::Paused);
+    pool.open_epoch(1).await;
+    assert_error(pool.trigger(1, 1_000).await, ErrorCode::Paused);
+
+    // Each bit gates its own operations only
+    pool.set_paused(PAUSE_ALL & !PAUSE_DEPOSITS).await.unwrap();
+    pool.pay_premium(&alice, usdc(100), None).await.unwrap();
+    assert_error(pool.withdraw(&alice, usdc(100), 0).await, ErrorCode::Paused);
+}
+
+#[tokio::test]
+async fn busy() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(5_000)).await;
+    let strategy = pool.setup_strategy(5_000, 0).await;
+    pool.deposit(&alice, usdc(2_000), 0).await.unwrap();
+    pool.register_referrer(&alice).await.unwrap();
+    pool.init_cover_book(flat_cover(5_000, 200)).await.unwrap();
+    pool.open_epoch(1).await;
+    pool.buy_cover(&alice, usdc(100), u64::MAX).await.unwrap();
+    let cover = pool.cover_mint(0);
+
+    // While another instruction holds the guard every CPI-making instruction is
+    // rejected before any other check
+    pool.set_processing(true).await;
+    assert_error(pool.deposit(&alice, usdc(500), 0).await, ErrorCode::Busy);
+    assert_error(pool.withdraw(&alice, usdc(100), 0).await, ErrorCode::Busy);
+    assert_error(pool.emergency_withdraw(&alice).await, ErrorCode::Busy);
+    assert_error(pool.pay_premium(&alice, usdc(100), None).await, ErrorCode::Busy);
+    assert_error(pool.claim_yield(&alice).await, ErrorCode::Busy);
+    assert_error(pool.claim_referral_rewards(&alice).await, ErrorCode::Busy);
+    assert_error(pool.collect_fees().await, ErrorCode::Busy);
+    assert_error(pool.strategy_deploy(&strategy, usdc(100)).await, ErrorCode::Busy);
+    assert_error(pool.strategy_recall(&strategy, None).await, ErrorCode::Busy);
+    assert_error(pool.buy_cover(&alice, usdc(100), u64::MAX).await, ErrorCode::Busy);
+    assert_error(pool.trigger(1, 1_000).await, ErrorCode::Busy);
+    // Instructions without CPIs are not guarded
+    pool.set_policy(0, None).await.unwrap();
+
+    pool.set_processing(false).await;
+    pool.trigger(1, 1_000).await.unwrap();
+    pool.set_processing(true).await;
+    assert_error(pool.payout(&alice, 1).await, ErrorCode::Busy);
+    assert_error(pool.crank_payouts(&alice, 1, &[alice.pubkey()]).await, ErrorCode::Busy);
+    assert_error(pool.claim_cover(&alice, &cover).await, ErrorCode::Busy);
+    assert_error(pool.finalize_epoch(1, None).await, ErrorCode::Busy);
+
+    // Each instruction releases the guard when it completes
+    pool.set_processing(false).await;
+    pool.payout(&alice, 1).await.unwrap();
+    pool.claim_cover(&alice, &cover).await.unwrap();
+    pool.finalize_epoch(1, None).await.unwrap();
+    assert!(!pool.state_account().await.processing);
+}
+
+#[tokio::test]
+async fn invalid_remaining_accounts() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(2_000)).await;
+    let bob = pool.user(0).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    pool.open_epoch(1).await;
+    assert_error(pool.crank_payouts(&bob, 1, &[alice.pubkey()]).await, ErrorCode::EpochNotActive);
+    pool.trigger(1, 1_000).await.unwrap();
+
+    assert_error(pool.crank_payouts(&bob, 1, &[]).await, ErrorCode::InvalidRemainingAccounts);
+    // A triple must be a position with its own claim receipt and ATA
+    let mut ix = pool.crank_payouts_ix(&bob, 1, &[alice.pubkey()]);
+    let last = ix.accounts.len() - 1;
+    ix.accounts[last].pubkey = pool.ata(&bob.pubkey());
+    assert_error(pool.send(&[ix], &[&bob]).await, ErrorCode::InvalidRemainingAccounts);
+
+    // ... and the ATA must still belong to the holder
+    let ata = pool.ata(&alice.pubkey());
+    let (owner, bob_key) = (spl_token::instruction::AuthorityType::AccountOwner, bob.pubkey());
+    let ix = spl_token::instruction::set_authority(&spl_token::ID, &ata, Some(&bob_key), owner, &alice.pubkey(), &[]).unwrap();
+    pool.send(&[ix], &[&alice]).await.unwrap();
+    assert_error(pool.crank_payouts(&bob, 1, &[alice.pubkey()]).await, ErrorCode::InvalidRemainingAccounts);
+}
+
+#[tokio::test]
+async fn merkle_settlement() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(2_000)).await;
+    let bob = pool.user(usdc(2_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(1_000), 0).await.unwrap();
+    let dist = Distribution::new(&[(alice.pubkey(), fp(5)), (bob.pubkey(), fp(5))]);
+
+    // A root goes on a triggered epoch nobody has been paid from on chain
+    pool.open_epoch(1).await;
+    assert_error(pool.post_merkle_root(1, &dist).await, ErrorCode::EpochNotActive);
+    pool.trigger(1, 1_000).await.unwrap();
+    pool.payout(&alice, 1).await.unwrap();
+    assert_error(pool.post_merkle_root(1, &dist).await, ErrorCode::SettlementModeConflict);
+    pool.finalize_epoch(1, None).await.unwrap();
+
+    pool.open_epoch(2).await;
+    pool.trigger(2, 1_000).await.unwrap();
+    assert_error(pool.post_merkle_root_with(2, dist.root(), fp(1_000), 2).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.post_merkle_root_with(2, dist.root(), fp(6), 0).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.post_merkle_root_with(2, [0; 32], fp(6), 2).await, ErrorCode::ParamOutOfBounds);
+    // Posted total below what the leaves add up to
+    pool.post_merkle_root_with(2, dist.root(), fp(6), 2).await.unwrap();
+
+    assert_error(pool.payout(&bob, 2).await, ErrorCode::SettlementModeConflict);
+    assert_error(pool.crank_payouts(&bob, 2, &[bob.pubkey()]).await, ErrorCode::SettlementModeConflict);
+    // A leaf pays only its own owner, amount and index
+    assert_error(pool.claim_with_proof(&bob, 2, 0, fp(5), dist.proof(0)).await, ErrorCode::InvalidMerkleProof);
+    assert_error(pool.claim_with_proof(&bob, 2, 1, fp(6), dist.proof(1)).await, ErrorCode::InvalidMerkleProof);
+    assert_error(pool.claim_with_proof(&bob, 2, 0, fp(5), dist.proof(1)).await, ErrorCode::InvalidMerkleProof);
+    pool.claim_with_proof(&alice, 2, 0, fp(5), dist.proof(0)).await.unwrap();
+    assert_error(pool.claim_with_proof(&bob, 2, 1, fp(5), dist.proof(1)).await, ErrorCode::InsufficientPoolBalance);
+
+    // The bitmap must close with its epoch
+    pool.finalize_epoch(2, None).await.unwrap();
+    assert_error(pool.close_epoch_with(2, None).await, ErrorCode::SettlementModeConflict);
+}
+
+#[tokio::test]
+async fn rolling_epochs() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    let keeper = pool.user(0).await;
+    assert_error(pool.set_rolling_mode(true, 0).await, ErrorCode::ParamOutOfBounds);
+    pool.set_rolling_mode(true, DAY as u32).await.unwrap();
+
+    // Windows are fixed and ids sequential; only the admin starts the first epoch
+    let now = pool.now().await;
+    assert_error(pool.start_epoch(1, now, now + DAY).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.start_epoch(2, 0, 0).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.roll_epoch(&keeper, 1).await, ErrorCode::Unauthorized);
+    pool.start_epoch(1, 0, 0).await.unwrap();
+    assert_error(pool.roll_epoch(&keeper, 2).await, ErrorCode::EpochNotActive);
+
+    // Deposits must carry the running epoch
+    let mint = pool.usdc_mint;
+    assert_error(pool.deposit_passing(&alice, &mint, usdc(1_000), 0, None, None).await, ErrorCode::CurrentEpochRequired);
+}
+
+#[tokio::test]
+async fn epoch_registry() {
+    let mut pool = Pool::boot().await;
+    pool.initialize(default_params(pool.treasury.pubkey())).await.unwrap();
+    let stranger = pool.user(0).await;
+    assert_error(pool.init_epoch_history_as(&stranger).await, ErrorCode::Unauthorized);
+    pool.init_epoch_history().await.unwrap();
+
+    // Ids only go up; an epoch may be scheduled but never start in the past
+    let now = pool.now().await;
+    assert_error(pool.start_epoch(0, now, 0).await, ErrorCode::ParamOutOfBounds);
+    pool.start_epoch(2, now + 60, now + 120).await.unwrap();
+    assert_error(pool.start_epoch(1, now + 120, 0).await, ErrorCode::ParamOutOfBounds);
+
+    // Windows never overlap; an open-ended epoch runs until it is finalized or ended
+    assert_error(pool.start_epoch(3, now + 100, 0).await, ErrorCode::EpochOverlap);
+    assert_error(pool.end_epoch(2).await, ErrorCode::EpochNotActive);
+    pool.start_epoch(3, now + 120, 0).await.unwrap();
+    assert_error(pool.start_epoch(4, now + 30 * DAY, 0).await, ErrorCode::EpochOverlap);
+    assert_error(pool.end_epoch(3).await, ErrorCode::EpochNotActive);
+    pool.warp(DAY).await;
+    pool.end_epoch(3).await.unwrap();
+    assert_error(pool.end_epoch(3).await, ErrorCode::EpochNotActive);
+    pool.start_epoch(4, now + 30 * DAY, 0).await.unwrap();
+}
+
+#[tokio::test]
+async fn cover_sales() {
+    let mut pool = Pool::start().await;
+    assert_error(pool.init_cover_book(flat_cover(10_001, 0)).await, ErrorCode::ParamOutOfBounds);
+    pool.init_cover_book(flat_cover(5_000, 200)).await.unwrap();
+    let steep = CoverParams { slope2_bps: 10_001, ..flat_cover(5_000, 200) };
+    assert_error(pool.set_cover_params(steep).await, ErrorCode::ParamOutOfBounds);
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(100)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap(); // 995 of capital
+    let now = pool.now().await;
+    pool.start_epoch(1, now + 60, now + 60 + YEAR).await.unwrap();
+
+    // Nothing is sold on a scheduled epoch before it opens
+    assert_error(pool.buy_cover(&bob, usdc(100), u64::MAX).await, ErrorCode::EpochNotActive);
+    pool.warp(60).await;
+    assert_error(pool.buy_cover(&bob, 0, 0).await, ErrorCode::MinDeposit);
+    assert_error(pool.buy_cover(&bob, usdc(498), u64::MAX).await, ErrorCode::CapacityExceeded);
+    assert_error(pool.buy_cover(&bob, usdc(100), usdc(2) - 1).await, ErrorCode::SlippageExceeded);
+    pool.buy_cover(&bob, usdc(100), usdc(2)).await.unwrap();
+    pool.set_cover_params(flat_cover(5_000, 0)).await.unwrap();
+    assert_error(pool.buy_cover(&bob, usdc(1), u64::MAX).await, ErrorCode::ZeroPremium);
+    let mint = pool.cover_mint(0);
+    assert_error(pool.claim_cover(&bob, &mint).await, ErrorCode::EpochNotActive);
+    assert_error(pool.close_cover(&bob, &mint).await, ErrorCode::CoverActive);
+
+    // Sales stop at the trigger; only the NFT's holder claims, and each cover pays once
+    pool.trigger(1, 1_000).await.unwrap();
+    assert_error(pool.buy_cover(&bob, usdc(1), u64::MAX).await, ErrorCode::EpochNotActive);
+    let carol = pool.user(0).await;
+    pool.transfer_cover(&bob, &carol.pubkey(), &mint).await.unwrap();
+    assert_error(pool.claim_cover(&bob, &mint).await, ErrorCode::NotCoverHolder);
+    assert_error(pool.finalize_epoch(1, None).await, ErrorCode::CoverClaimWindowActive);
+    pool.claim_cover(&carol, &mint).await.unwrap();
+    assert_error(pool.claim_cover(&carol, &mint).await, ErrorCode::NothingToPayout);
+
+    // No cover is sold on an open-ended epoch
+    pool.finalize_epoch(1, None).await.unwrap();
+    let now = pool.now().await;
+    pool.start_epoch(2, now, 0).await.unwrap();
+    assert_error(pool.buy_cover(&bob, usdc(1), u64::MAX).await, ErrorCode::EpochNotActive);
+}
+
+#[tokio::test]
+async fn deposit_limits() {
+    let mut pool = Pool::start_with(|p| p.user_deposit_cap_fp = fp(1_000)).await;
+    let alice = pool.user(usdc(5_000)).await;
+
+    assert_error(pool.deposit(&alice, usdc(99), 0).await, ErrorCode::MinDeposit);
+    assert_error(pool.pay_premium(&alice, 0, None).await, ErrorCode::MinDeposit);
+    assert_error(pool.deposit(&alice, usdc(1_100), 0).await, ErrorCode::UserCapExceeded);
+
+    pool.deposit(&alice, usdc(500), 0).await.unwrap();
+    assert_error(pool.deposit(&alice, usdc(100), 1).await, ErrorCode::DepositCooldown);
+}
+
+#[tokio::test]
+async fn seventeenth_lot_is_rejected() {
+    let mut pool = Pool::start_with(|p| p.min_seconds_between_deposits = 0).await;
+    let alice = pool.user(usdc(2_000)).await;
+    for _ in 0..16 {
+        pool.deposit(&alice, usdc(100), 0).await.unwrap();
+    }
+    assert_error(pool.deposit(&alice, usdc(100), 0).await, ErrorCode::TooManyLots);
+    // The other tranche has its own ring
+    pool.deposit(&alice, usdc(100), 1).await.unwrap();
+}
+
+#[tokio::test]
+async fn epoch_timing_and_claims() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+
+    let now = pool.now().await;
+    assert_error(pool.start_epoch(1, now - 60, now + 120).await, ErrorCode::EpochNotActive);
+    assert_error(pool.start_epoch(1, now, now).await, ErrorCode::EpochNotActive);
+
+    pool.open_epoch(1).await;
+    assert_error(pool.finalize_epoch(1, None).await, ErrorCode::EpochNotActive);
+    assert_error(pool.payout(&alice, 1).await, ErrorCode::EpochNotActive);
+
+    pool.trigger(1, 1_000).await.unwrap();
+    assert_error(pool.trigger(1, 2_000).await, ErrorCode::EpochAlreadyTriggered);
+    // One claim window at a time
+    pool.warp(DAY).await;
+    pool.open_epoch(2).await;
+    assert_error(pool.trigger(2, 1_000).await, ErrorCode::Paused);
+
+    pool.payout(&alice, 1).await.unwrap();
+    assert_error(pool.payout(&alice, 1).await, ErrorCode::NothingToPayout);
+
+    pool.finalize_epoch(1, None).await.unwrap();
+    assert_error(pool.finalize_epoch(1, None).await, ErrorCode::EpochNotActive);
+}
+
+#[tokio::test]
+async fn account_closing_guards() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    assert_error(pool.close_position(&alice).await, ErrorCode::AccountNotEmpty);
+
+    pool.open_epoch(1).await;
+    assert_error(pool.close_epoch(1).await, ErrorCode::EpochNotActive);
+    pool.trigger(1, 1_000).await.unwrap();
+    pool.payout(&alice, 1).await.unwrap();
+    assert_error(pool.close_claim_receipt(1, &alice.pubkey(), &alice.pubkey()).await, ErrorCode::EpochNotActive);
+
+    pool.finalize_epoch(1, None).await.unwrap();
+    assert_error(pool.close_claim_receipt(1, &alice.pubkey(), &alice.pubkey()).await, ErrorCode::RetentionPeriodActive);
+    // Rent only goes back to whoever paid it
+    let admin = pool.admin();
+    assert_error(pool.close_claim_receipt(1, &alice.pubkey(), &admin).await, ErrorCode::Unauthorized);
+    assert_error(pool.close_epoch(1).await, ErrorCode::AccountNotEmpty);
+}
+
+#[tokio::test]
+async fn nothing_to_withdraw_or_claim() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    let rita = pool.user(0).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    pool.register_referrer(&rita).await.unwrap();
+
+    // All 995 net is still in its lot; one more is beyond what will ever mature
+    assert_error(pool.withdraw(&alice, usdc(995), 0).await, ErrorCode::LockupNotExpired);
+    assert_error(pool.withdraw(&alice, usdc(996), 0).await, ErrorCode::InsufficientPoolBalance);
+    assert_error(pool.withdraw(&alice, usdc(100), 1).await, ErrorCode::InsufficientPoolBalance);
+    assert_error(pool.claim_yield(&alice).await, ErrorCode::NothingToPayout);
+    assert_error(pool.claim_referral_rewards(&rita).await, ErrorCode::NothingToPayout);
+    assert_error(pool.collect_fees().await, ErrorCode::NothingToPayout);
+}
+
+#[tokio::test]
+async fn param_out_of_bounds() {
+    let mut pool = Pool::boot().await;
+    let mut params = default_params(pool.treasury.pubkey());
+    params.tranche_weight_junior_bps = 15_000;
+    assert_error(pool.initialize(params).await, ErrorCode::ParamOutOfBounds);
+
+    pool.initialize(default_params(pool.treasury.pubkey())).await.unwrap();
+    assert_error(pool.set_fee_model(0, 1_001, 25, 1_000).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.set_fee_model(3, 50, 25, 1_000).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.set_fee_model(0, 50, 25, 5_001).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.set_curve_and_weights(0, 1, 0, 100, 10_001, 10_000).await, ErrorCode::ParamOutOfBounds);
+    assert_error(
+        pool.set_referral_tiers(vec![
+            ReferralTier { min_volume_fp: fp(50_000), fee_bps: 50 },
+            ReferralTier { min_volume_fp: fp(10_000), fee_bps: 100 },
+        ])
+        .await,
+        ErrorCode::ParamOutOfBounds,
+    );
+    assert_error(pool.set_strategy(mock_lending::ID, 10_001, 0).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.set_outflow_limits(DAY, 0, 10_001).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.set_outflow_limits(-1, fp(1_000), 0).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.set_crank_reward(usdc(10) + 1).await, ErrorCode::ParamOutOfBounds);
+
+    // Base collateral stays at par and enabled; haircuts must be below 100%
+    let usdc_mint = pool.usdc_mint;
+    assert_error(pool.set_collateral_params(&usdc_mint, 100, 10_000, true).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.set_collateral_params(&usdc_mint, 0, 10_000, false).await, ErrorCode::ParamOutOfBounds);
+    let usdt = pool.create_mint(6).await;
+    assert_error(pool.add_collateral(&usdt, 10_000, 10_000).await, ErrorCode::ParamOutOfBounds);
+    assert_error(pool.add_collateral(&usdt, 0, 0).await, ErrorCode::ParamOutOfBounds);
+}
+
+#[tokio::test]
+async fn collateral_registry_limits() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+
+    let usdt = pool.create_mint(6).await;
+    let dai = pool.create_mint(6).await;
+    let pyusd = pool.create_mint(6).await;
+    let extra = pool.create_mint(6).await;
+    pool.add_collateral(&usdt, 0, 2_000).await.unwrap();
+    pool.add_collateral(&dai, 0, 10_000).await.unwrap();
+    pool.add_collateral(&pyusd, 0, 10_000).await.unwrap();
+    assert_error(pool.add_collateral(&extra, 0, 10_000).await, ErrorCode::TooManyCollaterals);
+
+    let treasury = pool.treasury.pubkey();
+    for mint in [usdt, dai] {
+        pool.create_ata(&alice.pubkey(), &mint).await;
+        pool.create_ata(&treasury, &mint).await;
+        pool.mint_to(&mint, &alice.pubkey(), usdc(1_000)).await;
+    }
+
+    // USDT may be at most 20% of pool value
+    pool.warp(10).await;
+    assert_error(pool.deposit_asset(&alice, &usdt, usdc(1_000), 0, None).await, ErrorCode::ConcentrationCapExceeded);
+
+    // 199 USDT against 995 USDC is within the cap; taking USDC out must keep it there
+    pool.deposit_asset(&alice, &usdt, usdc(200), 0, None).await.unwrap();
+    pool.warp(60).await;
+    assert_error(pool.withdraw(&alice, usdc(500), 0).await, ErrorCode::ConcentrationCapExceeded);
+    pool.withdraw(&alice, usdc(100), 0).await.unwrap();
+
+    pool.set_collateral_params(&dai, 0, 10_000, false).await.unwrap();
+    assert_error(pool.deposit_asset(&alice, &dai, usdc(1_000), 0, None).await, ErrorCode::UnsupportedCollateral);
+}
+
+#[tokio::test]
+async fn strategy_guards() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+
+    let unset = Strategy {
+        obligation: Pubkey::new_unique(),
+        reserve_authority: Pubkey::new_unique(),
+        reserve_ata: Pubkey::new_unique(),
+    };
+    assert_error(pool.strategy_deploy(&unset, usdc(100)).await, ErrorCode::StrategyNotConfigured);
+    assert_error(pool.strategy_recall(&unset, None).await, ErrorCode::StrategyNotConfigured);
+
+    // At most 50% of 995
+    let strategy = pool.setup_strategy(5_000, 0).await;
+    assert_error(pool.strategy_deploy(&strategy, usdc(498)).await, ErrorCode::StrategyCapExceeded);
+    pool.strategy_deploy(&strategy, usdc(400)).await.unwrap();
+    assert_error(pool.set_strategy(Keypair::new().pubkey(), 5_000, 0).await, ErrorCode::StrategyActive);
+
+    pool.set_paused(PAUSE_WITHDRAWALS).await.unwrap();
+    assert_error(pool.strategy_deploy(&strategy, usdc(50)).await, ErrorCode::Paused);
+    pool.set_paused(0).await.unwrap();
+
+    // Deployed capital must be recalled on trigger, which needs the lending program
+    pool.open_epoch(1).await;
+    assert_error(pool.trigger(1, 1_000).await, ErrorCode::StrategyNotConfigured);
+}
+
+#[tokio::test]
+async fn shutdown_guards() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(2_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    assert_error(pool.emergency_withdraw(&alice).await, ErrorCode::NotShutdown);
+
+    // Deployed capital must come back first
+    let strategy = pool.setup_strategy(5_000, 0).await;
+    pool.strategy_deploy(&strategy, usdc(100)).await.unwrap();
+    assert_error(pool.shutdown().await, ErrorCode::StrategyActive);
+    pool.strategy_recall(&strategy, None).await.unwrap();
+
+    pool.open_epoch(1).await;
+    pool.shutdown().await.unwrap();
+    assert_error(pool.shutdown().await, ErrorCode::PoolShutdown);
+    pool.warp(60).await;
+    let now = pool.now().await;
+    assert_error(pool.deposit(&alice, usdc(500), 0).await, ErrorCode::PoolShutdown);
+    assert_error(pool.withdraw(&alice, usdc(100), 0).await, ErrorCode::PoolShutdown);
+    assert_error(pool.pay_premium(&alice, usdc(100), None).await, ErrorCode::PoolShutdown);
+    assert_error(pool.strategy_deploy(&strategy, usdc(50)).await, ErrorCode::PoolShutdown);
+    assert_error(pool.start_epoch(2, now, now + DAY).await, ErrorCode::PoolShutdown);
+    assert_error(pool.trigger(1, 1_000).await, ErrorCode::PoolShutdown);
+
+    pool.emergency_withdraw(&alice).await.unwrap();
+    assert_error(pool.emergency_withdraw(&alice).await, ErrorCode::NothingToPayout);
+}
@@ -0,0 +1,408 @@
+//! Happy path of every instruction and view.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::{flat_cover, fp, usdc, Pool, TriggerArgs, SCALE, YEAR};
+use solana_sdk::signature::Signer;
+use tail_risk_insurance_pool::{
+    CapacityView, CoverParams, DepositQuote, EpochHistoryView, EpochStats, PoolStats, PremiumQuote, QuoteOut, ReferralTier,
+    UserPositionView, WithdrawQuote, PAUSE_ALL, PAUSE_DEPOSITS, PAUSE_TRIGGERS,
+};
+
+#[tokio::test]
+async fn initialize_sets_state_oracles_and_base_collateral() {
+    let mut pool = Pool::start().await;
+    let admin = pool.admin();
+    let usdc_mint = pool.usdc_mint;
+
+    let s = pool.state_account().await;
+    assert_eq!(s.admin, admin);
+    assert_eq!(s.usdc_mint, usdc_mint);
+    assert_eq!(s.protocol_treasury, pool.treasury.pubkey());
+    assert!(!s.claim_window_open && !s.processing);
+    assert_eq!((s.pause_flags, s.guardian), (0, Pubkey::default()));
+    assert_eq!(s.protocol_fee_bps, 50);
+    assert_eq!(s.total_deposited_fp, 0);
+
+    let reg = pool.registry_account().await;
+    assert_eq!(reg.count, 1);
+    assert_eq!(reg.assets[0].mint, usdc_mint);
+    assert_eq!(reg.assets[0].vault, pool.vault(&usdc_mint));
+    assert_eq!(reg.assets[0].haircut_bps, 0);
+    assert!(reg.assets[0].enabled);
+
+    let oracles = pool.oracle_list_account().await;
+    assert!(oracles.enabled);
+    assert_eq!(oracles.count, 0);
+
+    let params = common::default_params(pool.treasury.pubkey());
+    assert!(pool.initialize(params).await.is_err(), "state can only be created once");
+}
+
+#[tokio::test]
+async fn admin_setters_update_state() {
+    let mut pool = Pool::start().await;
+
+    pool.set_paused(PAUSE_ALL).await.unwrap();
+    assert_eq!(pool.state_account().await.pause_flags, PAUSE_ALL);
+    pool.set_paused(0).await.unwrap();
+
+    // The guardian adds to the mask; the admin may use the same instruction
+    let guardian = pool.user(0).await;
+    pool.set_guardian(guardian.pubkey()).await.unwrap();
+    pool.guardian_pause(&guardian, PAUSE_DEPOSITS).await.unwrap();
+    let admin = pool.ctx.payer.insecure_clone();
+    pool.guardian_pause(&admin, PAUSE_TRIGGERS).await.unwrap();
+    let s = pool.state_account().await;
+    assert_eq!((s.guardian, s.pause_flags), (guardian.pubkey(), PAUSE_DEPOSITS | PAUSE_TRIGGERS));
+    pool.set_paused(0).await.unwrap();
+
+    pool.set_policy(2, Some(fp(1_000))).await.unwrap();
+    pool.set_fee_model(1, 100, 50, 2_000).await.unwrap();
+    pool.set_referral_tiers(vec![
+        ReferralTier { min_volume_fp: fp(10_000), fee_bps: 50 },
+        ReferralTier { min_volume_fp: fp(50_000), fee_bps: 100 },
+    ])
+    .await
+    .unwrap();
+    pool.set_curve_and_weights(1, 2 * SCALE, 0, 200, 7_000, 10_000).await.unwrap();
+    pool.set_outflow_limits(86_400, fp(5_000), 1_000).await.unwrap();
+    pool.set_crank_reward(usdc(1)).await.unwrap();
+    pool.set_rolling_mode(true, 3_600).await.unwrap();
+
+    let s = pool.state_account().await;
+    assert_eq!((s.payout_policy, s.epoch_cap_fp), (2, fp(1_000)));
+    assert_eq!((s.fee_model, s.protocol_fee_bps, s.referral_fee_bps, s.performance_fee_bps), (1, 100, 50, 2_000));
+    assert_eq!(s.referral_tiers[1].fee_bps, 100);
+    assert_eq!(s.referral_tiers[2].min_volume_fp, 0);
+    assert_eq!((s.sev_quad_a_fp, s.sev_quad_b_fp, s.severity_floor_bps), (1, 2 * SCALE, 200));
+    assert_eq!((s.tranche_weight_senior_bps, s.tranche_weight_junior_bps), (7_000, 10_000));
+    assert_eq!((s.outflow_window_secs, s.outflow_cap_fp, s.outflow_cap_bps), (86_400, fp(5_000), 1_000));
+    assert_eq!((s.rolling_mode, s.epoch_duration_secs, s.current_epoch_id), (true, 3_600, 0));
+    assert_eq!(s.crank_reward_u64, usdc(1));
+}
+
+#[tokio::test]
+async fn deposit_and_withdraw_move_tokens() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(2_000)).await;
+    let bob = pool.user(usdc(2_000)).await;
+    let usdc_mint = pool.usdc_mint;
+    let vault = pool.vault(&usdc_mint);
+
+    // First deposit: 0.5% protocol fee, no referral fee (no LPs to credit yet)
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    assert_eq!(pool.balance(pool.treasury_ata).await, usdc(5));
+    assert_eq!(pool.balance(vault).await, usdc(995));
+    let p = pool.position_account(&alice.pubkey()).await;
+    assert_eq!(p.owner, alice.pubkey());
+    assert_eq!(p.senior_deposited_fp, fp(995));
+    assert_eq!(p.senior_lots.len, 1);
+
+    // Unreferred 0.25% referral fee is credited to the senior LP as income
+    pool.deposit(&bob, usdc(1_000), 1).await.unwrap();
+    let half_usdc_fp = fp(1) / 2;
+    let p = pool.position_account(&bob.pubkey()).await;
+    assert_eq!(p.junior_deposited_fp, fp(992) + half_usdc_fp);
+    let s = pool.state_account().await;
+    assert_eq!(s.senior_total_fp, fp(995));
+    assert_eq!(s.junior_total_fp, fp(992) + half_usdc_fp);
+    assert_eq!(s.yield_reserve_fp, fp(2) + half_usdc_fp);
+    assert_eq!(pool.balance(vault).await, usdc(1_990));
+
+    // Lots become withdrawable after the 60s lockup
+    pool.warp(60).await;
+    let before = pool.balance(pool.ata(&alice.pubkey())).await;
+    pool.withdraw(&alice, usdc(500), 0).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&alice.pubkey())).await, before + usdc(500));
+    assert_eq!(pool.position_account(&alice.pubkey()).await.senior_deposited_fp, fp(495));
+    let s = pool.state_account().await;
+    assert_eq!(s.total_deposited_fp, fp(495) + fp(992) + half_usdc_fp);
+    assert_eq!(pool.registry_account().await.assets[0].balance_fp, fp(1_490));
+}
+
+#[tokio::test]
+async fn referrer_accrues_and_claims_rewards() {
+    let mut pool = Pool::start().await;
+    let rita = pool.user(0).await;
+    let alice = pool.user(usdc(5_000)).await;
+    pool.register_referrer(&rita).await.unwrap();
+
+    let mint = pool.usdc_mint;
+    pool.deposit_asset(&alice, &mint, usdc(1_000), 0, Some(rita.pubkey())).await.unwrap();
+    assert_eq!(pool.position_account(&alice.pubkey()).await.referrer, rita.pubkey());
+    let r = pool.referrer_account(&rita.pubkey()).await;
+    assert_eq!(r.total_referred_fp, fp(1_000));
+    assert_eq!(r.pending_rewards_fp, fp(5) / 2);
+    assert_eq!(pool.state_account().await.referral_rewards_owed_fp, fp(5) / 2);
+
+    // Premiums from a referred LP count as volume and, fee-on-premium, pay the same referrer
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap();
+    pool.pay_premium(&alice, usdc(100), Some(rita.pubkey())).await.unwrap();
+    let r = pool.referrer_account(&rita.pubkey()).await;
+    assert_eq!((r.total_referred_fp, r.pending_rewards_fp), (fp(1_100), fp(11) / 4));
+
+    pool.claim_referral_rewards(&rita).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&rita.pubkey())).await, usdc(11) / 4);
+    let r = pool.referrer_account(&rita.pubkey()).await;
+    assert_eq!((r.pending_rewards_fp, r.lifetime_rewards_fp), (0, fp(11) / 4));
+    assert_eq!(pool.state_account().await.referral_rewards_owed_fp, 0);
+}
+
+#[tokio::test]
+async fn referred_deposits_are_quoted_at_the_referrers_tier() {
+    let mut pool = Pool::start().await;
+    pool.set_referral_tiers(vec![ReferralTier { min_volume_fp: fp(1_000), fee_bps: 100 }]).await.unwrap();
+    let rita = pool.user(0).await;
+    let alice = pool.user(usdc(2_000)).await;
+    pool.register_referrer(&rita).await.unwrap();
+    let mint = pool.usdc_mint;
+    pool.deposit_asset(&alice, &mint, usdc(1_000), 0, Some(rita.pubkey())).await.unwrap();
+    assert_eq!(pool.referrer_account(&rita.pubkey()).await.tier, 1);
+
+    // Rita's volume earned her the 1% tier; unreferred deposits still pay the base 0.25%
+    let q: DepositQuote = pool.view(pool.quote_deposit_ix(&mint, usdc(1_000), Some(rita.pubkey()))).await;
+    assert_eq!((q.protocol_fee_u64, q.referral_fee_u64, q.net_fp), (usdc(5), usdc(10), fp(985)));
+    let plain: DepositQuote = pool.view(pool.quote_deposit_ix(&mint, usdc(1_000), None)).await;
+    assert_eq!(plain.referral_fee_u64, usdc(5) / 2);
+
+    let before = pool.position_account(&alice.pubkey()).await.senior_deposited_fp;
+    let pending = pool.referrer_account(&rita.pubkey()).await.pending_rewards_fp;
+    pool.warp(10).await;
+    pool.deposit_asset(&alice, &mint, usdc(1_000), 0, Some(rita.pubkey())).await.unwrap();
+    assert_eq!(pool.position_account(&alice.pubkey()).await.senior_deposited_fp, before + q.net_fp);
+    assert_eq!(pool.referrer_account(&rita.pubkey()).await.pending_rewards_fp, pending + fp(10));
+}
+
+#[tokio::test]
+async fn secondary_collateral_is_haircut_and_configurable() {
+    let mut pool = Pool::start().await;
+    let usdt = pool.create_mint(6).await;
+    pool.add_collateral(&usdt, 200, 10_000).await.unwrap();
+
+    let alice = pool.user(0).await;
+    let treasury = pool.treasury.pubkey();
+    pool.create_ata(&alice.pubkey(), &usdt).await;
+    pool.create_ata(&treasury, &usdt).await;
+    pool.mint_to(&usdt, &alice.pubkey(), usdc(1_000)).await;
+
+    // 1000 USDT - 0.5% fee = 995 at par, credited at 98%
+    let credited = fp(995) * 9_800 / 10_000;
+    let q: DepositQuote = pool.view(pool.quote_deposit_ix(&usdt, usdc(1_000), None)).await;
+    assert_eq!((q.net_fp, q.protocol_fee_u64), (credited, usdc(5)));
+    pool.deposit_asset(&alice, &usdt, usdc(1_000), 0, None).await.unwrap();
+    assert_eq!(pool.position_account(&alice.pubkey()).await.senior_deposited_fp, credited);
+    assert_eq!(pool.state_account().await.total_deposited_fp, credited);
+    let reg = pool.registry_account().await;
+    assert_eq!(reg.count, 2);
+    assert_eq!(reg.assets[1].balance_fp, fp(995));
+    assert_eq!(pool.balance(pool.vault(&usdt)).await, usdc(995));
+
+    pool.set_collateral_params(&usdt, 100, 5_000, false).await.unwrap();
+    let a = pool.registry_account().await.assets[1];
+    assert_eq!((a.haircut_bps, a.concentration_cap_bps, a.enabled), (100, 5_000, false));
+}
+
+#[tokio::test]
+async fn premiums_and_strategy_yield_reach_lps_and_treasury() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // fee-on-premium
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(1_000)).await;
+    let carol = pool.user(usdc(100)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(1_000), 1).await.unwrap();
+    let strategy = pool.setup_strategy(5_000, 5_000).await;
+
+    // 100 premium: 0.5 to treasury, 99.5 split evenly between tranches
+    pool.pay_premium(&carol, usdc(100), None).await.unwrap();
+    assert_eq!(pool.balance(pool.treasury_ata).await, usdc(1) / 2);
+    assert_eq!(pool.state_account().await.yield_reserve_fp, fp(199) / 2);
+
+    // The vault funds the escrow, the strategy pulls from the escrow
+    pool.strategy_deploy(&strategy, usdc(500)).await.unwrap();
+    assert_eq!(pool.state_account().await.strategy_deployed_fp, fp(500));
+    assert_eq!(pool.balance(strategy.reserve_ata).await, usdc(500));
+    assert_eq!(pool.balance(pool.strategy_escrow()).await, 0);
+
+    // 100 interest: 10% performance fee, 90 split evenly
+    pool.accrue(&strategy, usdc(100)).await;
+    pool.strategy_recall(&strategy, None).await.unwrap();
+    let s = pool.state_account().await;
+    assert_eq!(s.strategy_deployed_fp, 0);
+    assert_eq!(pool.balance(pool.strategy_escrow()).await, 0);
+    assert_eq!(s.protocol_fees_accrued_fp, fp(10));
+    assert_eq!(s.yield_reserve_fp, fp(199) / 2 + fp(90));
+
+    pool.collect_fees().await.unwrap();
+    assert_eq!(pool.balance(pool.treasury_ata).await, usdc(1) / 2 + usdc(10));
+
+    // Each LP: 49.75 of premium + 45 of interest
+    for lp in [&alice, &bob] {
+        pool.claim_yield(lp).await.unwrap();
+        assert_eq!(pool.balance(pool.ata(&lp.pubkey())).await, usdc(94) + usdc(3) / 4);
+    }
+    assert_eq!(pool.state_account().await.yield_reserve_fp, 0);
+}
+
+#[tokio::test]
+async fn cover_is_sold_within_capacity_and_pays_out_on_trigger() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // fee-on-premium
+    pool.init_cover_book(flat_cover(5_000, 200)).await.unwrap();
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(100)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    let now = pool.now().await;
+    pool.start_epoch(1, now, now + YEAR).await.unwrap();
+
+    // 400 of cover for a year at 2%: 8 premium, 0.04 of it to the treasury
+    pool.buy_cover(&bob, usdc(400), usdc(8)).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&bob.pubkey())).await, usdc(92));
+    assert_eq!(pool.balance(pool.treasury_ata).await, usdc(4) / 100);
+    assert_eq!(pool.state_account().await.yield_reserve_fp, fp(796) / 100);
+
+    let v: CapacityView = pool.view(pool.capacity_view_ix()).await;
+    assert_eq!((v.capital_fp, v.sold_fp, v.max_cover_fp, v.available_fp), (fp(1_000), fp(400), fp(500), fp(100)));
+    assert_eq!((v.utilisation_bps, v.marginal_rate_bps, v.expected_loss_bps), (4_000, 200, 0));
+
+    // Each purchase is its own cover, held as a one-off NFT
+    pool.set_cover_params(flat_cover(5_000, 100)).await.unwrap();
+    pool.buy_cover(&bob, usdc(100), usdc(1)).await.unwrap();
+    let (first, second) = (pool.cover_mint(0), pool.cover_mint(1));
+    let c = pool.cover_account(&second).await;
+    assert_eq!((c.mint, c.buyer, c.epoch_id, c.notional_fp, c.premium_fp), (second, bob.pubkey(), 1, fp(100), fp(1)));
+    assert_eq!(pool.cover_account(&first).await.notional_fp, fp(400));
+    let book = pool.cover_book_account().await;
+    assert_eq!((book.sold_fp, book.covers_minted), (fp(500), 2));
+    assert_eq!(pool.balance(pool.cover_nft(&bob.pubkey(), &first)).await, 1);
+
+    // The NFT carries the cover: the first one is sold on
+    let carol = pool.user(0).await;
+    pool.transfer_cover(&bob, &carol.pubkey(), &first).await.unwrap();
+
+    // 10% severity pays 10% of notional, to whoever holds the NFT
+    pool.trigger(1, 1_000).await.unwrap();
+    pool.claim_cover(&carol, &first).await.unwrap();
+    pool.claim_cover(&bob, &second).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&carol.pubkey())).await, usdc(40));
+    assert_eq!(pool.balance(pool.ata(&bob.pubkey())).await, usdc(101));
+    assert_eq!(pool.cover_account(&first).await.claimed_fp, fp(40));
+    let e = pool.epoch_account(1).await;
+    assert_eq!((e.total_payout_fp, e.cover_paid_fp), (fp(50), fp(50)));
+}
+
+#[tokio::test]
+async fn premium_follows_utilisation_duration_and_expected_loss() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    // 1% base, +4% up to 25% utilisation, +20% more up to 100%; a 10% a year chance of a
+    // trigger at 20% input, which the linear curve pays at 20%: 2% expected loss
+    let params = CoverParams {
+        max_utilisation_bps: 10_000,
+        base_rate_bps: 100,
+        kink_utilisation_bps: 2_500,
+        slope1_bps: 400,
+        slope2_bps: 2_000,
+        event_probability_bps: 1_000,
+        reference_input_bps: 2_000,
+    };
+    pool.init_cover_book(params).await.unwrap();
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(100)).await;
+    pool.deposit(&alice, usdc(1_000), 1).await.unwrap();
+
+    // 400 is priced along the curve from 0% to 40%: 250 at an average 2% charge, 150 at
+    // an average 6% (4% + 2% at 32.5%), so 1% + 3.5% + 2% = 6.5% a year
+    let q: PremiumQuote = pool.view(pool.quote_premium_ix(usdc(400), YEAR as u64)).await;
+    assert_eq!((q.utilisation_bps, q.expected_loss_bps, q.rate_bps, q.premium_fp), (4_000, 200, 650, fp(26)));
+    assert!(q.within_capacity);
+    let q: PremiumQuote = pool.view(pool.quote_premium_ix(usdc(400), YEAR as u64 / 2)).await;
+    assert_eq!(q.premium_fp, fp(13), "half the duration, half the premium");
+
+    let now = pool.now().await;
+    pool.start_epoch(1, now, now + YEAR).await.unwrap();
+    pool.buy_cover(&bob, usdc(400), usdc(26)).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&bob.pubkey())).await, usdc(74));
+
+    // The rest of the capacity averages 70% utilisation: 1% + 16% + 2%; buying all 1,000
+    // at once would have cost the same 140
+    let q: PremiumQuote = pool.view(pool.quote_premium_ix(usdc(600), YEAR as u64)).await;
+    assert_eq!((q.rate_bps, q.premium_fp, q.within_capacity), (1_900, fp(114), true));
+    let q: PremiumQuote = pool.view(pool.quote_premium_ix(usdc(601), YEAR as u64)).await;
+    assert!(!q.within_capacity);
+
+    // Expected loss tracks the pool's severity curve
+    pool.set_curve_and_weights(0, 2 * SCALE, 0, 100, 5_000, 10_000).await.unwrap();
+    let v: CapacityView = pool.view(pool.capacity_view_ix()).await;
+    assert_eq!((v.expected_loss_bps, v.marginal_rate_bps), (400, 1_300));
+}
+
+#[tokio::test]
+async fn trigger_recalls_deployed_capital() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    let strategy = pool.setup_strategy(5_000, 0).await;
+    pool.strategy_deploy(&strategy, usdc(400)).await.unwrap();
+
+    pool.open_epoch(1).await;
+    let admin = pool.ctx.payer.insecure_clone();
+    pool.trigger_with(&admin, 1, 1_000, TriggerArgs::default(), Some(&strategy)).await.unwrap();
+
+    let s = pool.state_account().await;
+    assert_eq!(s.strategy_deployed_fp, 0);
+    assert!(s.claim_window_open);
+    let usdc_mint = pool.usdc_mint;
+    assert_eq!(pool.balance(pool.vault(&usdc_mint)).await, usdc(995));
+    assert_eq!(pool.registry_account().await.assets[0].balance_fp, fp(995));
+}
+
+#[tokio::test]
+async fn views_report_pool_position_epoch_and_quotes() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+
+    let stats: PoolStats = pool.view(pool.pool_stats_ix()).await;
+    assert_eq!(stats.total_deposited_fp, fp(995));
+    assert_eq!(stats.liquid_balance_fp, fp(995));
+    assert_eq!(stats.tracked_liabilities_fp, fp(995));
+
+    // Pool has LPs now, so an unreferred deposit pays both fees
+    let q: DepositQuote = pool.view(pool.quote_deposit_ix(&pool.usdc_mint, usdc(1_000), None)).await;
+    assert_eq!((q.protocol_fee_u64, q.referral_fee_u64), (usdc(5), usdc(5) / 2));
+    assert_eq!(q.net_fp, fp(1_000) - fp(15) / 2);
+
+    let v: UserPositionView = pool.view(pool.user_position_view_ix(&alice.pubkey())).await;
+    assert_eq!((v.owner, v.senior_deposited_fp, v.junior_deposited_fp), (alice.pubkey(), fp(995), 0));
+    assert_eq!(v.referrer, Pubkey::default());
+
+    let locked: WithdrawQuote = pool.view(pool.quote_withdraw_ix(&alice.pubkey(), usdc(1), 0)).await;
+    assert!(!locked.can_withdraw);
+    pool.warp(60).await;
+    let ok: WithdrawQuote = pool.view(pool.quote_withdraw_ix(&alice.pubkey(), usdc(995), 0)).await;
+    let too_much: WithdrawQuote = pool.view(pool.quote_withdraw_ix(&alice.pubkey(), usdc(996), 0)).await;
+    assert!(ok.can_withdraw && !too_much.can_withdraw);
+    assert_eq!(ok.available_fp, fp(995));
+
+    pool.open_epoch(1).await;
+    let admin = pool.ctx.payer.insecure_clone();
+    let args = TriggerArgs { evidence_hash: Some([7; 32]), ..Default::default() };
+    pool.trigger_with(&admin, 1, 1_000, args, None).await.unwrap();
+
+    let e: EpochStats = pool.view(pool.epoch_stats_ix(1)).await;
+    assert!(e.triggered && !e.closed);
+    assert_eq!((e.severity_bps, e.total_stake_snapshot_fp, e.evidence_hash), (1_000, fp(995), [7; 32]));
+
+    // 10% of 995 is owed; Alice's senior stake is weighted at 50%
+    let q: QuoteOut = pool.view(pool.quote_user_payout_ix(1, &alice.pubkey())).await;
+    assert_eq!(q.max_epoch_liability_fp, fp(995) / 10);
+    assert_eq!(q.user_payout_fp, fp(995) / 20);
+
+    pool.finalize_epoch(1, None).await.unwrap();
+    let h: EpochHistoryView = pool.view(pool.epoch_history_view_ix()).await;
+    assert_eq!((h.current_epoch_id, h.last_epoch_id, h.finalized_count, h.records.len()), (1, 1, 1, 1));
+    assert_eq!((h.records[0].epoch_id, h.records[0].severity_bps), (1, 1_000));
+}
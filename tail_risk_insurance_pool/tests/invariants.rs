@@ -0,0 +1,223 @@
+//! Random action sequences from `tail_risk_sim::fuzz` replayed against the
+//! program, with the simulator's accounting invariants checked on the
+//! accounts after every transaction (rejected ones included), plus the
+//! minimised regression scenarios in `tail_risk_sim/scenarios/regressions/`.
+//!
+//! A failing case is reproducible off-chain with
+//! `tail_risk_sim::fuzz::random_scenario(SEED, case, STEPS)`.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::Pubkey;
+use common::{usdc, Pool, TriggerArgs, SCALE};
+use solana_sdk::signature::{Keypair, Signer};
+use tail_risk_sim::fuzz::random_scenario;
+use tail_risk_sim::invariants::{EpochView, PoolView, PositionView, Tracker, Violation};
+use tail_risk_sim::{Action, Scenario};
+
+const SEED: u64 = 0;
+const CASES: u32 = 12;
+const STEPS: usize = 40;
+const MAX_EPOCH_ID: u64 = 4;
+
+/// A pool driven by simulator actions; users are created on first use.
+struct Harness {
+    pool: Pool,
+    admin: Keypair,
+    users: BTreeMap<String, Keypair>,
+}
+
+impl Harness {
+    async fn start(scenario: &Scenario) -> Self {
+        let p = scenario.params.clone();
+        let mut pool = Pool::start_with(|ip| {
+            ip.payout_policy = p.payout_policy;
+            ip.user_deposit_cap_fp = p.user_deposit_cap_fp;
+            ip.min_deposit_fp = p.min_deposit_fp;
+            ip.protocol_fee_bps = p.protocol_fee_bps;
+            ip.referral_fee_bps = p.referral_fee_bps;
+            ip.fee_model = p.fee_model;
+            ip.lockup_secs = p.lockup_secs;
+            ip.min_seconds_between_deposits = p.min_seconds_between_deposits;
+            ip.epoch_cap_fp = p.epoch_cap_fp;
+            ip.sev_quad_a_fp = p.sev_quad_a_fp;
+            ip.sev_quad_b_fp = p.sev_quad_b_fp;
+            ip.sev_quad_c_fp = p.sev_quad_c_fp;
+            ip.severity_floor_bps = p.severity_floor_bps;
+            ip.tranche_weight_senior_bps = p.tranche_weight_senior_bps;
+            ip.tranche_weight_junior_bps = p.tranche_weight_junior_bps;
+        })
+        .await;
+        pool.set_strategy(Pubkey::default(), 0, p.junior_yield_bps).await.unwrap();
+        let admin = pool.ctx.payer.insecure_clone();
+        Self { pool, admin, users: BTreeMap::new() }
+    }
+
+    async fn user(&mut self, name: &str) -> Keypair {
+        if !self.users.contains_key(name) {
+            let user = self.pool.user(usdc(1_000_000)).await;
+            self.pool.register_referrer(&user).await.unwrap();
+            self.users.insert(name.to_string(), user);
+        }
+        self.users[name].insecure_clone()
+    }
+
+    /// Send the matching instruction; failures are expected and ignored.
+    async fn apply(&mut self, action: &Action) {
+        let pool = &mut self.pool;
+        let _ = match action {
+            Action::Deposit { user, amount, tranche, referrer } => {
+                let kp = self.user(user).await;
+                // A referred position must name its referrer on every deposit
+                let position = self.pool.position(&kp.pubkey());
+                let existing = if self.pool.exists(position).await {
+                    Some(self.pool.position_account(&kp.pubkey()).await.referrer).filter(|r| *r != Pubkey::default())
+                } else {
+                    None
+                };
+                let referrer = match (existing, referrer) {
+                    (Some(r), _) => Some(r),
+                    (None, Some(name)) if name != user => Some(self.user(name).await.pubkey()),
+                    _ => None,
+                };
+                let mint = self.pool.usdc_mint;
+                self.pool.deposit_asset(&kp, &mint, *amount, *tranche, referrer).await
+            }
+            Action::Withdraw { user, amount, tranche } => {
+                let kp = self.user(user).await;
+                self.pool.withdraw(&kp, *amount, *tranche).await
+            }
+            Action::AdvanceTime { secs } => {
+                pool.warp(*secs).await;
+                Ok(())
+            }
+            Action::StartEpoch { epoch_id, duration_secs } => {
+                let now = pool.now().await;
+                let end_ts = if *duration_secs == 0 { 0 } else { now + duration_secs };
+                pool.start_epoch(*epoch_id, now, end_ts).await
+            }
+            Action::Trigger { epoch_id, severity_input_bps, user_cap_bps, epoch_cap_fp } => {
+                let args = TriggerArgs {
+                    user_cap_bps: *user_cap_bps,
+                    epoch_cap_fp_override: *epoch_cap_fp,
+                    ..Default::default()
+                };
+                pool.trigger_with(&self.admin, *epoch_id, *severity_input_bps, args, None).await
+            }
+            Action::Payout { epoch_id, user } => {
+                let kp = self.user(user).await;
+                self.pool.payout(&kp, *epoch_id).await
+            }
+            Action::Finalize { epoch_id, sweep_dust } => pool.finalize_epoch(*epoch_id, *sweep_dust).await,
+            Action::SetPolicy { payout_policy, epoch_cap_fp } => pool.set_policy(*payout_policy, *epoch_cap_fp).await,
+            Action::SetCurveAndWeights {
+                sev_quad_a_fp,
+                sev_quad_b_fp,
+                sev_quad_c_fp,
+                severity_floor_bps,
+                tranche_weight_senior_bps,
+                tranche_weight_junior_bps,
+            } => {
+                pool.set_curve_and_weights(
+                    *sev_quad_a_fp,
+                    *sev_quad_b_fp,
+                    *sev_quad_c_fp,
+                    *severity_floor_bps,
+                    *tranche_weight_senior_bps,
+                    *tranche_weight_junior_bps,
+                )
+                .await
+            }
+        };
+    }
+
+    /// The invariants' view of the on-chain accounts.
+    async fn view(&mut self) -> PoolView {
+        let s = self.pool.state_account().await;
+        let usdc_mint = self.pool.usdc_mint;
+        let vault = self.pool.vault(&usdc_mint);
+        let mut v = PoolView {
+            processing: s.processing,
+            payout_policy: s.payout_policy,
+            tranche_weight_senior_bps: s.tranche_weight_senior_bps,
+            tranche_weight_junior_bps: s.tranche_weight_junior_bps,
+            vault_fp: self.pool.balance(vault).await as u128 * SCALE,
+            total_deposited_fp: s.total_deposited_fp,
+            senior_total_fp: s.senior_total_fp,
+            junior_total_fp: s.junior_total_fp,
+            yield_reserve_fp: s.yield_reserve_fp,
+            referral_rewards_owed_fp: s.referral_rewards_owed_fp,
+            protocol_fees_accrued_fp: s.protocol_fees_accrued_fp,
+            ..Default::default()
+        };
+        let users: Vec<(String, Pubkey)> = self.users.iter().map(|(n, k)| (n.clone(), k.pubkey())).collect();
+        for (name, owner) in users {
+            if self.pool.exists(self.pool.position(&owner)).await {
+                let p = self.pool.position_account(&owner).await;
+                let pv = PositionView {
+                    senior_deposited_fp: p.senior_deposited_fp,
+                    junior_deposited_fp: p.junior_deposited_fp,
+                    senior_withdrawable_fp: p.senior_withdrawable_fp,
+                    junior_withdrawable_fp: p.junior_withdrawable_fp,
+                };
+                v.positions.insert(name, pv);
+            }
+        }
+        for id in 1..=MAX_EPOCH_ID {
+            if self.pool.exists(self.pool.epoch(id)).await {
+                let e = self.pool.epoch_account(id).await;
+                let ev = EpochView {
+                    triggered: e.triggered,
+                    closed: e.closed,
+                    total_stake_snapshot_fp: e.total_stake_snapshot_fp,
+                    total_payout_fp: e.total_payout_fp,
+                    severity_bps: e.severity_bps,
+                    user_cap_bps: e.user_cap_bps,
+                    epoch_cap_fp: e.epoch_cap_fp,
+                };
+                v.epochs.insert(id, ev);
+            }
+        }
+        v
+    }
+}
+
+/// Replay `scenario`; returns the first broken invariant and its step.
+async fn replay(scenario: &Scenario) -> Result<(), (usize, Violation)> {
+    let mut h = Harness::start(scenario).await;
+    let mut tracker = Tracker::default();
+    let mut before = h.view().await;
+    for (step, action) in scenario.actions.iter().enumerate() {
+        h.apply(action).await;
+        let after = h.view().await;
+        tracker.check(action, &before, &after).map_err(|v| (step, v))?;
+        before = after;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn random_sequences_hold_invariants() {
+    for case in 0..CASES {
+        let scenario = random_scenario(SEED, case, STEPS);
+        if let Err((step, violation)) = replay(&scenario).await {
+            panic!("case {case}, step {step} ({:?}): {violation:?}", scenario.actions[step]);
+        }
+    }
+}
+
+#[tokio::test]
+async fn regression_scenarios_hold_invariants() {
+    for (name, json) in [
+        ("lockup_double_credit", include_str!("../../tail_risk_sim/scenarios/regressions/lockup_double_credit.json")),
+        ("withdraw_above_principal", include_str!("../../tail_risk_sim/scenarios/regressions/withdraw_above_principal.json")),
+        ("trigger_during_open_claims", include_str!("../../tail_risk_sim/scenarios/regressions/trigger_during_open_claims.json")),
+    ] {
+        let scenario: Scenario = serde_json::from_str(json).unwrap();
+        if let Err((step, violation)) = replay(&scenario).await {
+            panic!("{name}, step {step}: {violation:?}");
+        }
+    }
+}
@@ -0,0 +1,685 @@
+//! Full epoch lifecycles and clock-dependent rules (cooldown, lockup, epoch
+//! window, evidence staleness, claim receipt retention, outflow windows), payout
+//! cranks, Merkle settlement, rolling epochs, the epoch history ring, cover expiry and claim
+//! window, pausing and shutdown.
+
+mod common;
+
+use common::{assert_error, flat_cover, fp, usdc, Distribution, Pool, TriggerArgs, DAY, SCALE};
+use solana_sdk::signature::{Keypair, Signer};
+use tail_risk_insurance_pool::{
+    CapacityView, ClaimReceipt, EpochHistoryView, ErrorCode, PoolStats, QuoteOut, PAUSE_CLAIMS, PAUSE_FEE_SWEEPS, PAUSE_OUTFLOWS,
+    PAUSE_WITHDRAWALS,
+};
+
+/// Pays every claimant and checks the amount each received.
+async fn pay_all(pool: &mut Pool, epoch_id: u64, expected: &[(&Keypair, u64)]) {
+    for (user, amount) in expected {
+        let ata = pool.ata(&user.pubkey());
+        let before = pool.balance(ata).await;
+        pool.payout(user, epoch_id).await.unwrap();
+        assert_eq!(pool.balance(ata).await - before, *amount, "payout to {}", user.pubkey());
+    }
+}
+
+#[tokio::test]
+async fn two_epochs_with_multiple_users_and_both_tranches() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let alice = pool.user(usdc(10_000)).await;
+    let bob = pool.user(usdc(10_000)).await;
+    let carol = pool.user(usdc(10_000)).await;
+    pool.deposit(&alice, usdc(10_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(10_000), 1).await.unwrap();
+    pool.deposit(&carol, usdc(5_000), 0).await.unwrap();
+    pool.warp(10).await;
+    pool.deposit(&carol, usdc(5_000), 1).await.unwrap();
+    assert_eq!(pool.state_account().await.total_deposited_fp, fp(30_000));
+
+    // Epoch 1, proportional, 10%: 3_000 owed, shared by weighted stake
+    // (senior 50%, junior 100%) over the 30_000 snapshot
+    pool.open_epoch(1).await;
+    pool.trigger(1, 1_000).await.unwrap();
+    let q: QuoteOut = pool.view(pool.quote_user_payout_ix(1, &carol.pubkey())).await;
+    assert_eq!((q.max_epoch_liability_fp, q.user_payout_fp), (fp(3_000), fp(750)));
+    pay_all(&mut pool, 1, &[(&alice, usdc(500)), (&bob, usdc(1_000)), (&carol, usdc(750))]).await;
+    pool.finalize_epoch(1, None).await.unwrap();
+
+    let e = pool.epoch_account(1).await;
+    assert!(e.closed);
+    assert_eq!((e.total_stake_snapshot_fp, e.total_payout_fp, e.shortfall_fp), (fp(30_000), fp(2_250), 0));
+    assert!(!pool.state_account().await.claim_window_open);
+
+    // Epoch 2, capped at 5% of each user's weighted stake: 6_000 owed,
+    // pro-rata shares 1_000 / 2_000 / 1_500 are cut to 250 / 500 / 375
+    pool.set_policy(1, None).await.unwrap();
+    pool.open_epoch(2).await;
+    let admin = pool.ctx.payer.insecure_clone();
+    let args = TriggerArgs { user_cap_bps: Some(500), ..Default::default() };
+    pool.trigger_with(&admin, 2, 2_000, args, None).await.unwrap();
+    pay_all(&mut pool, 2, &[(&alice, usdc(250)), (&bob, usdc(500)), (&carol, usdc(375))]).await;
+
+    // Nothing above liabilities, so the sweep moves nothing
+    pool.finalize_epoch(2, Some(usdc(1_000))).await.unwrap();
+    assert_eq!(pool.balance(pool.treasury_ata).await, 0);
+    assert_eq!(pool.epoch_account(2).await.total_payout_fp, fp(1_125));
+    let usdc_mint = pool.usdc_mint;
+    assert_eq!(pool.balance(pool.vault(&usdc_mint)).await, usdc(30_000 - 2_250 - 1_125));
+
+    // Payouts leave principal untouched; LPs can still exit once the lockup is over
+    pool.warp(60).await;
+    pool.withdraw(&bob, usdc(1_000), 1).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&bob.pubkey())).await, usdc(1_000 + 500 + 1_000));
+    assert_eq!(pool.state_account().await.total_deposited_fp, fp(29_000));
+}
+
+#[tokio::test]
+async fn underfunded_bounded_epoch_carries_shortfall() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap();
+    let alice = pool.user(usdc(10_000)).await;
+    pool.deposit(&alice, usdc(10_000), 0).await.unwrap();
+
+    // 50% event: half-weighted senior stake takes 2_500 of the 5_000 owed
+    pool.open_epoch(1).await;
+    pool.trigger(1, 5_000).await.unwrap();
+    pay_all(&mut pool, 1, &[(&alice, usdc(2_500))]).await;
+    pool.finalize_epoch(1, None).await.unwrap();
+
+    // 100% event on a 7_500 pool: 2_500 unfunded, liability bounded at 1_000
+    pool.set_policy(2, Some(fp(5_000))).await.unwrap();
+    pool.open_epoch(2).await;
+    let admin = pool.ctx.payer.insecure_clone();
+    let args = TriggerArgs { epoch_cap_fp_override: Some(fp(1_000)), ..Default::default() };
+    pool.trigger_with(&admin, 2, 10_000, args, None).await.unwrap();
+    assert_eq!(pool.epoch_account(2).await.epoch_cap_fp, fp(1_000));
+    pay_all(&mut pool, 2, &[(&alice, usdc(500))]).await;
+    assert_eq!(pool.epoch_account(2).await.shortfall_fp, fp(2_500));
+
+    pool.finalize_epoch(2, None).await.unwrap();
+    assert_eq!(pool.state_account().await.carryover_shortfall_fp, fp(2_500));
+}
+
+#[tokio::test]
+async fn deposit_cooldown_follows_clock() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(500), 0).await.unwrap();
+    assert_error(pool.deposit(&alice, usdc(500), 0).await, ErrorCode::DepositCooldown);
+
+    pool.warp(10).await;
+    pool.deposit(&alice, usdc(500), 0).await.unwrap();
+    assert_eq!(pool.position_account(&alice.pubkey()).await.senior_lots.len, 2);
+}
+
+#[tokio::test]
+async fn lots_mature_after_lockup() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+
+    // Before the 60s lockup nothing is withdrawable and the lot stays queued
+    pool.warp(59).await;
+    assert_error(pool.withdraw(&alice, usdc(100), 0).await, ErrorCode::LockupNotExpired);
+    assert_eq!(pool.position_account(&alice.pubkey()).await.senior_lots.len, 1);
+
+    // Maturing moves the lot into the withdrawable balance exactly once
+    pool.warp(1).await;
+    pool.withdraw(&alice, usdc(100), 0).await.unwrap();
+    let p = pool.position_account(&alice.pubkey()).await;
+    assert_eq!(p.senior_lots.len, 0);
+    assert_eq!((p.senior_deposited_fp, p.senior_withdrawable_fp), (fp(895), fp(895)));
+    assert_error(pool.withdraw(&alice, usdc(896), 0).await, ErrorCode::InsufficientPoolBalance);
+}
+
+#[tokio::test]
+async fn epoch_window_and_evidence_staleness() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    let admin = pool.ctx.payer.insecure_clone();
+
+    // Evidence may be at most max_stale_secs (300) old
+    pool.open_epoch(1).await;
+    let now = pool.now().await;
+    let stale = TriggerArgs { evidence_ts: Some(now - 400), ..Default::default() };
+    assert_error(pool.trigger_with(&admin, 1, 1_000, stale, None).await, ErrorCode::EpochNotActive);
+    let fresh = TriggerArgs { evidence_ts: Some(now - 200), ..Default::default() };
+    pool.trigger_with(&admin, 1, 1_000, fresh, None).await.unwrap();
+    assert_eq!(pool.epoch_account(1).await.evidence_ts, now - 200);
+    pool.finalize_epoch(1, None).await.unwrap();
+
+    // Fixed window: no trigger after end_ts
+    let now = pool.now().await;
+    pool.start_epoch(2, now, now + 60).await.unwrap();
+    pool.warp(61).await;
+    assert_error(pool.trigger(2, 1_000).await, ErrorCode::EpochNotActive);
+
+    // Open-ended (end_ts = 0) epochs stay triggerable
+    let now = pool.now().await;
+    pool.start_epoch(3, now, 0).await.unwrap();
+    pool.warp(30 * DAY).await;
+    pool.trigger(3, 1_000).await.unwrap();
+    assert!(pool.epoch_account(3).await.triggered);
+    pool.finalize_epoch(3, None).await.unwrap();
+
+    // ... until the admin ends them untriggered: the next epoch may start, the ended one closes
+    let now = pool.now().await;
+    pool.start_epoch(4, now, 0).await.unwrap();
+    pool.warp(DAY).await;
+    assert_error(pool.start_epoch(5, now + DAY, 0).await, ErrorCode::EpochOverlap);
+    pool.end_epoch(4).await.unwrap();
+    let ended = pool.epoch_account(4).await;
+    assert!(ended.closed && !ended.triggered);
+    assert_eq!(pool.epoch_history_account().await.last_end_ts, now + DAY);
+    assert_error(pool.trigger(4, 1_000).await, ErrorCode::EpochNotActive);
+    pool.start_epoch(5, now + DAY, 0).await.unwrap();
+    pool.close_epoch(4).await.unwrap();
+    assert!(!pool.exists(pool.epoch(4)).await);
+}
+
+#[tokio::test]
+async fn closing_accounts_returns_rent_to_payers() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(1_000), 0).await.unwrap();
+    pool.open_epoch(1).await;
+    pool.trigger(1, 1_000).await.unwrap();
+    pool.payout(&alice, 1).await.unwrap();
+    assert_eq!(pool.epoch_account(1).await.open_receipts, 1);
+
+    // Receipts outlive the claim window by the retention period; the epoch outlives its receipts
+    assert_error(pool.close_claim_receipt(1, &alice.pubkey(), &alice.pubkey()).await, ErrorCode::EpochNotActive);
+    pool.finalize_epoch(1, None).await.unwrap();
+    let finalized_ts = pool.epoch_account(1).await.finalized_ts;
+    assert_eq!(finalized_ts, pool.now().await);
+    assert_error(pool.close_epoch(1).await, ErrorCode::AccountNotEmpty);
+    pool.warp(30 * DAY - 1).await;
+    assert_error(pool.close_claim_receipt(1, &alice.pubkey(), &alice.pubkey()).await, ErrorCode::RetentionPeriodActive);
+    pool.warp(1).await;
+
+    let claim = pool.claim(1, &alice.pubkey());
+    let (rent, before) = (pool.lamports(claim).await, pool.lamports(alice.pubkey()).await);
+    pool.close_claim_receipt(1, &alice.pubkey(), &alice.pubkey()).await.unwrap();
+    assert!(!pool.exists(claim).await);
+    assert_eq!(pool.lamports(alice.pubkey()).await, before + rent);
+    pool.close_epoch(1).await.unwrap();
+    assert!(!pool.exists(pool.epoch(1)).await);
+
+    // A position closes only once emptied; principal totals are untouched
+    assert_error(pool.close_position(&alice).await, ErrorCode::AccountNotEmpty);
+    pool.withdraw(&alice, usdc(1_000), 0).await.unwrap();
+    let position = pool.position(&alice.pubkey());
+    let (rent, before) = (pool.lamports(position).await, pool.lamports(alice.pubkey()).await);
+    pool.close_position(&alice).await.unwrap();
+    assert!(!pool.exists(position).await);
+    assert_eq!(pool.lamports(alice.pubkey()).await, before + rent);
+    assert_eq!(pool.state_account().await.total_deposited_fp, fp(1_000));
+
+    // Closing frees the address: a later deposit starts a fresh position
+    pool.warp(10).await;
+    pool.deposit(&alice, usdc(100), 1).await.unwrap();
+    assert_eq!(pool.position_account(&alice.pubkey()).await.junior_deposited_fp, fp(100));
+}
+
+#[tokio::test]
+async fn closing_and_reopening_a_position_keeps_its_referrer() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let rita = pool.user(0).await;
+    let mallory = pool.user(0).await;
+    let alice = pool.user(usdc(2_000)).await;
+    pool.register_referrer(&rita).await.unwrap();
+    pool.register_referrer(&mallory).await.unwrap();
+    let mint = pool.usdc_mint;
+    pool.deposit_asset(&alice, &mint, usdc(1_000), 0, Some(rita.pubkey())).await.unwrap();
+
+    pool.warp(60).await;
+    pool.withdraw(&alice, usdc(1_000), 0).await.unwrap();
+    pool.close_position(&alice).await.unwrap();
+    assert!(!pool.exists(pool.position(&alice.pubkey())).await);
+    assert_eq!(pool.attribution_account(&alice.pubkey()).await.referrer, rita.pubkey());
+
+    // The fresh position inherits rita: mallory can neither be credited nor take over
+    assert_error(pool.deposit_asset(&alice, &mint, usdc(500), 0, Some(mallory.pubkey())).await, ErrorCode::Unauthorized);
+    pool.deposit_offering(&alice, usdc(500), mallory.pubkey(), rita.pubkey()).await.unwrap();
+    assert_eq!(pool.position_account(&alice.pubkey()).await.referrer, rita.pubkey());
+    assert_eq!(pool.attribution_account(&alice.pubkey()).await.referrer, rita.pubkey());
+    assert_eq!(pool.referrer_account(&rita.pubkey()).await.total_referred_fp, fp(1_500));
+    assert_eq!(pool.referrer_account(&mallory.pubkey()).await.total_referred_fp, 0);
+}
+
+#[tokio::test]
+async fn shutdown_settles_open_claims_then_lps_exit_by_tranche_priority() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let alice = pool.user(usdc(6_000)).await;
+    let bob = pool.user(usdc(3_000)).await;
+    let carol = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(6_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(3_000), 1).await.unwrap();
+    pool.deposit(&carol, usdc(1_000), 1).await.unwrap();
+
+    // 50% event on 10_000: weighted claims 1_500 / 1_500 / 500, then governance shuts down
+    pool.open_epoch(1).await;
+    pool.trigger(1, 5_000).await.unwrap();
+    pay_all(&mut pool, 1, &[(&alice, usdc(1_500)), (&bob, usdc(1_500))]).await;
+    assert_error(pool.shutdown().await, ErrorCode::EpochNotActive);
+    pool.shutdown_with(Some(1)).await.unwrap();
+    let stats: PoolStats = pool.view(pool.pool_stats_ix()).await;
+    assert!(stats.shutdown);
+
+    // The open claim window is settled on the spot: carol's unpaid 500 becomes shortfall
+    let epoch = pool.epoch_account(1).await;
+    assert!(epoch.closed);
+    assert_eq!(epoch.shortfall_fp, fp(500));
+    assert_eq!(pool.state_account().await.carryover_shortfall_fp, fp(500));
+    assert_error(pool.payout(&carol, 1).await, ErrorCode::EpochNotActive);
+    assert_error(pool.finalize_epoch(1, None).await, ErrorCode::EpochNotActive);
+
+    // 7_000 left: senior (6_000) is repaid in full, junior shares the last 1_000 pro-rata,
+    // whatever the order and although every lot is still locked up
+    for (user, expected) in [(&bob, usdc(750)), (&alice, usdc(6_000)), (&carol, usdc(250))] {
+        let ata = pool.ata(&user.pubkey());
+        let start = pool.balance(ata).await;
+        pool.emergency_withdraw(user).await.unwrap();
+        assert_eq!(pool.balance(ata).await - start, expected, "emergency exit of {}", user.pubkey());
+    }
+    let s = pool.state_account().await;
+    assert_eq!((s.total_deposited_fp, s.senior_total_fp, s.junior_total_fp), (0, 0, 0));
+    let usdc_mint = pool.usdc_mint;
+    assert_eq!(pool.balance(pool.vault(&usdc_mint)).await, 0);
+
+    // Emptied positions can be closed
+    pool.close_position(&alice).await.unwrap();
+    assert!(!pool.exists(pool.position(&alice.pubkey())).await);
+}
+
+#[tokio::test]
+async fn shutdown_waits_for_cover_claims_like_finalize() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    pool.init_cover_book(flat_cover(5_000, 200)).await.unwrap();
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(100)).await;
+    pool.deposit(&alice, usdc(1_000), 0).await.unwrap();
+    pool.open_epoch(1).await;
+    pool.buy_cover(&bob, usdc(200), u64::MAX).await.unwrap();
+    pool.trigger(1, 1_000).await.unwrap();
+
+    // Unclaimed cover keeps the claim window open through a shutdown attempt
+    assert_error(pool.shutdown_with(Some(1)).await, ErrorCode::CoverClaimWindowActive);
+    let ata = pool.ata(&bob.pubkey());
+    let start = pool.balance(ata).await;
+    pool.claim_cover(&bob, &pool.cover_mint(0)).await.unwrap();
+    assert_eq!(pool.balance(ata).await - start, usdc(20));
+    pool.shutdown_with(Some(1)).await.unwrap();
+    assert!(pool.epoch_account(1).await.closed);
+}
+
+#[tokio::test]
+async fn claim_windows_freeze_principal_and_the_guardian_pauses_per_operation() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let alice = pool.user(usdc(3_000)).await;
+    let payer = pool.user(usdc(1_000)).await;
+    let guardian = pool.user(0).await;
+    pool.set_guardian(guardian.pubkey()).await.unwrap();
+    pool.deposit(&alice, usdc(2_000), 0).await.unwrap();
+
+    // A trigger freezes principal; premiums and yield keep flowing
+    pool.open_epoch(1).await;
+    pool.trigger(1, 1_000).await.unwrap();
+    pool.warp(10).await;
+    assert_error(pool.deposit(&alice, usdc(100), 0).await, ErrorCode::Paused);
+    assert_error(pool.withdraw(&alice, usdc(100), 0).await, ErrorCode::Paused);
+    pool.pay_premium(&payer, usdc(1_000), None).await.unwrap();
+    pool.claim_yield(&alice).await.unwrap();
+
+    // The guardian halts claims mid-window; only the admin resumes them
+    pool.guardian_pause(&guardian, PAUSE_CLAIMS).await.unwrap();
+    assert_error(pool.payout(&alice, 1).await, ErrorCode::Paused);
+    assert_error(pool.set_paused_as(&guardian, 0).await, ErrorCode::Unauthorized);
+    pool.set_paused(0).await.unwrap();
+    pool.payout(&alice, 1).await.unwrap();
+    pool.finalize_epoch(1, None).await.unwrap();
+
+    // Finalizing lifts the freeze but not the guardian's bits
+    pool.guardian_pause(&guardian, PAUSE_WITHDRAWALS | PAUSE_FEE_SWEEPS).await.unwrap();
+    let stats: PoolStats = pool.view(pool.pool_stats_ix()).await;
+    assert_eq!((stats.pause_flags, stats.claim_window_open), (PAUSE_WITHDRAWALS | PAUSE_FEE_SWEEPS, false));
+    pool.deposit(&alice, usdc(100), 0).await.unwrap();
+    assert_error(pool.withdraw(&alice, usdc(100), 0).await, ErrorCode::Paused);
+    assert_error(pool.collect_fees().await, ErrorCode::Paused);
+}
+
+#[tokio::test]
+async fn outflow_breaker_pauses_outflows_past_the_window_limit() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let alice = pool.user(usdc(10_000)).await;
+    let bob = pool.user(usdc(10_000)).await;
+    pool.deposit(&alice, usdc(6_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(4_000), 1).await.unwrap();
+    // Daily windows: at most 1_500, and at most 10% of the vault before the outflows counted
+    pool.set_outflow_limits(DAY, fp(1_500), 1_000).await.unwrap();
+    pool.warp(120).await; // past lockup
+
+    // More than the position holds fails on the position and never reaches the breaker
+    assert_error(pool.withdraw(&bob, usdc(5_000), 1).await, ErrorCode::InsufficientPoolBalance);
+    let s = pool.state_account().await;
+    assert_eq!((s.pause_flags, s.outflow_window_used_fp), (0, 0));
+
+    let ata = pool.ata(&alice.pubkey());
+    let start = pool.balance(ata).await;
+    pool.withdraw(&alice, usdc(600), 0).await.unwrap();
+    pool.withdraw(&bob, usdc(400), 1).await.unwrap();
+
+    // The outflow that would cross 1_000 is not paid; outflows pause instead
+    pool.withdraw(&alice, usdc(1), 0).await.unwrap();
+    assert_eq!(pool.balance(ata).await - start, usdc(600));
+    assert_eq!(pool.position_account(&alice.pubkey()).await.senior_deposited_fp, fp(5_400));
+    let s = pool.state_account().await;
+    assert_eq!((s.pause_flags, s.outflow_window_base_fp, s.outflow_window_used_fp), (PAUSE_OUTFLOWS, fp(10_000), fp(1_000)));
+    assert_error(pool.withdraw(&alice, usdc(1), 0).await, ErrorCode::Paused);
+    assert_error(pool.collect_fees().await, ErrorCode::Paused);
+    pool.deposit(&alice, usdc(100), 0).await.unwrap();
+
+    // Resuming while the window is still full trips again: the 100 deposited since only
+    // raised the limit to 1_010
+    pool.set_paused(0).await.unwrap();
+    pool.withdraw(&bob, usdc(11), 1).await.unwrap();
+    assert_eq!(pool.state_account().await.pause_flags, PAUSE_OUTFLOWS);
+
+    // The window slides: half a day later half the limit (505) has drained out of it
+    pool.set_paused(0).await.unwrap();
+    pool.warp(DAY / 2).await;
+    pool.withdraw(&bob, usdc(464), 1).await.unwrap();
+    let s = pool.state_account().await;
+    assert_eq!((s.pause_flags, s.outflow_window_base_fp, s.outflow_window_used_fp), (0, fp(9_595), fp(959)));
+    pool.withdraw(&bob, usdc(1), 1).await.unwrap();
+    assert_eq!(pool.state_account().await.pause_flags, PAUSE_OUTFLOWS);
+
+    // A whole window later nothing is counted any more
+    pool.set_paused(0).await.unwrap();
+    pool.warp(DAY).await;
+    pool.withdraw(&bob, usdc(800), 1).await.unwrap();
+    let s = pool.state_account().await;
+    assert_eq!((s.pause_flags, s.outflow_window_base_fp, s.outflow_window_used_fp), (0, fp(8_636), fp(800)));
+
+    // A payout stopped by the breaker leaves no claim receipt behind and can be retried
+    pool.open_epoch(1).await;
+    pool.trigger(1, 1_000).await.unwrap();
+    let start = pool.balance(ata).await;
+    pool.payout(&alice, 1).await.unwrap();
+    assert_eq!(pool.state_account().await.pause_flags, PAUSE_OUTFLOWS);
+    assert_eq!(pool.balance(ata).await, start);
+    assert!(!pool.exists(pool.claim(1, &alice.pubkey())).await);
+    pool.set_paused(0).await.unwrap();
+    pool.warp(DAY).await;
+    pool.payout(&alice, 1).await.unwrap();
+    let receipt: ClaimReceipt = pool.account(pool.claim(1, &alice.pubkey())).await;
+    assert_eq!((receipt.owner, receipt.epoch_id), (alice.pubkey(), 1));
+    assert_eq!(u128::from(pool.balance(ata).await - start), receipt.claimed_fp / 1_000_000);
+    assert_eq!(pool.epoch_account(1).await.open_receipts, 1);
+}
+
+#[tokio::test]
+async fn crank_pays_holders_who_did_not_claim() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(5_000)).await;
+    let bob = pool.user(usdc(5_000)).await;
+    let carol = pool.user(usdc(5_000)).await;
+    let dave = pool.user(usdc(100)).await;
+    let keeper = pool.user(0).await;
+    pool.deposit(&alice, usdc(4_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(4_000), 1).await.unwrap();
+    pool.deposit(&carol, usdc(2_000), 0).await.unwrap();
+    pool.deposit(&dave, usdc(100), 0).await.unwrap();
+
+    // Performance fees leave 10 of uncollected protocol fees to fund the reward
+    let strategy = pool.setup_strategy(5_000, 0).await;
+    pool.strategy_deploy(&strategy, usdc(1_000)).await.unwrap();
+    pool.accrue(&strategy, usdc(100)).await;
+    pool.strategy_recall(&strategy, None).await.unwrap();
+    assert_eq!(pool.state_account().await.protocol_fees_accrued_fp, fp(10));
+    pool.set_crank_reward(usdc(1)).await.unwrap();
+
+    pool.open_epoch(1).await;
+    pool.trigger(1, 1_000).await.unwrap();
+    pool.payout(&alice, 1).await.unwrap();
+
+    // Alice already holds a receipt and is skipped; Bob, Carol and Dave get what they could claim
+    let holders = [alice.pubkey(), bob.pubkey(), carol.pubkey(), dave.pubkey()];
+    let mut expected = Vec::new();
+    for h in &holders[1..] {
+        let quote: QuoteOut = pool.view(pool.quote_user_payout_ix(1, h)).await;
+        expected.push((pool.balance(pool.ata(h)).await, quote.user_payout_fp));
+    }
+    assert!(expected[2].1 < fp(10), "dave's payout is dust");
+    pool.crank_payouts(&keeper, 1, &holders).await.unwrap();
+    for (h, (before, quote_fp)) in holders[1..].iter().zip(expected) {
+        assert!(quote_fp > 0);
+        assert_eq!((pool.balance(pool.ata(h)).await - before) as u128, quote_fp / SCALE);
+        let receipt: ClaimReceipt = pool.account(pool.claim(1, h)).await;
+        assert_eq!((receipt.owner, receipt.claimed_fp, receipt.payer), (*h, quote_fp, keeper.pubkey()));
+    }
+
+    // One USDC per holder paid, out of the treasury's uncollected fees; Dave's dust earns nothing
+    assert_eq!(pool.balance(pool.ata(&keeper.pubkey())).await, usdc(2));
+    let s = pool.state_account().await;
+    assert_eq!(s.protocol_fees_accrued_fp, fp(8));
+    assert!(!s.processing);
+    assert_eq!(pool.epoch_account(1).await.open_receipts, 4);
+
+    // Receipts make the crank idempotent and still block a second claim
+    pool.crank_payouts(&keeper, 1, &holders).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&keeper.pubkey())).await, usdc(2));
+    assert_error(pool.payout(&bob, 1).await, ErrorCode::NothingToPayout);
+
+    // The rent the keeper fronted goes back to the keeper, not the holder
+    pool.finalize_epoch(1, None).await.unwrap();
+    pool.warp(30 * DAY).await;
+    let claim = pool.claim(1, &bob.pubkey());
+    let (rent, before) = (pool.lamports(claim).await, pool.lamports(keeper.pubkey()).await);
+    pool.close_claim_receipt(1, &bob.pubkey(), &keeper.pubkey()).await.unwrap();
+    assert_eq!(pool.lamports(keeper.pubkey()).await, before + rent);
+}
+
+#[tokio::test]
+async fn merkle_settled_epoch_pays_by_proof_and_closes_with_its_bitmap() {
+    let mut pool = Pool::start().await;
+    let alice = pool.user(usdc(5_000)).await;
+    let bob = pool.user(usdc(5_000)).await;
+    let carol = pool.user(usdc(5_000)).await;
+    pool.deposit(&alice, usdc(4_000), 0).await.unwrap();
+    pool.deposit(&bob, usdc(4_000), 1).await.unwrap();
+    pool.deposit(&carol, usdc(2_000), 0).await.unwrap();
+    pool.open_epoch(1).await;
+    pool.trigger(1, 1_000).await.unwrap();
+
+    // The off-chain job prices every holder as payout_user would
+    let mut leaves = Vec::new();
+    for h in [alice.pubkey(), bob.pubkey(), carol.pubkey()] {
+        let quote: QuoteOut = pool.view(pool.quote_user_payout_ix(1, &h)).await;
+        leaves.push((h, quote.user_payout_fp));
+    }
+    let dist = Distribution::new(&leaves);
+    pool.post_merkle_root(1, &dist).await.unwrap();
+    let e = pool.epoch_account(1).await;
+    assert_eq!((e.merkle_root, e.merkle_total_fp), (dist.root(), dist.total_fp()));
+    let b = pool.bitmap_account(1).await;
+    assert_eq!((b.claimants, b.claimed, b.bits.clone()), (3, 0, vec![0]));
+
+    for (index, user) in [(0u32, &alice), (2, &carol)] {
+        let ata = pool.ata(&user.pubkey());
+        let before = pool.balance(ata).await;
+        let amount_fp = dist.leaves[index as usize].1;
+        pool.claim_with_proof(user, 1, index, amount_fp, dist.proof(index)).await.unwrap();
+        assert_eq!((pool.balance(ata).await - before) as u128, amount_fp / SCALE);
+    }
+    let b = pool.bitmap_account(1).await;
+    assert_eq!((b.claimed, b.bits.clone()), (2, vec![0b101]));
+    assert_eq!(pool.epoch_account(1).await.total_payout_fp, dist.leaves[0].1 + dist.leaves[2].1);
+    assert_error(pool.claim_with_proof(&alice, 1, 0, dist.leaves[0].1, dist.proof(0)).await, ErrorCode::NothingToPayout);
+
+    // No receipts were written, so the epoch closes as soon as it is finalized; the
+    // bitmap goes with it and its rent returns to the admin
+    pool.finalize_epoch(1, None).await.unwrap();
+    assert_error(pool.claim_with_proof(&bob, 1, 1, dist.leaves[1].1, dist.proof(1)).await, ErrorCode::EpochNotActive);
+    let admin = pool.admin();
+    let (before, rent) = (pool.lamports(admin).await, pool.lamports(pool.bitmap(1)).await);
+    pool.close_epoch(1).await.unwrap();
+    assert!(!pool.exists(pool.bitmap(1)).await && !pool.exists(pool.epoch(1)).await);
+    assert!(pool.lamports(admin).await > before + rent);
+}
+
+#[tokio::test]
+async fn rolling_epochs_chain_and_cover_new_deposits_from_the_next_epoch() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(1_000)).await;
+    let keeper = pool.user(0).await;
+    pool.deposit(&alice, usdc(1_000), 1).await.unwrap();
+    pool.set_rolling_mode(true, DAY as u32).await.unwrap();
+    pool.start_epoch(1, 0, 0).await.unwrap();
+    let e1 = pool.epoch_account(1).await;
+    assert_eq!(e1.end_ts - e1.start_ts, DAY);
+
+    // Bob joins during epoch 1: covered from epoch 2, so epoch 1 pays Alice alone
+    pool.deposit(&bob, usdc(1_000), 1).await.unwrap();
+    let p = pool.position_account(&bob.pubkey()).await;
+    assert_eq!((p.pending_epoch_id, p.pending_senior_fp, p.pending_junior_fp), (1, 0, fp(1_000)));
+    pool.trigger(1, 1_000).await.unwrap();
+    let e1 = pool.epoch_account(1).await;
+    assert_eq!((e1.pending_deposits_fp, e1.total_stake_snapshot_fp), (fp(1_000), fp(1_000)));
+    assert_error(pool.payout(&bob, 1).await, ErrorCode::NothingToPayout);
+    pay_all(&mut pool, 1, &[(&alice, usdc(100))]).await;
+    pool.finalize_epoch(1, None).await.unwrap();
+
+    // Once epoch 1 has ended anyone rolls epoch 2, which starts where it ended
+    pool.warp(DAY + DAY / 2).await;
+    pool.roll_epoch(&keeper, 2).await.unwrap();
+    let e2 = pool.epoch_account(2).await;
+    assert_eq!((e2.start_ts, e2.end_ts), (e1.end_ts, e1.end_ts + DAY));
+    assert_eq!(pool.state_account().await.current_epoch_id, 2);
+    pool.trigger(2, 1_000).await.unwrap();
+    assert_eq!(pool.epoch_account(2).await.total_stake_snapshot_fp, fp(2_000));
+    pay_all(&mut pool, 2, &[(&alice, usdc(100)), (&bob, usdc(100))]).await;
+    pool.finalize_epoch(2, None).await.unwrap();
+
+    // Durations nobody rolled are skipped, keeping the epoch grid
+    pool.warp(2 * DAY + DAY / 4).await;
+    pool.roll_epoch(&keeper, 3).await.unwrap();
+    let e3 = pool.epoch_account(3).await;
+    assert_eq!((e3.start_ts, e3.end_ts), (e2.end_ts + DAY, e2.end_ts + 2 * DAY));
+    let stats: PoolStats = pool.view(pool.pool_stats_ix()).await;
+    assert_eq!((stats.rolling_mode, stats.current_epoch_id, stats.epoch_duration_secs), (true, 3, DAY as u32));
+}
+
+#[tokio::test]
+async fn epoch_history_keeps_the_last_sixteen_finalized_epochs() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    let alice = pool.user(usdc(1_000)).await;
+    pool.deposit(&alice, usdc(1_000), 1).await.unwrap();
+
+    for epoch_id in 1..=17 {
+        pool.open_epoch(epoch_id).await;
+        pool.trigger(epoch_id, 1_000).await.unwrap();
+        if epoch_id == 17 {
+            pay_all(&mut pool, 17, &[(&alice, usdc(100))]).await;
+        }
+        pool.finalize_epoch(epoch_id, None).await.unwrap();
+        // Finalizing ends the epoch's cover, so the next starts right away
+        assert_eq!(pool.epoch_history_account().await.last_end_ts, pool.now().await);
+    }
+
+    let h: EpochHistoryView = pool.view(pool.epoch_history_view_ix()).await;
+    assert_eq!((h.current_epoch_id, h.last_epoch_id, h.finalized_count), (17, 17, 17));
+    let ids: Vec<u64> = h.records.iter().map(|r| r.epoch_id).collect();
+    assert_eq!(ids, (2..=17).rev().collect::<Vec<_>>(), "newest first; epoch 1 rotated out");
+    let newest = &h.records[0];
+    assert_eq!((newest.severity_bps, newest.total_payout_fp, newest.shortfall_fp), (1_000, fp(100), 0));
+}
+
+#[tokio::test]
+async fn cover_expires_with_its_epoch_and_frees_capacity() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    pool.init_cover_book(flat_cover(5_000, 200)).await.unwrap();
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(100)).await;
+    pool.deposit(&alice, usdc(1_000), 1).await.unwrap();
+
+    let now = pool.now().await;
+    pool.start_epoch(1, now, now + 60).await.unwrap();
+    pool.buy_cover(&bob, usdc(500), u64::MAX).await.unwrap();
+    assert_error(pool.buy_cover(&bob, 1, 0).await, ErrorCode::CapacityExceeded);
+
+    // Sales close with the window; the next epoch's book starts empty
+    pool.warp(60).await;
+    assert_error(pool.buy_cover(&bob, 1, 0).await, ErrorCode::EpochNotActive);
+    let now = pool.now().await;
+    pool.start_epoch(2, now, now + 60).await.unwrap();
+    let v: CapacityView = pool.view(pool.capacity_view_ix()).await;
+    assert_eq!((v.sold_fp, v.available_fp), (0, fp(500)));
+    pool.buy_cover(&bob, usdc(500), u64::MAX).await.unwrap();
+    let book = pool.cover_book_account().await;
+    assert_eq!((book.epoch_id, book.sold_fp), (2, fp(500)));
+    let first = pool.cover_account(&pool.cover_mint(0)).await;
+    assert_eq!((first.epoch_id, first.notional_fp), (1, fp(500)));
+
+    // The expired cover closes once its epoch is ended, returning the buyer's rent
+    let mint = pool.cover_mint(0);
+    assert_error(pool.close_cover(&bob, &mint).await, ErrorCode::CoverActive);
+    pool.end_epoch(1).await.unwrap();
+    let (cover, nft) = (pool.cover(&mint), pool.cover_nft(&bob.pubkey(), &mint));
+    let rent = pool.lamports(cover).await + pool.lamports(nft).await;
+    let before = pool.lamports(bob.pubkey()).await;
+    pool.close_cover(&bob, &mint).await.unwrap();
+    assert!(!pool.exists(cover).await && !pool.exists(nft).await);
+    assert_eq!(pool.lamports(bob.pubkey()).await, before + rent);
+}
+
+#[tokio::test]
+async fn cover_claims_share_the_epoch_liability_and_hold_finalize_open() {
+    let mut pool = Pool::start().await;
+    pool.set_fee_model(1, 50, 25, 1_000).await.unwrap(); // no deposit fees
+    pool.set_policy(2, Some(fp(75))).await.unwrap();
+    pool.init_cover_book(flat_cover(5_000, 200)).await.unwrap();
+    let alice = pool.user(usdc(1_000)).await;
+    let bob = pool.user(usdc(100)).await;
+    pool.deposit(&alice, usdc(1_000), 1).await.unwrap();
+    let now = pool.now().await;
+    pool.start_epoch(1, now, now + 30 * DAY).await.unwrap();
+    pool.buy_cover(&bob, usdc(300), u64::MAX).await.unwrap();
+    pool.buy_cover(&bob, usdc(200), u64::MAX).await.unwrap();
+    assert_eq!(pool.epoch_account(1).await.cover_sold_fp, fp(500));
+
+    // 10% of 1_000 stake and 500 cover owes 150, bounded at 75: everyone gets half, pro rata
+    pool.trigger(1, 1_000).await.unwrap();
+    assert_eq!(pool.state_account().await.cover_owed_fp, fp(50));
+    pay_all(&mut pool, 1, &[(&alice, usdc(50))]).await;
+    let (first, second) = (pool.cover_mint(0), pool.cover_mint(1));
+    let before = pool.balance(pool.ata(&bob.pubkey())).await;
+    pool.claim_cover(&bob, &first).await.unwrap();
+    assert_eq!(pool.balance(pool.ata(&bob.pubkey())).await - before, usdc(15));
+    pool.close_cover(&bob, &first).await.unwrap();
+    assert!(!pool.exists(pool.cover(&first)).await);
+    let e = pool.epoch_account(1).await;
+    assert_eq!((e.total_payout_fp, e.cover_paid_fp, e.cover_claimed_fp), (fp(65), fp(15), fp(300)));
+    assert_eq!(pool.state_account().await.cover_owed_fp, fp(20));
+
+    // Unclaimed cover holds the epoch open for the claim window, then lapses
+    assert_error(pool.finalize_epoch(1, None).await, ErrorCode::CoverClaimWindowActive);
+    pool.warp(7 * DAY).await;
+    pool.finalize_epoch(1, None).await.unwrap();
+    assert_eq!(pool.state_account().await.cover_owed_fp, 0);
+    assert_error(pool.claim_cover(&bob, &second).await, ErrorCode::EpochNotActive);
+    pool.close_cover(&bob, &second).await.unwrap();
+}
@@ -0,0 +1,225 @@
+//! Layout migrations: accounts in the layouts of the last unversioned build
+//! (`fixtures/v0`) are loaded at their PDAs and rewritten in place.
+
+mod common;
+
+use anchor_lang::{AccountSerialize, Discriminator};
+use common::{assert_error, fp, usdc, Pool};
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::TransactionError,
+};
+use tail_risk_insurance_pool::{
+    ClaimReceipt, CollateralRegistry, Epoch, ErrorCode, OracleList, State, UserPosition, PAUSE_ALL,
+};
+
+/// `ACCOUNT_VERSION` in the program.
+const ACCOUNT_VERSION: u8 = 1;
+
+const STATE: &[u8] = include_bytes!("fixtures/v0/state.bin");
+const ORACLE_LIST: &[u8] = include_bytes!("fixtures/v0/oracle_list.bin");
+const USER_POSITION: &[u8] = include_bytes!("fixtures/v0/user_position.bin");
+const EPOCH: &[u8] = include_bytes!("fixtures/v0/epoch.bin");
+const CLAIM_RECEIPT: &[u8] = include_bytes!("fixtures/v0/claim_receipt.bin");
+
+/// Fixture admin and position owner.
+fn admin() -> Keypair {
+    Keypair::new_from_array([7; 32])
+}
+
+fn alice() -> Pubkey {
+    Pubkey::new_from_array([11; 32])
+}
+
+/// Anchor's "account did not deserialize", which typed accounts raise on a legacy layout.
+#[track_caller]
+fn assert_legacy_rejected(res: Result<(), BanksClientError>) {
+    let expected = u32::from(anchor_lang::error::ErrorCode::AccountDidNotDeserialize);
+    match res.expect_err("legacy account should be rejected").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(got)) => assert_eq!(got, expected),
+        other => panic!("expected custom error {expected}, got {other:?}"),
+    }
+}
+
+fn rent(len: usize) -> u64 {
+    Rent::default().minimum_balance(len)
+}
+
+/// Put raw program-owned `data` at `address`, rent-exempt at its current size.
+fn load(pool: &mut Pool, address: Pubkey, data: &[u8]) {
+    let mut account = AccountSharedData::new(rent(data.len()), data.len(), &tail_risk_insurance_pool::ID);
+    account.set_data_from_slice(data);
+    pool.ctx.set_account(&address, &account);
+}
+
+/// A booted (uninitialized) pool holding every v0 fixture, with the fixture admin funded
+/// and 2,850 USDC in the vault (3,000 deposited, 150 paid out). The fixture's base mint is
+/// swapped for the test mint.
+async fn legacy_pool() -> Pool {
+    let mut pool = Pool::boot().await;
+    let (state, oracle_list) = (pool.state(), pool.oracle_list());
+    let (position, epoch, claim) = (pool.position(&alice()), pool.epoch(1), pool.claim(1, &alice()));
+    let mut legacy_state = STATE.to_vec();
+    legacy_state[8 + 32..8 + 64].copy_from_slice(pool.usdc_mint.as_ref());
+    for (address, data) in [
+        (state, legacy_state.as_slice()),
+        (oracle_list, ORACLE_LIST),
+        (position, USER_POSITION),
+        (epoch, EPOCH),
+        (claim, CLAIM_RECEIPT),
+    ] {
+        load(&mut pool, address, data);
+    }
+    let mint = pool.usdc_mint;
+    pool.create_ata(&state, &mint).await;
+    pool.mint_to(&mint, &state, usdc(2_850)).await;
+    let payer = pool.admin();
+    pool.send(&[system_instruction::transfer(&payer, &admin().pubkey(), 1_000_000_000)], &[])
+        .await
+        .unwrap();
+    pool
+}
+
+async fn data_len(pool: &mut Pool, address: Pubkey) -> usize {
+    pool.ctx.banks_client.get_account(address).await.unwrap().expect("account exists").data.len()
+}
+
+#[tokio::test]
+async fn legacy_global_accounts_migrate_in_place() {
+    let mut pool = legacy_pool().await;
+    let admin = admin();
+    assert_eq!(STATE.len(), 8 + 271, "baseline State layout");
+
+    // Typed instructions cannot read the old layout
+    assert_legacy_rejected(pool.set_paused_as(&admin, PAUSE_ALL).await);
+    assert_legacy_rejected(pool.close_epoch(1).await);
+
+    let stranger = Keypair::new();
+    let payer = pool.admin();
+    pool.send(&[system_instruction::transfer(&payer, &stranger.pubkey(), 1_000_000_000)], &[])
+        .await
+        .unwrap();
+    assert_error(pool.migrate_state(&stranger, &[1]).await, ErrorCode::Unauthorized);
+
+    assert!(!pool.exists(pool.registry()).await);
+    pool.migrate_state(&admin, &[1]).await.unwrap();
+    let now = pool.now().await;
+    for (address, space) in [
+        (pool.state(), State::SIZE),
+        (pool.oracle_list(), OracleList::SIZE),
+        (pool.registry(), CollateralRegistry::SIZE),
+        (pool.epoch(1), Epoch::SIZE),
+    ] {
+        assert_eq!(data_len(&mut pool, address).await, 8 + space);
+        assert_eq!(pool.lamports(address).await, rent(8 + space), "rent topped up");
+    }
+
+    // Fields carried over by name; `paused` was an admin pause here (the epoch is closed)
+    let s = pool.state_account().await;
+    assert_eq!((s.version, s.admin, s.usdc_mint, s.payout_policy), (ACCOUNT_VERSION, admin.pubkey(), pool.usdc_mint, 1));
+    assert_eq!((s.lockup_secs, s.epoch_cap_fp, s.tranche_weight_junior_bps), (86_400, fp(20_000), 10_000));
+    assert_eq!((s.total_deposited_fp, s.carryover_shortfall_fp, s.last_event_ts), (fp(3_000), fp(1), 1_700_000_000));
+    assert_eq!((s.claim_window_open, s.pause_flags, s.current_epoch_id), (false, PAUSE_ALL, 1));
+    assert_eq!((s.fee_model, s.protocol_fees_accrued_fp, s.referral_tiers[0].min_volume_fp), (0, 0, 0));
+    assert_eq!((s.senior_total_fp, s.junior_total_fp, s.yield_reserve_fp), (0, 0, 0), "rebuilt by migrate_position");
+    let o = pool.oracle_list_account().await;
+    let oracle_bump = Pubkey::find_program_address(&[b"oracle", tail_risk_insurance_pool::ID.as_ref()], &tail_risk_insurance_pool::ID).1;
+    assert_eq!((o.version, o.enabled, o.count, o.keys[1]), (ACCOUNT_VERSION, true, 2, Pubkey::new_from_array([22; 32])));
+    assert_eq!(o.bump, oracle_bump, "missing bump is recorded");
+    let r = pool.registry_account().await;
+    assert_eq!((r.version, r.count, r.assets[0].mint, r.assets[0].vault), (ACCOUNT_VERSION, 1, pool.usdc_mint, pool.vault(&pool.usdc_mint)));
+    assert_eq!((r.assets[0].haircut_bps, r.assets[0].enabled, r.assets[0].balance_fp), (0, true, fp(2_850)));
+    let e = pool.epoch_account(1).await;
+    assert_eq!((e.version, e.epoch_id, e.triggered, e.closed, e.severity_bps), (ACCOUNT_VERSION, 1, true, true, 1_000));
+    assert_eq!((e.total_payout_fp, e.evidence_hash, e.evidence_ts), (fp(150), [9; 32], 1_700_000_000));
+    assert_eq!((e.open_receipts, e.finalized_ts, e.merkle_root), (0, now, [0; 32]), "retention runs from migration");
+
+    // Migrated accounts work with typed instructions; migrating again is a no-op anyone may send
+    pool.set_paused_as(&admin, 0).await.unwrap();
+    pool.migrate_state(&stranger, &[1]).await.unwrap();
+    let s = pool.state_account().await;
+    assert_eq!((s.pause_flags, s.guardian, s.version), (0, Pubkey::default(), ACCOUNT_VERSION));
+    assert_eq!(pool.lamports(pool.state()).await, rent(8 + State::SIZE));
+    assert_eq!(pool.registry_account().await.count, 1);
+}
+
+#[tokio::test]
+async fn legacy_positions_migrate_with_receipts() {
+    let mut pool = legacy_pool().await;
+    let (position, claim, epoch) = (pool.position(&alice()), pool.claim(1, &alice()), pool.epoch(1));
+    let view = pool.user_position_view_ix(&alice());
+    assert_legacy_rejected(pool.send(std::slice::from_ref(&view), &[]).await);
+    // The state (and with it every epoch) goes first
+    assert_legacy_rejected(pool.migrate_position(&alice(), &[]).await);
+    pool.migrate_state(&admin(), &[1]).await.unwrap();
+
+    // Receipts come with their epoch and must belong to the position's owner
+    let bob = Pubkey::new_unique();
+    let mut foreign = CLAIM_RECEIPT.to_vec();
+    foreign[16..48].copy_from_slice(bob.as_ref());
+    let bobs_claim = pool.claim(1, &bob);
+    load(&mut pool, bobs_claim, &foreign);
+    assert_error(pool.migrate_position(&alice(), &[bobs_claim, epoch]).await, ErrorCode::Unauthorized);
+    assert_error(pool.migrate_position(&alice(), &[claim]).await, ErrorCode::InvalidRemainingAccounts);
+    assert!(pool.migrate_position(&bob, &[]).await.is_err(), "bob has no position");
+
+    pool.migrate_position(&alice(), &[claim, epoch]).await.unwrap();
+    for (address, space) in [(position, UserPosition::SIZE), (claim, ClaimReceipt::SIZE)] {
+        assert_eq!(data_len(&mut pool, address).await, 8 + space);
+        assert_eq!(pool.lamports(address).await, rent(8 + space), "rent topped up");
+    }
+
+    let p = pool.position_account(&alice()).await;
+    let bump = Pubkey::find_program_address(&[b"position", alice().as_ref()], &tail_risk_insurance_pool::ID).1;
+    assert_eq!((p.version, p.bump), (ACCOUNT_VERSION, bump), "missing bump is recorded");
+    assert_eq!((p.owner, p.referrer, p.last_deposit_ts), (alice(), Pubkey::new_from_array([12; 32]), 1_699_995_000));
+    assert_eq!((p.senior_deposited_fp, p.junior_deposited_fp), (fp(2_000), fp(1_000)));
+    // Lots are trimmed (oldest first) to the principal and only what they do not hold is withdrawable
+    let senior: Vec<_> = p.senior_lots.buf[..2].iter().map(|l| l.amount_fp).collect();
+    assert_eq!((senior, p.senior_lots.len, p.junior_lots.buf[0].amount_fp), (vec![0, fp(2_000)], 2, fp(1_000)));
+    assert_eq!((p.senior_withdrawable_fp, p.junior_withdrawable_fp, p.yield_accrued_fp), (0, 0, 0));
+    let s = pool.state_account().await;
+    assert_eq!((s.senior_total_fp, s.junior_total_fp), (fp(2_000), fp(1_000)));
+    assert_eq!(s.senior_total_fp + s.junior_total_fp, s.total_deposited_fp);
+    let c: ClaimReceipt = pool.account(claim).await;
+    assert_eq!((c.version, c.epoch_id, c.owner, c.claimed_fp), (ACCOUNT_VERSION, 1, alice(), fp(150)));
+    assert_eq!(c.payer, alice(), "legacy receipts were paid by their claimant");
+    assert_eq!(pool.epoch_account(1).await.open_receipts, 1, "legacy receipt counted on its epoch");
+
+    // Bump-checked views now resolve the position; migrating again changes nothing
+    pool.send(&[view], &[]).await.unwrap();
+    pool.migrate_position(&alice(), &[claim, epoch]).await.unwrap();
+    assert_eq!(pool.state_account().await.senior_total_fp, fp(2_000));
+    assert_eq!(pool.epoch_account(1).await.open_receipts, 1);
+}
+
+#[tokio::test]
+async fn current_accounts_are_untouched_and_newer_versions_rejected() {
+    let mut pool = Pool::start().await;
+    let admin = pool.ctx.payer.insecure_clone();
+    let state = pool.state();
+    let (len, lamports) = (data_len(&mut pool, state).await, pool.lamports(state).await);
+    assert_eq!(pool.state_account().await.version, ACCOUNT_VERSION);
+    pool.migrate_state(&admin, &[]).await.unwrap();
+    assert_eq!((data_len(&mut pool, state).await, pool.lamports(state).await), (len, lamports));
+
+    // A layout from a later build is never downgraded
+    let mut account: AccountSharedData = pool.ctx.banks_client.get_account(state).await.unwrap().unwrap().into();
+    let mut newer = pool.state_account().await;
+    newer.version = ACCOUNT_VERSION + 1;
+    newer.try_serialize(&mut account.data_as_mut_slice()).unwrap();
+    pool.ctx.set_account(&state, &account);
+    assert_error(pool.migrate_state(&admin, &[]).await, ErrorCode::UnsupportedAccountVersion);
+
+    // Only program accounts of the expected type are accepted
+    let mut other = pool.ctx.banks_client.get_account(state).await.unwrap().unwrap();
+    other.data[..8].copy_from_slice(OracleList::DISCRIMINATOR);
+    pool.ctx.set_account(&state, &other.into());
+    assert!(pool.migrate_state(&admin, &[]).await.is_err());
+}
@@ -0,0 +1,37 @@
+//! `tail-risk-fuzz [--cases <n>] [--steps <n>] [--seed <n>] [--out <dir>]`: run
+//! random action sequences against the simulator and print every minimised
+//! invariant violation as one JSON line. With `--out`, each minimised scenario
+//! is also written to `<dir>/case-<n>.json`. Exits with 1 if anything failed.
+
+use std::{env, fs, path::Path, process};
+
+use tail_risk_sim::fuzz::{run_fuzz, FuzzConfig};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let flag = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+    let num = |name: &str, default: u64| match flag(name) {
+        Some(v) => v.parse().unwrap_or_else(|_| fail(&format!("{name} expects a number, got {v}"))),
+        None => default,
+    };
+    let cfg = FuzzConfig { cases: num("--cases", 1_000) as u32, steps: num("--steps", 60) as usize, seed: num("--seed", 0) };
+
+    let failures = run_fuzz(&cfg).unwrap_or_else(|e| fail(&format!("invalid parameters: {e}")));
+    for f in &failures {
+        println!("{}", serde_json::to_string(f).expect("failures serialise"));
+        if let Some(dir) = flag("--out") {
+            let path = Path::new(dir).join(format!("case-{}.json", f.case));
+            let json = serde_json::to_string_pretty(&f.scenario).expect("scenarios serialise");
+            fs::write(&path, json).unwrap_or_else(|e| fail(&format!("cannot write {}: {e}", path.display())));
+        }
+    }
+    eprintln!("{} of {} cases failed", failures.len(), cfg.cases);
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    process::exit(1);
+}
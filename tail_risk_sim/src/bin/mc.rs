@@ -0,0 +1,36 @@
+//! `tail-risk-mc <config.json> [--csv] [--trials-csv <path>]`: run a Monte Carlo
+//! stress test and print tail metrics as JSON (default) or CSV.
+
+use std::{env, fs, process};
+
+use tail_risk_sim::montecarlo::{run_stress, StressConfig};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        fail("usage: tail-risk-mc <config.json> [--csv] [--trials-csv <path>]");
+    };
+    let csv = args.iter().any(|a| a == "--csv");
+    let trials_csv = args.iter().position(|a| a == "--trials-csv").and_then(|i| args.get(i + 1));
+
+    let cfg: StressConfig = match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+        Ok(Ok(c)) => c,
+        Ok(Err(e)) => fail(&format!("invalid config {path}: {e}")),
+        Err(e) => fail(&format!("cannot read {path}: {e}")),
+    };
+    let report = run_stress(&cfg).unwrap_or_else(|e| fail(&e.to_string()));
+
+    if let Some(out) = trials_csv {
+        fs::write(out, report.trials_csv()).unwrap_or_else(|e| fail(&format!("cannot write {out}: {e}")));
+    }
+    if csv {
+        print!("{}", report.metrics_csv());
+    } else {
+        println!("{}", serde_json::to_string_pretty(&report).expect("report serialises"));
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    process::exit(1);
+}
@@ -0,0 +1,770 @@
+//! Replays pool instructions against the in-memory model.
+//!
+//! Each handler follows the matching program instruction step for step and
+//! uses `tail_risk_core` for all math. A failing action leaves the simulator
+//! untouched, like a failed transaction.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tail_risk_core::{
+    fees::{deposit_fee_bps, split_fees, FeeModel},
+    lots::Lot,
+    math::{asset_to_fp, bps_of, fp_to_asset, mul_div_floor},
+    payout::{claimable_fp, epoch_liability, user_payout_fp, weighted_stake_fp, PayoutPolicy},
+    severity::effective_severity_bps,
+    yields::{accrued_fp, index_increment, split_yield},
+    BPS_DENOM, COMMON_DECIMALS,
+};
+
+use crate::invariants::{EpochView, PoolView, PositionView};
+use crate::model::{Epoch, PoolParams, PoolState, Position};
+use crate::report::{EpochReport, FeeFlows, StepReport, TrancheReport, UserReport};
+
+/// One step of a scenario. Amounts are native USDC units (6 decimals).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Action {
+    /// `deposit_insurance`.
+    Deposit {
+        /// Depositor.
+        user: String,
+        /// Amount in native units.
+        amount: u64,
+        /// 0 = senior, 1 = junior.
+        tranche: u8,
+        /// Referrer (first touch only).
+        #[serde(default)]
+        referrer: Option<String>,
+    },
+    /// `withdraw`.
+    Withdraw {
+        /// Withdrawer.
+        user: String,
+        /// Amount in native units.
+        amount: u64,
+        /// 0 = senior, 1 = junior.
+        tranche: u8,
+    },
+    /// Move the clock forward.
+    AdvanceTime {
+        /// Seconds to advance.
+        secs: i64,
+    },
+    /// `start_epoch` at the current time.
+    StartEpoch {
+        /// Epoch id.
+        epoch_id: u64,
+        /// Length in seconds (0 = open-ended, until finalized).
+        duration_secs: i64,
+    },
+    /// `trigger_event`.
+    Trigger {
+        /// Epoch id.
+        epoch_id: u64,
+        /// Oracle severity, input to the curve.
+        severity_input_bps: u16,
+        /// Per-user cap for `Capped`.
+        #[serde(default)]
+        user_cap_bps: Option<u16>,
+        /// Epoch cap override for `EpochBounded`.
+        #[serde(default)]
+        epoch_cap_fp: Option<u128>,
+    },
+    /// `payout_user`.
+    Payout {
+        /// Epoch id.
+        epoch_id: u64,
+        /// Claimant.
+        user: String,
+    },
+    /// `finalize_epoch`.
+    Finalize {
+        /// Epoch id.
+        epoch_id: u64,
+        /// Optional dust sweep to the treasury (native units).
+        #[serde(default)]
+        sweep_dust: Option<u64>,
+    },
+    /// `set_policy`.
+    SetPolicy {
+        /// `PayoutPolicy` as `u8`.
+        payout_policy: u8,
+        /// New epoch cap, if any.
+        #[serde(default)]
+        epoch_cap_fp: Option<u128>,
+    },
+    /// `set_curve_and_weights`.
+    SetCurveAndWeights {
+        /// Quadratic coefficient.
+        sev_quad_a_fp: u128,
+        /// Linear coefficient.
+        sev_quad_b_fp: u128,
+        /// Constant term.
+        sev_quad_c_fp: u128,
+        /// Severity floor.
+        severity_floor_bps: u16,
+        /// Senior weight.
+        tranche_weight_senior_bps: u16,
+        /// Junior weight.
+        tranche_weight_junior_bps: u16,
+    },
+}
+
+/// Why an action was rejected (names match the program's `ErrorCode`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimError {
+    /// Pool is paused.
+    Paused,
+    /// Caller or tranche not allowed.
+    Unauthorized,
+    /// Below `min_deposit_fp`.
+    MinDeposit,
+    /// Deposit cooldown not elapsed.
+    DepositCooldown,
+    /// Above `user_deposit_cap_fp`.
+    UserCapExceeded,
+    /// Lot ring full.
+    TooManyLots,
+    /// Amount only available once pending lots mature.
+    LockupNotExpired,
+    /// Not enough matured balance or vault balance.
+    InsufficientPoolBalance,
+    /// Epoch missing, closed, untriggered or outside its window.
+    EpochNotActive,
+    /// Epoch already triggered.
+    EpochAlreadyTriggered,
+    /// Nothing to pay for this claim.
+    NothingToPayout,
+    /// Parameter out of bounds.
+    ParamOutOfBounds,
+    /// Arithmetic overflow.
+    MathOverflow,
+    /// Epoch id already used (account already initialised).
+    AccountExists,
+    /// Epoch would start before the previous epoch's cover ended.
+    EpochOverlap,
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for SimError {}
+
+type SimResult<T> = Result<T, SimError>;
+
+fn ovf<T>(x: Option<T>) -> SimResult<T> {
+    x.ok_or(SimError::MathOverflow)
+}
+
+/// Cumulative token and fee flows for one user.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Ledger {
+    deposited_u64: u64,
+    withdrawn_u64: u64,
+    payouts_u64: u64,
+    referral_rewards_fp: u128,
+}
+
+/// Cumulative flows for one tranche.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct TrancheLedger {
+    deposited_fp: u128,
+    withdrawn_fp: u128,
+    income_fp: u128,
+    payouts_fp: u128,
+}
+
+/// Deterministic pool simulator.
+#[derive(Clone, Debug)]
+pub struct Simulator {
+    params: PoolParams,
+    state: PoolState,
+    positions: BTreeMap<String, Position>,
+    epochs: BTreeMap<u64, Epoch>,
+    claims: BTreeSet<(u64, String)>,
+    ledgers: BTreeMap<String, Ledger>,
+    tranches: [TrancheLedger; 2],
+    vault_u64: u64,
+    vault_fp: u128,
+    treasury_u64: u64,
+    referral_to_lps_fp: u128,
+    dust_swept_u64: u64,
+    now: i64,
+    step: u64,
+}
+
+impl Simulator {
+    /// Fresh pool at time `start_ts`.
+    pub fn new(params: PoolParams, start_ts: i64) -> SimResult<Self> {
+        assert_param_bounds(&params)?;
+        Ok(Self {
+            params,
+            state: PoolState::default(),
+            positions: BTreeMap::new(),
+            epochs: BTreeMap::new(),
+            claims: BTreeSet::new(),
+            ledgers: BTreeMap::new(),
+            tranches: Default::default(),
+            vault_u64: 0,
+            vault_fp: 0,
+            treasury_u64: 0,
+            referral_to_lps_fp: 0,
+            dust_swept_u64: 0,
+            now: start_ts,
+            step: 0,
+        })
+    }
+
+    /// Current parameters.
+    pub fn params(&self) -> &PoolParams {
+        &self.params
+    }
+
+    /// Current global accounting.
+    pub fn state(&self) -> &PoolState {
+        &self.state
+    }
+
+    /// A user's position, if any.
+    pub fn position(&self, user: &str) -> Option<&Position> {
+        self.positions.get(user)
+    }
+
+    /// An epoch, if started.
+    pub fn epoch(&self, epoch_id: u64) -> Option<&Epoch> {
+        self.epochs.get(&epoch_id)
+    }
+
+    /// Current simulated time.
+    pub fn now(&self) -> i64 {
+        self.now
+    }
+
+    /// The accounting state the invariants check.
+    pub fn view(&self) -> PoolView {
+        PoolView {
+            processing: false,
+            payout_policy: self.params.payout_policy,
+            tranche_weight_senior_bps: self.params.tranche_weight_senior_bps,
+            tranche_weight_junior_bps: self.params.tranche_weight_junior_bps,
+            vault_fp: self.vault_fp,
+            total_deposited_fp: self.state.total_deposited_fp,
+            senior_total_fp: self.state.senior_total_fp,
+            junior_total_fp: self.state.junior_total_fp,
+            yield_reserve_fp: self.state.yield_reserve_fp,
+            referral_rewards_owed_fp: self.state.referral_rewards_owed_fp,
+            protocol_fees_accrued_fp: 0,
+            positions: self
+                .positions
+                .iter()
+                .map(|(u, p)| {
+                    let v = PositionView {
+                        senior_deposited_fp: p.senior_deposited_fp,
+                        junior_deposited_fp: p.junior_deposited_fp,
+                        senior_withdrawable_fp: p.senior_withdrawable_fp,
+                        junior_withdrawable_fp: p.junior_withdrawable_fp,
+                    };
+                    (u.clone(), v)
+                })
+                .collect(),
+            epochs: self
+                .epochs
+                .iter()
+                .map(|(id, e)| {
+                    let v = EpochView {
+                        triggered: e.triggered,
+                        closed: e.closed,
+                        total_stake_snapshot_fp: e.total_stake_snapshot_fp,
+                        total_payout_fp: e.total_payout_fp,
+                        severity_bps: e.severity_bps,
+                        user_cap_bps: e.user_cap_bps,
+                        epoch_cap_fp: e.epoch_cap_fp,
+                    };
+                    (*id, v)
+                })
+                .collect(),
+        }
+    }
+
+    /// Apply one action and report the resulting pool. On error nothing changes.
+    pub fn apply(&mut self, action: &Action) -> SimResult<StepReport> {
+        self.step(action)?;
+        self.report()
+    }
+
+    /// Apply one action without building a report. On error nothing changes.
+    pub fn step(&mut self, action: &Action) -> SimResult<()> {
+        let mut next = self.clone();
+        next.dispatch(action)?;
+        next.step += 1;
+        *self = next;
+        Ok(())
+    }
+
+    fn dispatch(&mut self, action: &Action) -> SimResult<()> {
+        match action {
+            Action::Deposit { user, amount, tranche, referrer } => {
+                self.deposit(user, *amount, *tranche, referrer.as_deref())
+            }
+            Action::Withdraw { user, amount, tranche } => self.withdraw(user, *amount, *tranche),
+            Action::AdvanceTime { secs } => {
+                self.now = self.now.saturating_add(*secs);
+                Ok(())
+            }
+            Action::StartEpoch { epoch_id, duration_secs } => self.start_epoch(*epoch_id, *duration_secs),
+            Action::Trigger { epoch_id, severity_input_bps, user_cap_bps, epoch_cap_fp } => {
+                self.trigger(*epoch_id, *severity_input_bps, *user_cap_bps, *epoch_cap_fp)
+            }
+            Action::Payout { epoch_id, user } => self.payout(*epoch_id, user),
+            Action::Finalize { epoch_id, sweep_dust } => self.finalize(*epoch_id, *sweep_dust),
+            Action::SetPolicy { payout_policy, epoch_cap_fp } => {
+                self.params.payout_policy = *payout_policy;
+                if let Some(cap) = epoch_cap_fp {
+                    self.params.epoch_cap_fp = *cap;
+                }
+                assert_param_bounds(&self.params)
+            }
+            Action::SetCurveAndWeights {
+                sev_quad_a_fp,
+                sev_quad_b_fp,
+                sev_quad_c_fp,
+                severity_floor_bps,
+                tranche_weight_senior_bps,
+                tranche_weight_junior_bps,
+            } => {
+                let p = &mut self.params;
+                p.sev_quad_a_fp = *sev_quad_a_fp;
+                p.sev_quad_b_fp = *sev_quad_b_fp;
+                p.sev_quad_c_fp = *sev_quad_c_fp;
+                p.severity_floor_bps = *severity_floor_bps;
+                p.tranche_weight_senior_bps = *tranche_weight_senior_bps;
+                p.tranche_weight_junior_bps = *tranche_weight_junior_bps;
+                assert_param_bounds(&self.params)
+            }
+        }
+    }
+
+    // ----------------------------- user flow -----------------------------
+
+    fn deposit(&mut self, user: &str, amount: u64, tranche: u8, referrer: Option<&str>) -> SimResult<()> {
+        require(!self.state.paused, SimError::Paused)?;
+        let p = &self.params;
+        let model = FeeModel::from_u8(p.fee_model).ok_or(SimError::ParamOutOfBounds)?;
+        let (proto_bps, ref_bps) = deposit_fee_bps(model, p.protocol_fee_bps, p.referral_fee_bps);
+
+        let amount_fp = ovf(asset_to_fp(amount, COMMON_DECIMALS))?;
+        require(amount_fp >= p.min_deposit_fp, SimError::MinDeposit)?;
+
+        let mut position = self.positions.get(user).cloned().unwrap_or_default();
+        if position.last_deposit_ts != 0 && p.min_seconds_between_deposits > 0 {
+            require(
+                self.now.saturating_sub(position.last_deposit_ts) >= p.min_seconds_between_deposits,
+                SimError::DepositCooldown,
+            )?;
+        }
+        require(tranche <= 1, SimError::Unauthorized)?;
+
+        // First-touch attribution, no self-referral
+        if position.referrer.is_none() {
+            if let Some(r) = referrer.filter(|r| *r != user) {
+                position.referrer = Some(r.to_string());
+            }
+        }
+
+        self.vault_u64 = ovf(self.vault_u64.checked_add(amount))?;
+
+        let split = ovf(split_fees(amount_fp, proto_bps, ref_bps))?;
+        let proto_fee_fp = split.protocol_fp;
+        let mut ref_fee_fp = split.referral_fp;
+        if position.referrer.is_none() && ref_fee_fp > 0 {
+            if self.state.senior_total_fp.saturating_add(self.state.junior_total_fp) == 0 {
+                ref_fee_fp = 0;
+            } else {
+                self.distribute_income(ref_fee_fp)?;
+                self.referral_to_lps_fp = self.referral_to_lps_fp.saturating_add(ref_fee_fp);
+            }
+        }
+        let idx = (self.state.senior_yield_index_fp, self.state.junior_yield_index_fp);
+
+        let proto_fee_u64 = ovf(fp_to_asset(proto_fee_fp, COMMON_DECIMALS))?;
+        self.vault_u64 -= proto_fee_u64;
+        self.treasury_u64 = self.treasury_u64.saturating_add(proto_fee_u64);
+
+        if let Some(r) = &position.referrer {
+            let l = self.ledgers.entry(r.clone()).or_default();
+            l.referral_rewards_fp = l.referral_rewards_fp.saturating_add(ref_fee_fp);
+            self.state.referral_rewards_owed_fp = self.state.referral_rewards_owed_fp.saturating_add(ref_fee_fp);
+        }
+
+        let net_fp = amount_fp.saturating_sub(proto_fee_fp.saturating_add(ref_fee_fp));
+        settle_income(&mut position, idx)?;
+        let now = self.now;
+        let lot = Lot { amount_fp: net_fp, ts: now };
+        if tranche == 0 {
+            require(position.senior_lots.ring().push(lot), SimError::TooManyLots)?;
+            position.senior_deposited_fp = position.senior_deposited_fp.saturating_add(net_fp);
+        } else {
+            require(position.junior_lots.ring().push(lot), SimError::TooManyLots)?;
+            position.junior_deposited_fp = position.junior_deposited_fp.saturating_add(net_fp);
+        }
+        position.last_deposit_ts = now;
+        sync_income_debt(&mut position, idx)?;
+
+        let user_total = position.senior_deposited_fp.saturating_add(position.junior_deposited_fp);
+        require(user_total <= self.params.user_deposit_cap_fp, SimError::UserCapExceeded)?;
+
+        let kept_fp = ovf(asset_to_fp(amount - proto_fee_u64, COMMON_DECIMALS))?;
+        self.vault_fp = self.vault_fp.saturating_add(kept_fp);
+
+        self.state.total_deposited_fp = self.state.total_deposited_fp.saturating_add(net_fp);
+        if tranche == 0 {
+            self.state.senior_total_fp = self.state.senior_total_fp.saturating_add(net_fp);
+        } else {
+            self.state.junior_total_fp = self.state.junior_total_fp.saturating_add(net_fp);
+        }
+
+        self.positions.insert(user.to_string(), position);
+        let l = self.ledgers.entry(user.to_string()).or_default();
+        l.deposited_u64 = l.deposited_u64.saturating_add(amount);
+        let t = &mut self.tranches[tranche as usize];
+        t.deposited_fp = t.deposited_fp.saturating_add(amount_fp);
+        Ok(())
+    }
+
+    fn withdraw(&mut self, user: &str, amount: u64, tranche: u8) -> SimResult<()> {
+        require(!self.state.paused, SimError::Paused)?;
+        require(tranche <= 1, SimError::Unauthorized)?;
+        let amount_fp = ovf(asset_to_fp(amount, COMMON_DECIMALS))?;
+        require(self.vault_fp >= amount_fp, SimError::InsufficientPoolBalance)?;
+
+        let idx = (self.state.senior_yield_index_fp, self.state.junior_yield_index_fp);
+        let lockup = self.params.lockup_secs;
+        let now = self.now;
+        let position = self.positions.get_mut(user).ok_or(SimError::Unauthorized)?;
+        settle_income(position, idx)?;
+
+        let mut remaining = amount_fp;
+        let (lots, withdrawable) = if tranche == 0 {
+            (&mut position.senior_lots, &mut position.senior_withdrawable_fp)
+        } else {
+            (&mut position.junior_lots, &mut position.junior_withdrawable_fp)
+        };
+        let mut ring = lots.ring();
+        ring.mature_and_consume(lockup, now, withdrawable, &mut remaining);
+        if remaining > 0 {
+            require(remaining > ring.locked_fp(), SimError::LockupNotExpired)?;
+            return Err(SimError::InsufficientPoolBalance);
+        }
+
+        if tranche == 0 {
+            position.senior_deposited_fp = position.senior_deposited_fp.saturating_sub(amount_fp);
+        } else {
+            position.junior_deposited_fp = position.junior_deposited_fp.saturating_sub(amount_fp);
+        }
+        sync_income_debt(position, idx)?;
+
+        let s = &mut self.state;
+        s.total_deposited_fp = s.total_deposited_fp.saturating_sub(amount_fp);
+        if tranche == 0 {
+            s.senior_total_fp = s.senior_total_fp.saturating_sub(amount_fp);
+        } else {
+            s.junior_total_fp = s.junior_total_fp.saturating_sub(amount_fp);
+        }
+        self.vault_fp = self.vault_fp.saturating_sub(amount_fp);
+        self.vault_u64 = self.vault_u64.checked_sub(amount).ok_or(SimError::InsufficientPoolBalance)?;
+
+        let l = self.ledgers.entry(user.to_string()).or_default();
+        l.withdrawn_u64 = l.withdrawn_u64.saturating_add(amount);
+        let t = &mut self.tranches[tranche as usize];
+        t.withdrawn_fp = t.withdrawn_fp.saturating_add(amount_fp);
+        Ok(())
+    }
+
+    // ----------------------------- epochs -----------------------------
+
+    fn start_epoch(&mut self, epoch_id: u64, duration_secs: i64) -> SimResult<()> {
+        require(!self.epochs.contains_key(&epoch_id), SimError::AccountExists)?;
+        // Ids only go up and epochs never overlap
+        require(epoch_id > self.state.current_epoch_id, SimError::ParamOutOfBounds)?;
+        require(duration_secs >= 0, SimError::EpochNotActive)?;
+        require(self.now >= self.state.last_epoch_end_ts, SimError::EpochOverlap)?;
+        let end_ts = if duration_secs == 0 { 0 } else { self.now.saturating_add(duration_secs) };
+        self.epochs.insert(epoch_id, Epoch { epoch_id, start_ts: self.now, end_ts, ..Epoch::default() });
+        self.state.current_epoch_id = epoch_id;
+        self.state.last_epoch_end_ts = if end_ts == 0 { i64::MAX } else { end_ts };
+        Ok(())
+    }
+
+    fn trigger(
+        &mut self,
+        epoch_id: u64,
+        severity_input_bps: u16,
+        user_cap_bps: Option<u16>,
+        epoch_cap_fp: Option<u128>,
+    ) -> SimResult<()> {
+        let now = self.now;
+        let p = &self.params;
+        let epoch = self.epochs.get_mut(&epoch_id).ok_or(SimError::EpochNotActive)?;
+        require(!epoch.triggered, SimError::EpochAlreadyTriggered)?;
+        require(!self.state.paused, SimError::Paused)?;
+        if epoch.end_ts != 0 {
+            require(now >= epoch.start_ts && now <= epoch.end_ts, SimError::EpochNotActive)?;
+        } else {
+            require(now >= epoch.start_ts, SimError::EpochNotActive)?;
+        }
+
+        epoch.total_stake_snapshot_fp = self.state.total_deposited_fp;
+        let sev = ovf(effective_severity_bps(
+            severity_input_bps as u128,
+            p.sev_quad_a_fp,
+            p.sev_quad_b_fp,
+            p.sev_quad_c_fp,
+            p.severity_floor_bps,
+        ))?;
+        epoch.severity_bps = sev as u16;
+        epoch.user_cap_bps = user_cap_bps.unwrap_or(0);
+        epoch.epoch_cap_fp = if p.payout_policy == PayoutPolicy::EpochBounded as u8 {
+            epoch_cap_fp.unwrap_or(p.epoch_cap_fp)
+        } else {
+            0
+        };
+        epoch.triggered = true;
+        self.state.last_event_ts = now;
+        self.state.paused = true;
+        Ok(())
+    }
+
+    fn payout(&mut self, epoch_id: u64, user: &str) -> SimResult<()> {
+        let p = &self.params;
+        let policy = PayoutPolicy::from_u8(p.payout_policy).ok_or(SimError::ParamOutOfBounds)?;
+        let epoch = self.epochs.get_mut(&epoch_id).ok_or(SimError::EpochNotActive)?;
+        require(epoch.triggered && !epoch.closed, SimError::EpochNotActive)?;
+
+        let liability = ovf(epoch_liability(
+            epoch.total_stake_snapshot_fp,
+            epoch.severity_bps,
+            policy,
+            epoch.epoch_cap_fp,
+            self.vault_fp,
+        ))?;
+        if liability.shortfall_fp > 0 {
+            epoch.shortfall_fp = liability.shortfall_fp;
+        }
+        let max_liability_fp = liability.max_fp;
+        require(max_liability_fp > 0, SimError::NothingToPayout)?;
+
+        let position = self.positions.get(user).ok_or(SimError::Unauthorized)?;
+        let stake_fp = ovf(weighted_stake_fp(
+            position.senior_deposited_fp,
+            position.junior_deposited_fp,
+            p.tranche_weight_senior_bps as u128,
+            p.tranche_weight_junior_bps as u128,
+        ))?;
+        require(stake_fp > 0 && epoch.total_stake_snapshot_fp > 0, SimError::NothingToPayout)?;
+
+        let user_fp = ovf(user_payout_fp(
+            max_liability_fp,
+            stake_fp,
+            epoch.total_stake_snapshot_fp,
+            policy,
+            epoch.user_cap_bps,
+        ))?;
+        require(!self.claims.contains(&(epoch_id, user.to_string())), SimError::NothingToPayout)?;
+        let pay_fp = claimable_fp(user_fp, max_liability_fp, epoch.total_payout_fp);
+        require(pay_fp > 0, SimError::NothingToPayout)?;
+
+        require(self.vault_fp >= pay_fp, SimError::InsufficientPoolBalance)?;
+        let pay_u64 = ovf(fp_to_asset(pay_fp, COMMON_DECIMALS))?;
+        self.vault_fp = self.vault_fp.saturating_sub(ovf(asset_to_fp(pay_u64, COMMON_DECIMALS))?);
+        self.vault_u64 = self.vault_u64.checked_sub(pay_u64).ok_or(SimError::InsufficientPoolBalance)?;
+        epoch.total_payout_fp = epoch.total_payout_fp.saturating_add(pay_fp);
+        self.claims.insert((epoch_id, user.to_string()));
+
+        // Attribute the payout to tranches by their share of the weighted stake
+        let senior_w = ovf(bps_of(position.senior_deposited_fp, p.tranche_weight_senior_bps as u128))?;
+        let senior_pay = ovf(mul_div_floor(pay_fp, senior_w, stake_fp))?;
+        self.tranches[0].payouts_fp = self.tranches[0].payouts_fp.saturating_add(senior_pay);
+        self.tranches[1].payouts_fp = self.tranches[1].payouts_fp.saturating_add(pay_fp - senior_pay);
+
+        let l = self.ledgers.entry(user.to_string()).or_default();
+        l.payouts_u64 = l.payouts_u64.saturating_add(pay_u64);
+        Ok(())
+    }
+
+    fn finalize(&mut self, epoch_id: u64, sweep_dust: Option<u64>) -> SimResult<()> {
+        let liabilities = self.tracked_liabilities_fp();
+        let epoch = self.epochs.get_mut(&epoch_id).ok_or(SimError::EpochNotActive)?;
+        require(epoch.triggered && !epoch.closed, SimError::EpochNotActive)?;
+        if epoch.shortfall_fp > 0 {
+            self.state.carryover_shortfall_fp = self.state.carryover_shortfall_fp.saturating_add(epoch.shortfall_fp);
+        }
+
+        if let Some(sweep) = sweep_dust.filter(|s| *s > 0) {
+            if self.vault_fp > liabilities {
+                let dust_fp = self.vault_fp - liabilities;
+                let move_u64 = sweep.min(ovf(fp_to_asset(dust_fp, COMMON_DECIMALS))?);
+                if move_u64 > 0 {
+                    self.vault_fp -= ovf(asset_to_fp(move_u64, COMMON_DECIMALS))?;
+                    self.vault_u64 -= move_u64;
+                    self.treasury_u64 = self.treasury_u64.saturating_add(move_u64);
+                    self.dust_swept_u64 = self.dust_swept_u64.saturating_add(move_u64);
+                }
+            }
+        }
+
+        epoch.closed = true;
+        self.state.paused = false;
+        // A finalized epoch covers nothing more
+        if epoch_id == self.state.current_epoch_id {
+            self.state.last_epoch_end_ts = self.state.last_epoch_end_ts.min(self.now);
+        }
+        Ok(())
+    }
+
+    // ----------------------------- accounting -----------------------------
+
+    fn distribute_income(&mut self, amount_fp: u128) -> SimResult<()> {
+        let s = &mut self.state;
+        let (senior, junior) = ovf(split_yield(amount_fp, self.params.junior_yield_bps, s.senior_total_fp, s.junior_total_fp))?;
+        s.senior_yield_index_fp = s.senior_yield_index_fp.saturating_add(ovf(index_increment(senior, s.senior_total_fp))?);
+        s.junior_yield_index_fp = s.junior_yield_index_fp.saturating_add(ovf(index_increment(junior, s.junior_total_fp))?);
+        s.yield_reserve_fp = s.yield_reserve_fp.saturating_add(senior.saturating_add(junior));
+        self.tranches[0].income_fp = self.tranches[0].income_fp.saturating_add(senior);
+        self.tranches[1].income_fp = self.tranches[1].income_fp.saturating_add(junior);
+        Ok(())
+    }
+
+    fn tracked_liabilities_fp(&self) -> u128 {
+        self.state
+            .total_deposited_fp
+            .saturating_add(self.state.yield_reserve_fp)
+            .saturating_add(self.state.referral_rewards_owed_fp)
+    }
+
+    /// Snapshot of the pool after the last applied step.
+    pub fn report(&self) -> SimResult<StepReport> {
+        let idx = (self.state.senior_yield_index_fp, self.state.junior_yield_index_fp);
+        let mut users = Vec::new();
+        let names: BTreeSet<&String> = self.positions.keys().chain(self.ledgers.keys()).collect();
+        for name in names {
+            let position = self.positions.get(name).cloned().unwrap_or_default();
+            let ledger = self.ledgers.get(name).cloned().unwrap_or_default();
+            let mut settled = position.clone();
+            settle_income(&mut settled, idx)?;
+            let value_fp = settled
+                .senior_deposited_fp
+                .saturating_add(settled.junior_deposited_fp)
+                .saturating_add(settled.yield_accrued_fp)
+                .saturating_add(ledger.referral_rewards_fp);
+            let out_fp = ovf(asset_to_fp(ledger.withdrawn_u64.saturating_add(ledger.payouts_u64), COMMON_DECIMALS))?;
+            let in_fp = ovf(asset_to_fp(ledger.deposited_u64, COMMON_DECIMALS))?;
+            users.push(UserReport {
+                user: name.clone(),
+                deposited_u64: ledger.deposited_u64,
+                withdrawn_u64: ledger.withdrawn_u64,
+                payouts_u64: ledger.payouts_u64,
+                senior_fp: settled.senior_deposited_fp,
+                junior_fp: settled.junior_deposited_fp,
+                yield_accrued_fp: settled.yield_accrued_fp,
+                referral_rewards_fp: ledger.referral_rewards_fp,
+                pnl_fp: signed(value_fp.saturating_add(out_fp)) - signed(in_fp),
+            });
+        }
+
+        let principal = [self.state.senior_total_fp, self.state.junior_total_fp];
+        let tranches = [0usize, 1].map(|i| {
+            let t = &self.tranches[i];
+            let back = principal[i].saturating_add(t.withdrawn_fp).saturating_add(t.income_fp).saturating_add(t.payouts_fp);
+            TrancheReport {
+                tranche: i as u8,
+                principal_fp: principal[i],
+                deposited_fp: t.deposited_fp,
+                withdrawn_fp: t.withdrawn_fp,
+                income_fp: t.income_fp,
+                payouts_fp: t.payouts_fp,
+                pnl_fp: signed(back) - signed(t.deposited_fp),
+            }
+        });
+
+        let liabilities = self.tracked_liabilities_fp();
+        Ok(StepReport {
+            step: self.step,
+            now: self.now,
+            paused: self.state.paused,
+            vault_u64: self.vault_u64,
+            vault_fp: self.vault_fp,
+            total_deposited_fp: self.state.total_deposited_fp,
+            tracked_liabilities_fp: liabilities,
+            surplus_fp: signed(self.vault_fp) - signed(liabilities),
+            carryover_shortfall_fp: self.state.carryover_shortfall_fp,
+            fees: FeeFlows {
+                protocol_fees_u64: self.treasury_u64.saturating_sub(self.dust_swept_u64),
+                referral_rewards_owed_fp: self.state.referral_rewards_owed_fp,
+                referral_to_lps_fp: self.referral_to_lps_fp,
+                dust_swept_u64: self.dust_swept_u64,
+            },
+            tranches: tranches.to_vec(),
+            users,
+            epochs: self
+                .epochs
+                .values()
+                .map(|e| EpochReport {
+                    epoch_id: e.epoch_id,
+                    triggered: e.triggered,
+                    closed: e.closed,
+                    severity_bps: e.severity_bps,
+                    total_stake_snapshot_fp: e.total_stake_snapshot_fp,
+                    total_payout_fp: e.total_payout_fp,
+                    shortfall_fp: e.shortfall_fp,
+                })
+                .collect(),
+        })
+    }
+}
+
+fn require(cond: bool, err: SimError) -> SimResult<()> {
+    if cond {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
+
+fn signed(x: u128) -> i128 {
+    i128::try_from(x).unwrap_or(i128::MAX)
+}
+
+fn settle_income(p: &mut Position, idx: (u128, u128)) -> SimResult<()> {
+    let s_acc = ovf(accrued_fp(p.senior_deposited_fp, idx.0))?;
+    let j_acc = ovf(accrued_fp(p.junior_deposited_fp, idx.1))?;
+    let pending = s_acc
+        .saturating_sub(p.senior_yield_debt_fp)
+        .saturating_add(j_acc.saturating_sub(p.junior_yield_debt_fp));
+    p.yield_accrued_fp = p.yield_accrued_fp.saturating_add(pending);
+    p.senior_yield_debt_fp = s_acc;
+    p.junior_yield_debt_fp = j_acc;
+    Ok(())
+}
+
+fn sync_income_debt(p: &mut Position, idx: (u128, u128)) -> SimResult<()> {
+    p.senior_yield_debt_fp = ovf(accrued_fp(p.senior_deposited_fp, idx.0))?;
+    p.junior_yield_debt_fp = ovf(accrued_fp(p.junior_deposited_fp, idx.1))?;
+    Ok(())
+}
+
+// Same bounds as the program's `assert_param_bounds` for the simulated fields
+fn assert_param_bounds(p: &PoolParams) -> SimResult<()> {
+    require(PayoutPolicy::from_u8(p.payout_policy).is_some(), SimError::ParamOutOfBounds)?;
+    require(FeeModel::from_u8(p.fee_model).is_some(), SimError::ParamOutOfBounds)?;
+    require(p.protocol_fee_bps <= 1_000, SimError::ParamOutOfBounds)?;
+    require(p.referral_fee_bps <= 1_000, SimError::ParamOutOfBounds)?;
+    require(p.tranche_weight_senior_bps as u128 <= BPS_DENOM, SimError::ParamOutOfBounds)?;
+    require(p.tranche_weight_junior_bps as u128 <= BPS_DENOM, SimError::ParamOutOfBounds)?;
+    require(p.junior_yield_bps as u128 <= BPS_DENOM, SimError::ParamOutOfBounds)?;
+    Ok(())
+}
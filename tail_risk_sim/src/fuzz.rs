@@ -0,0 +1,209 @@
+//! Random action sequences checked against the [`invariants`](crate::invariants).
+//!
+//! Each case draws pool parameters and a sequence of deposits, withdrawals,
+//! clock moves, epoch starts, triggers, payouts, finalizations and admin
+//! changes from a seeded RNG, replays it and checks every invariant after
+//! every step. A failing case is minimised by dropping actions while the same
+//! invariant still breaks, and comes out as a [`Scenario`] that
+//! `tail-risk-sim` replays as-is.
+//!
+//! Runs are reproducible: case `i` of seed `s` is always the same scenario.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use tail_risk_core::SCALE;
+
+use crate::engine::{Action, SimError, Simulator};
+use crate::invariants::{Tracker, Violation};
+use crate::model::{PoolParams, USDC_FP};
+use crate::Scenario;
+
+const USDC: u64 = 1_000_000;
+const USERS: [&str; 4] = ["alice", "bob", "carol", "dave"];
+const MAX_EPOCH_ID: u64 = 4;
+
+/// Fuzz run configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FuzzConfig {
+    /// Number of random cases.
+    pub cases: u32,
+    /// Actions per case.
+    pub steps: usize,
+    /// RNG seed.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+/// An invariant broken by a scenario.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// Index of the action after which the invariant broke.
+    pub step: usize,
+    /// The broken invariant.
+    pub violation: Violation,
+}
+
+/// A minimised failing case.
+#[derive(Clone, Debug, Serialize)]
+pub struct FuzzFailure {
+    /// Case index within the run.
+    pub case: u32,
+    /// Finding on the minimised scenario.
+    pub finding: Finding,
+    /// Minimised scenario.
+    pub scenario: Scenario,
+}
+
+/// Run `cfg.cases` random scenarios; returns every failing case, minimised.
+pub fn run_fuzz(cfg: &FuzzConfig) -> Result<Vec<FuzzFailure>, SimError> {
+    let mut failures = Vec::new();
+    for case in 0..cfg.cases {
+        let scenario = random_scenario(cfg.seed, case, cfg.steps);
+        if let Err(finding) = check(&scenario)? {
+            let kind = finding.violation.kind();
+            let scenario = minimise(&scenario, |s| matches!(check(s), Ok(Err(f)) if f.violation.kind() == kind));
+            let finding = check(&scenario)?.expect_err("minimised scenario still fails");
+            failures.push(FuzzFailure { case, finding, scenario });
+        }
+    }
+    Ok(failures)
+}
+
+/// Replay a scenario, checking the invariants after every step (rejected
+/// actions included). The outer error is an invalid parameter set.
+pub fn check(scenario: &Scenario) -> Result<Result<(), Finding>, SimError> {
+    let mut sim = Simulator::new(scenario.params.clone(), scenario.start_ts)?;
+    let mut tracker = Tracker::default();
+    let mut before = sim.view();
+    for (step, action) in scenario.actions.iter().enumerate() {
+        let _ = sim.step(action);
+        let after = sim.view();
+        if let Err(violation) = tracker.check(action, &before, &after) {
+            return Ok(Err(Finding { step, violation }));
+        }
+        before = after;
+    }
+    Ok(Ok(()))
+}
+
+/// Shrink a scenario that satisfies `fails`: fall back to default parameters
+/// if it still fails with them, cut everything after the first failure, then
+/// drop chunks of actions (halving the chunk size down to one) while `fails`
+/// still holds.
+pub fn minimise(scenario: &Scenario, fails: impl Fn(&Scenario) -> bool) -> Scenario {
+    let mut best = scenario.clone();
+    let defaults = Scenario { params: PoolParams::default(), ..scenario.clone() };
+    if fails(&defaults) {
+        best = defaults;
+    }
+    while best.actions.len() > 1 {
+        let mut shorter = best.clone();
+        shorter.actions.pop();
+        if !fails(&shorter) {
+            break;
+        }
+        best = shorter;
+    }
+
+    let mut chunk = best.actions.len().div_ceil(2).max(1);
+    loop {
+        let mut start = 0;
+        while start < best.actions.len() {
+            let mut candidate = best.clone();
+            let end = (start + chunk).min(candidate.actions.len());
+            candidate.actions.drain(start..end);
+            if fails(&candidate) {
+                best = candidate;
+            } else {
+                start += chunk;
+            }
+        }
+        if chunk == 1 {
+            return best;
+        }
+        chunk /= 2;
+    }
+}
+
+/// Case `case` of a run seeded with `seed`.
+pub fn random_scenario(seed: u64, case: u32, steps: usize) -> Scenario {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(case as u64);
+    let params = random_params(&mut rng);
+    let actions = (0..steps).map(|_| random_action(&mut rng)).collect();
+    Scenario { params, start_ts: 1_700_000_000, actions }
+}
+
+fn random_params(rng: &mut ChaCha8Rng) -> PoolParams {
+    PoolParams {
+        payout_policy: rng.gen_range(0..=2),
+        user_deposit_cap_fp: rng.gen_range(2_000..=50_000) * USDC_FP,
+        min_deposit_fp: rng.gen_range(1..=200) * USDC_FP,
+        protocol_fee_bps: rng.gen_range(0..=1_000),
+        referral_fee_bps: rng.gen_range(0..=1_000),
+        fee_model: rng.gen_range(0..=2),
+        lockup_secs: rng.gen_range(0..=120),
+        min_seconds_between_deposits: rng.gen_range(0..=20),
+        epoch_cap_fp: rng.gen_range(0..=20_000) * USDC_FP,
+        sev_quad_a_fp: 0,
+        sev_quad_b_fp: rng.gen_range(SCALE / 2..=2 * SCALE),
+        sev_quad_c_fp: 0,
+        severity_floor_bps: rng.gen_range(0..=1_000),
+        tranche_weight_senior_bps: rng.gen_range(0..=10_000),
+        tranche_weight_junior_bps: rng.gen_range(0..=10_000),
+        junior_yield_bps: rng.gen_range(0..=10_000),
+    }
+}
+
+fn random_action(rng: &mut ChaCha8Rng) -> Action {
+    let user = |rng: &mut ChaCha8Rng| USERS[rng.gen_range(0..USERS.len())].to_string();
+    let epoch_id = |rng: &mut ChaCha8Rng| rng.gen_range(1..=MAX_EPOCH_ID);
+    // Whole USDC most of the time, odd native amounts otherwise (rounding)
+    let amount = |rng: &mut ChaCha8Rng, max: u64| {
+        let whole = rng.gen_range(1..=max) * USDC;
+        if rng.gen_bool(0.25) {
+            whole + rng.gen_range(1..USDC)
+        } else {
+            whole
+        }
+    };
+    match rng.gen_range(0..100) {
+        0..=24 => {
+            let referrer = rng.gen_bool(0.3).then(|| user(rng));
+            Action::Deposit { user: user(rng), amount: amount(rng, 5_000), tranche: rng.gen_range(0..=1), referrer }
+        }
+        25..=39 => Action::Withdraw { user: user(rng), amount: amount(rng, 3_000), tranche: rng.gen_range(0..=1) },
+        40..=54 => {
+            let secs = if rng.gen_bool(0.1) { rng.gen_range(600..=7_200) } else { rng.gen_range(1..=90) };
+            Action::AdvanceTime { secs }
+        }
+        55..=61 => {
+            let duration_secs = if rng.gen_bool(0.5) { 0 } else { rng.gen_range(60..=3_600) };
+            Action::StartEpoch { epoch_id: epoch_id(rng), duration_secs }
+        }
+        62..=69 => Action::Trigger {
+            epoch_id: epoch_id(rng),
+            severity_input_bps: rng.gen_range(0..=10_000),
+            user_cap_bps: rng.gen_bool(0.5).then(|| rng.gen_range(0..=10_000)),
+            epoch_cap_fp: rng.gen_bool(0.3).then(|| rng.gen_range(0..=20_000) * USDC_FP),
+        },
+        70..=89 => Action::Payout { epoch_id: epoch_id(rng), user: user(rng) },
+        90..=95 => Action::Finalize {
+            epoch_id: epoch_id(rng),
+            sweep_dust: rng.gen_bool(0.3).then(|| rng.gen_range(0..=100) * USDC),
+        },
+        96..=97 => Action::SetPolicy {
+            payout_policy: rng.gen_range(0..=2),
+            epoch_cap_fp: rng.gen_bool(0.5).then(|| rng.gen_range(0..=20_000) * USDC_FP),
+        },
+        _ => Action::SetCurveAndWeights {
+            sev_quad_a_fp: 0,
+            sev_quad_b_fp: rng.gen_range(SCALE / 2..=2 * SCALE),
+            sev_quad_c_fp: 0,
+            severity_floor_bps: rng.gen_range(0..=1_000),
+            tranche_weight_senior_bps: rng.gen_range(0..=10_000),
+            tranche_weight_junior_bps: rng.gen_range(0..=10_000),
+        },
+    }
+}
@@ -0,0 +1,231 @@
+//! Accounting invariants checked after every step.
+//!
+//! The checks read a [`PoolView`], which the simulator builds from its model
+//! and a program-test harness can build from on-chain accounts, so the same
+//! invariants hold both off-chain and against the program:
+//!
+//! - the vault covers every withdrawable claim (matured principal, LP income,
+//!   referral rewards, accrued fees), allowing for losses already paid out
+//!   (payouts do not write principal down)
+//! - `total_deposited_fp` (and each tranche total) equals the sum of position principals
+//! - an epoch never pays more than its liability after the policy cap
+//! - no claim exceeds the claimant's pro-rata share of the stake at trigger time
+//! - `processing` is false between transactions
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tail_risk_core::payout::{epoch_liability, user_payout_fp, weighted_stake_fp, PayoutPolicy};
+
+use crate::engine::Action;
+
+/// Accounting state the invariants read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolView {
+    /// Re-entrancy guard (`State.processing`).
+    pub processing: bool,
+    /// `PayoutPolicy` as `u8`.
+    pub payout_policy: u8,
+    /// Senior payout weight (bps).
+    pub tranche_weight_senior_bps: u16,
+    /// Junior payout weight (bps).
+    pub tranche_weight_junior_bps: u16,
+    /// Base vault balance.
+    pub vault_fp: u128,
+    /// Sum of all principal.
+    pub total_deposited_fp: u128,
+    /// Senior principal.
+    pub senior_total_fp: u128,
+    /// Junior principal.
+    pub junior_total_fp: u128,
+    /// Income owed to LPs.
+    pub yield_reserve_fp: u128,
+    /// Referral rewards owed to referrers.
+    pub referral_rewards_owed_fp: u128,
+    /// Protocol fees held in the vault for the treasury.
+    pub protocol_fees_accrued_fp: u128,
+    /// Positions by user.
+    pub positions: BTreeMap<String, PositionView>,
+    /// Epochs by id.
+    pub epochs: BTreeMap<u64, EpochView>,
+}
+
+/// Principal and withdrawable balances of one position.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PositionView {
+    /// Senior principal.
+    pub senior_deposited_fp: u128,
+    /// Junior principal.
+    pub junior_deposited_fp: u128,
+    /// Senior balance free of lockup.
+    pub senior_withdrawable_fp: u128,
+    /// Junior balance free of lockup.
+    pub junior_withdrawable_fp: u128,
+}
+
+/// Payout state of one epoch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EpochView {
+    /// Triggered by the oracle.
+    pub triggered: bool,
+    /// Finalized.
+    pub closed: bool,
+    /// Principal snapshot at trigger.
+    pub total_stake_snapshot_fp: u128,
+    /// Paid so far.
+    pub total_payout_fp: u128,
+    /// Effective severity (bps).
+    pub severity_bps: u16,
+    /// Per-user cap under `Capped` (bps).
+    pub user_cap_bps: u16,
+    /// Epoch cap under `EpochBounded`.
+    pub epoch_cap_fp: u128,
+}
+
+/// A broken invariant.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Violation {
+    /// Vault plus paid losses is below the withdrawable claims on it.
+    Undercollateralised {
+        /// Vault balance.
+        vault_fp: u128,
+        /// Sum of all epoch payouts.
+        paid_fp: u128,
+        /// Withdrawable principal + income + referral rewards + fees.
+        claims_fp: u128,
+    },
+    /// A principal total disagrees with the positions.
+    PrincipalMismatch {
+        /// `total`, `senior` or `junior`.
+        field: &'static str,
+        /// Value in `State`.
+        state_fp: u128,
+        /// Sum over positions.
+        positions_fp: u128,
+    },
+    /// An epoch paid more than its capped liability.
+    EpochOverpaid {
+        /// Epoch id.
+        epoch_id: u64,
+        /// Paid so far.
+        paid_fp: u128,
+        /// Liability after the policy cap.
+        max_fp: u128,
+    },
+    /// A claim paid more than the claimant's pro-rata share.
+    ClaimAboveShare {
+        /// Epoch id.
+        epoch_id: u64,
+        /// Claimant.
+        user: String,
+        /// Amount paid.
+        paid_fp: u128,
+        /// Share of the capped liability for the stake held at trigger.
+        share_fp: u128,
+    },
+    /// `processing` left set after a transaction.
+    ProcessingLeftSet,
+}
+
+impl Violation {
+    /// Stable name of the invariant, used to match findings while minimising.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Violation::Undercollateralised { .. } => "undercollateralised",
+            Violation::PrincipalMismatch { .. } => "principal_mismatch",
+            Violation::EpochOverpaid { .. } => "epoch_overpaid",
+            Violation::ClaimAboveShare { .. } => "claim_above_share",
+            Violation::ProcessingLeftSet => "processing_left_set",
+        }
+    }
+}
+
+/// Checks a sequence of steps; remembers each user's stake when an epoch triggers.
+#[derive(Clone, Debug, Default)]
+pub struct Tracker {
+    stakes: BTreeMap<u64, BTreeMap<String, (u128, u128)>>,
+}
+
+impl Tracker {
+    /// Check the pool after `action` moved it from `before` to `after`.
+    /// Call it for rejected actions too (with `after == before`).
+    pub fn check(&mut self, action: &Action, before: &PoolView, after: &PoolView) -> Result<(), Violation> {
+        for (id, e) in &after.epochs {
+            let was_triggered = before.epochs.get(id).is_some_and(|b| b.triggered);
+            if e.triggered && !was_triggered {
+                let stakes = after
+                    .positions
+                    .iter()
+                    .map(|(u, p)| (u.clone(), (p.senior_deposited_fp, p.junior_deposited_fp)))
+                    .collect();
+                self.stakes.insert(*id, stakes);
+            }
+        }
+        if let Action::Payout { epoch_id, user } = action {
+            self.check_claim(*epoch_id, user, before, after)?;
+        }
+        check_state(after)
+    }
+
+    fn check_claim(&self, epoch_id: u64, user: &str, before: &PoolView, after: &PoolView) -> Result<(), Violation> {
+        let (Some(b), Some(e)) = (before.epochs.get(&epoch_id), after.epochs.get(&epoch_id)) else {
+            return Ok(());
+        };
+        let paid_fp = e.total_payout_fp.saturating_sub(b.total_payout_fp);
+        if paid_fp == 0 {
+            return Ok(());
+        }
+        // Parameters in force when the claim was made
+        let policy = PayoutPolicy::from_u8(before.payout_policy).unwrap_or(PayoutPolicy::Proportional);
+        let max_fp = epoch_liability(e.total_stake_snapshot_fp, e.severity_bps, policy, e.epoch_cap_fp, u128::MAX)
+            .map_or(u128::MAX, |l| l.capped_fp);
+        if e.total_payout_fp > max_fp {
+            return Err(Violation::EpochOverpaid { epoch_id, paid_fp: e.total_payout_fp, max_fp });
+        }
+
+        let (senior, junior) = self.stakes.get(&epoch_id).and_then(|s| s.get(user)).copied().unwrap_or_default();
+        let share_fp = weighted_stake_fp(
+            senior,
+            junior,
+            before.tranche_weight_senior_bps as u128,
+            before.tranche_weight_junior_bps as u128,
+        )
+        .and_then(|stake| user_payout_fp(max_fp, stake, e.total_stake_snapshot_fp, policy, e.user_cap_bps))
+        .unwrap_or(u128::MAX);
+        if paid_fp > share_fp {
+            return Err(Violation::ClaimAboveShare { epoch_id, user: user.to_string(), paid_fp, share_fp });
+        }
+        Ok(())
+    }
+}
+
+/// Invariants that hold for any single pool state.
+pub fn check_state(v: &PoolView) -> Result<(), Violation> {
+    if v.processing {
+        return Err(Violation::ProcessingLeftSet);
+    }
+
+    let sum = |f: fn(&PositionView) -> u128| v.positions.values().fold(0u128, |acc, p| acc.saturating_add(f(p)));
+    let senior = sum(|p| p.senior_deposited_fp);
+    let junior = sum(|p| p.junior_deposited_fp);
+    for (field, state_fp, positions_fp) in [
+        ("total", v.total_deposited_fp, senior.saturating_add(junior)),
+        ("senior", v.senior_total_fp, senior),
+        ("junior", v.junior_total_fp, junior),
+    ] {
+        if state_fp != positions_fp {
+            return Err(Violation::PrincipalMismatch { field, state_fp, positions_fp });
+        }
+    }
+
+    let claims_fp = sum(|p| p.senior_withdrawable_fp.saturating_add(p.junior_withdrawable_fp))
+        .saturating_add(v.yield_reserve_fp)
+        .saturating_add(v.referral_rewards_owed_fp)
+        .saturating_add(v.protocol_fees_accrued_fp);
+    let paid_fp = v.epochs.values().fold(0u128, |acc, e| acc.saturating_add(e.total_payout_fp));
+    if v.vault_fp.saturating_add(paid_fp) < claims_fp {
+        return Err(Violation::Undercollateralised { vault_fp: v.vault_fp, paid_fp, claims_fp });
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! Deterministic off-chain simulator of the tail-risk insurance pool.
+//!
+//! Models `State`, `Epoch`, `UserPosition` and the base vault in memory and
+//! replays deposits, withdrawals, triggers, payouts and finalizations with the
+//! program's own math (`tail_risk_core`). Every step yields a [`StepReport`]
+//! with per-user and per-tranche P&L, vault balance, shortfall and fee flows.
+//!
+//! Scope: a single 6-decimal collateral, no strategy, premiums or referral
+//! tiers; fees are only charged under fee-on-deposit, as on-chain.
+//!
+//! [`montecarlo`] runs many simulated epochs under sampled events for tail metrics.
+//! [`fuzz`] replays random action sequences and checks the [`invariants`] after
+//! every step.
+
+pub mod engine;
+pub mod fuzz;
+pub mod invariants;
+pub mod model;
+pub mod montecarlo;
+pub mod report;
+
+pub use engine::{Action, SimError, Simulator};
+pub use model::PoolParams;
+pub use report::StepReport;
+
+use serde::{Deserialize, Serialize};
+
+/// A parameter set and the actions to replay.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Pool parameters.
+    #[serde(default)]
+    pub params: PoolParams,
+    /// Clock at the first step.
+    #[serde(default)]
+    pub start_ts: i64,
+    /// Steps, in order.
+    pub actions: Vec<Action>,
+}
+
+/// Outcome of one scenario step: the report, or why the action was rejected.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StepOutcome {
+    /// The action.
+    pub action: Action,
+    /// Rejection reason; the pool is unchanged when set.
+    pub error: Option<SimError>,
+    /// Pool after the step.
+    pub report: StepReport,
+}
+
+/// Replay a scenario; rejected actions are recorded and skipped, like failed transactions.
+pub fn run(scenario: &Scenario) -> Result<Vec<StepOutcome>, SimError> {
+    let mut sim = Simulator::new(scenario.params.clone(), scenario.start_ts)?;
+    let mut out = Vec::with_capacity(scenario.actions.len());
+    for action in &scenario.actions {
+        let (error, report) = match sim.apply(action) {
+            Ok(r) => (None, r),
+            Err(e) => (Some(e), sim.report()?),
+        };
+        out.push(StepOutcome { action: action.clone(), error, report });
+    }
+    Ok(out)
+}
@@ -0,0 +1,28 @@
+//! `tail-risk-sim <scenario.json>`: replay a scenario and print one JSON report per step.
+
+use std::{env, fs, process};
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: tail-risk-sim <scenario.json>");
+        process::exit(2);
+    };
+    let scenario: tail_risk_sim::Scenario = match fs::read_to_string(&path).map(|s| serde_json::from_str(&s)) {
+        Ok(Ok(s)) => s,
+        Ok(Err(e)) => fail(&format!("invalid scenario {path}: {e}")),
+        Err(e) => fail(&format!("cannot read {path}: {e}")),
+    };
+    match tail_risk_sim::run(&scenario) {
+        Ok(steps) => {
+            for step in steps {
+                println!("{}", serde_json::to_string(&step).expect("reports serialise"));
+            }
+        }
+        Err(e) => fail(&format!("invalid parameters: {e}")),
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    process::exit(1);
+}
@@ -0,0 +1,180 @@
+//! In-memory mirrors of the program accounts.
+//!
+//! Field names follow `State`, `UserPosition` and `Epoch` in the program so a
+//! report can be compared line by line with on-chain data.
+
+use serde::{Deserialize, Serialize};
+use tail_risk_core::{
+    lots::{Lot, LotRing},
+    SCALE,
+};
+
+/// Ring capacity per tranche (`MAX_LOTS` in the program).
+pub const MAX_LOTS: usize = 16;
+
+/// One whole USDC in fixed-point (native 6-decimal units scaled by `SCALE`).
+pub const USDC_FP: u128 = 1_000_000 * SCALE;
+
+/// Pool parameters, as passed to `initialize` / `set_policy` / `set_curve_and_weights`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoolParams {
+    /// `PayoutPolicy` as `u8`.
+    pub payout_policy: u8,
+    /// Per-user cap on senior + junior principal (fixed-point).
+    pub user_deposit_cap_fp: u128,
+    /// Minimum deposit (fixed-point).
+    pub min_deposit_fp: u128,
+    /// Protocol fee (bps).
+    pub protocol_fee_bps: u16,
+    /// Referral fee (bps).
+    pub referral_fee_bps: u16,
+    /// `FeeModel` as `u8`; only fee-on-deposit charges anything in the simulator.
+    pub fee_model: u8,
+    /// Lot lockup before withdrawal (seconds).
+    pub lockup_secs: i64,
+    /// Per-user deposit cooldown (seconds).
+    pub min_seconds_between_deposits: i64,
+    /// Epoch liability cap under `EpochBounded` (fixed-point).
+    pub epoch_cap_fp: u128,
+    /// Severity curve coefficients (fixed-point).
+    pub sev_quad_a_fp: u128,
+    /// Linear coefficient.
+    pub sev_quad_b_fp: u128,
+    /// Constant term.
+    pub sev_quad_c_fp: u128,
+    /// Severity floor (bps).
+    pub severity_floor_bps: u16,
+    /// Senior tranche payout weight (bps).
+    pub tranche_weight_senior_bps: u16,
+    /// Junior tranche payout weight (bps).
+    pub tranche_weight_junior_bps: u16,
+    /// Junior share of tranche income (`strategy_junior_yield_bps`).
+    pub junior_yield_bps: u16,
+}
+
+impl Default for PoolParams {
+    /// Playground test-suite values, with tranche weights inside the program's 100% bound.
+    fn default() -> Self {
+        Self {
+            payout_policy: 0,
+            user_deposit_cap_fp: 1_000_000 * USDC_FP,
+            min_deposit_fp: 100 * USDC_FP,
+            protocol_fee_bps: 50,
+            referral_fee_bps: 25,
+            fee_model: 0,
+            lockup_secs: 60,
+            min_seconds_between_deposits: 10,
+            epoch_cap_fp: 500_000 * USDC_FP,
+            sev_quad_a_fp: 0,
+            sev_quad_b_fp: SCALE,
+            sev_quad_c_fp: 0,
+            severity_floor_bps: 100,
+            tranche_weight_senior_bps: 10_000,
+            tranche_weight_junior_bps: 10_000,
+            junior_yield_bps: 0,
+        }
+    }
+}
+
+/// Global accounting (subset of `State`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolState {
+    /// Pool paused (set by a trigger until finalization).
+    pub paused: bool,
+    /// Sum of all principal.
+    pub total_deposited_fp: u128,
+    /// Senior principal.
+    pub senior_total_fp: u128,
+    /// Junior principal.
+    pub junior_total_fp: u128,
+    /// Senior income index.
+    pub senior_yield_index_fp: u128,
+    /// Junior income index.
+    pub junior_yield_index_fp: u128,
+    /// Income owed to LPs.
+    pub yield_reserve_fp: u128,
+    /// Referral rewards owed to referrers.
+    pub referral_rewards_owed_fp: u128,
+    /// Shortfall carried over from finalized epochs.
+    pub carryover_shortfall_fp: u128,
+    /// Last trigger time.
+    pub last_event_ts: i64,
+    /// Latest started epoch.
+    pub current_epoch_id: u64,
+    /// When the latest epoch's cover ends (`EpochHistory.last_end_ts`; `i64::MAX` while an
+    /// open-ended epoch runs).
+    pub last_epoch_end_ts: i64,
+}
+
+/// Fixed-size FIFO ring of lots (`Lots` in the program).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lots {
+    /// Oldest lot.
+    pub head: u8,
+    /// Live lots.
+    pub len: u8,
+    /// Storage.
+    pub buf: [Lot; MAX_LOTS],
+}
+
+impl Lots {
+    /// Borrow as a core ring.
+    pub fn ring(&mut self) -> LotRing<'_, Lot> {
+        LotRing { head: &mut self.head, len: &mut self.len, buf: &mut self.buf }
+    }
+}
+
+/// A user's position (`UserPosition`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    /// Senior principal.
+    pub senior_deposited_fp: u128,
+    /// Junior principal.
+    pub junior_deposited_fp: u128,
+    /// Senior balance free of lockup.
+    pub senior_withdrawable_fp: u128,
+    /// Junior balance free of lockup.
+    pub junior_withdrawable_fp: u128,
+    /// Senior lots.
+    pub senior_lots: Lots,
+    /// Junior lots.
+    pub junior_lots: Lots,
+    /// Last deposit time (0 = never).
+    pub last_deposit_ts: i64,
+    /// First-touch referrer.
+    pub referrer: Option<String>,
+    /// Senior reward debt.
+    pub senior_yield_debt_fp: u128,
+    /// Junior reward debt.
+    pub junior_yield_debt_fp: u128,
+    /// Settled, unclaimed income.
+    pub yield_accrued_fp: u128,
+}
+
+/// A coverage epoch (`Epoch`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Epoch {
+    /// Epoch id.
+    pub epoch_id: u64,
+    /// Start time.
+    pub start_ts: i64,
+    /// End time (0 = rolling).
+    pub end_ts: i64,
+    /// Principal snapshot at trigger.
+    pub total_stake_snapshot_fp: u128,
+    /// Paid so far.
+    pub total_payout_fp: u128,
+    /// Unfunded liability seen by claims.
+    pub shortfall_fp: u128,
+    /// Effective severity (bps).
+    pub severity_bps: u16,
+    /// Per-user cap under `Capped` (bps).
+    pub user_cap_bps: u16,
+    /// Epoch cap under `EpochBounded`.
+    pub epoch_cap_fp: u128,
+    /// Triggered by the oracle.
+    pub triggered: bool,
+    /// Finalized.
+    pub closed: bool,
+}
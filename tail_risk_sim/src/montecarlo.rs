@@ -0,0 +1,320 @@
+//! Monte Carlo stress engine.
+//!
+//! Each trial funds a fresh [`Simulator`] pool, then runs a sequence of
+//! epochs. Per epoch, the number of events is drawn from a Poisson
+//! distribution; if at least one occurs the worst severity triggers the epoch
+//! (the program allows one trigger per epoch), every depositor claims and the
+//! epoch is finalized. All payout math is the program's own.
+//!
+//! Runs are reproducible: the same config and seed give the same report.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, LogNormal, Pareto, Poisson};
+use serde::{Deserialize, Serialize};
+use tail_risk_core::BPS_DENOM;
+
+use crate::engine::{Action, SimError, Simulator};
+use crate::model::PoolParams;
+
+/// Event severity distribution, sampled in bps of oracle input (clamped to 100%).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SeverityDist {
+    /// Pareto with minimum `scale_bps` and tail index `shape`.
+    Pareto {
+        /// Minimum severity (bps).
+        scale_bps: f64,
+        /// Tail index (smaller = heavier tail).
+        shape: f64,
+    },
+    /// Lognormal: `ln(severity_bps) ~ N(mu, sigma)`.
+    Lognormal {
+        /// Mean of the log.
+        mu: f64,
+        /// Standard deviation of the log.
+        sigma: f64,
+    },
+}
+
+/// Starting pool for each trial.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSetup {
+    /// Senior depositors.
+    pub senior_depositors: u32,
+    /// Junior depositors.
+    pub junior_depositors: u32,
+    /// Deposit per depositor (native units).
+    pub deposit_amount: u64,
+}
+
+/// Stress run configuration.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StressConfig {
+    /// Pool parameters (policy, curve, weights, fees).
+    #[serde(default)]
+    pub params: PoolParams,
+    /// Starting pool.
+    pub pool: PoolSetup,
+    /// Number of trials.
+    pub trials: u32,
+    /// Epochs per trial.
+    pub epochs_per_trial: u32,
+    /// Expected events per epoch (Poisson rate).
+    pub events_per_epoch: f64,
+    /// Severity distribution.
+    pub severity: SeverityDist,
+    /// Per-user cap passed to triggers (`Capped` policy).
+    #[serde(default)]
+    pub user_cap_bps: Option<u16>,
+    /// Confidence levels for VaR / ES.
+    #[serde(default = "default_confidence")]
+    pub confidence_levels: Vec<f64>,
+    /// RNG seed.
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_confidence() -> Vec<f64> {
+    vec![0.95, 0.99]
+}
+
+/// Outcome of one trial.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TrialResult {
+    /// Trial index.
+    pub trial: u32,
+    /// Epochs that triggered.
+    pub triggered_epochs: u32,
+    /// Total paid out (fixed-point).
+    pub loss_fp: u128,
+    /// Unfunded liability recorded across the trial (`carryover_shortfall_fp`).
+    pub carryover_shortfall_fp: u128,
+    /// Any epoch could not fund its full liability.
+    pub ruined: bool,
+}
+
+/// VaR and expected shortfall at one confidence level.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TailMetric {
+    /// Confidence level, e.g. 0.99.
+    pub confidence: f64,
+    /// Loss not exceeded with this confidence (fixed-point).
+    pub var_fp: u128,
+    /// Mean loss at or beyond the VaR (fixed-point).
+    pub expected_shortfall_fp: u128,
+}
+
+/// Aggregated tail metrics.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StressReport {
+    /// Trials run.
+    pub trials: u32,
+    /// Epochs per trial.
+    pub epochs_per_trial: u32,
+    /// Seed used.
+    pub seed: u64,
+    /// Principal at the start of each trial (fixed-point).
+    pub initial_capital_fp: u128,
+    /// Share of epochs that triggered.
+    pub trigger_frequency: f64,
+    /// Mean loss per trial (fixed-point).
+    pub mean_loss_fp: u128,
+    /// Worst trial loss (fixed-point).
+    pub max_loss_fp: u128,
+    /// VaR / ES per confidence level.
+    pub tail: Vec<TailMetric>,
+    /// Share of trials with an unfunded epoch.
+    pub probability_of_ruin: f64,
+    /// Mean `carryover_shortfall_fp` at the end of a trial.
+    pub expected_carryover_shortfall_fp: u128,
+    /// Per-trial outcomes.
+    #[serde(skip)]
+    pub trial_results: Vec<TrialResult>,
+}
+
+impl StressReport {
+    /// Metrics as `metric,confidence,value` CSV.
+    pub fn metrics_csv(&self) -> String {
+        let mut out = String::from("metric,confidence,value\n");
+        let mut row = |m: &str, c: &str, v: String| out.push_str(&format!("{m},{c},{v}\n"));
+        row("trials", "", self.trials.to_string());
+        row("epochs_per_trial", "", self.epochs_per_trial.to_string());
+        row("seed", "", self.seed.to_string());
+        row("initial_capital_fp", "", self.initial_capital_fp.to_string());
+        row("trigger_frequency", "", self.trigger_frequency.to_string());
+        row("mean_loss_fp", "", self.mean_loss_fp.to_string());
+        row("max_loss_fp", "", self.max_loss_fp.to_string());
+        for t in &self.tail {
+            row("var_fp", &t.confidence.to_string(), t.var_fp.to_string());
+            row("expected_shortfall_fp", &t.confidence.to_string(), t.expected_shortfall_fp.to_string());
+        }
+        row("probability_of_ruin", "", self.probability_of_ruin.to_string());
+        row("expected_carryover_shortfall_fp", "", self.expected_carryover_shortfall_fp.to_string());
+        out
+    }
+
+    /// Per-trial outcomes as CSV.
+    pub fn trials_csv(&self) -> String {
+        let mut out = String::from("trial,triggered_epochs,loss_fp,carryover_shortfall_fp,ruined\n");
+        for t in &self.trial_results {
+            out.push_str(&format!(
+                "{},{},{},{},{}\n",
+                t.trial, t.triggered_epochs, t.loss_fp, t.carryover_shortfall_fp, t.ruined
+            ));
+        }
+        out
+    }
+}
+
+/// Why a stress run could not start.
+#[derive(Debug, PartialEq)]
+pub enum StressError {
+    /// Invalid distribution or run parameters.
+    InvalidConfig(&'static str),
+    /// The simulator rejected the pool setup.
+    Sim(SimError),
+}
+
+impl std::fmt::Display for StressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidConfig(m) => write!(f, "invalid config: {m}"),
+            Self::Sim(e) => write!(f, "pool setup rejected: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for StressError {}
+
+impl From<SimError> for StressError {
+    fn from(e: SimError) -> Self {
+        Self::Sim(e)
+    }
+}
+
+enum Severity {
+    Pareto(Pareto<f64>),
+    Lognormal(LogNormal<f64>),
+}
+
+impl Severity {
+    fn sample_bps(&self, rng: &mut ChaCha8Rng) -> u16 {
+        let x = match self {
+            Self::Pareto(d) => d.sample(rng),
+            Self::Lognormal(d) => d.sample(rng),
+        };
+        x.clamp(0.0, BPS_DENOM as f64) as u16
+    }
+}
+
+/// Run the stress test.
+pub fn run_stress(cfg: &StressConfig) -> Result<StressReport, StressError> {
+    if cfg.trials == 0 || cfg.epochs_per_trial == 0 {
+        return Err(StressError::InvalidConfig("trials and epochs_per_trial must be positive"));
+    }
+    if cfg.confidence_levels.iter().any(|c| !(0.0..1.0).contains(c)) {
+        return Err(StressError::InvalidConfig("confidence levels must be in [0, 1)"));
+    }
+    let frequency = if cfg.events_per_epoch > 0.0 {
+        Some(Poisson::new(cfg.events_per_epoch).map_err(|_| StressError::InvalidConfig("events_per_epoch"))?)
+    } else {
+        None
+    };
+    let severity = match cfg.severity {
+        SeverityDist::Pareto { scale_bps, shape } => {
+            Severity::Pareto(Pareto::new(scale_bps, shape).map_err(|_| StressError::InvalidConfig("pareto"))?)
+        }
+        SeverityDist::Lognormal { mu, sigma } => {
+            Severity::Lognormal(LogNormal::new(mu, sigma).map_err(|_| StressError::InvalidConfig("lognormal"))?)
+        }
+    };
+
+    let funded = funded_pool(cfg)?;
+    let initial_capital_fp = funded.state().total_deposited_fp;
+    let users = depositors(&cfg.pool);
+    let mut rng = ChaCha8Rng::seed_from_u64(cfg.seed);
+
+    let mut results = Vec::with_capacity(cfg.trials as usize);
+    for trial in 0..cfg.trials {
+        let mut sim = funded.clone();
+        let mut r = TrialResult { trial, triggered_epochs: 0, loss_fp: 0, carryover_shortfall_fp: 0, ruined: false };
+
+        for e in 0..cfg.epochs_per_trial {
+            let epoch_id = e as u64 + 1;
+            // One-second epochs: each has ended by the time the next starts
+            sim.step(&Action::StartEpoch { epoch_id, duration_secs: 1 })?;
+            let events = frequency.as_ref().map(|d| d.sample(&mut rng) as u64).unwrap_or(0);
+            let worst = (0..events).map(|_| severity.sample_bps(&mut rng)).max();
+            if let Some(severity_input_bps) = worst {
+                sim.step(&Action::Trigger { epoch_id, severity_input_bps, user_cap_bps: cfg.user_cap_bps, epoch_cap_fp: None })?;
+                for user in &users {
+                    // Claims the pool can no longer fund are rejected, as on-chain
+                    let _ = sim.step(&Action::Payout { epoch_id, user: user.clone() });
+                }
+                let epoch = sim.epoch(epoch_id).cloned().unwrap_or_default();
+                sim.step(&Action::Finalize { epoch_id, sweep_dust: None })?;
+                r.triggered_epochs += 1;
+                r.loss_fp = r.loss_fp.saturating_add(epoch.total_payout_fp);
+                r.ruined |= epoch.shortfall_fp > 0;
+            }
+            sim.step(&Action::AdvanceTime { secs: 1 })?;
+        }
+        r.carryover_shortfall_fp = sim.state().carryover_shortfall_fp;
+        results.push(r);
+    }
+
+    Ok(summarise(cfg, initial_capital_fp, results))
+}
+
+fn depositors(pool: &PoolSetup) -> Vec<String> {
+    (0..pool.senior_depositors)
+        .map(|i| format!("senior-{i}"))
+        .chain((0..pool.junior_depositors).map(|i| format!("junior-{i}")))
+        .collect()
+}
+
+fn funded_pool(cfg: &StressConfig) -> Result<Simulator, StressError> {
+    let mut sim = Simulator::new(cfg.params.clone(), 0)?;
+    for user in depositors(&cfg.pool) {
+        let tranche = if user.starts_with("senior") { 0 } else { 1 };
+        sim.step(&Action::Deposit { user, amount: cfg.pool.deposit_amount, tranche, referrer: None })?;
+    }
+    Ok(sim)
+}
+
+fn summarise(cfg: &StressConfig, initial_capital_fp: u128, results: Vec<TrialResult>) -> StressReport {
+    let n = results.len();
+    let mut losses: Vec<u128> = results.iter().map(|r| r.loss_fp).collect();
+    losses.sort_unstable();
+
+    let tail = cfg
+        .confidence_levels
+        .iter()
+        .map(|&c| {
+            let idx = ((c * n as f64).ceil() as usize).clamp(1, n) - 1;
+            let var_fp = losses[idx];
+            let beyond = &losses[idx..];
+            TailMetric {
+                confidence: c,
+                var_fp,
+                expected_shortfall_fp: beyond.iter().sum::<u128>() / beyond.len() as u128,
+            }
+        })
+        .collect();
+
+    let triggered: u64 = results.iter().map(|r| r.triggered_epochs as u64).sum();
+    StressReport {
+        trials: cfg.trials,
+        epochs_per_trial: cfg.epochs_per_trial,
+        seed: cfg.seed,
+        initial_capital_fp,
+        trigger_frequency: triggered as f64 / (n as f64 * cfg.epochs_per_trial as f64),
+        mean_loss_fp: losses.iter().sum::<u128>() / n as u128,
+        max_loss_fp: losses.last().copied().unwrap_or(0),
+        tail,
+        probability_of_ruin: results.iter().filter(|r| r.ruined).count() as f64 / n as f64,
+        expected_carryover_shortfall_fp: results.iter().map(|r| r.carryover_shortfall_fp).sum::<u128>() / n as u128,
+        trial_results: results,
+    }
+}
@@ -0,0 +1,108 @@
+//! Per-step reports (serialised as JSON by the binary).
+
+use serde::Serialize;
+
+/// Pool snapshot after one step.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StepReport {
+    /// Number of applied steps.
+    pub step: u64,
+    /// Simulated time.
+    pub now: i64,
+    /// Pool paused.
+    pub paused: bool,
+    /// Vault token balance (native units).
+    pub vault_u64: u64,
+    /// Vault balance as tracked by the collateral registry (fixed-point).
+    pub vault_fp: u128,
+    /// Total principal.
+    pub total_deposited_fp: u128,
+    /// Principal + LP income + referral rewards owed.
+    pub tracked_liabilities_fp: u128,
+    /// `vault_fp - tracked_liabilities_fp` (negative after payouts).
+    pub surplus_fp: i128,
+    /// Shortfall carried over from finalized epochs.
+    pub carryover_shortfall_fp: u128,
+    /// Fee flows.
+    pub fees: FeeFlows,
+    /// Senior (0) and junior (1).
+    pub tranches: Vec<TrancheReport>,
+    /// Every user seen so far, by name.
+    pub users: Vec<UserReport>,
+    /// Every epoch started so far, by id.
+    pub epochs: Vec<EpochReport>,
+}
+
+/// Cumulative fee flows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FeeFlows {
+    /// Protocol fees sent to the treasury.
+    pub protocol_fees_u64: u64,
+    /// Referral rewards accrued and not yet claimed.
+    pub referral_rewards_owed_fp: u128,
+    /// Unattributed referral fees credited to LPs.
+    pub referral_to_lps_fp: u128,
+    /// Dust swept to the treasury at finalization.
+    pub dust_swept_u64: u64,
+}
+
+/// Cumulative flows and P&L of one tranche.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TrancheReport {
+    /// 0 = senior, 1 = junior.
+    pub tranche: u8,
+    /// Current principal.
+    pub principal_fp: u128,
+    /// Gross deposits (before fees).
+    pub deposited_fp: u128,
+    /// Withdrawals.
+    pub withdrawn_fp: u128,
+    /// Income credited (referral fees routed to LPs).
+    pub income_fp: u128,
+    /// Payouts attributed by weighted stake.
+    pub payouts_fp: u128,
+    /// `principal + withdrawn + income + payouts - deposited`.
+    pub pnl_fp: i128,
+}
+
+/// Cumulative flows and P&L of one user.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct UserReport {
+    /// User name.
+    pub user: String,
+    /// Gross deposits (native units).
+    pub deposited_u64: u64,
+    /// Withdrawals (native units).
+    pub withdrawn_u64: u64,
+    /// Payouts received (native units).
+    pub payouts_u64: u64,
+    /// Senior principal.
+    pub senior_fp: u128,
+    /// Junior principal.
+    pub junior_fp: u128,
+    /// Income accrued (settled at report time).
+    pub yield_accrued_fp: u128,
+    /// Referral rewards earned as a referrer.
+    pub referral_rewards_fp: u128,
+    /// Position value + cash received - cash paid in.
+    pub pnl_fp: i128,
+}
+
+/// Epoch summary.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct EpochReport {
+    /// Epoch id.
+    pub epoch_id: u64,
+    /// Triggered.
+    pub triggered: bool,
+    /// Finalized.
+    pub closed: bool,
+    /// Effective severity.
+    pub severity_bps: u16,
+    /// Principal snapshot at trigger.
+    pub total_stake_snapshot_fp: u128,
+    /// Paid so far.
+    pub total_payout_fp: u128,
+    /// Unfunded liability.
+    pub shortfall_fp: u128,
+}
@@ -0,0 +1,94 @@
+use tail_risk_sim::fuzz::{check, minimise, run_fuzz, FuzzConfig};
+use tail_risk_sim::invariants::{check_state, EpochView, PoolView, PositionView, Tracker, Violation};
+use tail_risk_sim::{run, Action, PoolParams, Scenario, SimError};
+
+fn regression(json: &str) -> Scenario {
+    let scenario: Scenario = serde_json::from_str(json).unwrap();
+    assert_eq!(check(&scenario).unwrap(), Ok(()));
+    scenario
+}
+
+fn errors(scenario: &Scenario) -> Vec<Option<SimError>> {
+    run(scenario).unwrap().into_iter().map(|s| s.error).collect()
+}
+
+#[test]
+fn random_sequences_hold_invariants() {
+    let failures = run_fuzz(&FuzzConfig { cases: 300, steps: 60, seed: 0 }).unwrap();
+    if let Some(f) = failures.first() {
+        panic!("{} failing cases, first: {}", failures.len(), serde_json::to_string(f).unwrap());
+    }
+}
+
+#[test]
+fn minimised_findings_stay_fixed() {
+    // Deposits were credited as withdrawable and again when their lot matured
+    let s = regression(include_str!("../scenarios/regressions/lockup_double_credit.json"));
+    assert_eq!(errors(&s), [None, Some(SimError::LockupNotExpired), None, None]);
+
+    // ... which let an LP withdraw other LPs' principal
+    let s = regression(include_str!("../scenarios/regressions/withdraw_above_principal.json"));
+    assert_eq!(errors(&s)[3], Some(SimError::InsufficientPoolBalance));
+
+    // A second trigger during open claims let deposits in after the first finalize
+    let s = regression(include_str!("../scenarios/regressions/trigger_during_open_claims.json"));
+    assert_eq!(errors(&s)[5], Some(SimError::Paused));
+}
+
+#[test]
+fn minimise_keeps_only_what_fails() {
+    let deposit = |user: &str| Action::Deposit { user: user.into(), amount: 500_000_000, tranche: 0, referrer: None };
+    let mut actions = vec![Action::AdvanceTime { secs: 5 }; 10];
+    actions.insert(3, deposit("alice"));
+    actions.insert(7, deposit("bob"));
+    let params = PoolParams { lockup_secs: 5, ..PoolParams::default() };
+    let scenario = Scenario { params, start_ts: 0, actions };
+
+    let bob_deposits = |s: &Scenario| s.actions.iter().any(|a| matches!(a, Action::Deposit { user, .. } if user == "bob"));
+    let min = minimise(&scenario, bob_deposits);
+    assert_eq!(min.actions, [deposit("bob")]);
+    assert_eq!(min.params, PoolParams::default());
+}
+
+#[test]
+fn tracker_flags_each_invariant() {
+    let alice = PositionView { senior_deposited_fp: 1_000, senior_withdrawable_fp: 1_000, ..Default::default() };
+    let pool = PoolView {
+        tranche_weight_senior_bps: 10_000,
+        tranche_weight_junior_bps: 10_000,
+        vault_fp: 1_000,
+        total_deposited_fp: 1_000,
+        senior_total_fp: 1_000,
+        positions: [("alice".to_string(), alice)].into(),
+        ..Default::default()
+    };
+    assert_eq!(check_state(&pool), Ok(()));
+    assert_eq!(check_state(&PoolView { processing: true, ..pool.clone() }), Err(Violation::ProcessingLeftSet));
+    assert_eq!(
+        check_state(&PoolView { total_deposited_fp: 900, ..pool.clone() }).unwrap_err().kind(),
+        "principal_mismatch"
+    );
+    assert_eq!(check_state(&PoolView { vault_fp: 999, ..pool.clone() }).unwrap_err().kind(), "undercollateralised");
+
+    // 10% event on a 1_000 snapshot: at most 100 in total, all of it alice's
+    let epoch = |paid| EpochView {
+        triggered: true,
+        total_stake_snapshot_fp: 1_000,
+        total_payout_fp: paid,
+        severity_bps: 1_000,
+        ..Default::default()
+    };
+    let triggered = PoolView { epochs: [(1, epoch(0))].into(), ..pool.clone() };
+    let mut tracker = Tracker::default();
+    let trigger = Action::Trigger { epoch_id: 1, severity_input_bps: 1_000, user_cap_bps: None, epoch_cap_fp: None };
+    tracker.check(&trigger, &pool, &triggered).unwrap();
+
+    let payout = |user: &str| Action::Payout { epoch_id: 1, user: user.into() };
+    let paid = |amount| PoolView { vault_fp: 1_000 - amount, epochs: [(1, epoch(amount))].into(), ..pool.clone() };
+    assert_eq!(tracker.check(&payout("alice"), &triggered, &paid(100)), Ok(()));
+    assert_eq!(tracker.check(&payout("alice"), &triggered, &paid(101)).unwrap_err().kind(), "epoch_overpaid");
+    assert_eq!(
+        tracker.check(&payout("bob"), &triggered, &paid(50)),
+        Err(Violation::ClaimAboveShare { epoch_id: 1, user: "bob".into(), paid_fp: 50, share_fp: 0 })
+    );
+}
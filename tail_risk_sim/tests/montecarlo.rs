@@ -0,0 +1,63 @@
+use tail_risk_sim::montecarlo::{run_stress, PoolSetup, SeverityDist, StressConfig, StressError};
+use tail_risk_sim::PoolParams;
+
+fn config(events_per_epoch: f64, severity: SeverityDist) -> StressConfig {
+    StressConfig {
+        params: PoolParams::default(),
+        pool: PoolSetup { senior_depositors: 3, junior_depositors: 2, deposit_amount: 1_000_000_000 },
+        trials: 300,
+        epochs_per_trial: 3,
+        events_per_epoch,
+        severity,
+        user_cap_bps: None,
+        confidence_levels: vec![0.9, 0.99],
+        seed: 7,
+    }
+}
+
+#[test]
+fn same_seed_same_report() {
+    let cfg = config(0.5, SeverityDist::Lognormal { mu: 7.0, sigma: 0.8 });
+    let a = run_stress(&cfg).unwrap();
+    let b = run_stress(&cfg).unwrap();
+    assert_eq!(a, b);
+
+    let other = run_stress(&StressConfig { seed: 8, ..cfg }).unwrap();
+    assert_ne!(a.trial_results, other.trial_results);
+}
+
+#[test]
+fn no_events_means_no_loss() {
+    let r = run_stress(&config(0.0, SeverityDist::Pareto { scale_bps: 100.0, shape: 2.0 })).unwrap();
+    assert_eq!(r.trigger_frequency, 0.0);
+    assert_eq!((r.mean_loss_fp, r.max_loss_fp), (0, 0));
+    assert!(r.tail.iter().all(|t| t.var_fp == 0 && t.expected_shortfall_fp == 0));
+    assert_eq!(r.probability_of_ruin, 0.0);
+}
+
+#[test]
+fn heavy_tails_drive_tail_metrics() {
+    let r = run_stress(&config(2.0, SeverityDist::Pareto { scale_bps: 3_000.0, shape: 1.1 })).unwrap();
+    assert!(r.trigger_frequency > 0.5);
+    assert!(r.tail[0].var_fp <= r.tail[1].var_fp);
+    assert!(r.tail.iter().all(|t| t.expected_shortfall_fp >= t.var_fp));
+    assert!(r.max_loss_fp >= r.tail[1].var_fp);
+    // Payouts do not reduce principal, so repeated severe epochs exhaust the vault
+    assert!(r.probability_of_ruin > 0.0);
+    assert!(r.expected_carryover_shortfall_fp > 0);
+
+    let csv = r.metrics_csv();
+    assert!(csv.starts_with("metric,confidence,value\n"));
+    assert!(csv.contains("var_fp,0.99,"));
+    assert_eq!(r.trials_csv().lines().count(), 301);
+    assert!(serde_json::to_string(&r).unwrap().contains("\"probability_of_ruin\""));
+}
+
+#[test]
+fn rejects_bad_configs() {
+    let mut cfg = config(0.5, SeverityDist::Pareto { scale_bps: 100.0, shape: -1.0 });
+    assert_eq!(run_stress(&cfg), Err(StressError::InvalidConfig("pareto")));
+    cfg.severity = SeverityDist::Pareto { scale_bps: 100.0, shape: 2.0 };
+    cfg.confidence_levels = vec![1.0];
+    assert!(matches!(run_stress(&cfg), Err(StressError::InvalidConfig(_))));
+}
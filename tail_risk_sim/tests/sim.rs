@@ -0,0 +1,107 @@
+use tail_risk_sim::{run, Action, PoolParams, Scenario, SimError, Simulator};
+
+const USDC: u64 = 1_000_000;
+const FP: u128 = 1_000_000;
+
+fn deposit(user: &str, usdc: u64, tranche: u8) -> Action {
+    Action::Deposit { user: user.into(), amount: usdc * USDC, tranche, referrer: None }
+}
+
+fn payout(user: &str) -> Action {
+    Action::Payout { epoch_id: 1, user: user.into() }
+}
+
+fn claim_cycle() -> Vec<Action> {
+    vec![
+        deposit("alice", 10_000, 0),
+        Action::AdvanceTime { secs: 30 },
+        deposit("bob", 5_000, 1),
+        Action::StartEpoch { epoch_id: 1, duration_secs: 3_600 },
+        Action::AdvanceTime { secs: 60 },
+        Action::Trigger { epoch_id: 1, severity_input_bps: 5_000, user_cap_bps: None, epoch_cap_fp: None },
+        payout("alice"),
+        payout("bob"),
+        Action::Finalize { epoch_id: 1, sweep_dust: None },
+    ]
+}
+
+#[test]
+fn full_claim_cycle_matches_program_math() {
+    let mut sim = Simulator::new(PoolParams::default(), 1_000).unwrap();
+    let mut last = None;
+    for a in claim_cycle() {
+        last = Some(sim.apply(&a).unwrap());
+    }
+    let r = last.unwrap();
+
+    // 0.5% protocol fee on both deposits; bob's unreferred 0.25% went to alice's tranche
+    assert_eq!(r.fees.protocol_fees_u64, 75 * USDC);
+    assert_eq!(r.fees.referral_to_lps_fp, 12_500_000 * FP);
+    assert_eq!(r.tranches[0].income_fp, 12_500_000 * FP);
+
+    // 50% severity on equal weights: everyone gets half their principal
+    let alice = r.users.iter().find(|u| u.user == "alice").unwrap();
+    let bob = r.users.iter().find(|u| u.user == "bob").unwrap();
+    assert_eq!(alice.payouts_u64, 4_975 * USDC);
+    assert_eq!(bob.payouts_u64, 2_481_250_000);
+    assert_eq!(r.vault_u64, 15_000 * USDC - 75 * USDC - alice.payouts_u64 - bob.payouts_u64);
+    assert!(alice.yield_accrued_fp > 12_490_000 * FP && alice.yield_accrued_fp <= 12_500_000 * FP);
+
+    assert_eq!(r.epochs[0].total_payout_fp, (4_975 * USDC + 2_481_250_000) as u128 * FP);
+    assert!(r.epochs[0].closed && !r.paused);
+    assert_eq!(r.tranches[0].payouts_fp + r.tranches[1].payouts_fp, r.epochs[0].total_payout_fp);
+}
+
+#[test]
+fn unreferred_deposits_keep_the_vault_equal_to_tracked_liabilities() {
+    let tracked = |sim: &Simulator| {
+        let v = sim.view();
+        assert_eq!(v.vault_fp, v.total_deposited_fp + v.yield_reserve_fp + v.referral_rewards_owed_fp);
+        v
+    };
+    let mut sim = Simulator::new(PoolParams::default(), 1_000).unwrap();
+
+    // Empty pool: nobody to credit, so the 0.25% referral fee is not charged
+    sim.apply(&deposit("alice", 1_000, 0)).unwrap();
+    let v = tracked(&sim);
+    assert_eq!(v.total_deposited_fp, (995 * USDC) as u128 * FP);
+    assert_eq!(v.yield_reserve_fp, 0);
+
+    // Funded pool: the fee is charged and credited to alice's tranche, not left as vault dust
+    sim.apply(&Action::AdvanceTime { secs: 30 }).unwrap();
+    sim.apply(&deposit("bob", 1_000, 1)).unwrap();
+    let v = tracked(&sim);
+    assert_eq!(v.total_deposited_fp, (995 * USDC + 992_500_000) as u128 * FP);
+    assert_eq!(v.yield_reserve_fp, 2_500_000 * FP);
+    assert_eq!(v.referral_rewards_owed_fp, 0);
+}
+
+#[test]
+fn rejected_actions_leave_the_pool_untouched() {
+    let mut sim = Simulator::new(PoolParams::default(), 1_000).unwrap();
+    sim.apply(&deposit("alice", 1_000, 0)).unwrap();
+    sim.apply(&Action::StartEpoch { epoch_id: 1, duration_secs: 0 }).unwrap();
+    sim.apply(&Action::Trigger { epoch_id: 1, severity_input_bps: 1_000, user_cap_bps: None, epoch_cap_fp: None })
+        .unwrap();
+    let before = sim.report().unwrap();
+
+    assert_eq!(sim.apply(&deposit("bob", 1_000, 0)), Err(SimError::Paused));
+    assert_eq!(sim.apply(&payout("carol")), Err(SimError::Unauthorized));
+    assert_eq!(sim.report().unwrap(), before);
+
+    sim.apply(&payout("alice")).unwrap();
+    assert_eq!(sim.apply(&payout("alice")), Err(SimError::NothingToPayout));
+}
+
+#[test]
+fn scenarios_replay_deterministically() {
+    let scenario = Scenario { params: PoolParams::default(), start_ts: 0, actions: claim_cycle() };
+    let a = run(&scenario).unwrap();
+    let b = run(&scenario).unwrap();
+    assert_eq!(a, b);
+    assert!(a.iter().all(|s| s.error.is_none()));
+
+    let json = serde_json::to_string(&scenario).unwrap();
+    let parsed: Scenario = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, scenario);
+}